@@ -0,0 +1,130 @@
+// Maschinette: Bitwig Studio controller script for the Maschine Mikro MK3 driver's Bitwig page.
+//
+// Written by `driver install-bitwig-script`. In Bitwig's Settings > Controllers, add
+// "Maschinette" with the driver's MIDI ports as its input and output. The protocol is
+// described in crates/driver/src/modes/bitwig.rs.
+loadAPI(17);
+
+host.defineController("Maschinette", "Maschine Mikro MK3 (Maschinette)", "1.0", "c041d16f-9938-4059-888e-5e287374b8f3", "Maschinette");
+host.defineMidiPorts(1, 1);
+host.addDeviceNameBasedDiscoveryPair(["Maschine Mikro MK3 MIDI Out"], ["Maschine Mikro MK3 MIDI In"]);
+
+// 0-based MIDI channel the driver sends its controls on ([bitwig] channel - 1)
+var MIDI_CHANNEL = 14;
+
+var HEADER = "f07d4d";
+var HELLO = "30", PARAMETER = "31", DEVICE = "32", PAGE = "33", REFRESH = "3f";
+var CC_PAGE = 20, CC_DEVICE = 21;
+var PARAMETERS = 8;
+var NAME_CHARS = 16;
+
+var output, device, remotes;
+var names = [], values = [], deviceName = "", pageName = "";
+
+function hex(n) {
+    return (n < 16 ? "0" : "") + n.toString(16);
+}
+
+// Printable ASCII only, since the screen font has nothing else.
+function text(s) {
+    var out = "";
+    for (var i = 0; i < s.length && out.length < NAME_CHARS * 2; i++) {
+        var c = s.charCodeAt(i);
+        if (c >= 32 && c < 127) {
+            out += hex(c);
+        }
+    }
+    return out;
+}
+
+function send(command, body) {
+    output.sendSysex(HEADER + command + body + "f7");
+}
+
+function sendParameter(i) {
+    send(PARAMETER, hex(i) + hex(values[i]) + text(names[i]));
+}
+
+function sendAll() {
+    for (var i = 0; i < PARAMETERS; i++) {
+        sendParameter(i);
+    }
+    send(DEVICE, text(deviceName));
+    send(PAGE, text(pageName));
+}
+
+function init() {
+    var input = host.getMidiInPort(0);
+    input.setMidiCallback(onMidi);
+    input.setSysexCallback(onSysex);
+    output = host.getMidiOutPort(0);
+
+    var track = host.createCursorTrack("MASCHINETTE", "Maschinette", 0, 0, true);
+    device = track.createCursorDevice();
+    remotes = device.createCursorRemoteControlsPage(PARAMETERS);
+
+    for (var i = 0; i < PARAMETERS; i++) {
+        names.push("");
+        values.push(0);
+        var parameter = remotes.getParameter(i);
+        parameter.setIndication(true);
+        parameter.name().addValueObserver(nameObserver(i));
+        parameter.value().addValueObserver(128, valueObserver(i));
+    }
+    device.name().addValueObserver(function (name) {
+        deviceName = name;
+        send(DEVICE, text(name));
+    });
+    remotes.getName().addValueObserver(function (name) {
+        pageName = name;
+        send(PAGE, text(name));
+    });
+
+    // Brings the driver's Bitwig page up.
+    send(HELLO, "01");
+}
+
+function nameObserver(i) {
+    return function (name) {
+        names[i] = name;
+        sendParameter(i);
+    };
+}
+
+function valueObserver(i) {
+    return function (value) {
+        values[i] = value;
+        sendParameter(i);
+    };
+}
+
+function onMidi(status, data1, data2) {
+    if (status !== (0xb0 | MIDI_CHANNEL)) {
+        return;
+    }
+    if (data1 < PARAMETERS) {
+        remotes.getParameter(data1).value().set(data2, 128);
+    } else if (data1 === CC_PAGE) {
+        if (data2 > 64) {
+            remotes.selectNextPage(true);
+        } else {
+            remotes.selectPreviousPage(true);
+        }
+    } else if (data1 === CC_DEVICE) {
+        if (data2 > 64) {
+            device.selectNext();
+        } else {
+            device.selectPrevious();
+        }
+    }
+}
+
+function onSysex(data) {
+    if (data.toLowerCase() === HEADER + REFRESH + "f7") {
+        sendAll();
+    }
+}
+
+function flush() {}
+
+function exit() {}
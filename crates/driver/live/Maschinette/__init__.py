@@ -0,0 +1,145 @@
+# Maschinette: Ableton Live remote script for the Maschine Mikro MK3 driver's Live page.
+#
+# Written by `driver install-live-script`. In Live's preferences, pick "Maschinette" as a
+# control surface with the driver's MIDI ports as its input and output. The protocol is
+# described in crates/driver/src/modes/live.rs.
+from __future__ import absolute_import
+
+import Live
+from _Framework.ControlSurface import ControlSurface
+
+# 0-based MIDI channel the driver sends its controls on ([live] channel - 1)
+MIDI_CHANNEL = 15
+
+HEADER = (0xF0, 0x7D, 0x4D)
+TRACK_NAME, CLIP, TRANSPORT, RING, HELLO = 0x20, 0x21, 0x22, 0x23, 0x2F
+CC_LAUNCH, CC_STOP_TRACK, CC_RING_TRACKS, CC_RING_SCENES = 0, 16, 20, 21
+CC_PLAY, CC_STOP, CC_RECORD, CC_STOP_ALL = 24, 25, 26, 27
+SIZE = 4
+NAME_CHARS = 16
+
+# The pad colors, in the driver's order (1 = red ... 17 = white)
+PALETTE = [
+    (255, 0, 0), (255, 96, 0), (255, 150, 60), (255, 190, 0), (255, 255, 0),
+    (160, 255, 0), (0, 255, 0), (0, 255, 140), (0, 255, 255), (0, 190, 255),
+    (0, 60, 255), (120, 80, 200), (140, 0, 255), (180, 0, 255), (255, 0, 255),
+    (255, 0, 120), (255, 255, 255),
+]
+
+EMPTY, STOPPED, PLAYING, TRIGGERED, RECORDING = range(5)
+
+
+def create_instance(c_instance):
+    return Maschinette(c_instance)
+
+
+def nearest_color(rgb):
+    r, g, b = (rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF
+    distances = [(pr - r) ** 2 + (pg - g) ** 2 + (pb - b) ** 2 for pr, pg, pb in PALETTE]
+    return distances.index(min(distances)) + 1
+
+
+def to_14bit(n):
+    return ((n >> 7) & 0x7F, n & 0x7F)
+
+
+class Maschinette(ControlSurface):
+    """Keeps the driver's session ring in step with Live by comparing the state with what
+    was last sent on every display update, and acts on the driver's controllers."""
+
+    def __init__(self, c_instance):
+        ControlSurface.__init__(self, c_instance)
+        self._track_offset = 0
+        self._scene_offset = 0
+        self._sent = {}
+
+    def build_midi_map(self, midi_map_handle):
+        ControlSurface.build_midi_map(self, midi_map_handle)
+        for cc in range(CC_STOP_ALL + 1):
+            Live.MidiMap.forward_midi_cc(self._c_instance.handle(), midi_map_handle, MIDI_CHANNEL, cc)
+
+    def receive_midi(self, midi_bytes):
+        if tuple(midi_bytes) == HEADER + (HELLO, 0xF7):
+            self._sent = {}
+            self._send_state()
+        elif len(midi_bytes) == 3 and midi_bytes[0] == 0xB0 | MIDI_CHANNEL:
+            self._control(midi_bytes[1], midi_bytes[2])
+        else:
+            ControlSurface.receive_midi(self, midi_bytes)
+
+    def update_display(self):
+        ControlSurface.update_display(self)
+        self._send_state()
+
+    def _tracks(self):
+        return list(self.song().visible_tracks)
+
+    def _control(self, cc, value):
+        song = self.song()
+        tracks = self._tracks()
+        if CC_LAUNCH <= cc < CC_LAUNCH + SIZE * SIZE:
+            row, column = divmod(cc - CC_LAUNCH, SIZE)
+            slot = self._slot(tracks, column, row)
+            if slot is not None:
+                slot.fire()
+        elif CC_STOP_TRACK <= cc < CC_STOP_TRACK + SIZE:
+            index = self._track_offset + cc - CC_STOP_TRACK
+            if index < len(tracks):
+                tracks[index].stop_all_clips()
+        elif cc == CC_RING_TRACKS:
+            self._track_offset = max(0, min(len(tracks) - 1, self._track_offset + value - 64))
+        elif cc == CC_RING_SCENES:
+            self._scene_offset = max(0, min(len(song.scenes) - 1, self._scene_offset + value - 64))
+        elif cc == CC_PLAY:
+            if song.is_playing:
+                song.stop_playing()
+            else:
+                song.start_playing()
+        elif cc == CC_STOP:
+            song.stop_playing()
+        elif cc == CC_RECORD:
+            song.session_record = not song.session_record
+        elif cc == CC_STOP_ALL:
+            song.stop_all_clips()
+        self._send_state()
+
+    def _slot(self, tracks, column, row):
+        track_index = self._track_offset + column
+        scene_index = self._scene_offset + row
+        if track_index >= len(tracks):
+            return None
+        slots = tracks[track_index].clip_slots
+        return slots[scene_index] if scene_index < len(slots) else None
+
+    def _send(self, key, data):
+        message = HEADER + tuple(data) + (0xF7,)
+        if self._sent.get(key) != message:
+            self._sent[key] = message
+            self._send_midi(message)
+
+    def _send_state(self):
+        song = self.song()
+        tracks = self._tracks()
+        for column in range(SIZE):
+            index = self._track_offset + column
+            name = tracks[index].name if index < len(tracks) else ""
+            chars = [ord(c) for c in name if 32 <= ord(c) < 127][:NAME_CHARS]
+            self._send(("name", column), [TRACK_NAME, column] + chars)
+        for pad in range(SIZE * SIZE):
+            row, column = divmod(pad, SIZE)
+            self._send(("clip", pad), [CLIP, pad] + list(self._clip_state(self._slot(tracks, column, row))))
+        self._send("transport", [TRANSPORT, int(song.is_playing), int(song.session_record)])
+        self._send("ring", [RING] + list(to_14bit(self._track_offset)) + list(to_14bit(self._scene_offset)))
+
+    def _clip_state(self, slot):
+        if slot is None or not slot.has_clip:
+            return (0, EMPTY)
+        clip = slot.clip
+        color = nearest_color(clip.color)
+        if clip.is_recording:
+            return (color, RECORDING)
+        if clip.is_triggered:
+            return (color, TRIGGERED)
+        if clip.is_playing:
+            return (color, PLAYING)
+        return (color, STOPPED)
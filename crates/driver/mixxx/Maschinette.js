@@ -0,0 +1,15 @@
+// Maschinette: Mixxx script for the Maschine Mikro MK3 driver.
+//
+// Written with Maschinette.midi.xml by `driver export-mixxx-mapping`. The XML maps the
+// pads and buttons straight to Mixxx controls and lights them from Mixxx's outputs; this
+// script only tells the driver the mapping is loaded.
+var Maschinette = {};
+
+// Brings the driver's Custom MIDI mode up when [mixxx] is enabled there.
+var HELLO = [0xf0, 0x7d, 0x4d, 0x40, 0xf7];
+
+Maschinette.init = function (id, debugging) {
+    midi.sendSysexMsg(HELLO, HELLO.length);
+};
+
+Maschinette.shutdown = function () {};
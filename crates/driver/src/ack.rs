@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use crate::input::HardwareEvent;
+use crate::modes::ButtonEdges;
+
+const ACK_FLASH: Duration = Duration::from_millis(60);
+/// The encoder has no light; it acknowledges on the middle of the touch strip.
+const ENCODER_LED: usize = 12;
+const SLIDER_LEDS: usize = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AckLight {
+    Button(Buttons),
+    Pad(usize),
+    Slider(usize),
+}
+
+/// A brief light for every accepted input, standing in for the haptics the hardware
+/// lacks. It only shows on lights the mode left dark, so it never hides real feedback.
+pub struct InputAck {
+    buttons: ButtonEdges,
+    last_encoder: Option<u8>,
+    flashes: Vec<(AckLight, Instant)>,
+}
+
+impl InputAck {
+    pub fn new() -> Self {
+        Self { buttons: ButtonEdges::new(), last_encoder: None, flashes: Vec::new() }
+    }
+
+    /// Follows raw events, which repeat every button's state in each report; returns
+    /// true when a new flash starts.
+    pub fn handle(&mut self, event: &HardwareEvent, now: Instant) -> bool {
+        let light = match *event {
+            HardwareEvent::Button { index, pressed } => {
+                match index {
+                    _ if !self.buttons.pressed(index, pressed) => None,
+                    Buttons::EncoderPress => Some(AckLight::Slider(ENCODER_LED)),
+                    button => Some(AckLight::Button(button)),
+                }
+            }
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value } if value > 0 => {
+                Some(AckLight::Pad(index))
+            }
+            HardwareEvent::Encoder { value } => {
+                let moved = self.last_encoder.is_some_and(|last| last != value);
+                self.last_encoder = Some(value);
+                moved.then_some(AckLight::Slider(ENCODER_LED))
+            }
+            HardwareEvent::Slider { value } if value > 0 => {
+                Some(AckLight::Slider(((value as usize - 1) * SLIDER_LEDS / 200).min(SLIDER_LEDS - 1)))
+            }
+            _ => None,
+        };
+        let Some(light) = light else {
+            return false;
+        };
+        self.flashes.retain(|(l, _)| *l != light);
+        self.flashes.push((light, now + ACK_FLASH));
+        true
+    }
+
+    /// Ends flashes that are over; returns true when any did.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let before = self.flashes.len();
+        self.flashes.retain(|(_, until)| now < *until);
+        self.flashes.len() != before
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.flashes.is_empty()
+    }
+
+    pub fn compose_lights(&self, lights: &mut Lights, b: Brightness) {
+        for (light, _) in &self.flashes {
+            match *light {
+                AckLight::Button(button) if lights.get_button(button) == Brightness::Off => lights.set_button(button, b),
+                AckLight::Pad(pad) if lights.get_pad(pad).1 == Brightness::Off => lights.set_pad(pad, PadColors::White, b),
+                AckLight::Slider(led) if lights.get_slider(led) == Brightness::Off => lights.set_slider(led, b),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Velocity histogram buckets; 16 velocity steps each.
+pub const VELOCITY_BUCKETS: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct PadStats {
+    pub hits: u32,
+    pub velocity_sum: u32,
+    pub velocities: [u32; VELOCITY_BUCKETS],
+    /// Hits measured against a running metronome; the offsets below average over these.
+    pub timed_hits: u32,
+    pub offset_sum_ms: f64,
+    pub abs_offset_sum_ms: f64,
+}
+
+impl PadStats {
+    pub fn mean_velocity(&self) -> Option<f64> {
+        (self.hits > 0).then(|| self.velocity_sum as f64 / self.hits as f64)
+    }
+
+    /// Average signed offset from the grid; negative is early
+    pub fn mean_offset_ms(&self) -> Option<f64> {
+        (self.timed_hits > 0).then(|| self.offset_sum_ms / self.timed_hits as f64)
+    }
+
+    /// Average distance from the grid, early or late
+    pub fn mean_abs_offset_ms(&self) -> Option<f64> {
+        (self.timed_hits > 0).then(|| self.abs_offset_sum_ms / self.timed_hits as f64)
+    }
+}
+
+/// Collects pad hits for practice: counts, velocity spread and timing against a metronome grid.
+pub struct PracticeStats {
+    pads: Vec<PadStats>,
+}
+
+impl PracticeStats {
+    pub fn new() -> Self {
+        Self { pads: vec![PadStats::default(); 16] }
+    }
+
+    pub fn reset(&mut self) {
+        self.pads.fill(PadStats::default());
+    }
+
+    pub fn pad(&self, index: usize) -> &PadStats {
+        &self.pads[index]
+    }
+
+    /// All pads added together
+    pub fn total(&self) -> PadStats {
+        let mut total = PadStats::default();
+        for pad in &self.pads {
+            total.hits += pad.hits;
+            total.velocity_sum += pad.velocity_sum;
+            for (t, v) in total.velocities.iter_mut().zip(pad.velocities) {
+                *t += v;
+            }
+            total.timed_hits += pad.timed_hits;
+            total.offset_sum_ms += pad.offset_sum_ms;
+            total.abs_offset_sum_ms += pad.abs_offset_sum_ms;
+        }
+        total
+    }
+
+    /// Records a hit; `offset_ms` is the distance from the nearest grid step, when a metronome runs.
+    pub fn record(&mut self, pad: usize, velocity: u8, offset_ms: Option<f64>) {
+        let Some(stats) = self.pads.get_mut(pad) else {
+            return;
+        };
+        stats.hits += 1;
+        stats.velocity_sum += velocity as u32;
+        stats.velocities[(velocity as usize * VELOCITY_BUCKETS / 128).min(VELOCITY_BUCKETS - 1)] += 1;
+        if let Some(offset) = offset_ms {
+            stats.timed_hits += 1;
+            stats.offset_sum_ms += offset;
+            stats.abs_offset_sum_ms += offset.abs();
+        }
+    }
+
+    /// One row per pad that was hit, plus a header; velocity buckets are `vel_0_15` and so on.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("pad,hits,mean_velocity,mean_offset_ms,mean_abs_offset_ms");
+        for b in 0..VELOCITY_BUCKETS {
+            let low = b * 128 / VELOCITY_BUCKETS;
+            write!(out, ",vel_{}_{}", low, low + 128 / VELOCITY_BUCKETS - 1).unwrap();
+        }
+        out.push('\n');
+
+        let fmt = |v: Option<f64>| v.map(|v| format!("{:.1}", v)).unwrap_or_default();
+        for (i, pad) in self.pads.iter().enumerate().filter(|(_, p)| p.hits > 0) {
+            write!(
+                out,
+                "{},{},{},{},{}",
+                i + 1,
+                pad.hits,
+                fmt(pad.mean_velocity()),
+                fmt(pad.mean_offset_ms()),
+                fmt(pad.mean_abs_offset_ms()),
+            )
+            .unwrap();
+            for count in pad.velocities {
+                write!(out, ",{}", count).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn export(&self, path: &str) -> std::io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+/// Signed distance in milliseconds from `at` to the nearest step of a grid that starts at
+/// `anchor` and repeats every `step`.
+pub fn grid_offset_ms(anchor: Instant, step: Duration, at: Instant) -> f64 {
+    let step_ms = step.as_secs_f64() * 1000.0;
+    let since = at.saturating_duration_since(anchor).as_secs_f64() * 1000.0;
+    let offset = since % step_ms;
+    if offset > step_ms / 2.0 { offset - step_ms } else { offset }
+}
@@ -0,0 +1,193 @@
+use std::time::Instant;
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::MidiSink;
+use crate::input::HardwareEvent;
+use crate::modes::custom_midi::button_from_name;
+use crate::modes::game::Rng;
+use crate::settings::{ArpPattern, ArpSettings, Settings};
+use crate::step_clock::StepClock;
+
+struct Sounding {
+    channel: u8,
+    note: u8,
+    pad: usize,
+    off_at: Instant,
+}
+
+/// Arpeggiator for Custom MIDI mode: while it's on (a press of `[arp] button`), pads
+/// don't play their note but join the chord the arpeggiator steps through, one note per
+/// step, in `pattern` order across `octaves`. Steps follow the host's MIDI clock while it
+/// runs, and otherwise an internal clock at `[arp] bpm` that starts with the first pad.
+pub struct Arpeggiator {
+    button: Option<Buttons>,
+    on: bool,
+    button_held: bool,
+    /// Pads the arpeggiator took, with their velocity and the pad bank they were hit in,
+    /// in the order they went down
+    held: Vec<(usize, u8, usize)>,
+    /// Pads held down that went to the mode instead, since their reports repeat
+    passing: [bool; 16],
+    /// Steps played since the chord was first held
+    count: usize,
+    clock: StepClock,
+    sounding: Option<Sounding>,
+    rng: Rng,
+}
+
+impl Arpeggiator {
+    pub fn new(settings: &ArpSettings, seed: u64) -> Self {
+        Self {
+            button: if settings.enabled { button_from_name(&settings.button) } else { None },
+            on: false,
+            button_held: false,
+            held: Vec::new(),
+            passing: [false; 16],
+            count: 0,
+            clock: StepClock::new(),
+            sounding: None,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Takes the arpeggiator button and, while it's on, pad hits in `bank`. Returns true
+    /// when the event was taken and shouldn't reach the mode.
+    pub fn handle(&mut self, event: &HardwareEvent, bank: usize) -> bool {
+        match *event {
+            HardwareEvent::Button { index, pressed } if Some(index) == self.button => {
+                if pressed && !self.button_held {
+                    self.on = !self.on;
+                }
+                self.button_held = pressed;
+                true
+            }
+            HardwareEvent::Pad { index, event_type, value } => {
+                let down = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+                match (self.held.iter().position(|(pad, _, _)| *pad == index), down) {
+                    (Some(i), false) => {
+                        self.held.remove(i);
+                        true
+                    }
+                    (Some(_), true) => true,
+                    (None, true) if self.on && !self.passing[index] => {
+                        self.held.push((index, ((value >> 5) as u8).max(1), bank));
+                        true
+                    }
+                    (None, _) => {
+                        self.passing[index] = down;
+                        false
+                    }
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The notes the held chord steps through, lowest first.
+    fn sequence(&self, settings: &Settings) -> Vec<(usize, u8, u8, u8)> {
+        let mut chord: Vec<_> = self
+            .held
+            .iter()
+            .map(|&(pad, velocity, bank)| {
+                let (channel, note) = settings.pad_note_at(pad, bank, velocity);
+                (pad, channel, note, velocity)
+            })
+            .collect();
+        chord.sort_by_key(|&(_, _, note, _)| note);
+        (0..settings.arp.octaves)
+            .flat_map(|octave| chord.iter().map(move |&(pad, channel, note, velocity)| (pad, channel, note as u32 + 12 * octave as u32, velocity)))
+            .filter(|&(_, _, note, _)| note <= 127)
+            .map(|(pad, channel, note, velocity)| (pad, channel, note as u8, velocity))
+            .collect()
+    }
+
+    fn next_index(&mut self, len: usize, pattern: ArpPattern) -> usize {
+        let count = self.count;
+        self.count += 1;
+        match pattern {
+            ArpPattern::Up => count % len,
+            ArpPattern::Down => len - 1 - count % len,
+            ArpPattern::UpDown => {
+                // Turns at the top and bottom without playing them twice.
+                let period = (2 * len).saturating_sub(2).max(1);
+                let position = count % period;
+                if position < len { position } else { period - position }
+            }
+            ArpPattern::Random => self.rng.below(len),
+        }
+    }
+
+    fn note_off(&mut self, midi: &mut dyn MidiSink) -> bool {
+        let Some(sounding) = self.sounding.take() else {
+            return false;
+        };
+        send(midi, sounding.channel, MidiMessage::NoteOff { key: sounding.note.into(), vel: 0.into() });
+        true
+    }
+
+    /// Plays the steps that are due. `clock` is the host's position in MIDI clock ticks
+    /// while it runs and `bpm` its tempo; `active` is false outside Custom MIDI mode,
+    /// which ends the arpeggio. Returns true when the lights changed.
+    pub fn tick(&mut self, clock: Option<u32>, bpm: Option<f32>, active: bool, now: Instant, settings: &Settings, midi: &mut dyn MidiSink) -> bool {
+        if !active {
+            self.held.clear();
+            self.passing = [false; 16];
+        }
+        if !self.on || self.held.is_empty() {
+            self.count = 0;
+            self.clock.reset();
+            return self.note_off(midi);
+        }
+        let mut changed = false;
+        if self.sounding.as_ref().is_some_and(|s| now >= s.off_at) {
+            changed |= self.note_off(midi);
+        }
+
+        let bpm = bpm.unwrap_or(settings.arp.bpm);
+        if !self.clock.step(clock, bpm, settings.arp.rate, now) {
+            return changed;
+        }
+
+        let sequence = self.sequence(settings);
+        if sequence.is_empty() {
+            return changed;
+        }
+        let (pad, channel, note, velocity) = sequence[self.next_index(sequence.len(), settings.arp.pattern)];
+        self.note_off(midi);
+        send(midi, channel, MidiMessage::NoteOn { key: note.into(), vel: velocity.into() });
+        let off_at = now + StepClock::step_length(bpm, settings.arp.rate).mul_f64(settings.arp.gate as f64 / 100.0);
+        self.sounding = Some(Sounding { channel, note, pad, off_at });
+        true
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.on && !self.held.is_empty() || self.sounding.is_some()
+    }
+
+    /// Held pads glow and the one sounding lights up; the button shows whether it's on.
+    pub fn compose_lights(&self, lights: &mut Lights) {
+        if let Some(button) = self.button
+            && lights.button_has_light(button)
+        {
+            lights.set_button(button, if self.on { Brightness::Bright } else { Brightness::Dim });
+        }
+        for &(pad, _, _) in &self.held {
+            lights.set_pad(pad, PadColors::Blue, Brightness::Dim);
+        }
+        if let Some(sounding) = &self.sounding {
+            lights.set_pad(sounding.pad, PadColors::Blue, Brightness::Bright);
+        }
+    }
+}
+
+fn send(midi: &mut dyn MidiSink, channel: u8, message: MidiMessage) {
+    let mut midibuf = Vec::new();
+    if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+        let _ = midi.send(&midibuf[..]);
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+use maschine_library::controls::PadEventType;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, Lights};
+use maschine_library::screen::Screen;
+use midly::{live::LiveEvent, MidiMessage};
+use rosc::{OscMessage, OscType};
+use crate::context::{MidiSink, OscSink};
+use crate::input::HardwareEvent;
+use crate::modes::ButtonEdges;
+use crate::self_test::rainbow_color;
+use crate::settings::AttractSettings;
+
+/// One step of the animation
+const FRAME: Duration = Duration::from_millis(120);
+const SLIDER_LEDS: usize = 25;
+const TEXT_SCALE: usize = 2;
+
+/// Attract loop for installations: after `[attract] idle_s` without anyone touching the
+/// surface, the pads roll through the color wheel, the text bounces across the screen and
+/// the optional MIDI phrase plays, until the next touch ends it at once. That touch still
+/// reaches the mode. `/maschine/attract 1` and `0` mark the start and end, and with
+/// `report_touches` every touch goes out as `/maschine/touch "<control>"`, so an exhibit
+/// can count its visitors.
+pub struct Attract {
+    last_touch: Instant,
+    /// When the loop started, while it runs
+    running_since: Option<Instant>,
+    buttons: ButtonEdges,
+    last_encoder: Option<u8>,
+    slider_touched: bool,
+    /// Next phrase note to play, and when
+    phrase_step: usize,
+    next_note_at: Option<Instant>,
+    sounding: Option<u8>,
+    /// Animation frame last drawn
+    frame: u64,
+}
+
+impl Attract {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            last_touch: now,
+            running_since: None,
+            buttons: ButtonEdges::new(),
+            last_encoder: None,
+            slider_touched: false,
+            phrase_step: 0,
+            next_note_at: None,
+            sounding: None,
+            frame: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /// The control a person just touched, from raw events that repeat every button's
+    /// state in each report; None for those repeats and for releases.
+    fn touch(&mut self, event: &HardwareEvent) -> Option<String> {
+        match *event {
+            HardwareEvent::Button { index, pressed } => {
+                self.buttons.pressed(index, pressed).then(|| format!("{:?}", index).to_lowercase())
+            }
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value } if value > 0 => {
+                Some(format!("pad{}", index + 1))
+            }
+            HardwareEvent::Encoder { value } => {
+                let moved = self.last_encoder.is_some_and(|last| last != value);
+                self.last_encoder = Some(value);
+                moved.then(|| "encoder".to_string())
+            }
+            HardwareEvent::Slider { value } => {
+                let was_touched = std::mem::replace(&mut self.slider_touched, value > 0);
+                (value > 0 && !was_touched).then(|| "slider".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Follows input; returns true when a touch ended the loop.
+    pub fn handle(&mut self, event: &HardwareEvent, now: Instant, settings: &AttractSettings, osc: &dyn OscSink, midi: &mut dyn MidiSink) -> bool {
+        let touched = matches!(event, HardwareEvent::Slider { value } if *value > 0);
+        let Some(control) = self.touch(event) else {
+            // Keeping a finger on the strip keeps the loop away.
+            if touched {
+                self.last_touch = now;
+            }
+            return false;
+        };
+        self.last_touch = now;
+        if settings.report_touches {
+            osc.send(OscMessage { addr: "/maschine/touch".to_string(), args: vec![OscType::String(control)] });
+        }
+        if self.running_since.take().is_none() {
+            return false;
+        }
+        self.note_off(settings, midi);
+        self.next_note_at = None;
+        osc.send_int("/maschine/attract", 0);
+        true
+    }
+
+    /// Starts the loop once the surface has been idle long enough and plays the phrase.
+    /// Returns true when the lights and screen need redrawing.
+    pub fn tick(&mut self, now: Instant, settings: &AttractSettings, osc: &dyn OscSink, midi: &mut dyn MidiSink) -> bool {
+        let Some(since) = self.running_since else {
+            if now.duration_since(self.last_touch) < Duration::from_secs(settings.idle_s) {
+                return false;
+            }
+            self.running_since = Some(now);
+            self.frame = 0;
+            self.phrase_step = 0;
+            self.next_note_at = (!settings.phrase.is_empty()).then_some(now);
+            osc.send_int("/maschine/attract", 1);
+            return true;
+        };
+
+        if let Some(at) = self.next_note_at.filter(|at| now >= *at) {
+            self.note_off(settings, midi);
+            if let Some(note) = settings.phrase.get(self.phrase_step).copied() {
+                Self::send(MidiMessage::NoteOn { key: note.into(), vel: settings.velocity.into() }, settings, midi);
+                self.sounding = Some(note);
+                self.phrase_step += 1;
+                self.next_note_at = Some(at + Duration::from_millis(settings.step_ms));
+            } else {
+                // The phrase is over: it starts again after repeat_s, counted from its start.
+                self.phrase_step = 0;
+                let played = Duration::from_millis(settings.step_ms * settings.phrase.len() as u64);
+                let wait = Duration::from_secs(settings.repeat_s).saturating_sub(played);
+                self.next_note_at = (settings.repeat_s > 0).then_some(at + wait);
+            }
+        }
+
+        let frame = (now.duration_since(since).as_millis() / FRAME.as_millis()) as u64;
+        std::mem::replace(&mut self.frame, frame) != frame
+    }
+
+    fn note_off(&mut self, settings: &AttractSettings, midi: &mut dyn MidiSink) {
+        if let Some(note) = self.sounding.take() {
+            Self::send(MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, settings, midi);
+        }
+    }
+
+    fn send(message: MidiMessage, settings: &AttractSettings, midi: &mut dyn MidiSink) {
+        let mut buf = Vec::new();
+        if (LiveEvent::Midi { channel: (settings.channel - 1).into(), message }).write(&mut buf).is_ok() {
+            let _ = midi.send(&buf);
+        }
+    }
+
+    /// The color wheel rolling diagonally across the pads and a dot running up and down
+    /// the strip, in place of everything else.
+    pub fn compose_lights(&self, lights: &mut Lights) {
+        lights.reset();
+        let frame = self.frame as usize;
+        for i in 0..16 {
+            lights.set_pad(i, rainbow_color(i / 4, i % 4, frame), Brightness::Normal);
+        }
+        let bounce = frame % (2 * (SLIDER_LEDS - 1));
+        let dot = if bounce < SLIDER_LEDS { bounce } else { 2 * (SLIDER_LEDS - 1) - bounce };
+        lights.set_slider(dot, Brightness::Bright);
+    }
+
+    /// `[attract] text` bouncing from side to side.
+    pub fn compose_screen(&self, settings: &AttractSettings) -> Screen {
+        let mut screen = Screen::new();
+        let width = settings.text.chars().count() * 8 * TEXT_SCALE;
+        let room = 128usize.saturating_sub(width);
+        let x = match room {
+            0 => 0,
+            _ => {
+                let bounce = self.frame as usize * 2 % (2 * room);
+                if bounce <= room { bounce } else { 2 * room - bounce }
+            }
+        };
+        Font::write_string(&mut screen, 8, x, &settings.text, TEXT_SCALE);
+        screen
+    }
+}
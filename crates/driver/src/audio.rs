@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use jack::{AsyncClient, AudioIn, Client, ClientOptions, Control, Port, ProcessHandler, ProcessScope};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use crate::settings::AudioSettings;
+
+const SLIDER_LEDS: usize = 25;
+/// How often the meter reads the audio thread's levels
+const METER_PERIOD: Duration = Duration::from_millis(30);
+/// How long the pads flash on an onset
+const ONSET_FLASH: Duration = Duration::from_millis(80);
+/// Weight of a new reading in the slow average onsets are measured against
+const AVERAGE_WEIGHT: f32 = 0.05;
+/// Quieter than this never counts as an onset
+const ONSET_FLOOR: f32 = 0.01;
+/// Share of its height the meter falls per period after a peak
+const FALL_PER_PERIOD: f32 = 0.04;
+const METER_ROWS: std::ops::Range<usize> = 30..32;
+
+/// Loudest sample and loudest period RMS since the meter last looked, as f32 bits.
+/// For positive floats the bit patterns order like the values, so `fetch_max` works.
+#[derive(Default)]
+struct Levels {
+    peak: AtomicU32,
+    rms: AtomicU32,
+}
+
+struct Analyzer {
+    port: Port<AudioIn>,
+    levels: Arc<Levels>,
+}
+
+impl ProcessHandler for Analyzer {
+    fn process(&mut self, _: &Client, scope: &ProcessScope) -> Control {
+        let samples = self.port.as_slice(scope);
+        if !samples.is_empty() {
+            let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+            self.levels.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+            self.levels.rms.fetch_max(rms.to_bits(), Ordering::Relaxed);
+        }
+        Control::Continue
+    }
+}
+
+/// Level meter and beat flash from a JACK input (PipeWire's JACK layer works too): the
+/// slider and pads become a VU meter, every pad flashes on an onset and the bottom of
+/// the screen shows the level, all over what the mode draws.
+pub struct AudioMeter {
+    _client: AsyncClient<(), Analyzer>,
+    levels: Arc<Levels>,
+    next_read: Option<Instant>,
+    /// Shown level, 0 to 1 on the dB scale
+    level: f32,
+    /// Slow average of the RMS, the background onsets stand out from
+    average: f32,
+    flash_until: Option<Instant>,
+    flashing: bool,
+}
+
+impl AudioMeter {
+    /// Registers `maschinette:in` and connects `[audio] port` to it when set.
+    pub fn open(settings: &AudioSettings) -> Result<Self, jack::Error> {
+        let (client, _) = Client::new("maschinette", ClientOptions::NO_START_SERVER)?;
+        let port = client.register_port("in", AudioIn)?;
+        let own_name = port.name()?;
+        let levels = Arc::new(Levels::default());
+        let client = client.activate_async((), Analyzer { port, levels: levels.clone() })?;
+        if let Some(source) = &settings.port {
+            client.as_client().connect_ports_by_name(source, &own_name)?;
+        }
+        Ok(Self { _client: client, levels, next_read: None, level: 0.0, average: 0.0, flash_until: None, flashing: false })
+    }
+
+    /// Takes in what the audio thread measured; returns true when the display changed.
+    pub fn tick(&mut self, now: Instant, settings: &AudioSettings) -> bool {
+        if self.next_read.is_some_and(|at| now < at) {
+            return false;
+        }
+        self.next_read = Some(now + METER_PERIOD);
+        let peak = f32::from_bits(self.levels.peak.swap(0, Ordering::Relaxed));
+        let rms = f32::from_bits(self.levels.rms.swap(0, Ordering::Relaxed));
+
+        let before = (self.leds(), self.flashing);
+        let db = 20.0 * peak.max(1e-6).log10();
+        let level = ((db + settings.range_db) / settings.range_db).clamp(0.0, 1.0);
+        // Up at once, down slowly, like a VU meter.
+        self.level = level.max(self.level - FALL_PER_PERIOD);
+
+        if rms > ONSET_FLOOR && rms > self.average * settings.onset_threshold {
+            self.flash_until = Some(now + ONSET_FLASH);
+        }
+        self.average += (rms - self.average) * AVERAGE_WEIGHT;
+        self.flashing = self.flash_until.is_some_and(|until| now < until);
+        (self.leds(), self.flashing) != before
+    }
+
+    fn leds(&self) -> usize {
+        (self.level * SLIDER_LEDS as f32).round() as usize
+    }
+
+    /// Slider lit from the bottom; pad rows fill from the bottom, green to red, and all
+    /// flash white on an onset.
+    pub fn compose_lights(&self, lights: &mut Lights, settings: &AudioSettings) {
+        if settings.slider {
+            let leds = self.leds();
+            for i in 0..SLIDER_LEDS {
+                lights.set_slider(i, if i < leds { Brightness::Normal } else { Brightness::Off });
+            }
+        }
+        if settings.pads {
+            let rows = (self.level * 4.0).ceil() as usize;
+            for i in 0..16 {
+                // Pad 1 is top-left, so the bottom row is row 3.
+                let row_from_bottom = 3 - i / 4;
+                let (color, brightness) = if self.flashing {
+                    (PadColors::White, Brightness::Bright)
+                } else if row_from_bottom < rows {
+                    let color = [PadColors::Green, PadColors::Lime, PadColors::Yellow, PadColors::Red][row_from_bottom];
+                    (color, Brightness::Normal)
+                } else {
+                    (PadColors::Off, Brightness::Off)
+                };
+                lights.set_pad(i, color, brightness);
+            }
+        }
+    }
+
+    /// A bar along the bottom of the screen
+    pub fn compose_screen(&self, mut screen: Screen) -> Screen {
+        let width = (self.level * 128.0) as usize;
+        for i in METER_ROWS {
+            for j in 0..128 {
+                screen.set(i, j, j < width);
+            }
+        }
+        screen
+    }
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::sequence::NoteSequence;
+
+const PREFIX: &str = "loop-";
+const SUFFIX: &str = ".json";
+
+/// Writes the loop to `dir` as `loop-<unix ms>.json` on a thread of its own, then
+/// deletes the oldest files beyond `keep`. A failed save is reported and otherwise ignored.
+pub fn save(dir: &str, keep: usize, sequence: &NoteSequence) {
+    let dir = PathBuf::from(dir);
+    let json = sequence.to_json();
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    thread::spawn(move || {
+        let path = path(&dir, stamp);
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, json)) {
+            eprintln!("Autosave to {} failed: {}", path.display(), e);
+            return;
+        }
+        for old in list(&dir).iter().skip(keep) {
+            let _ = fs::remove_file(old);
+        }
+    });
+}
+
+/// The file a loop saved at `stamp` (unix ms) goes to
+pub fn path(dir: &Path, stamp: u128) -> PathBuf {
+    dir.join(format!("{PREFIX}{stamp:013}{SUFFIX}"))
+}
+
+/// Autosaves in `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(PREFIX) && n.ends_with(SUFFIX)))
+        .collect();
+    // The stamps are zero-padded, so names sort by age.
+    files.sort();
+    files.reverse();
+    files
+}
+
+pub fn load(path: &Path) -> Result<NoteSequence, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    NoteSequence::from_json(&text)
+}
+
+/// How long ago the file was saved, from the stamp in its name.
+pub fn age(path: &Path) -> Option<Duration> {
+    let name = path.file_name()?.to_str()?;
+    let stamp: u64 = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?.parse().ok()?;
+    SystemTime::now().duration_since(UNIX_EPOCH + Duration::from_millis(stamp)).ok()
+}
@@ -0,0 +1,112 @@
+use hidapi::{HidDevice, HidResult};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use crate::input::{parse_hid_report, HardwareEvent};
+use crate::pad_check::prompt;
+
+/// Full scale of the pads' 12-bit pressure readings.
+pub const PAD_FULL_SCALE: u16 = 4095;
+/// A pad whose softest and hardest hits are closer than this hasn't been played through
+/// its range yet.
+const USABLE_RANGE: u16 = PAD_FULL_SCALE / 2;
+const READ_TIMEOUT_MS: i32 = 20;
+
+/// Stretches a pad's raw reading from its calibrated `[min, max]` onto the full scale,
+/// so every pad reaches the same velocities. A touch never reads as zero.
+pub fn normalize(value: u16, [min, max]: [u16; 2]) -> u16 {
+    if value == 0 {
+        return 0;
+    }
+    let scaled = u32::from(value.saturating_sub(min)) * u32::from(PAD_FULL_SCALE) / u32::from(max - min);
+    (scaled as u16).clamp(1, PAD_FULL_SCALE)
+}
+
+/// Applies `[pad_calibration]` to the pad events of one report, by physical pad.
+pub fn normalize_pads(events: &mut [HardwareEvent], calibration: &HashMap<String, [u16; 2]>) {
+    if calibration.is_empty() {
+        return;
+    }
+    for event in events {
+        if let HardwareEvent::Pad { index, value, .. } = event
+            && let Some(range) = calibration.get(&(*index + 1).to_string())
+        {
+            *value = normalize(*value, *range);
+        }
+    }
+}
+
+/// Measures each pad's softest and hardest hit. Pads that weren't hit are left out.
+pub(crate) fn calibrate_pads(device: &HidDevice, screen: &mut Screen, lights: &mut Lights) -> HidResult<HashMap<String, [u16; 2]>> {
+    let mut ranges: [Option<[u16; 2]>; 16] = [None; 16];
+    let mut play_held = false;
+    let mut buf = [0u8; 64];
+
+    lights.reset();
+    for i in 0..16 {
+        lights.set_pad(i, PadColors::White, Brightness::Dim);
+    }
+    lights.set_button(Buttons::Play, Brightness::Normal);
+    lights.write(device)?;
+    prompt(device, screen, &["PAD CALIBRATION", "EACH PAD: SOFT,", "HARD. PLAY: DONE"])?;
+
+    'measure: loop {
+        let size = device.read_timeout(&mut buf, READ_TIMEOUT_MS)?;
+        let mut changed = false;
+        for event in parse_hid_report(&buf[..size]) {
+            match event {
+                HardwareEvent::Pad { index, event_type, value } if value > 0 => {
+                    let range = ranges[index].get_or_insert([value, value]);
+                    // The softest hit is where a press starts; pressure while held only adds to the top.
+                    if matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn) {
+                        range[0] = range[0].min(value);
+                    }
+                    range[1] = range[1].max(value);
+                    // Normal once hit, bright once played through a usable range.
+                    let level = if range[1] - range[0] >= USABLE_RANGE { Brightness::Bright } else { Brightness::Normal };
+                    lights.set_pad(index, PadColors::Green, level);
+                    changed = true;
+                }
+                HardwareEvent::Button { index: Buttons::Play, pressed } => {
+                    if pressed && !play_held {
+                        break 'measure;
+                    }
+                    play_held = pressed;
+                }
+                _ => {}
+            }
+        }
+        if changed {
+            lights.write(device)?;
+        }
+    }
+
+    lights.reset();
+    lights.write(device)?;
+    screen.reset();
+    screen.write(device)?;
+
+    let mut calibration = HashMap::new();
+    for (i, range) in ranges.iter().enumerate() {
+        match range {
+            Some([min, max]) if min < max => {
+                calibration.insert((i + 1).to_string(), [*min, *max]);
+            }
+            _ => println!("Pad {} wasn't played through a range; leaving it uncalibrated", i + 1),
+        }
+    }
+    Ok(calibration)
+}
+
+/// The measured ranges as a `[pad_calibration]` table, in pad order.
+pub(crate) fn to_toml(calibration: &HashMap<String, [u16; 2]>) -> String {
+    let mut out = String::from("[pad_calibration]\n");
+    for pad in 1..=16 {
+        if let Some([min, max]) = calibration.get(&pad.to_string()) {
+            let _ = writeln!(out, "\"{pad}\" = [{min}, {max}]");
+        }
+    }
+    out
+}
@@ -0,0 +1,44 @@
+use std::time::Instant;
+
+/// Source of time for everything that schedules or measures musical timing.
+/// The driver runs on `SystemClock`; tests substitute `MockClock` to step time by hand.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+#[cfg(test)]
+pub struct MockClock {
+    now: std::cell::Cell<Instant>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new() -> Self {
+        Self { now: std::cell::Cell::new(Instant::now()) }
+    }
+
+    pub fn advance(&self, by: std::time::Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
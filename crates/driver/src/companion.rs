@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::mixxx;
+use crate::modes::reaper;
+use crate::settings::Settings;
+
+const LIVE_SCRIPT: &str = include_str!("../live/Maschinette/__init__.py");
+const LIVE_CHANNEL_LINE: &str = "MIDI_CHANNEL = 15";
+const BITWIG_SCRIPT: &str = include_str!("../bitwig/Maschinette.control.js");
+const BITWIG_CHANNEL_LINE: &str = "var MIDI_CHANNEL = 14;";
+const MIXXX_SCRIPT: &str = include_str!("../mixxx/Maschinette.js");
+
+/// `driver install-live-script`: writes the Maschinette remote script, set to `[live]
+/// channel`, into Live's User Library (or `dir`), replacing an older copy.
+pub fn install_live(dir: Option<&str>, channel: u8) -> Result<(), String> {
+    let dir = folder(dir, "Music/Ableton/User Library/Remote Scripts")?.join("Maschinette");
+    let script = LIVE_SCRIPT.replacen(LIVE_CHANNEL_LINE, &format!("MIDI_CHANNEL = {}", channel - 1), 1);
+    write(&dir, "__init__.py", &script)?;
+    println!("Restart Live, then in Preferences > Link, Tempo & MIDI pick Maschinette as a control surface");
+    println!("with the driver's MIDI ports as input and output, and set [live] enabled = true in the config.");
+    Ok(())
+}
+
+/// `driver install-bitwig-script`: the same for the Bitwig controller script, set to
+/// `[bitwig] channel`, in Bitwig's Controller Scripts folder.
+pub fn install_bitwig(dir: Option<&str>, channel: u8) -> Result<(), String> {
+    let dir = folder(dir, "Documents/Bitwig Studio/Controller Scripts")?.join("Maschinette");
+    let script = BITWIG_SCRIPT.replacen(BITWIG_CHANNEL_LINE, &format!("var MIDI_CHANNEL = {};", channel - 1), 1);
+    write(&dir, "Maschinette.control.js", &script)?;
+    println!("In Bitwig's Settings > Controllers, add Maschinette with the driver's MIDI ports as input and");
+    println!("output, and set [bitwig] enabled = true in the config; the page comes up when Bitwig connects.");
+    Ok(())
+}
+
+/// `driver export-mixxx-mapping`: writes the Mixxx mapping for this config, with its
+/// script, into Mixxx's controllers folder (or `dir`).
+pub fn install_mixxx(dir: Option<&str>, settings: &Settings) -> Result<(), String> {
+    let dir = folder(dir, ".mixxx/controllers")?;
+    write(&dir, "Maschinette.midi.xml", &mixxx::mapping(settings)?)?;
+    write(&dir, "Maschinette.js", MIXXX_SCRIPT)?;
+    if settings.pad_feedback.channel.is_none() {
+        println!("Set [pad_feedback] channel and export again for the pads to light from Mixxx.");
+    }
+    println!("In Mixxx's Preferences > Controllers, pick Maschinette for the driver's MIDI ports, and set");
+    println!("[mixxx] enabled = true in the config.");
+    Ok(())
+}
+
+/// `driver export-reaper-osc`: writes the Reaper page's OSC pattern config into Reaper's
+/// OSC folder (or `dir`).
+pub fn install_reaper(dir: Option<&str>) -> Result<(), String> {
+    let dir = folder(dir, ".config/REAPER/OSC")?;
+    write(&dir, "Maschinette.ReaperOSC", &reaper::pattern_file())?;
+    println!("In Reaper's Preferences > Control/OSC/web, add an OSC device with the Maschinette pattern");
+    println!("config, send to the driver's osc_listen_port and listen on its osc_port, and set");
+    println!("[reaper] enabled = true in the config.");
+    Ok(())
+}
+
+/// `dir`, or `default` under the home folder
+fn folder(dir: Option<&str>, default: &str) -> Result<PathBuf, String> {
+    match dir {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => {
+            let home = std::env::var_os("HOME").ok_or("HOME isn't set; pass --dir")?;
+            Ok(PathBuf::from(home).join(default))
+        }
+    }
+}
+
+fn write(dir: &Path, name: &str, text: &str) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(name);
+    fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))?;
+    println!("Installed {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use config::Config;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::settings::Settings;
+
+/// Editors save in bursts (truncate, write, rename); the reload waits for it to settle.
+const SETTLE: Duration = Duration::from_millis(250);
+
+/// Watches the config file for changes. The folder is watched rather than the file,
+/// since editors that save by renaming a new file over the old one leave a watch on the
+/// file itself looking at nothing.
+pub struct ConfigWatch {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    path: PathBuf,
+    changed_at: Option<Instant>,
+}
+
+impl ConfigWatch {
+    /// `path` as passed to `--config`, where the extension is optional.
+    pub fn new(path: &str) -> Result<Self, String> {
+        let path = PathBuf::from(path);
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(|e| e.to_string())?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive).map_err(|e| format!("{}: {e}", dir.display()))?;
+        Ok(Self { _watcher: watcher, events, path, changed_at: None })
+    }
+
+    fn is_config(&self, changed: &Path) -> bool {
+        let name = self.path.file_name();
+        changed.file_name() == name || (self.path.extension().is_none() && changed.file_stem() == name)
+    }
+
+    /// True once the file changed and then stayed as it is for a moment.
+    pub fn changed(&mut self, now: Instant) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) if !event.kind.is_access() && event.paths.iter().any(|p| self.is_config(p)) => self.changed_at = Some(now),
+                Ok(_) => {}
+                Err(e) => eprintln!("Watching the config: {}", e),
+            }
+        }
+        match self.changed_at {
+            Some(at) if now.duration_since(at) >= SETTLE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reads the file again and checks it, as at startup.
+    pub fn load(&self) -> Result<Settings, String> {
+        let settings: Settings = Config::builder()
+            .add_source(config::File::with_name(&self.path.to_string_lossy()))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .map_err(|e| e.to_string())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+}
@@ -1,13 +1,92 @@
 use maschine_library::lights::Lights;
-use midir::MidiOutputConnection;
-use std::net::{SocketAddr, UdpSocket};
-use crate::settings::Settings;
+use maschine_library::screen::Screen;
+use midir::SendError;
+use rosc::{OscMessage, OscType};
+use crate::clock::Clock;
+use crate::groups::GroupState;
+use crate::midi_queue::QueueStats;
+use crate::settings::{OscFormat, Settings};
+
+/// Destination for raw outgoing MIDI bytes.
+pub trait MidiSink {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError>;
+
+    /// Number of sends that failed in a row; 0 while the backend is healthy.
+    fn consecutive_errors(&self) -> u32 {
+        0
+    }
+
+    /// Tears down and recreates the underlying connection.
+    fn reconnect(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Messages sent since the last call, for sinks that keep them (see `monitor::MidiTap`).
+    fn take_sent(&mut self) -> Vec<Vec<u8>> {
+        Vec::new()
+    }
+
+    /// Sends what waited, for sinks that queue (see `midi_queue::MidiQueue`); once per pass.
+    fn flush(&mut self) {}
+
+    /// Counters of the send queue, for sinks that queue
+    fn queue_stats(&self) -> Option<QueueStats> {
+        None
+    }
+
+    /// Sends a NoteOff for every note still on, for sinks that track them (see
+    /// `note_guard::NoteGuard`).
+    fn release_notes(&mut self) {}
+}
+
+/// Destination for outgoing OSC messages.
+pub trait OscSink {
+    fn send(&self, msg: OscMessage);
+
+    fn send_int(&self, addr: &str, val: i32) {
+        self.send(OscMessage {
+            addr: addr.to_string(),
+            args: vec![OscType::Int(val)],
+        });
+    }
+
+    /// Sends `val` laid out as the mapping's receiver expects.
+    fn send_formatted(&self, addr: &str, val: i32, format: OscFormat) {
+        let (addr, args) = match format {
+            OscFormat::Int => (addr.to_string(), vec![OscType::Int(val)]),
+            OscFormat::Float => (addr.to_string(), vec![OscType::Float(val as f32)]),
+            OscFormat::Bool => (addr.to_string(), vec![OscType::Bool(val != 0)]),
+            OscFormat::String => (addr.to_string(), vec![OscType::String(val.to_string())]),
+            OscFormat::Address => (format!("{}/{}", addr, val), Vec::new()),
+        };
+        self.send(OscMessage { addr, args });
+    }
+}
+
+/// Emulated keyboard/mouse output, for software that can't be MIDI-mapped.
+pub trait UinputSink {
+    /// Mouse wheel clicks; positive is up / right.
+    fn scroll(&mut self, vertical: i32, horizontal: i32);
+
+    /// Presses or releases a Linux input key code (see `evdev::KeyCode`).
+    fn key(&mut self, code: u16, pressed: bool);
+
+    fn tap(&mut self, code: u16) {
+        self.key(code, true);
+        self.key(code, false);
+    }
+}
 
 /// Holds references to the shared resources needed by the driver modes.
 pub struct DriverContext<'a> {
     pub lights: &'a mut Lights,
-    pub midi_port: &'a mut MidiOutputConnection,
-    pub osc_socket: &'a UdpSocket,
-    pub osc_addr: &'a SocketAddr,
+    /// Modes draw below the status bar, from `status::CONTENT_ROW` down.
+    pub screen: &'a mut Screen,
+    pub midi_port: &'a mut dyn MidiSink,
+    pub osc: &'a dyn OscSink,
+    pub uinput: &'a mut dyn UinputSink,
     pub settings: &'a Settings,
-}
\ No newline at end of file
+    pub clock: &'a dyn Clock,
+    /// Toggle and exclusive group states, kept across mode switches.
+    pub groups: &'a mut GroupState,
+}
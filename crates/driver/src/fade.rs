@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+use maschine_library::lights::Lights;
+
+/// Stands between the composed light frames and the device: with a fade time set, each
+/// change walks through the brightness steps instead of switching at once.
+pub struct LightFader {
+    target: Option<Lights>,
+    shown: Option<Lights>,
+    last_step: Option<Instant>,
+}
+
+impl LightFader {
+    pub fn new() -> Self {
+        Self { target: None, shown: None, last_step: None }
+    }
+
+    pub fn set_target(&mut self, frame: Lights) {
+        self.target = Some(frame);
+    }
+
+    /// The frame to write now, if it differs from the one on the device. `fade` is the
+    /// time from off to full brightness; zero switches at once.
+    pub fn next_frame(&mut self, now: Instant, fade: Duration) -> Option<Lights> {
+        let target = self.target.as_ref()?;
+        let Some(shown) = self.shown.as_mut().filter(|_| !fade.is_zero()) else {
+            if self.shown.as_ref() == Some(target) {
+                return None;
+            }
+            self.shown = Some(target.clone());
+            return self.shown.clone();
+        };
+        // Off to bright is three steps.
+        if self.last_step.is_some_and(|t| now.duration_since(t) < fade / 3) {
+            return None;
+        }
+        if !shown.step_towards(target) {
+            return None;
+        }
+        self.last_step = Some(now);
+        Some(shown.clone())
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.shown != self.target
+    }
+}
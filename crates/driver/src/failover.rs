@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+use rosc::{OscMessage, OscPacket, OscType};
+use crate::osc::OscIo;
+use crate::settings::FailoverSettings;
+
+/// Failover for installations: the primary OSC destination gets `/maschine/heartbeat
+/// <count>` every `interval_ms` and should answer `/maschine/heartbeat/ack`. Once it has
+/// been silent for `timeout_ms`, its messages go to the backup instead; the next answer
+/// brings them back.
+pub struct Failover {
+    /// Index of the primary among the OSC targets
+    target: usize,
+    interval: Duration,
+    timeout: Duration,
+    next_beat: Instant,
+    last_ack: Instant,
+    count: i32,
+    failed_over: bool,
+}
+
+impl Failover {
+    pub fn new(target: usize, settings: &FailoverSettings, now: Instant) -> Self {
+        Self {
+            target,
+            interval: Duration::from_millis(settings.interval_ms),
+            timeout: Duration::from_millis(settings.timeout_ms),
+            next_beat: now,
+            last_ack: now,
+            count: 0,
+            failed_over: false,
+        }
+    }
+
+    /// Takes `/maschine/heartbeat/ack`; returns false for any other packet.
+    pub fn handle_ack(&mut self, packet: &[u8], now: Instant) -> bool {
+        match rosc::decoder::decode_udp(packet) {
+            Ok((_, OscPacket::Message(msg))) if msg.addr == "/maschine/heartbeat/ack" => {
+                self.last_ack = now;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sends the heartbeat when it's due and fails over or back. Returns the new state
+    /// when it changed: true for failed over.
+    pub fn tick(&mut self, now: Instant, osc: &OscIo) -> Option<bool> {
+        if now >= self.next_beat {
+            self.count = self.count.wrapping_add(1);
+            let msg = OscMessage { addr: "/maschine/heartbeat".to_string(), args: vec![OscType::Int(self.count)] };
+            osc.send_to_target(self.target, msg);
+            self.next_beat = now + self.interval;
+        }
+        let silent = now.duration_since(self.last_ack) >= self.timeout;
+        if silent == self.failed_over {
+            return None;
+        }
+        self.failed_over = silent;
+        osc.set_failed_over(self.target, silent);
+        Some(silent)
+    }
+}
@@ -0,0 +1,61 @@
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use crate::jsonio::JsonIo;
+
+/// The `--json-io` protocol over two named pipes in `dir`: `events` (out) and `commands`
+/// (in). Scripts can come and go while the driver keeps running.
+pub fn open(dir: &str) -> io::Result<JsonIo> {
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+    let events = make_fifo(&dir.join("events"))?;
+    let commands = make_fifo(&dir.join("commands"))?;
+    // Opened read-write, the pipe always has a writer: no end of file between scripts.
+    let commands = OpenOptions::new().read(true).write(true).open(commands)?;
+    Ok(JsonIo::new(Box::new(FifoWriter { path: events, file: None }), BufReader::new(commands)))
+}
+
+fn make_fifo(path: &Path) -> io::Result<PathBuf> {
+    match fs::metadata(path) {
+        Ok(meta) if meta.file_type().is_fifo() => return Ok(path.to_path_buf()),
+        Ok(_) => return Err(io::Error::new(ErrorKind::AlreadyExists, format!("{} isn't a FIFO", path.display()))),
+        Err(_) => {}
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+    // SAFETY: c_path is a valid NUL-terminated string for the duration of the call.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o660) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Writes to the events pipe while someone reads it and drops lines otherwise, so a
+/// missing or stalled reader never holds up the driver.
+struct FifoWriter {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl Write for FifoWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.file.is_none() {
+            // Without a reader, a non-blocking open fails (ENXIO) instead of waiting for one.
+            self.file = OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(&self.path).ok();
+        }
+        if let Some(file) = &mut self.file
+            && let Err(e) = file.write_all(buf)
+            && e.kind() != ErrorKind::WouldBlock
+        {
+            // The reader went away; reopen once the next one turns up.
+            self.file = None;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use maschine_library::controls::PadEventType;
+use maschine_library::lights::{Brightness, Lights};
+use crate::input::HardwareEvent;
+use crate::modes::custom_midi::button_from_name;
+use crate::settings::FixedVelocitySettings;
+
+/// Fixed velocity, like the hardware's Fixed Vel: `[fixed_velocity] button` turns it on and
+/// off, and while it's on every pad hit plays at `velocity`, or at `accent` while
+/// `accent_button` is held. Hits are rewritten before any mode sees them, so every mode,
+/// the arpeggiator and note repeat play them alike; aftertouch keeps the real pressure.
+pub struct FixedVelocity {
+    on: bool,
+    /// The toggle button as of the last report, since reports repeat every button
+    button_held: bool,
+    accent_held: bool,
+}
+
+impl FixedVelocity {
+    pub fn new() -> Self {
+        Self { on: false, button_held: false, accent_held: false }
+    }
+
+    /// Velocity hits play at right now, while it's on
+    pub fn level(&self, settings: &FixedVelocitySettings) -> Option<u8> {
+        self.on.then_some(if self.accent_held { settings.accent } else { settings.velocity })
+    }
+
+    /// Takes the toggle button out of the events and sets the velocity of pad hits.
+    /// Returns the new state when the button turned it on or off.
+    pub fn apply(&mut self, events: &mut Vec<HardwareEvent>, settings: &FixedVelocitySettings) -> Option<bool> {
+        if !settings.enabled {
+            return None;
+        }
+        let button = button_from_name(&settings.button);
+        let accent_button = button_from_name(&settings.accent_button);
+        let mut toggled = None;
+        events.retain(|event| match *event {
+            HardwareEvent::Button { index, pressed } if Some(index) == button => {
+                let was_held = std::mem::replace(&mut self.button_held, pressed);
+                if pressed && !was_held {
+                    self.on = !self.on;
+                    toggled = Some(self.on);
+                }
+                false
+            }
+            HardwareEvent::Button { index, pressed } if Some(index) == accent_button => {
+                self.accent_held = pressed;
+                true
+            }
+            _ => true,
+        });
+        if let Some(velocity) = self.level(settings) {
+            for event in events.iter_mut() {
+                if let HardwareEvent::Pad { event_type: PadEventType::NoteOn | PadEventType::PressOn, value, .. } = event
+                    && *value > 0
+                {
+                    *value = (velocity as u16) << 5;
+                }
+            }
+        }
+        toggled
+    }
+
+    /// Lights the toggle button while it's on, over whatever the mode does with it.
+    pub fn compose_lights(&self, lights: &mut Lights, settings: &FixedVelocitySettings) {
+        if self.on
+            && let Some(button) = button_from_name(&settings.button)
+        {
+            lights.set_button(button, Brightness::Bright);
+        }
+    }
+}
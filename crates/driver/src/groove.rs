@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+
+/// Steps of the grid a groove is measured on, per quarter note
+const STEPS_PER_BEAT: u32 = 4;
+/// Past this the off-beat 16ths crowd the next beat.
+pub const MAX_SWING: u8 = 75;
+
+/// How one 16th of a groove is played: early or late by a fraction of the step, and
+/// louder or softer than the groove's average
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GrooveStep {
+    shift: f64,
+    velocity: f64,
+}
+
+impl Default for GrooveStep {
+    fn default() -> Self {
+        Self { shift: 0.0, velocity: 1.0 }
+    }
+}
+
+/// The feel of a played or programmed pattern, taken from a MIDI file: for each 16th of
+/// it, how far off the grid its notes fall and how hard they are compared with the rest.
+/// Applied to a loop, it moves each note by its step's shift and scales its velocity,
+/// without touching the recorded notes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    pub name: String,
+    steps: Vec<GrooveStep>,
+}
+
+impl Groove {
+    /// Reads the note ons of every track. The groove is as long as the file, in whole
+    /// steps, or else runs to the end of the beat of its last note.
+    pub fn from_midi(name: &str, bytes: &[u8]) -> Result<Self, String> {
+        let smf = Smf::parse(bytes).map_err(|e| e.to_string())?;
+        let Timing::Metrical(ppq) = smf.header.timing else {
+            return Err("the file counts time in SMPTE frames rather than beats".to_string());
+        };
+        let step_ticks = ppq.as_int() as f64 / STEPS_PER_BEAT as f64;
+        let mut notes: Vec<(u32, u8)> = Vec::new();
+        let mut end = 0;
+        for track in &smf.tracks {
+            let mut tick = 0u32;
+            for event in track {
+                tick += event.delta.as_int();
+                match event.kind {
+                    TrackEventKind::Midi { message: MidiMessage::NoteOn { vel, .. }, .. } if vel > 0 => notes.push((tick, vel.as_int())),
+                    TrackEventKind::Meta(MetaMessage::EndOfTrack) => end = end.max(tick),
+                    _ => {}
+                }
+            }
+        }
+        if notes.is_empty() {
+            return Err("the file has no notes".to_string());
+        }
+        let step_of = |tick: u32| (tick as f64 / step_ticks).round() as usize;
+        let last = notes.iter().map(|&(tick, _)| step_of(tick)).max().unwrap_or(0);
+        let length = match (end as f64 / step_ticks).round() as usize {
+            steps if steps > last => steps,
+            _ => (last + 1).next_multiple_of(STEPS_PER_BEAT as usize),
+        };
+        let average = notes.iter().map(|&(_, vel)| vel as f64).sum::<f64>() / notes.len() as f64;
+
+        // Notes falling on the same step average out.
+        let mut sums = vec![(0.0, 0.0, 0u32); length];
+        for &(tick, vel) in &notes {
+            let step = step_of(tick);
+            let sum = &mut sums[step % length];
+            sum.0 += tick as f64 / step_ticks - step as f64;
+            sum.1 += vel as f64 / average;
+            sum.2 += 1;
+        }
+        let steps = sums
+            .into_iter()
+            .map(|(shift, velocity, count)| match count {
+                0 => GrooveStep::default(),
+                n => GrooveStep { shift: shift / n as f64, velocity: velocity / n as f64 },
+            })
+            .collect();
+        Ok(Self { name: name.to_string(), steps })
+    }
+
+    /// The groove's step nearest to `offset`, the pattern repeating as often as it fits
+    fn step_at(&self, offset: Duration, step: Duration) -> GrooveStep {
+        let index = (offset.as_secs_f64() / step.as_secs_f64().max(f64::EPSILON)).round() as usize;
+        self.steps[index % self.steps.len()]
+    }
+
+    /// Where a note recorded at `offset` plays, with `step` the length of a 16th of the
+    /// loop and `amount` how much of the groove to apply, 0 to 1. Never before the top.
+    pub fn shift(&self, offset: Duration, step: Duration, amount: f64) -> Duration {
+        let groove = self.step_at(offset, step);
+        let moved = offset.as_secs_f64() + groove.shift * amount * step.as_secs_f64();
+        Duration::from_secs_f64(moved.max(0.0))
+    }
+
+    pub fn velocity(&self, offset: Duration, step: Duration, velocity: u8, amount: f64) -> u8 {
+        let groove = self.step_at(offset, step);
+        let scale = 1.0 + (groove.velocity - 1.0) * amount;
+        (velocity as f64 * scale).round().clamp(1.0, 127.0) as u8
+    }
+}
+
+/// Where a note at `offset` plays with `swing` percent of swing, `step` being a 16th:
+/// the second 16th of each 8th comes later by that much of a step, and everything in
+/// between stretches to fit, so notes keep their order.
+pub fn swing(offset: Duration, step: Duration, swing: u8) -> Duration {
+    if swing == 0 {
+        return offset;
+    }
+    let pair = step.as_secs_f64().max(f64::EPSILON) * 2.0;
+    let at = offset.as_secs_f64() / pair;
+    let into = at - at.floor();
+    let off_beat = 0.5 * (1.0 + swing as f64 / 100.0);
+    let moved = if into < 0.5 { into / 0.5 * off_beat } else { off_beat + (into - 0.5) / 0.5 * (1.0 - off_beat) };
+    Duration::from_secs_f64((at.floor() + moved) * pair)
+}
+
+/// The MIDI files in `dir`, by name
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mid") || e.eq_ignore_ascii_case("midi")))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Loads every groove in `dir`, named after its file; a file that doesn't read is
+/// reported and left out.
+pub fn load_all(dir: &str) -> Vec<Groove> {
+    let mut grooves = Vec::new();
+    for path in list(Path::new(dir)) {
+        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("?").to_uppercase();
+        match fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| Groove::from_midi(&name, &bytes)) {
+            Ok(groove) => grooves.push(groove),
+            Err(e) => eprintln!("Ignoring the groove {}: {}", path.display(), e),
+        }
+    }
+    grooves
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::DriverContext;
+use crate::modes::custom_midi::button_from_name;
+use crate::settings::{ButtonMode, GroupConfig, Settings};
+
+/// Toggle button states and their exclusive groups, and the pad bank in use, shared by
+/// every mode through the driver context so they outlive mode switches.
+pub struct GroupState {
+    toggle_states: HashMap<Buttons, bool>,
+    /// Member names per group id, sorted
+    groups: HashMap<u8, Vec<String>>,
+    /// 0-based, see `[pad_banks]`
+    bank: usize,
+}
+
+impl GroupState {
+    pub fn new(settings: &Settings) -> Self {
+        let mut groups: HashMap<u8, Vec<String>> = HashMap::new();
+        for (button_name, config) in settings.button_configs.iter() {
+            if config.mode == ButtonMode::Toggle
+                && let Some(group_id) = config.group_id
+            {
+                groups.entry(group_id).or_default().push(button_name.clone());
+            }
+        }
+        // Settings come from a HashMap; sort so group announcements go out in a stable order.
+        for members in groups.values_mut() {
+            members.sort();
+        }
+
+        let mut state = Self { toggle_states: HashMap::new(), groups, bank: 0 };
+        state.apply_defaults(settings);
+        state
+    }
+
+    pub fn config(settings: &Settings, group_id: u8) -> Option<&GroupConfig> {
+        settings.group_configs.get(&group_id.to_string())
+    }
+
+    pub fn is_on(&self, button: Buttons) -> bool {
+        self.toggle_states.get(&button).copied().unwrap_or(false)
+    }
+
+    /// Sets a toggle in one step with the rest of its group: switching a member on
+    /// switches the others off. Returns the names of the members that went off.
+    pub fn set(&mut self, button: Buttons, on: bool, settings: &Settings) -> Vec<String> {
+        let name = format!("{:?}", button);
+        let mut switched_off = Vec::new();
+        if on
+            && let Some(group_id) = settings.button_configs.get(&name).and_then(|c| c.group_id)
+            && let Some(members) = self.groups.get(&group_id)
+        {
+            for other_name in members {
+                if other_name != &name
+                    && let Some(other) = button_from_name(other_name)
+                {
+                    self.toggle_states.insert(other, false);
+                    switched_off.push(other_name.clone());
+                }
+            }
+        }
+        self.toggle_states.insert(button, on);
+        switched_off
+    }
+
+    /// Builds the groups over from a reloaded config. Buttons that are still toggles keep
+    /// their state, through their new groups, and the bank stays if the config has it.
+    pub fn reload(&mut self, settings: &Settings) {
+        let old = std::mem::replace(self, Self::new(settings));
+        self.bank = old.bank.min(settings.bank_count() - 1);
+        let mut kept: Vec<(Buttons, bool)> = old
+            .toggle_states
+            .into_iter()
+            .filter(|(button, _)| settings.button_configs.get(&format!("{:?}", button)).is_some_and(|c| c.mode == ButtonMode::Toggle))
+            .collect();
+        // Those that were on go last, so switching one on settles its new group.
+        kept.sort_by_key(|&(_, on)| on);
+        for (button, on) in kept {
+            self.set(button, on, settings);
+        }
+    }
+
+    /// Selects each group's default member and switches the others off.
+    pub fn apply_defaults(&mut self, settings: &Settings) {
+        for (group_id, member_names) in &self.groups {
+            let Some(default) = Self::config(settings, *group_id).and_then(|g| g.default.as_ref()) else {
+                continue;
+            };
+            for name in member_names {
+                if let Some(button) = button_from_name(name) {
+                    self.toggle_states.insert(button, name.eq_ignore_ascii_case(default));
+                }
+            }
+        }
+    }
+
+    /// 1-based position of the active member in the (sorted) group, 0 when none is on.
+    pub fn value(&self, group_id: u8) -> i32 {
+        self.groups
+            .get(&group_id)
+            .and_then(|members| members.iter().position(|name| button_from_name(name).is_some_and(|b| self.is_on(b))))
+            .map_or(0, |pos| pos as i32 + 1)
+    }
+
+    pub fn bank(&self) -> usize {
+        self.bank
+    }
+
+    pub fn set_bank(&mut self, bank: usize) {
+        self.bank = bank;
+    }
+
+    pub fn sorted_ids(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.groups.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn draw(&self, lights: &mut Lights) {
+        for (button, is_active) in &self.toggle_states {
+            lights.set_button(*button, if *is_active { Brightness::Bright } else { Brightness::Off });
+        }
+    }
+
+    /// Sends `/maschine/group/<id> <value>` (and the group CC), optionally preceded by every member's state.
+    pub fn announce(ctx: &mut DriverContext, group_id: u8, with_members: bool) {
+        let groups = &*ctx.groups;
+        if with_members && let Some(member_names) = groups.groups.get(&group_id) {
+            for name in member_names {
+                let on = button_from_name(name).is_some_and(|b| groups.is_on(b));
+                let format = ctx.settings.button_osc_format(name);
+                ctx.osc.send_formatted(&format!("/maschine/{}", name.to_lowercase()), if on { 1 } else { 0 }, format);
+            }
+        }
+        let value = groups.value(group_id);
+        ctx.osc.send_int(&format!("/maschine/group/{}", group_id), value);
+        if let Some(config) = Self::config(ctx.settings, group_id)
+            && let Some(cc) = config.cc
+        {
+            let message = MidiMessage::Controller { controller: cc.into(), value: (value as u8).into() };
+            let channel = ctx.settings.channel(config.channel);
+            let mut midibuf = Vec::new();
+            if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+                let _ = ctx.midi_port.send(&midibuf[..]);
+            }
+        }
+    }
+}
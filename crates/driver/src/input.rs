@@ -1,4 +1,15 @@
 use maschine_library::controls::{Buttons, PadEventType};
+use midly::{live::LiveEvent, MidiMessage};
+use rosc::{OscMessage, OscType};
+use crate::light_frame::LightFrame;
+use crate::mixxx;
+use crate::modes::bitwig::{self, BitwigMessage};
+use crate::modes::clock_in::{self, ClockMessage};
+use crate::modes::live::{self, LiveMessage};
+use crate::modes::reaper::{self, ReaperMessage};
+use crate::modes::custom_midi::button_from_name;
+use crate::sequence::{LoopFormat, NoteSequence};
+use crate::settings::{PadGridSettings, RemapSettings};
 
 #[derive(Debug, Clone)]
 pub enum HardwareEvent {
@@ -8,6 +19,171 @@ pub enum HardwareEvent {
     Slider { value: u8 },
 }
 
+/// Feedback from the host (DAW, SuperCollider, ...) about state the driver mirrors.
+#[derive(Debug, Clone)]
+pub enum HostEvent {
+    /// An incoming OSC message with a numeric first argument, e.g. `/maschine/select 1`.
+    Osc { addr: String, value: i32 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    /// Note on or off (velocity 0) from the host.
+    Note { channel: u8, note: u8, velocity: u8 },
+    /// A note sequence to load into the looper (`/maschine/loop/load`).
+    LoadLoop(NoteSequence),
+    /// The host wants the current loop back (`/maschine/loop/dump`).
+    DumpLoop(LoopFormat),
+    /// Recall a light scene by name (`/maschine/scene "verse"`), optionally with its own fade.
+    Scene { name: String, fade_ms: Option<u64> },
+    /// Program change on any channel
+    ProgramChange { program: u8 },
+    /// The host asks for the list of OSC addresses (`/maschine/namespace`).
+    Namespace,
+    /// The host asks for the driver's counters (`/maschine/metrics`).
+    Metrics,
+    /// Run a named macro (`/maschine/macro "chorus"`).
+    Macro { name: String },
+    /// A whole LED frame in one blob (`/maschine/lights/frame`).
+    LightFrame(LightFrame),
+    /// Recall a performance snapshot, 0-based (Group+pad, `/maschine/snapshot 1-4`).
+    Snapshot { slot: usize },
+    /// Session state from the Live remote script
+    Live(LiveMessage),
+    /// Device state from the Bitwig extension
+    Bitwig(BitwigMessage),
+    /// Track state from Reaper's OSC control surface
+    Reaper(ReaperMessage),
+    /// Mixxx loaded the mapping from `export-mixxx-mapping`.
+    MixxxHello,
+    /// Clock and transport from the MIDI input, for `[clock_in]`
+    Clock(ClockMessage),
+    /// A live-coding client registered with `/maschine/hello` and wants the state.
+    Hello,
+}
+
+/// Feedback in an OSC message that isn't one of the driver's own commands: Reaper's
+/// track state, or a numeric value for the modes.
+pub fn parse_osc_feedback(msg: &OscMessage) -> Option<HostEvent> {
+    if let Some(message) = reaper::parse(msg) {
+        return Some(HostEvent::Reaper(message));
+    }
+    match msg.args.first()? {
+        OscType::Int(i) => Some(HostEvent::Osc { addr: msg.addr.clone(), value: *i }),
+        OscType::Float(f) => Some(HostEvent::Osc { addr: msg.addr.clone(), value: f.round() as i32 }),
+        _ => None,
+    }
+}
+
+/// Parses one message received on the MIDI input port; only what modes react to is kept.
+pub fn parse_midi_input(bytes: &[u8]) -> Option<HostEvent> {
+    if let Some(message) = live::parse(bytes) {
+        return Some(HostEvent::Live(message));
+    }
+    if let Some(message) = bitwig::parse(bytes) {
+        return Some(HostEvent::Bitwig(message));
+    }
+    if bytes == mixxx::HELLO {
+        return Some(HostEvent::MixxxHello);
+    }
+    if let Some(message) = clock_in::parse(bytes) {
+        return Some(HostEvent::Clock(message));
+    }
+    match LiveEvent::parse(bytes).ok()? {
+        LiveEvent::Midi { channel, message: MidiMessage::Controller { controller, value } } => {
+            Some(HostEvent::ControlChange { channel: channel.into(), controller: controller.into(), value: value.into() })
+        }
+        LiveEvent::Midi { channel, message: MidiMessage::NoteOn { key, vel } } => {
+            Some(HostEvent::Note { channel: channel.into(), note: key.into(), velocity: vel.into() })
+        }
+        LiveEvent::Midi { channel, message: MidiMessage::NoteOff { key, .. } } => {
+            Some(HostEvent::Note { channel: channel.into(), note: key.into(), velocity: 0 })
+        }
+        LiveEvent::Midi { message: MidiMessage::ProgramChange { program }, .. } => {
+            Some(HostEvent::ProgramChange { program: program.into() })
+        }
+        _ => None,
+    }
+}
+
+/// Pad value a button standing in for a pad hits with (velocity 100).
+const BUTTON_PAD_VALUE: u16 = 100 << 5;
+
+/// Applies the remap table to each report's events, before any mode sees them. Buttons
+/// mapped onto the same control read as pressed while any of them is held.
+pub struct InputRemap {
+    /// Buttons playing a pad, as of the last report
+    held_as_pad: [bool; 41],
+}
+
+impl InputRemap {
+    pub fn new() -> Self {
+        Self { held_as_pad: [false; 41] }
+    }
+
+    pub fn apply(&mut self, events: Vec<HardwareEvent>, remap: &RemapSettings) -> Vec<HardwareEvent> {
+        if remap.is_empty() {
+            return events;
+        }
+        let mut out: Vec<HardwareEvent> = Vec::with_capacity(events.len());
+        for event in events {
+            self.apply_one(event, remap, &mut out);
+        }
+        out
+    }
+
+    fn apply_one(&mut self, event: HardwareEvent, remap: &RemapSettings, out: &mut Vec<HardwareEvent>) {
+        match event {
+            HardwareEvent::Button { index, pressed } => {
+                let name = format!("{:?}", index);
+                // Reports repeat every button's state; a pad only hears the changes.
+                if let Some((_, pad)) = remap.button_pads.iter().find(|(from, _)| from.eq_ignore_ascii_case(&name)) {
+                    if std::mem::replace(&mut self.held_as_pad[index as usize], pressed) != pressed {
+                        let (event_type, value) =
+                            if pressed { (PadEventType::PressOn, BUTTON_PAD_VALUE) } else { (PadEventType::PressOff, 0) };
+                        out.push(HardwareEvent::Pad { index: *pad as usize - 1, event_type, value });
+                    }
+                    return;
+                }
+                let target = match remap.buttons.iter().find(|(from, _)| from.eq_ignore_ascii_case(&name)) {
+                    Some((_, to)) => match button_from_name(to) {
+                        Some(button) => button,
+                        None => return,
+                    },
+                    None => index,
+                };
+                let merged = out.iter_mut().find_map(|e| match e {
+                    HardwareEvent::Button { index, pressed } if *index == target => Some(pressed),
+                    _ => None,
+                });
+                match merged {
+                    Some(held) => *held |= pressed,
+                    None => out.push(HardwareEvent::Button { index: target, pressed }),
+                }
+            }
+            HardwareEvent::Pad { index, event_type, value } => match remap.pads.get(&(index + 1).to_string()) {
+                Some(0) => {}
+                Some(pad) => out.push(HardwareEvent::Pad { index: *pad as usize - 1, event_type, value }),
+                None => out.push(event),
+            },
+            HardwareEvent::Encoder { value } if remap.reverse_encoder => {
+                // Mirroring the 4-bit position turns it the other way; 0 means no reading.
+                out.push(HardwareEvent::Encoder { value: if value == 0 { 0 } else { 16 - value } });
+            }
+            _ => out.push(event),
+        }
+    }
+}
+
+/// Renumbers pads from their physical position to where the player sees them.
+pub fn orient_pads(events: &mut [HardwareEvent], grid: &PadGridSettings) {
+    if grid.is_identity() {
+        return;
+    }
+    for event in events {
+        if let HardwareEvent::Pad { index, .. } = event {
+            *index = grid.to_logical(*index);
+        }
+    }
+}
+
 /// Parses the raw HID report buffer into a vector of high-level events.
 pub fn parse_hid_report(buf: &[u8]) -> Vec<HardwareEvent> {
     let mut events = Vec::new();
@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::os::fd::FromRawFd;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use maschine_library::controls::PadEventType;
+use maschine_library::lights::{Brightness, Lights};
+use serde::Deserialize;
+use serde_json::json;
+use crate::input::HardwareEvent;
+use crate::modes::custom_midi::button_from_name;
+use crate::modes::{encoder_direction, ButtonEdges};
+use crate::settings::ColorName;
+
+/// Light level in a command; unlike the config's, it can switch a light off.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Dim,
+    Normal,
+    Bright,
+}
+
+impl Level {
+    fn brightness(self) -> Brightness {
+        match self {
+            Level::Off => Brightness::Off,
+            Level::Dim => Brightness::Dim,
+            Level::Normal => Brightness::Normal,
+            Level::Bright => Brightness::Bright,
+        }
+    }
+}
+
+/// One command line, e.g. `{"cmd": "pad", "pad": 1, "color": "red", "level": "bright"}`,
+/// `{"cmd": "button", "button": "Play", "level": "off"}`, `{"cmd": "screen", "text": "HI"}`
+/// or `{"cmd": "midi", "bytes": [144, 60, 100]}`. Pads are numbered 1-16.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum JsonCommand {
+    Pad { pad: usize, color: ColorName, level: Level },
+    Button { button: String, level: Level },
+    Slider { led: usize, level: Level },
+    Screen { text: String },
+    Midi { bytes: Vec<u8> },
+}
+
+impl JsonCommand {
+    /// Sets the light the command names; false for anything that isn't a light or is out of range.
+    pub fn apply_light(&self, lights: &mut Lights) -> bool {
+        match self {
+            JsonCommand::Pad { pad, color, level } if (1..=16).contains(pad) => {
+                lights.set_pad(pad - 1, color.pad_color(), level.brightness());
+                true
+            }
+            JsonCommand::Button { button, level } => match button_from_name(button) {
+                Some(button) if lights.button_has_light(button) => {
+                    lights.set_button(button, level.brightness());
+                    true
+                }
+                _ => false,
+            },
+            JsonCommand::Slider { led, level } if *led < 25 => {
+                lights.set_slider(*led, level.brightness());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Line-based JSON link to an external process: control events go out as one JSON object
+/// per line, commands come in the same way. Runs over stdio or named pipes (`fifo`).
+pub struct JsonIo {
+    out: Box<dyn Write>,
+    commands: Receiver<String>,
+    buttons: ButtonEdges,
+    last_encoder: u8,
+    last_slider: u8,
+}
+
+impl JsonIo {
+    /// Takes over stdout for events and stdin for commands. The driver's own log lines
+    /// move to stderr so they can't end up in the event stream.
+    pub fn stdio() -> io::Result<Self> {
+        // SAFETY: plain descriptor calls; the duplicate of fd 1 is owned by the File alone.
+        let events = unsafe {
+            let fd = libc::dup(libc::STDOUT_FILENO);
+            if fd < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_fd(fd)
+        };
+        Ok(Self::new(Box::new(LineWriter::new(events)), BufReader::new(io::stdin())))
+    }
+
+    pub fn new(out: Box<dyn Write>, input: impl BufRead + Send + 'static) -> Self {
+        let (tx, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { out, commands, buttons: ButtonEdges::new(), last_encoder: 0, last_slider: 0 }
+    }
+
+    /// Writes the event, if it says anything new: button reports repeat every button's
+    /// state, and the encoder and slider their position.
+    pub fn emit(&mut self, event: &HardwareEvent) {
+        let line = match *event {
+            HardwareEvent::Button { index, pressed } => {
+                if !self.buttons.changed(index, pressed) {
+                    return;
+                }
+                json!({ "event": "button", "button": format!("{:?}", index), "pressed": pressed })
+            }
+            HardwareEvent::Pad { index, event_type, value } => {
+                json!({ "event": "pad", "pad": index + 1, "type": pad_event_name(event_type), "value": value })
+            }
+            HardwareEvent::Encoder { value } => {
+                // The first reading only sets where the encoder starts.
+                let direction = if self.last_encoder == 0 { 0 } else { encoder_direction(self.last_encoder, value) };
+                if value != 0 {
+                    self.last_encoder = value;
+                }
+                if direction == 0 {
+                    return;
+                }
+                json!({ "event": "encoder", "value": value, "direction": direction })
+            }
+            HardwareEvent::Slider { value } => {
+                if std::mem::replace(&mut self.last_slider, value) == value {
+                    return;
+                }
+                json!({ "event": "slider", "value": value })
+            }
+        };
+        self.write_line(&line);
+    }
+
+    /// Commands received since the last call. Lines that don't parse are answered with
+    /// an `{"error": ...}` line.
+    pub fn poll(&mut self) -> Vec<JsonCommand> {
+        let mut commands = Vec::new();
+        while let Ok(line) = self.commands.try_recv() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(command) => commands.push(command),
+                Err(e) => self.write_line(&json!({ "error": e.to_string(), "line": line })),
+            }
+        }
+        commands
+    }
+
+    fn write_line(&mut self, value: &serde_json::Value) {
+        // One write per line, so a pipe gets each line whole.
+        let line = format!("{}\n", value);
+        // A reader that went away mustn't stop the driver.
+        let _ = self.out.write_all(line.as_bytes());
+    }
+}
+
+fn pad_event_name(event_type: PadEventType) -> &'static str {
+    match event_type {
+        PadEventType::NoteOn => "note_on",
+        PadEventType::NoteOff => "note_off",
+        PadEventType::Aftertouch => "aftertouch",
+        PadEventType::PressOn => "press_on",
+        PadEventType::PressOff => "press_off",
+    }
+}
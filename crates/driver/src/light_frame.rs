@@ -0,0 +1,84 @@
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+
+const PAD_BYTES: usize = 16 * 2;
+/// Buttons with a light: everything before the encoder
+const BUTTON_COUNT: usize = Buttons::EncoderPress as usize;
+const SLIDER_COUNT: usize = 25;
+
+/// A whole LED frame in one `/maschine/lights/frame` blob, so a visualizer can repaint
+/// everything in a single message:
+///
+/// - 16 pads, 2 bytes each: color (0-17, as in `PadColors`), then level
+/// - optionally 39 buttons, one level byte each, in `Buttons` order
+/// - after the buttons, optionally the 25 slider LEDs, one level byte each
+///
+/// Levels are 0 off, 1 dim, 2 normal, 3 bright. The blob is 32, 71 or 96 bytes long;
+/// parts left out keep their lights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightFrame {
+    pub pads: [(PadColors, Brightness); 16],
+    pub buttons: Option<[Brightness; BUTTON_COUNT]>,
+    pub slider: Option<[Brightness; SLIDER_COUNT]>,
+}
+
+impl LightFrame {
+    pub fn from_blob(data: &[u8]) -> Result<Self, String> {
+        let buttons_end = PAD_BYTES + BUTTON_COUNT;
+        if ![PAD_BYTES, buttons_end, buttons_end + SLIDER_COUNT].contains(&data.len()) {
+            return Err(format!(
+                "a light frame is {} (pads), {} (and buttons) or {} bytes (and slider), not {}",
+                PAD_BYTES,
+                buttons_end,
+                buttons_end + SLIDER_COUNT,
+                data.len()
+            ));
+        }
+        let mut pads = [(PadColors::Off, Brightness::Off); 16];
+        for (pad, bytes) in pads.iter_mut().zip(data[..PAD_BYTES].chunks_exact(2)) {
+            let color = num::FromPrimitive::from_u8(bytes[0]).ok_or(format!("no pad color {}", bytes[0]))?;
+            *pad = (color, level(bytes[1])?);
+        }
+        let buttons = match data.get(PAD_BYTES..buttons_end) {
+            Some(bytes) => Some(levels(bytes)?),
+            None => None,
+        };
+        let slider = match data.get(buttons_end..) {
+            Some(bytes) if !bytes.is_empty() => Some(levels(bytes)?),
+            _ => None,
+        };
+        Ok(Self { pads, buttons, slider })
+    }
+
+    pub fn apply(&self, lights: &mut Lights) {
+        for (i, (color, brightness)) in self.pads.iter().enumerate() {
+            lights.set_pad(i, *color, *brightness);
+        }
+        for (i, brightness) in self.buttons.iter().flatten().enumerate() {
+            if let Some(button) = num::FromPrimitive::from_usize(i) {
+                lights.set_button(button, *brightness);
+            }
+        }
+        for (i, brightness) in self.slider.iter().flatten().enumerate() {
+            lights.set_slider(i, *brightness);
+        }
+    }
+}
+
+fn level(byte: u8) -> Result<Brightness, String> {
+    match byte {
+        0 => Ok(Brightness::Off),
+        1 => Ok(Brightness::Dim),
+        2 => Ok(Brightness::Normal),
+        3 => Ok(Brightness::Bright),
+        _ => Err(format!("light levels are 0 to 3, not {byte}")),
+    }
+}
+
+fn levels<const N: usize>(bytes: &[u8]) -> Result<[Brightness; N], String> {
+    let mut out = [Brightness::Off; N];
+    for (slot, byte) in out.iter_mut().zip(bytes) {
+        *slot = level(*byte)?;
+    }
+    Ok(out)
+}
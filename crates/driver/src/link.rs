@@ -0,0 +1,129 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use crate::settings::LinkSettings;
+
+const CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// How often the session's beat is asked for, to keep up with tempo changes
+const POLL: Duration = Duration::from_millis(250);
+const RECONNECT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The state of the Link session as of one status message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkState {
+    pub peers: u32,
+    pub bpm: f64,
+    /// Beats on the session's timeline when the status was sent
+    pub beat: f64,
+}
+
+/// Ableton Link through Carabiner, the Link bridge from Deep Symmetry: it joins the session
+/// on the driver's behalf and answers `status` on a local TCP port with the tempo, the
+/// peer count and where the shared timeline is. Carabiner not running just means no
+/// session; the driver tries again every few seconds.
+pub struct Link {
+    address: String,
+    stream: Option<TcpStream>,
+    received: String,
+    next_poll: Instant,
+    next_connect: Instant,
+    /// The newest status, and when it came
+    last: Option<(LinkState, Instant)>,
+    /// A failed connection was reported; the retries stay quiet until one works.
+    warned: bool,
+}
+
+impl Link {
+    pub fn new(settings: &LinkSettings, now: Instant) -> Self {
+        Self { address: settings.carabiner.clone(), stream: None, received: String::new(), next_poll: now, next_connect: now, last: None, warned: false }
+    }
+
+    fn connect(&mut self) -> Result<TcpStream, String> {
+        let address = self.address.to_socket_addrs().ok().and_then(|mut a| a.next()).ok_or_else(|| format!("can't resolve {}", self.address))?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).map_err(|e| e.to_string())?;
+        stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(stream)
+    }
+
+    /// Keeps the connection up, asks for the status when due and reads the answers.
+    /// Returns the newest status that arrived.
+    pub fn poll(&mut self, now: Instant) -> Option<LinkState> {
+        if self.stream.is_none() && now >= self.next_connect {
+            self.next_connect = now + RECONNECT;
+            match self.connect() {
+                Ok(stream) => {
+                    println!("Connected to Carabiner at {}", self.address);
+                    self.warned = false;
+                    self.stream = Some(stream);
+                    self.received.clear();
+                    self.next_poll = now;
+                }
+                Err(e) if !self.warned => {
+                    eprintln!("No Link session: Carabiner at {}: {}", self.address, e);
+                    self.warned = true;
+                }
+                Err(_) => {}
+            }
+        }
+        let stream = self.stream.as_mut()?;
+        if now >= self.next_poll {
+            self.next_poll = now + POLL;
+            if stream.write_all(b"status\n").is_err() {
+                self.drop_connection(now);
+                return None;
+            }
+        }
+        let mut buf = [0u8; 1024];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) => {
+                    self.drop_connection(now);
+                    return None;
+                }
+                Ok(size) => self.received.push_str(&String::from_utf8_lossy(&buf[..size])),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    self.drop_connection(now);
+                    return None;
+                }
+            }
+        }
+        // Messages end with their closing brace; anything after it is still coming.
+        let mut latest = None;
+        while let Some(end) = self.received.find('}') {
+            let message: String = self.received.drain(..=end).collect();
+            latest = parse_status(&message).or(latest);
+        }
+        if let Some(state) = latest {
+            self.last = Some((state, now));
+        }
+        latest
+    }
+
+    pub fn bpm(&self) -> Option<f32> {
+        self.last.map(|(state, _)| state.bpm as f32)
+    }
+
+    /// The session's timeline at `now`, in MIDI clock ticks, for the step clock
+    pub fn clock_ticks(&self, now: Instant) -> Option<u32> {
+        let (state, at) = self.last?;
+        let beat = state.beat + now.duration_since(at).as_secs_f64() * state.bpm / 60.0;
+        Some((beat.max(0.0) * CLOCKS_PER_BEAT) as u32)
+    }
+
+    fn drop_connection(&mut self, now: Instant) {
+        eprintln!("Lost the connection to Carabiner at {}", self.address);
+        self.stream = None;
+        self.next_connect = now + RECONNECT;
+    }
+}
+
+/// `status { :peers 1 :bpm 120.000000 :start 73743731220 :beat 597.737570 }`
+fn parse_status(message: &str) -> Option<LinkState> {
+    let body = message.trim().strip_prefix("status")?.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let words: Vec<&str> = body.split_whitespace().collect();
+    let value = |key: &str| words.windows(2).find(|w| w[0] == key).and_then(|w| w[1].parse::<f64>().ok());
+    Some(LinkState { peers: value(":peers")? as u32, bpm: value(":bpm")?, beat: value(":beat")? })
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use maschine_library::controls::PadEventType;
+use rosc::{OscMessage, OscType};
+use serde::Serialize;
+use crate::input::HardwareEvent;
+use crate::sequence::{NoteSequence, SeqNote};
+use crate::settings::Settings;
+
+/// Pad hits for live-coding clients: `<group> <pad 1-16> <velocity>`, 0 on release.
+pub const TAGGED_PAD: &str = "/maschine/pad/tagged";
+/// Reply to `/maschine/hello`, with `State` as JSON
+pub const STATE: &str = "/maschine/hello/state";
+
+/// Everything a client needs to pick up where the surface is, sent when it says hello.
+#[derive(Serialize, Debug)]
+pub struct State {
+    pub mode: &'static str,
+    /// Modes Maschine+pad reaches, in pad order
+    pub modes: Vec<&'static str>,
+    pub toggles: BTreeMap<String, bool>,
+    /// Note of each pad, in pad order
+    pub pad_notes: Vec<u8>,
+    /// Group each pad's hits are tagged with, in pad order
+    pub pad_groups: Vec<u8>,
+    #[serde(rename = "loop")]
+    pub sequence: Option<NoteSequence>,
+    /// Tempo of the host's MIDI clock, while one comes in
+    pub bpm: Option<f32>,
+}
+
+impl State {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("the state always serializes")
+    }
+}
+
+/// Group a pad's hits are tagged with; pads past the end of `pad_groups` are in group 0.
+pub fn pad_group(settings: &Settings, pad: usize) -> u8 {
+    settings.livecoding.pad_groups.get(pad).copied().unwrap_or(0)
+}
+
+/// The tagged message for a pad hit or release; None for aftertouch and for anything else.
+pub fn pad_message(event: &HardwareEvent, settings: &Settings) -> Option<OscMessage> {
+    let HardwareEvent::Pad { index, event_type, value } = *event else {
+        return None;
+    };
+    let velocity = match event_type {
+        PadEventType::NoteOn | PadEventType::PressOn if value > 0 => ((value >> 5) as i32).max(1),
+        PadEventType::NoteOn | PadEventType::PressOn | PadEventType::NoteOff | PadEventType::PressOff => 0,
+        _ => return None,
+    };
+    let args = vec![OscType::Int(pad_group(settings, index) as i32), OscType::Int(index as i32 + 1), OscType::Int(velocity)];
+    Some(OscMessage { addr: TAGGED_PAD.to_string(), args })
+}
+
+fn number(arg: &OscType) -> Option<f64> {
+    match arg {
+        OscType::Int(i) => Some(*i as f64),
+        OscType::Float(f) => Some(*f as f64),
+        OscType::Double(d) => Some(*d),
+        _ => None,
+    }
+}
+
+/// A Pbind-like pattern for the looper: `/maschine/pattern` with keys followed by their
+/// values, e.g. `"midinote", 60, 62, "rest", 67, "dur", 0.5, 0.25, "legato", 0.5`. There
+/// is one event per `midinote` ("rest" for none) and shorter lists wrap around, as
+/// `Pseq`s of different lengths would. `dur` is in beats (1 by default), `legato` the share
+/// of it a note lasts (0.8), `amp` 0-1 (velocity 100 without) and `tempo` in beats per
+/// second like a `TempoClock` (1). The loop is one pass through the events.
+pub fn parse_pattern(msg: &OscMessage) -> Result<NoteSequence, String> {
+    let mut lists: BTreeMap<&str, Vec<Option<f64>>> = BTreeMap::new();
+    let mut key = None;
+    for arg in &msg.args {
+        // Symbols from sclang may keep their backslash.
+        let name = match arg {
+            OscType::String(s) => Some(s.trim_start_matches('\\')),
+            _ => None,
+        };
+        match name {
+            Some(k @ ("midinote" | "dur" | "legato" | "amp" | "tempo")) => {
+                key = Some(k);
+                lists.entry(k).or_default();
+            }
+            Some("rest" | "r") | None => {
+                let value = match name {
+                    Some(_) => None,
+                    None => Some(number(arg).ok_or("values should be numbers or \"rest\"")?),
+                };
+                lists.get_mut(key.ok_or("values before the first key")?).expect("every key has a list").push(value);
+            }
+            Some(other) => return Err(format!("unknown key {other}")),
+        }
+    }
+    let notes = lists.get("midinote").filter(|l| !l.is_empty()).ok_or("the pattern needs midinote values")?;
+    let value = |key: &str, i: usize, default: f64| -> Result<f64, String> {
+        match lists.get(key).filter(|l| !l.is_empty()) {
+            Some(list) => list[i % list.len()].ok_or(format!("{key} can't rest")),
+            None => Ok(default),
+        }
+    };
+    let tempo = value("tempo", 0, 1.0)?;
+    if tempo <= 0.0 {
+        return Err("tempo should be above 0".to_string());
+    }
+    let beat_ms = 1000.0 / tempo;
+
+    let mut sequence = NoteSequence { length_ms: 0, notes: Vec::new() };
+    let mut start: f64 = 0.0;
+    for (i, note) in notes.iter().enumerate() {
+        let dur = value("dur", i, 1.0)?;
+        if dur <= 0.0 {
+            return Err("dur should be above 0".to_string());
+        }
+        if let Some(note) = note {
+            let length = dur * value("legato", i, 0.8)? * beat_ms;
+            let velocity = if lists.contains_key("amp") {
+                (value("amp", i, 0.0)?.clamp(0.0, 1.0) * 127.0).round().max(1.0) as u8
+            } else {
+                100
+            };
+            sequence.notes.push(SeqNote {
+                start_ms: start.round() as u32,
+                duration_ms: length.round().clamp(1.0, u16::MAX as f64) as u16,
+                note: note.round().clamp(0.0, 255.0) as u8,
+                velocity,
+            });
+        }
+        start += dur * beat_ms;
+    }
+    sequence.length_ms = start.round() as u32;
+    sequence.validate()?;
+    Ok(sequence)
+}
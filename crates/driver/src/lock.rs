@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+use maschine_library::controls::Buttons;
+use crate::input::HardwareEvent;
+use crate::modes::custom_midi::button_from_name;
+use crate::modes::ButtonEdges;
+use crate::settings::LockSettings;
+
+/// Performance lock: a long press on the lock button keeps Erase and the mode buttons
+/// from doing anything until the next long press, so a stray hit can't wipe a loop or
+/// change pages mid-set.
+pub struct PerformanceLock {
+    button: Option<Buttons>,
+    hold: Duration,
+    pressed_at: Option<Instant>,
+    /// The current press already toggled the lock
+    fired: bool,
+    locked: bool,
+    /// Blocked buttons held down, so each press is reported once
+    blocked: ButtonEdges,
+}
+
+impl PerformanceLock {
+    pub fn new(settings: &LockSettings) -> Self {
+        Self {
+            button: if settings.enabled { button_from_name(&settings.button) } else { None },
+            hold: Duration::from_millis(settings.hold_ms),
+            pressed_at: None,
+            fired: false,
+            locked: false,
+            blocked: ButtonEdges::new(),
+        }
+    }
+
+    /// Follows the lock button. Button reports repeat every button's state, so only the
+    /// first report of a press starts the hold.
+    pub fn handle(&mut self, event: &HardwareEvent, now: Instant) {
+        if let HardwareEvent::Button { index, pressed } = *event
+            && Some(index) == self.button
+        {
+            match (pressed, self.pressed_at) {
+                (true, None) => self.pressed_at = Some(now),
+                (false, Some(_)) => {
+                    self.pressed_at = None;
+                    self.fired = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Toggles the lock once the button has been held long enough; returns the new state
+    /// when it changed.
+    pub fn tick(&mut self, now: Instant) -> Option<bool> {
+        let pressed_at = self.pressed_at?;
+        if self.fired || now.duration_since(pressed_at) < self.hold {
+            return None;
+        }
+        self.fired = true;
+        self.locked = !self.locked;
+        Some(self.locked)
+    }
+
+    /// Keeps the poll fast while a hold is being timed.
+    pub fn is_animating(&self) -> bool {
+        self.pressed_at.is_some() && !self.fired
+    }
+
+    /// None lets the event through. While locked, Erase and the mode buttons are held
+    /// back: Some(true) for the first report of a press, Some(false) for the rest.
+    pub fn block(&mut self, event: &HardwareEvent) -> Option<bool> {
+        let HardwareEvent::Button { index, pressed } = *event else {
+            return None;
+        };
+        if !self.locked || !matches!(index, Buttons::Erase | Buttons::Maschine | Buttons::Star | Buttons::Browse) {
+            return None;
+        }
+        Some(self.blocked.pressed(index, pressed))
+    }
+}
@@ -0,0 +1,190 @@
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use evdev::KeyCode;
+use midly::{live::LiveEvent, MidiMessage};
+use serde::Deserialize;
+use crate::context::DriverContext;
+
+fn default_velocity() -> u8 {
+    100
+}
+
+fn default_channel() -> u8 {
+    1
+}
+
+/// One step of a macro. In TOML: `{ type = "cc", cc = 20, value = 127 }`,
+/// `{ type = "osc", address = "/obs/scene", value = 2 }`, `{ type = "keys", keys = ["ctrl", "s"] }`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MacroStep {
+    /// Note on immediately followed by note off.
+    Note {
+        note: u8,
+        #[serde(default = "default_velocity")]
+        velocity: u8,
+        #[serde(default = "default_channel")]
+        channel: u8,
+    },
+    Cc {
+        cc: u8,
+        value: u8,
+        #[serde(default = "default_channel")]
+        channel: u8,
+    },
+    /// Sends the value as an int; without one the message has no arguments.
+    Osc {
+        address: String,
+        #[serde(default)]
+        value: Option<i32>,
+    },
+    /// Key combination through uinput: pressed in order, released in reverse.
+    Keys { keys: Vec<String> },
+    /// Starts an external program (no shell involved) without waiting for it; its
+    /// output is logged. Only allowed with `allow_exec = true`.
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Runs one of the named `[macros]`.
+    Macro { name: String },
+}
+
+impl MacroStep {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            MacroStep::Note { note, velocity, channel } => check_midi(&[*note, *velocity], *channel),
+            MacroStep::Cc { cc, value, channel } => check_midi(&[*cc, *value], *channel),
+            MacroStep::Osc { address, .. } if !address.starts_with('/') => {
+                Err(format!("OSC address {address} must start with /"))
+            }
+            MacroStep::Osc { .. } => Ok(()),
+            MacroStep::Keys { keys } => match keys.iter().find(|k| parse_key(k).is_none()) {
+                Some(bad) => Err(format!("unknown key {bad}")),
+                None => Ok(()),
+            },
+            MacroStep::Exec { command, .. } if command.is_empty() => Err("exec needs a command".to_string()),
+            MacroStep::Exec { .. } => Ok(()),
+            MacroStep::Macro { name } if name.is_empty() => Err("macro needs a name".to_string()),
+            MacroStep::Macro { .. } => Ok(()),
+        }
+    }
+
+    pub fn is_exec(&self) -> bool {
+        matches!(self, MacroStep::Exec { .. })
+    }
+
+    pub fn needs_uinput(&self) -> bool {
+        matches!(self, MacroStep::Keys { .. })
+    }
+}
+
+fn check_midi(values: &[u8], channel: u8) -> Result<(), String> {
+    if values.iter().any(|v| *v >= 128) {
+        return Err("MIDI values should be 0 to 127".to_string());
+    }
+    if !(1..=16).contains(&channel) {
+        return Err("MIDI channel must be between 1 and 16".to_string());
+    }
+    Ok(())
+}
+
+/// Accepts evdev names (`KEY_F13`), names without the prefix (`f13`) and the
+/// modifiers `ctrl`, `shift`, `alt` and `super`.
+pub fn parse_key(name: &str) -> Option<u16> {
+    let upper = name.to_uppercase();
+    let full = match upper.as_str() {
+        "CTRL" => "KEY_LEFTCTRL".to_string(),
+        "SHIFT" => "KEY_LEFTSHIFT".to_string(),
+        "ALT" => "KEY_LEFTALT".to_string(),
+        "SUPER" | "META" => "KEY_LEFTMETA".to_string(),
+        _ if upper.starts_with("KEY_") => upper,
+        _ => format!("KEY_{upper}"),
+    };
+    KeyCode::from_str(&full).ok().map(|k| k.0)
+}
+
+pub fn run_macro(steps: &[MacroStep], ctx: &mut DriverContext) {
+    for step in steps {
+        match step {
+            MacroStep::Note { note, velocity, channel } => {
+                send_midi(*channel, MidiMessage::NoteOn { key: (*note).into(), vel: (*velocity).into() }, ctx);
+                send_midi(*channel, MidiMessage::NoteOff { key: (*note).into(), vel: 0.into() }, ctx);
+            }
+            MacroStep::Cc { cc, value, channel } => {
+                send_midi(*channel, MidiMessage::Controller { controller: (*cc).into(), value: (*value).into() }, ctx);
+            }
+            MacroStep::Osc { address, value } => {
+                let args = value.map(rosc::OscType::Int).into_iter().collect();
+                ctx.osc.send(rosc::OscMessage { addr: address.clone(), args });
+            }
+            MacroStep::Keys { keys } => {
+                let codes: Vec<u16> = keys.iter().filter_map(|k| parse_key(k)).collect();
+                for code in &codes {
+                    ctx.uinput.key(*code, true);
+                }
+                for code in codes.iter().rev() {
+                    ctx.uinput.key(*code, false);
+                }
+            }
+            MacroStep::Exec { command, args } => {
+                // Settings validation already refuses this; checked again so nothing slips through.
+                if ctx.settings.allow_exec {
+                    spawn_logged(command, args);
+                } else {
+                    eprintln!("Not running {}: allow_exec is off", command);
+                }
+            }
+            MacroStep::Macro { name } => {
+                let settings = ctx.settings;
+                match settings.macros.get(name) {
+                    Some(steps) => run_macro(steps, ctx),
+                    None => eprintln!("No macro named {}", name),
+                }
+            }
+        }
+    }
+}
+
+/// Starts the command and hands it to a thread that logs its output once it exits,
+/// so a slow script never holds up the MIDI loop.
+fn spawn_logged(command: &str, args: &[String]) {
+    let child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("[exec {}] couldn't start: {}", command, e);
+            return;
+        }
+    };
+    let name = command.to_string();
+    thread::spawn(move || match child.wait_with_output() {
+        Ok(output) => {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                println!("[exec {}] {}", name, line);
+            }
+            for line in String::from_utf8_lossy(&output.stderr).lines() {
+                eprintln!("[exec {}] {}", name, line);
+            }
+            if !output.status.success() {
+                eprintln!("[exec {}] exited with {}", name, output.status);
+            }
+        }
+        Err(e) => eprintln!("[exec {}] {}", name, e),
+    });
+}
+
+fn send_midi(channel: u8, message: MidiMessage, ctx: &mut DriverContext) {
+    let live_event = LiveEvent::Midi { channel: (channel - 1).into(), message };
+    let mut midibuf = Vec::new();
+    if live_event.write(&mut midibuf).is_ok() {
+        let _ = ctx.midi_port.send(&midibuf[..]);
+    }
+}
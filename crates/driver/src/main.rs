@@ -3,35 +3,119 @@ mod settings;
 mod input;
 mod context;
 mod modes;
+mod clock;
+mod midi;
+mod watchdog;
+mod status;
+mod polling;
+mod realtime;
+mod osc;
+mod uinput;
+mod macros;
+mod analytics;
+mod sequence;
+mod monitor;
+mod transport;
+mod fade;
+mod scenes;
+mod pressure;
+mod namespace;
+mod oscquery;
+mod ack;
+mod groups;
+mod jsonio;
+mod fifo;
+mod migrate;
+mod lock;
+mod pad_check;
+mod calibration;
+mod autosave;
+mod light_frame;
+mod tap_tempo;
+mod quantize;
+mod snapshots;
+mod sysex;
+mod osc_test;
+mod companion;
+mod arp;
+mod step_clock;
+mod mixxx;
+mod modular;
+mod livecoding;
+mod failover;
+mod attract;
+mod fixed_velocity;
+mod midi_queue;
+mod overlay;
+mod link;
+mod note_guard;
+mod midi_file;
+mod groove;
+mod maschine_import;
+mod config_watch;
+#[cfg(feature = "audio")]
+mod audio;
+#[cfg(test)]
+mod testing;
 
 use crate::self_test::self_test;
+use crate::pad_check::{pad_check, save_table};
+use crate::calibration::{calibrate_pads, normalize_pads};
 use crate::settings::Settings;
+use crate::config_watch::ConfigWatch;
+use crate::clock::SystemClock;
 use crate::context::DriverContext;
-use crate::input::{parse_hid_report, HardwareEvent};
-use crate::modes::{MachineMode, CustomMidiMode, PlayMode};
+use crate::input::{orient_pads, parse_hid_report, parse_midi_input, parse_osc_feedback, HardwareEvent, HostEvent, InputRemap};
+use crate::modes::{DriverMode, ModeManager};
+use crate::modes::bitwig::BitwigMessage;
+use crate::midi::{VirtualMidiIn, VirtualMidiOut};
+use crate::midi_queue::MidiQueue;
+use crate::osc::{bind_listener, listen_addr, OscIo, OscRecorder, OscReplay, OscTarget};
+use crate::failover::Failover;
+use crate::attract::Attract;
+use crate::fixed_velocity::FixedVelocity;
+use crate::oscquery::OscQueryServer;
+use crate::polling::AdaptivePoll;
+use crate::monitor::{Direction, MidiMonitor, MidiTap};
+use crate::transport::{draw_pad_chase, HostTransport};
+use crate::fade::LightFader;
+use crate::scenes::LightScenes;
+use crate::pressure::PressureMeter;
+use crate::ack::InputAck;
+use crate::arp::Arpeggiator;
+use crate::quantize::InputQuantizer;
+use crate::link::Link;
+use crate::note_guard::NoteGuard;
+use crate::modes::clock_in::ClockMessage;
+use crate::snapshots::{set_toggles, toggle_states, Snapshots, SLOTS};
+use crate::sysex::{Backup, Restore};
+use crate::overlay::ScreenOverlay;
+use crate::groups::GroupState;
+use crate::jsonio::{JsonCommand, JsonIo};
+use crate::light_frame::LightFrame;
+use crate::lock::PerformanceLock;
+use crate::sequence::{LoopFormat, NoteSequence};
+use crate::macros::run_macro;
+use crate::uinput::UinputOut;
+use crate::status::{Indicator, Severity, StatusBar, StatusDisplay, CONTENT_ROW};
+use crate::watchdog::{Watchdog, WatchdogAction};
 
 use clap::Parser;
 use config::Config;
-use maschine_library::controls::Buttons;
+use maschine_library::controls::{Buttons, PadEventType};
 use maschine_library::lights::{Brightness, Lights};
 use maschine_library::screen::Screen;
 use maschine_library::font::Font;
-use midir::MidiOutput;
-use midir::os::unix::VirtualOutput;
-use rosc::{OscPacket, OscType};
+use rosc::{OscMessage, OscPacket, OscType};
 use rosc::decoder;
-use std::net::{UdpSocket, ToSocketAddrs};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket, ToSocketAddrs};
 use std::error::Error as StdError;
 use std::io::ErrorKind;
-use std::time::Duration;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::thread;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum DriverMode {
-    CustomMidi,
-    Playability,
-}
-
 #[derive(Parser, Debug)]
 #[clap(
     name = "Maschine Mikro MK3 Userspace MIDI driver",
@@ -41,51 +125,377 @@ enum DriverMode {
 struct Args {
     #[clap(short, long, help = "Config file (see example_config.toml)")]
     config: Option<String>,
+
+    #[clap(long, help = "Record the timestamped OSC dialog (in and out) to a file")]
+    record_osc: Option<String>,
+
+    #[clap(long, help = "Replay incoming OSC messages from a recorded session file")]
+    replay_osc: Option<String>,
+
+    #[clap(long, help = "Check that every pad answers, and hand dead ones to other pads or buttons")]
+    pad_check: bool,
+
+    #[clap(long, help = "Measure each pad's softest and hardest hit, so all pads play the same velocities")]
+    calibrate_pads: bool,
+
+    #[clap(long, help = "Print every control event as a JSON line on stdout and take JSON commands on stdin")]
+    json_io: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Upgrade a config file from an older driver version to the current schema
+    MigrateConfig {
+        path: String,
+        #[clap(long, help = "Save the upgraded file (the original is kept as <path>.bak)")]
+        write: bool,
+    },
+    /// Check the OSC connection to a running driver: subscribe, send screen text and a
+    /// light frame, ask for the namespace and the loop, and report what comes back
+    OscTest {
+        #[clap(long, help = "Driver's OSC address as host:port (default: osc_ip and osc_listen_port from the config)")]
+        driver: Option<String>,
+    },
+    /// Install the Ableton Live remote script for the Live page, set to `[live] channel`
+    InstallLiveScript {
+        #[clap(long, help = "Remote Scripts folder (default: ~/Music/Ableton/User Library/Remote Scripts)")]
+        dir: Option<String>,
+    },
+    /// Install the Bitwig Studio controller script for the Bitwig page, set to `[bitwig] channel`
+    InstallBitwigScript {
+        #[clap(long, help = "Controller Scripts folder (default: ~/Documents/Bitwig Studio/Controller Scripts)")]
+        dir: Option<String>,
+    },
+    /// Write a Mixxx mapping for what Custom MIDI mode sends with this config
+    ExportMixxxMapping {
+        #[clap(long, help = "Mixxx's controllers folder (default: ~/.mixxx/controllers)")]
+        dir: Option<String>,
+    },
+    /// Write the Reaper OSC pattern config for the Reaper page
+    ExportReaperOsc {
+        #[clap(long, help = "Reaper's OSC folder (default: ~/.config/REAPER/OSC)")]
+        dir: Option<String>,
+    },
+    /// Convert patterns exported from the Maschine software as MIDI files into loops for
+    /// Play mode's loop browser
+    ImportMaschine {
+        /// Folder of the exported MIDI files (subfolders too)
+        folder: String,
+        #[clap(long, help = "Where the loops go (default: [autosave] dir)")]
+        out: Option<String>,
+    },
+}
+
+/// Applies one incoming OSC packet (live or replayed) to the driver; numeric messages
+/// and Reaper's track state are queued for the modes as host feedback. Returns true when the screen content changed.
+fn handle_osc_packet(packet: &[u8], screen: &mut Screen, host_events: &mut Vec<HostEvent>) -> bool {
+    let Ok((_, OscPacket::Message(msg))) = decoder::decode_udp(packet) else {
+        return false;
+    };
+    match msg.args.first() {
+        // Replies with a JSON string unless asked for "blob".
+        first if msg.addr == "/maschine/loop/dump" => {
+            let format = match first {
+                Some(OscType::String(s)) if s == "blob" => LoopFormat::Blob,
+                _ => LoopFormat::Json,
+            };
+            host_events.push(HostEvent::DumpLoop(format));
+        }
+        _ if msg.addr == "/maschine/namespace" => host_events.push(HostEvent::Namespace),
+        _ if msg.addr == "/maschine/metrics" => host_events.push(HostEvent::Metrics),
+        _ if msg.addr == "/maschine/hello" => host_events.push(HostEvent::Hello),
+        _ if msg.addr == "/maschine/pattern" => match livecoding::parse_pattern(&msg) {
+            Ok(sequence) => host_events.push(HostEvent::LoadLoop(sequence)),
+            Err(e) => eprintln!("Ignoring {}: {}", msg.addr, e),
+        },
+        Some(OscType::String(s)) if msg.addr == "/maschine/screen/text" => {
+            screen.reset();
+            Font::write_string(screen, CONTENT_ROW, 0, s, 1);
+            return true;
+        }
+        Some(OscType::String(s)) if msg.addr == "/maschine/scene" => {
+            let fade_ms = match msg.args.get(1) {
+                Some(OscType::Int(ms)) => Some((*ms).max(0) as u64),
+                Some(OscType::Float(ms)) => Some(ms.max(0.0) as u64),
+                _ => None,
+            };
+            host_events.push(HostEvent::Scene { name: s.clone(), fade_ms });
+        }
+        Some(OscType::String(s)) if msg.addr == "/maschine/macro" => {
+            host_events.push(HostEvent::Macro { name: s.clone() });
+        }
+        Some(OscType::String(s)) if msg.addr == "/maschine/loop/load" => match NoteSequence::from_json(s) {
+            Ok(sequence) => host_events.push(HostEvent::LoadLoop(sequence)),
+            Err(e) => eprintln!("Ignoring {}: {}", msg.addr, e),
+        },
+        Some(OscType::Blob(b)) if msg.addr == "/maschine/loop/load" => match NoteSequence::from_blob(b) {
+            Ok(sequence) => host_events.push(HostEvent::LoadLoop(sequence)),
+            Err(e) => eprintln!("Ignoring {}: {}", msg.addr, e),
+        },
+        Some(OscType::Blob(b)) if msg.addr == "/maschine/lights/frame" => match LightFrame::from_blob(b) {
+            Ok(frame) => host_events.push(HostEvent::LightFrame(frame)),
+            Err(e) => eprintln!("Ignoring {}: {}", msg.addr, e),
+        },
+        Some(OscType::Int(i)) if msg.addr == "/maschine/snapshot" => match *i {
+            1..=4 => host_events.push(HostEvent::Snapshot { slot: *i as usize - 1 }),
+            _ => eprintln!("Ignoring {}: snapshots are 1 to 4", msg.addr),
+        },
+        _ => host_events.extend(parse_osc_feedback(&msg)),
+    }
+    false
+}
+
+/// Switches modes and puts the new one's name in the status bar, unless `id` is up already.
+fn switch_mode(modes: &mut ModeManager, id: DriverMode, ctx: &mut DriverContext, status: &mut StatusDisplay) {
+    if modes.switch(id, ctx) {
+        mode_entered(modes, ctx, status);
+    }
+}
+
+/// Follows up on entering the current mode, after a switch or a config reload.
+fn mode_entered(modes: &mut ModeManager, ctx: &mut DriverContext, status: &mut StatusDisplay) {
+    // Whatever the old mode left on ends here.
+    if ctx.settings.note_guard.enabled {
+        ctx.midi_port.release_notes();
+    }
+    let mode = modes.current();
+    status.set_bar(StatusBar { mode: mode.name(), channel: mode.channel(ctx), ..status.bar().clone() });
+    status.hide_bar(mode.full_screen());
+}
+
+fn open_device(api: &hidapi::HidApi) -> hidapi::HidResult<hidapi::HidDevice> {
+    let device = api.open(0x17cc, 0x1700)?;
+    device.set_blocking_mode(false)?;
+    Ok(device)
 }
 
 fn main() -> Result<(), Box<dyn StdError>> {
     let args = Args::parse();
+    if let Some(Command::MigrateConfig { path, write }) = &args.command {
+        return migrate::run(path, *write).map_err(Into::into);
+    }
+    // Before anything is printed: from here on stdout carries only events.
+    let mut json_links: Vec<JsonIo> = Vec::new();
+    if args.json_io {
+        json_links.push(JsonIo::stdio()?);
+    }
 
     let mut cfg = Config::builder();
-    if let Some(config_fn) = args.config {
+    if let Some(config_fn) = &args.config {
         cfg = cfg.add_source(config::File::with_name(config_fn.as_str()));
     }
     let cfg = cfg.build().expect("Can't create settings");
-    let settings: Settings = cfg.try_deserialize().expect("Can't parse settings");
+    let mut settings: Settings = cfg.try_deserialize().expect("Can't parse settings");
 
     settings.validate().unwrap();
+    if let Some(Command::OscTest { driver }) = &args.command {
+        let default = format!("{}:{}", settings.osc_ip, settings.osc_listen_port);
+        return osc_test::run(driver.as_deref().unwrap_or(&default)).map_err(Into::into);
+    }
+    if let Some(Command::InstallLiveScript { dir }) = &args.command {
+        return companion::install_live(dir.as_deref(), settings.live.channel).map_err(Into::into);
+    }
+    if let Some(Command::InstallBitwigScript { dir }) = &args.command {
+        return companion::install_bitwig(dir.as_deref(), settings.bitwig.channel).map_err(Into::into);
+    }
+    if let Some(Command::ExportMixxxMapping { dir }) = &args.command {
+        return companion::install_mixxx(dir.as_deref(), &settings).map_err(Into::into);
+    }
+    if let Some(Command::ExportReaperOsc { dir }) = &args.command {
+        return companion::install_reaper(dir.as_deref()).map_err(Into::into);
+    }
+    if let Some(Command::ImportMaschine { folder, out }) = &args.command {
+        return maschine_import::run(folder, out.as_deref(), &settings).map_err(Into::into);
+    }
+    if args.config.is_some() && settings.config_version < migrate::CURRENT_CONFIG_VERSION {
+        eprintln!("The config predates this driver's schema; `driver migrate-config <file>` upgrades it.");
+    }
     println!("Running with settings: {:?}", settings);
 
     let osc_socket = UdpSocket::bind("0.0.0.0:0")?;
-    let osc_addr: std::net::SocketAddr = format!("{}:{}", settings.osc_ip, settings.osc_port)
-        .to_socket_addrs()?.next().unwrap();
+    let failover_backup = match &settings.osc.failover {
+        failover if failover.enabled => Some(
+            failover.backup.to_socket_addrs()?.next().ok_or_else(|| format!("osc.failover: can't resolve {}", failover.backup))?,
+        ),
+        _ => None,
+    };
+    let mut failover_target = 0;
+    let osc_targets = if !settings.osc.default_peer && settings.osc.destinations.is_empty() {
+        Vec::new()
+    } else if settings.osc.destinations.is_empty() {
+        let osc_addr: std::net::SocketAddr = format!("{}:{}", settings.osc_ip, settings.osc_port)
+            .to_socket_addrs()?.next().unwrap();
+        let target = OscTarget::new(osc_addr, &[], &[]);
+        vec![match failover_backup {
+            Some(backup) => target.with_backup(backup),
+            None => target,
+        }]
+    } else {
+        let mut names: Vec<&String> = settings.osc.destinations.keys().collect();
+        names.sort();
+        let mut targets = Vec::new();
+        for name in names {
+            let dest = &settings.osc.destinations[name];
+            let addr = dest.address.to_socket_addrs()?.next()
+                .ok_or_else(|| format!("osc.destinations.{name}: can't resolve {}", dest.address))?;
+            println!("OSC destination {} -> {}", name, addr);
+            let target = OscTarget::new(addr, &dest.include, &dest.exclude);
+            match failover_backup {
+                Some(backup) if *name == settings.osc.failover.primary => {
+                    failover_target = targets.len();
+                    targets.push(target.with_backup(backup));
+                }
+                _ => targets.push(target),
+            }
+        }
+        targets
+    };
+    let osc_recorder = match &args.record_osc {
+        Some(path) => {
+            println!("Recording OSC session to {}", path);
+            Some(OscRecorder::create(path)?)
+        }
+        None => None,
+    };
+    let osc = OscIo::new(osc_socket, osc_targets, osc_recorder, settings.osc.timetags);
+    // IPv6 targets and subscribers need a socket of their own; hosts without IPv6 do without.
+    let osc = match UdpSocket::bind("[::]:0") {
+        Ok(socket6) => osc.with_ipv6(socket6),
+        Err(_) => osc,
+    };
+    let mut osc_replay = match &args.replay_osc {
+        Some(path) => {
+            println!("Replaying OSC session from {}", path);
+            Some(OscReplay::load(path)?)
+        }
+        None => None,
+    };
     
-    let osc_listener = UdpSocket::bind(format!("{}:{}", settings.osc_ip, settings.osc_listen_port))?;
-    osc_listener.set_nonblocking(true)?;
+    let listen_addrs: Vec<SocketAddr> = if settings.osc.listen.is_empty() {
+        // The target's own address only works for a listener when the target is this machine.
+        let target = (settings.osc_ip.as_str(), settings.osc_listen_port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| format!("can't resolve osc_ip {}", settings.osc_ip))?;
+        if target.ip().is_loopback() || target.ip().is_unspecified() {
+            vec![target]
+        } else {
+            vec![SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), settings.osc_listen_port)]
+        }
+    } else {
+        settings.osc.listen.iter().map(|entry| listen_addr(entry, settings.osc_listen_port)).collect::<Result<_, _>>()?
+    };
+    let mut osc_listeners = Vec::new();
+    for addr in listen_addrs {
+        osc_listeners.push(bind_listener(addr)?);
+        println!("Listening for OSC on {}", addr);
+    }
+    let oscquery = match settings.osc.query_port {
+        0 => None,
+        port => {
+            println!("OSCQuery on port {}", port);
+            Some(OscQueryServer::bind(port, &settings, osc.watch())?)
+        }
+    };
+    if let Some(dir) = &settings.fifo.dir {
+        println!("JSON event and command pipes in {}", dir);
+        json_links.push(fifo::open(dir)?);
+    }
+    #[cfg(feature = "audio")]
+    let mut audio_meter = if settings.audio.enabled {
+        match audio::AudioMeter::open(&settings.audio) {
+            Ok(meter) => Some(meter),
+            Err(e) => {
+                eprintln!("Audio input unavailable: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(not(feature = "audio"))]
+    if settings.audio.enabled {
+        eprintln!("[audio] is set, but this driver was built without the audio feature");
+    }
+
+    let clock = SystemClock;
+    let mut port = NoteGuard::new(
+        MidiTap::new(MidiQueue::new(
+            VirtualMidiOut::open(&settings.client_name, &settings.port_name).expect("Couldn't open MIDI port"),
+            &settings.midi_queue,
+        )),
+        &settings.note_guard,
+        &clock,
+    );
+    if settings.note_guard.enabled {
+        note_guard::catch_shutdown();
+    }
+    let input_port_name = if settings.input_port_name.is_empty() {
+        format!("{} MIDI In", settings.client_name)
+    } else {
+        settings.input_port_name.clone()
+    };
+    // Host feedback is optional: without it toggles simply aren't synced.
+    let midi_in = match VirtualMidiIn::open(&settings.client_name, &input_port_name) {
+        Ok(midi_in) => Some(midi_in),
+        Err(e) => {
+            eprintln!("{}; toggle states won't follow the host", e);
+            None
+        }
+    };
 
-    let output = MidiOutput::new(&settings.client_name).expect("Couldn't open MIDI output");
-    let mut port = output.create_virtual(&settings.port_name).expect("Couldn't create virtual port");
+    let mut uinput = if settings.needs_uinput() {
+        UinputOut::open(&settings.client_name)
+    } else {
+        UinputOut::disabled()
+    };
 
     let api = hidapi::HidApi::new()?;
-    let device = api.open(0x17cc, 0x1700)?;
-    device.set_blocking_mode(false)?;
+    let mut device = open_device(&api)?;
 
     let mut screen = Screen::new();
     let mut lights = Lights::new();
 
-    self_test(&device, &mut screen, &mut lights)?;
+    self_test(&device, &mut screen, &mut lights, &settings.startup)?;
+    if args.calibrate_pads {
+        let calibration = calibrate_pads(&device, &mut screen, &mut lights)?;
+        if !calibration.is_empty() {
+            save_table(args.config.as_deref(), "pad_calibration", &calibration::to_toml(&calibration), "--calibrate-pads");
+            settings.pad_calibration.extend(calibration);
+        }
+    }
+    if args.pad_check {
+        let workaround = pad_check(&device, &mut screen, &mut lights)?;
+        if !workaround.is_empty() {
+            workaround.apply(&mut settings.remap);
+            save_table(args.config.as_deref(), "remap", &workaround.to_toml(), "--pad-check");
+        }
+    }
+
+    realtime::apply(&settings.realtime);
 
+    let mut groups = GroupState::new(&settings);
     let mut context = DriverContext {
         lights: &mut lights,
+        screen: &mut screen,
         midi_port: &mut port,
-        osc_socket: &osc_socket,
-        osc_addr: &osc_addr,
+        osc: &osc,
+        uinput: &mut uinput,
         settings: &settings,
+        clock: &clock,
+        groups: &mut groups,
     };
 
-    let mut current_mode_id = DriverMode::CustomMidi;
-    let mut custom_midi = CustomMidiMode::new(&settings);
-    let mut play_mode = PlayMode::new();
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64);
+    let mut modes = ModeManager::new(&settings, seed);
+    let mut shift_held = false;
+    let mut group_held = false;
+    let mut snapshot_pads = [false; SLOTS];
+    let mut maschine_held = false;
+    let mut mode_pads = [false; 16];
     
     println!("Starting in Custom MIDI Mode.");
     context.lights.set_button(Buttons::Maschine, Brightness::Bright);
@@ -93,140 +503,748 @@ fn main() -> Result<(), Box<dyn StdError>> {
     context.lights.set_button(Buttons::Browse, Brightness::Dim);
     context.lights.write(&device)?;
     
-    custom_midi.on_enter(&mut context);
+    modes.current().on_enter(&mut context);
+
+    let mut status = StatusDisplay::new(&settings.accessibility);
+    // The config's file name tells profiles apart.
+    let profile = args.config.as_deref().and_then(|path| Path::new(path).file_stem()).map(|stem| stem.to_string_lossy().to_uppercase());
+    let mode = modes.current();
+    status.set_bar(StatusBar { mode: mode.name(), profile, channel: mode.channel(&context), ..StatusBar::default() });
+    status.compose_screen(context.screen).write(&device)?;
+    let mut monitor = MidiMonitor::new();
+    let mut transport = HostTransport::new();
+    let mut chase: Option<u32> = None;
+    let mut fader = LightFader::new();
+    let mut scenes = LightScenes::new();
+    let mut pressure = PressureMeter::new();
+    let mut input_ack = InputAck::new();
+    let mut lock = PerformanceLock::new(&settings.lock);
+    let mut remap = InputRemap::new();
+    let mut fixed_velocity = FixedVelocity::new();
+    let mut quantizer = InputQuantizer::new();
+    let mut arp = Arpeggiator::new(&settings.arp, seed);
+    let mut link = settings.link.enabled.then(|| Link::new(&settings.link, context.clock.now()));
+    let mut link_peers = None;
+    let mut snapshots = Snapshots::new();
+    let mut restore = Restore::new();
+    let mut overlay = ScreenOverlay::new();
+    let mut attract = Attract::new(context.clock.now());
+    let mut failover = settings.osc.failover.enabled.then(|| Failover::new(failover_target, &settings.osc.failover, context.clock.now()));
+    // Sixteenth of the running clock as of the last pass, for quantized input
+    let mut clock_now: Option<u32> = None;
+    // Crossfade of the scene change in progress, over the usual fade time
+    let mut scene_fade: Option<Duration> = None;
+
+    // An edited config applies as soon as it's saved.
+    let mut config_watch = match args.config.as_deref().map(ConfigWatch::new) {
+        Some(Ok(watch)) => Some(watch),
+        Some(Err(e)) => {
+            eprintln!("Not watching the config for changes: {}", e);
+            None
+        }
+        None => None,
+    };
 
     let mut buf = [0u8; 64];
     let mut osc_recv_buf = [0u8; 1024]; 
+    let mut host_events: Vec<HostEvent> = Vec::new();
+    let mut watchdog = Watchdog::default();
+    let mut hid_errors: u32 = 0;
+    let mut poll = AdaptivePoll::new();
+    // The first HID read of each pass blocks for this long, so pad hits wake the loop at once.
+    let mut hid_wait = Duration::ZERO;
 
     loop {
+        if note_guard::shutting_down() {
+            context.midi_port.release_notes();
+            context.midi_port.flush();
+            return Ok(());
+        }
         let mut loop_activity = false;
         let mut should_write_lights = false;
+        let mut should_write_screen = false;
 
         loop {
-            let size = match device.read_timeout(&mut buf, 0) {
-                Ok(s) => s,
+            let size = match device.read_timeout(&mut buf, hid_wait.as_millis() as i32) {
+                Ok(s) => {
+                    hid_errors = 0;
+                    s
+                }
                 Err(e) => {
-                    eprintln!("HID Error: {}", e);
+                    if hid_errors == 0 {
+                        eprintln!("HID Error: {}", e);
+                    }
+                    hid_errors = hid_errors.saturating_add(1);
+                    // A failing device returns at once; don't let that turn into a busy loop.
+                    thread::sleep(hid_wait);
                     0 
                 }
             };
+            hid_wait = Duration::ZERO;
             
             if size == 0 {
+                osc.set_input_time(None);
                 break;
             }
             loop_activity = true;
 
-            let events = parse_hid_report(&buf[..size]);
+            // Everything this report causes is stamped with when it was read.
+            let read_at = context.clock.now();
+            osc.set_input_time(Some(read_at));
+            let mut events = parse_hid_report(&buf[..size]);
+            normalize_pads(&mut events, &settings.pad_calibration);
+            let mut events = remap.apply(events, &settings.remap);
+            orient_pads(&mut events, &settings.pad_grid);
+            if let Some(on) = fixed_velocity.apply(&mut events, &settings.fixed_velocity) {
+                let text = match fixed_velocity.level(&settings.fixed_velocity) {
+                    Some(velocity) if on => format!("FIXED VEL {velocity}"),
+                    _ => "FIXED VEL OFF".to_string(),
+                };
+                status.post(Severity::Info, &text, read_at);
+                should_write_lights = true;
+                should_write_screen = true;
+            }
 
             for event in events {
-                match event {
-                    HardwareEvent::Button { index: Buttons::Maschine, pressed: true } => {
-                        current_mode_id = DriverMode::CustomMidi;
-                        
-                        context.lights.set_button(Buttons::Maschine, Brightness::Bright);
-                        context.lights.set_button(Buttons::Star, Brightness::Dim);
-                        context.lights.set_button(Buttons::Browse, Brightness::Dim);
-                        
-                        custom_midi.on_enter(&mut context);
-                        
-                        screen.reset();
-                        Font::write_string(&mut screen, 0, 0, "MIDI MODE", 1);
-                        screen.write(&device)?;
+                for link in &mut json_links {
+                    link.emit(&event);
+                }
+                if settings.lights.pressure_meter && pressure.handle(&event) {
+                    should_write_lights = true;
+                }
+                if settings.lights.input_ack.is_some() && input_ack.handle(&event, read_at) {
+                    should_write_lights = true;
+                }
+                if settings.attract.enabled && attract.handle(&event, read_at, &settings.attract, context.osc, context.midi_port) {
+                    should_write_lights = true;
+                    should_write_screen = true;
+                }
+                lock.handle(&event, read_at);
+                if let Some(first_press) = lock.block(&event) {
+                    if first_press {
+                        status.post(Severity::Info, "LOCKED", read_at);
+                        should_write_screen = true;
+                    }
+                    continue;
+                }
+                if settings.livecoding.enabled
+                    && let Some(message) = livecoding::pad_message(&event, &settings)
+                {
+                    context.osc.send(message);
+                }
+                should_write_screen |= overlay.handle(&event, &settings);
+                // Group+pad 1-4 recalls a performance snapshot; with Shift it stores one.
+                if let HardwareEvent::Button { index: Buttons::Group, pressed } = event {
+                    group_held = pressed;
+                }
+                if settings.snapshots.enabled
+                    && group_held
+                    && let HardwareEvent::Pad { index, event_type, value } = event
+                    && index < SLOTS
+                {
+                    let held = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+                    if held && !snapshot_pads[index] {
+                        if shift_held {
+                            snapshots.store(index, scenes.active(), modes.play.mix(), &context);
+                            status.post(Severity::Info, &format!("SNAPSHOT {} STORED", index + 1), read_at);
+                            should_write_screen = true;
+                        } else {
+                            host_events.push(HostEvent::Snapshot { slot: index });
+                        }
+                    }
+                    snapshot_pads[index] = held;
+                    continue;
+                }
+                // Maschine+pad jumps straight to a mode; the pad's release doesn't reach it.
+                if let HardwareEvent::Button { index: Buttons::Maschine, pressed } = event {
+                    maschine_held = pressed;
+                }
+                if let HardwareEvent::Pad { index, event_type, value } = event
+                    && (maschine_held || mode_pads[index])
+                {
+                    let held = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+                    if held
+                        && !mode_pads[index]
+                        && let Some(id) = ModeManager::available(&settings).get(index).copied()
+                    {
+                        switch_mode(&mut modes, id, &mut context, &mut status);
+                        should_write_screen = true;
                         should_write_lights = true;
-                    },
-                    HardwareEvent::Button { index: Buttons::Star, pressed: true } => {
-                        current_mode_id = DriverMode::Playability;
-                        
-                        context.lights.set_button(Buttons::Star, Brightness::Bright);
-                        context.lights.set_button(Buttons::Maschine, Brightness::Dim);
-                        context.lights.set_button(Buttons::Browse, Brightness::Dim);
-
-                        play_mode.on_enter(&mut context);
-
-                        screen.reset();
-                        Font::write_string(&mut screen, 0, 0, "PLAY MODE", 1);
-                        screen.write(&device)?;
+                    }
+                    mode_pads[index] = held;
+                    continue;
+                }
+                // The arpeggiator takes its button, and the pads it plays, from Custom MIDI mode.
+                if settings.arp.enabled && modes.current_id() == DriverMode::CustomMidi {
+                    let was_on = arp.is_on();
+                    if arp.handle(&event, context.groups.bank()) {
+                        if arp.is_on() != was_on {
+                            status.post(Severity::Info, if arp.is_on() { "ARP ON" } else { "ARP OFF" }, read_at);
+                            should_write_screen = true;
+                        }
                         should_write_lights = true;
+                        continue;
+                    }
+                }
+                let selection = modes.selection(&event, shift_held, &settings);
+                match event {
+                    HardwareEvent::Button { index: Buttons::Maschine, pressed: true } if shift_held => {
+                        if selection.is_some() {
+                            monitor.toggle();
+                            should_write_screen = true;
+                        }
                     },
-                    HardwareEvent::Button { index: Buttons::Browse, pressed: true } => {
+
+                    HardwareEvent::Button { index: Buttons::Maschine | Buttons::Star | Buttons::Browse, pressed: true } => {
+                        if let Some(id) = selection {
+                            switch_mode(&mut modes, id, &mut context, &mut status);
+                            should_write_screen = true;
+                            should_write_lights = true;
+                        }
                     },
-                    
+
                     _ => {
-                        let mode_changed = match current_mode_id {
-                            DriverMode::CustomMidi => {
-                                let mut mode_ctx = DriverContext {
-                                    lights: context.lights,
-                                    midi_port: context.midi_port,
-                                    osc_socket: context.osc_socket,
-                                    osc_addr: context.osc_addr,
-                                    settings: context.settings,
-                                };
-                                custom_midi.handle_event(&event, &mut mode_ctx);
-                                true 
-                            },
-                            DriverMode::Playability => {
-                                let mut mode_ctx = DriverContext {
-                                    lights: context.lights,
-                                    midi_port: context.midi_port,
-                                    osc_socket: context.osc_socket,
-                                    osc_addr: context.osc_addr,
-                                    settings: context.settings,
-                                };
-                                play_mode.handle_event(&event, &mut mode_ctx);
-                                true
-                            }
+                        if let HardwareEvent::Button { index: Buttons::Shift, pressed } = event {
+                            shift_held = pressed;
+                        }
+                        if quantizer.hold(&event, clock_now, &settings, read_at) {
+                            should_write_lights = true;
+                            continue;
+                        }
+                        let mut mode_ctx = DriverContext {
+                            lights: context.lights,
+                            screen: context.screen,
+                            midi_port: context.midi_port,
+                            osc: context.osc,
+                            uinput: context.uinput,
+                            settings: context.settings,
+                            clock: context.clock,
+                            groups: context.groups,
                         };
-                        if mode_changed { should_write_lights = true; }
+                        let mode = modes.current();
+                        let screen_before = mode_ctx.screen.clone();
+                        mode.handle_event(&event, &mut mode_ctx);
+                        should_write_lights = true;
+                        should_write_screen |= *mode_ctx.screen != screen_before;
                     }
                 }
             }
         }
 
-        if current_mode_id == DriverMode::Playability {
+        let mode_clock = {
+            let mut mode_ctx = DriverContext {
+                lights: context.lights,
+                screen: context.screen,
+                midi_port: context.midi_port,
+                osc: context.osc,
+                uinput: context.uinput,
+                settings: context.settings,
+                clock: context.clock,
+                groups: context.groups,
+            };
+            let mode = modes.current();
+            if mode.tick(&mut mode_ctx) {
+                should_write_lights = true;
+                should_write_screen = true;
+            }
+            mode.clock_position(mode_ctx.clock.now())
+        };
+
+        // Quantized presses and scene changes land on the beat or bar they waited for.
+        clock_now = transport.clock_position().or(mode_clock);
+        let (due_events, mut due_host) = quantizer.due(clock_now, settings.transport.meter());
+        for event in due_events {
             let mut mode_ctx = DriverContext {
                 lights: context.lights,
+                screen: context.screen,
                 midi_port: context.midi_port,
-                osc_socket: context.osc_socket,
-                osc_addr: context.osc_addr,
+                osc: context.osc,
+                uinput: context.uinput,
                 settings: context.settings,
+                clock: context.clock,
+                groups: context.groups,
             };
-            if play_mode.tick(&mut mode_ctx) {
+            let mode = modes.current();
+            mode.handle_event(&event, &mut mode_ctx);
+            should_write_lights = true;
+            should_write_screen = true;
+        }
+
+        let now = context.clock.now();
+        if let Some(watch) = &mut config_watch
+            && watch.changed(now)
+        {
+            match watch.load() {
+                Ok(mut reloaded) => {
+                    let restart = reloaded.keep_startup(&mut settings);
+                    settings = reloaded;
+                    context = DriverContext {
+                        lights: &mut lights,
+                        screen: &mut screen,
+                        midi_port: &mut port,
+                        osc: &osc,
+                        uinput: &mut uinput,
+                        settings: &settings,
+                        clock: &clock,
+                        groups: &mut groups,
+                    };
+                    context.groups.reload(&settings);
+                    if let Some(query) = &oscquery {
+                        query.settings_changed(&settings);
+                    }
+                    modes.settings_changed(&mut context);
+                    mode_entered(&mut modes, &mut context, &mut status);
+                    println!("Reloaded the config");
+                    if !restart.is_empty() {
+                        println!("Restart the driver for the changes to {} to apply", restart.join(", "));
+                    }
+                    status.post(Severity::Info, "CONFIG RELOADED", now);
+                }
+                Err(e) => {
+                    eprintln!("Keeping the running config: {}", e);
+                    status.post(Severity::Warning, "CONFIG ERROR", now);
+                }
+            }
+            should_write_lights = true;
+            should_write_screen = true;
+        }
+        match watchdog.hid.observe(hid_errors, now, &settings.watchdog) {
+            Some(WatchdogAction::Recover) => {
+                eprintln!("Watchdog: HID device is not responding, reopening it");
+                status.post(Severity::Warning, "HID RECOVERING", now);
+                match open_device(&api) {
+                    Ok(d) => {
+                        device = d;
+                        should_write_lights = true;
+                    }
+                    Err(e) => eprintln!("Watchdog: reopening the HID device failed: {}", e),
+                }
+            }
+            Some(WatchdogAction::Escalate) => {
+                eprintln!("Watchdog: HID device keeps failing");
+                status.set_fault("HID ERROR", now);
+                should_write_lights = true;
+                should_write_screen = true;
+            }
+            Some(WatchdogAction::Recovered) => {
+                eprintln!("Watchdog: HID device recovered");
+                status.clear_fault();
+                status.post(Severity::Info, "HID OK", now);
+                should_write_lights = true;
+                should_write_screen = true;
+            }
+            None => {}
+        }
+        match watchdog.midi.observe(context.midi_port.consecutive_errors(), now, &settings.watchdog) {
+            Some(WatchdogAction::Recover) => {
+                eprintln!("Watchdog: MIDI sends are failing, recreating the port");
+                status.post(Severity::Warning, "MIDI RECOVERING", now);
+                should_write_screen = true;
+                if let Err(e) = context.midi_port.reconnect() {
+                    eprintln!("Watchdog: {}", e);
+                }
+            }
+            Some(WatchdogAction::Escalate) => {
+                eprintln!("Watchdog: MIDI port keeps failing");
+                status.set_fault("MIDI ERROR", now);
+                should_write_lights = true;
+                should_write_screen = true;
+            }
+            Some(WatchdogAction::Recovered) => {
+                eprintln!("Watchdog: MIDI port recovered");
+                status.clear_fault();
+                status.post(Severity::Info, "MIDI OK", now);
                 should_write_lights = true;
+                should_write_screen = true;
             }
+            None => {}
         }
 
-        if should_write_lights {
-            context.lights.write(&device)?;
+        // A replayed session stands in for the live listener so runs are deterministic.
+        if let Some(replay) = &mut osc_replay {
+            for packet in replay.poll(context.clock.now()) {
+                loop_activity = true;
+                osc.record_incoming(&packet);
+                should_write_screen |= handle_osc_packet(&packet, context.screen, &mut host_events);
+            }
+            if replay.is_finished() {
+                println!("OSC replay finished.");
+                osc_replay = None;
+            }
         }
 
-        loop {
-            match osc_listener.recv_from(&mut osc_recv_buf) {
-                Ok((size, _)) => {
-                    loop_activity = true;
-                    if let Ok((_, packet)) = decoder::decode_udp(&osc_recv_buf[..size]) {
-                        if let OscPacket::Message(msg) = packet {
-                            if msg.addr == "/maschine/screen/text" {
-                                if let Some(OscType::String(s)) = msg.args.first() {
-                                    screen.reset();
-                                    Font::write_string(&mut screen, 0, 0, s, 1);
-                                    screen.write(&device)?; 
-                                }
+        for osc_listener in osc_listeners.iter().filter(|_| osc_replay.is_none()) {
+            loop {
+                match osc_listener.recv_from(&mut osc_recv_buf) {
+                    Ok((size, from)) => {
+                        loop_activity = true;
+                        osc.record_incoming(&osc_recv_buf[..size]);
+                        if osc.handle_handshake(&osc_recv_buf[..size], from)
+                            || failover.as_mut().is_some_and(|f| f.handle_ack(&osc_recv_buf[..size], now))
+                        {
+                            continue;
+                        }
+                        let ttl = Duration::from_secs(settings.osc.subscription_ttl_s);
+                        if osc.handle_subscription(&osc_recv_buf[..size], from, now, ttl, settings.livecoding.enabled) {
+                            continue;
+                        }
+                        should_write_screen |= handle_osc_packet(&osc_recv_buf[..size], context.screen, &mut host_events);
+                    },
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                        break; 
+                    }
+                    Err(e) => {
+                        eprintln!("OSC error: {}", e);
+                        break;
+                    },
+                }
+            }
+        }
+
+        for link in &mut json_links {
+            for command in link.poll() {
+                loop_activity = true;
+                match command {
+                    JsonCommand::Screen { text } => {
+                        context.screen.reset();
+                        Font::write_string(context.screen, CONTENT_ROW, 0, &text, 1);
+                        should_write_screen = true;
+                    }
+                    JsonCommand::Midi { bytes } => {
+                        if let Err(e) = context.midi_port.send(&bytes) {
+                            eprintln!("Can't send MIDI from a JSON command: {}", e);
+                        }
+                    }
+                    light => should_write_lights |= light.apply_light(context.lights),
+                }
+            }
+        }
+
+        osc.expire_subscribers(now);
+
+        if let Some(midi_in) = &midi_in {
+            for bytes in midi_in.drain() {
+                should_write_screen |= monitor.record(Direction::In, &bytes);
+                if settings.sysex.enabled && sysex::is_dump_request(&bytes) {
+                    let backup = Backup {
+                        config: args.config.as_ref().and_then(|path| fs::read_to_string(path).ok()),
+                        toggles: toggle_states(&context).into_iter().collect(),
+                        sequence: Some(modes.play.to_sequence()).filter(|s| s.length_ms > 0),
+                    };
+                    for message in sysex::encode(&backup) {
+                        if let Err(e) = context.midi_port.send(&message) {
+                            eprintln!("Can't send the SysEx dump: {}", e);
+                            break;
+                        }
+                    }
+                    status.post(Severity::Info, "BACKUP SENT", now);
+                    should_write_screen = true;
+                    continue;
+                }
+                if settings.sysex.enabled
+                    && let Some(result) = restore.feed(&bytes)
+                {
+                    match result {
+                        Ok(backup) => {
+                            let toggles: Vec<(String, bool)> = backup.toggles.into_iter().collect();
+                            set_toggles(&toggles, &mut context);
+                            if matches!(modes.current_id(), DriverMode::CustomMidi | DriverMode::Visualizer) {
+                                context.groups.draw(context.lights);
+                            }
+                            match backup.sequence.map(|s| s.validate().map(|_| s)) {
+                                Some(Ok(sequence)) => host_events.push(HostEvent::LoadLoop(sequence)),
+                                Some(Err(e)) => eprintln!("Not restoring the loop: {}", e),
+                                None => {}
                             }
+                            match (backup.config, args.config.as_deref()) {
+                                (Some(text), Some(path)) if settings.sysex.write_config => match sysex::write_config(path, &text) {
+                                    Ok(()) => println!("Restored {} from SysEx", path),
+                                    Err(e) => eprintln!("Not restoring the config: {}", e),
+                                },
+                                (Some(_), _) => println!("The SysEx backup has a config; set [sysex] write_config to restore it."),
+                                (None, _) => {}
+                            }
+                            status.post(Severity::Info, "BACKUP RESTORED", now);
+                        }
+                        Err(e) => {
+                            eprintln!("Ignoring SysEx restore: {}", e);
+                            status.post(Severity::Warning, "RESTORE FAILED", now);
                         }
                     }
-                },
-                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
-                    break; 
+                    should_write_lights = true;
+                    should_write_screen = true;
+                    continue;
                 }
-                Err(e) => {
-                    eprintln!("OSC error: {}", e);
-                    break;
-                },
+                if settings.transport.follow && transport.handle(&bytes, now) {
+                    let bar = StatusBar {
+                        clock: transport.bar_beat(settings.transport.meter()),
+                        bpm: transport.bpm(),
+                        ..status.bar().clone()
+                    };
+                    should_write_screen |= status.set_bar(bar);
+                    should_write_lights = true;
+                }
+                // Clock comes 24 times a beat; nothing but [clock_in] wants it as an event.
+                host_events.extend(parse_midi_input(&bytes).filter(|e| settings.clock_in.enabled || !matches!(e, HostEvent::Clock(_))));
+            }
+        }
+        if let Some(link) = &mut link
+            && let Some(state) = link.poll(now)
+        {
+            host_events.push(HostEvent::Clock(ClockMessage::Beat { beat: state.beat, bpm: state.bpm }));
+            should_write_screen |= status.set_bar(StatusBar { bpm: Some(state.bpm as f32), ..status.bar().clone() });
+            if link_peers.replace(state.peers) != Some(state.peers) {
+                status.post(Severity::Info, &format!("LINK {} PEERS", state.peers), now);
+                should_write_screen = true;
+            }
+        }
+
+        // Every mode keeps its state in step with the host; an inactive mode draws into a
+        // scratch frame, since it redraws in on_enter anyway.
+        // Quantized events that came due go first, and aren't held back again.
+        if !host_events.is_empty() || !due_host.is_empty() {
+            loop_activity = true;
+            let mut scratch_lights = context.lights.clone();
+            let mut scratch_screen = context.screen.clone();
+            let events = due_host.drain(..).map(|e| (e, true)).chain(host_events.drain(..).map(|e| (e, false)));
+            for (event, due) in events {
+                let quantize = match event {
+                    HostEvent::Scene { .. } | HostEvent::ProgramChange { .. } => settings.transport.scene_quantize,
+                    HostEvent::Snapshot { .. } => settings.snapshots.quantize,
+                    _ => None,
+                };
+                if !due && quantizer.hold_host(&event, quantize, clock_now, now) {
+                    continue;
+                }
+                let recalled = match &event {
+                    HostEvent::Scene { name, fade_ms } => scenes.recall(name, *fade_ms, &settings),
+                    HostEvent::ProgramChange { program } => scenes.recall_program(*program, &settings),
+                    HostEvent::Namespace => {
+                        let arg = OscType::String(namespace::to_json(&settings));
+                        context.osc.send(OscMessage { addr: "/maschine/namespace/data".to_string(), args: vec![arg] });
+                        None
+                    }
+                    HostEvent::Metrics => {
+                        let metrics = serde_json::json!({ "midi": context.midi_port.queue_stats() });
+                        context.osc.send(OscMessage { addr: "/maschine/metrics/data".to_string(), args: vec![OscType::String(metrics.to_string())] });
+                        None
+                    }
+                    HostEvent::Hello if settings.livecoding.enabled => {
+                        let state = livecoding::State {
+                            mode: modes.current().name(),
+                            modes: modes.available_names(&settings),
+                            toggles: toggle_states(&context).into_iter().collect(),
+                            pad_notes: (0..16).map(|i| settings.pad_note(i, context.groups.bank()).1).collect(),
+                            pad_groups: (0..16).map(|i| livecoding::pad_group(&settings, i)).collect(),
+                            sequence: Some(modes.play.to_sequence()).filter(|s| s.length_ms > 0),
+                            bpm: transport.bpm(),
+                        };
+                        let arg = OscType::String(state.to_json());
+                        context.osc.send(OscMessage { addr: livecoding::STATE.to_string(), args: vec![arg] });
+                        None
+                    }
+                    HostEvent::Macro { name } => {
+                        match settings.macros.get(name) {
+                            Some(steps) => run_macro(steps, &mut context),
+                            None => eprintln!("Ignoring /maschine/macro: no macro named {}", name),
+                        }
+                        None
+                    }
+                    HostEvent::Snapshot { slot } => {
+                        let recalled = snapshots.recall(*slot, &mut context);
+                        // Toggle lights belong to the Custom MIDI mappings; other modes redraw them on leaving.
+                        if matches!(modes.current_id(), DriverMode::CustomMidi | DriverMode::Visualizer) {
+                            context.groups.draw(context.lights);
+                        }
+                        // Play mode's patterns only switch while it's up, where its pads show them.
+                        if let Some((_, mix)) = recalled
+                            && modes.current_id() == DriverMode::Playability
+                        {
+                            modes.play.recall_mix(mix, &mut context);
+                        }
+                        should_write_lights = true;
+                        recalled.and_then(|(name, _)| scenes.recall(&name, None, &settings))
+                    }
+                    // The Bitwig extension announces itself when it starts; its page comes up.
+                    HostEvent::Bitwig(BitwigMessage::Hello) if settings.bitwig.enabled && modes.current_id() != DriverMode::Bitwig => {
+                        switch_mode(&mut modes, DriverMode::Bitwig, &mut context, &mut status);
+                        should_write_lights = true;
+                        should_write_screen = true;
+                        None
+                    }
+                    // Mixxx's mapping plays Custom MIDI mode, so that comes up when it loads.
+                    HostEvent::MixxxHello if settings.mixxx.enabled => {
+                        if modes.current_id() != DriverMode::CustomMidi {
+                            switch_mode(&mut modes, DriverMode::CustomMidi, &mut context, &mut status);
+                            should_write_lights = true;
+                        }
+                        status.post(Severity::Info, "MIXXX CONNECTED", now);
+                        should_write_screen = true;
+                        None
+                    }
+                    // The visualizer takes frames itself and fades between them.
+                    HostEvent::LightFrame(frame) if modes.current_id() != DriverMode::Visualizer => {
+                        // All of it lands before the next write, so the frame shows at once.
+                        frame.apply(context.lights);
+                        should_write_lights = true;
+                        None
+                    }
+                    _ => None,
+                };
+                if recalled.is_some() {
+                    scene_fade = recalled;
+                    should_write_lights = true;
+                }
+                let current_id = modes.current_id();
+                for (mode_id, mode) in modes.all() {
+                    let active = mode_id == current_id;
+                    let mut mode_ctx = DriverContext {
+                        lights: if active { &mut *context.lights } else { &mut scratch_lights },
+                        screen: if active { &mut *context.screen } else { &mut scratch_screen },
+                        midi_port: context.midi_port,
+                        osc: context.osc,
+                        uinput: context.uinput,
+                        settings: context.settings,
+                        clock: context.clock,
+                        groups: context.groups,
+                    };
+                    if mode.handle_host_event(&event, &mut mode_ctx) && active {
+                        should_write_lights = true;
+                        should_write_screen = true;
+                    }
+                }
+            }
+        }
+
+        if let Some(failed_over) = failover.as_mut().and_then(|f| f.tick(now, &osc)) {
+            status.set_bar(StatusBar { failover: failed_over, ..status.bar().clone() });
+            if failed_over {
+                status.post(Severity::Warning, "OSC FAILOVER", now);
+            } else {
+                status.post(Severity::Info, "OSC PRIMARY BACK", now);
+            }
+            should_write_screen = true;
+        }
+        let osc_unreachable = osc.consecutive_errors() > 0;
+        if status.set_indicator(Indicator::OscUnreachable, osc_unreachable) {
+            if osc_unreachable {
+                status.post(Severity::Warning, "OSC UNREACHABLE", now);
+            }
+            should_write_lights = true;
+            should_write_screen = true;
+        }
+        if let Some(locked) = lock.tick(now) {
+            status.set_bar(StatusBar { locked, ..status.bar().clone() });
+            status.post(Severity::Info, if locked { "LOCKED" } else { "UNLOCKED" }, now);
+            should_write_screen = true;
+        }
+        if status.tick(now, context.midi_port) {
+            should_write_lights = true;
+            should_write_screen = true;
+        }
+        should_write_lights |= transport.tick(now);
+        should_write_lights |= input_ack.tick(now);
+        should_write_lights |= quantizer.tick(now);
+        if settings.attract.enabled && attract.tick(now, &settings.attract, context.osc, context.midi_port) {
+            should_write_lights = true;
+            should_write_screen = true;
+        }
+        if settings.arp.enabled {
+            let active = modes.current_id() == DriverMode::CustomMidi;
+            // A Link session sets the steps when there is one.
+            let (ticks, bpm) = match &link {
+                Some(link) if link.bpm().is_some() => (link.clock_ticks(now), link.bpm()),
+                _ => (transport.clock_ticks(), transport.bpm()),
+            };
+            should_write_lights |= arp.tick(ticks, bpm, active, now, &settings, context.midi_port);
+        }
+        #[cfg(feature = "audio")]
+        if let Some(meter) = &mut audio_meter
+            && meter.tick(now, &settings.audio)
+        {
+            should_write_lights = true;
+            should_write_screen |= settings.audio.meter;
+        }
+        // The host's clock wins over a mode's own while both run.
+        let chase_now = if settings.transport.pad_chase { transport.clock_position().or(mode_clock) } else { None };
+        if chase_now != chase {
+            chase = chase_now;
+            should_write_lights = true;
+        }
+
+        status.send_cues(context.midi_port, now);
+        context.midi_port.flush();
+        for bytes in context.midi_port.take_sent() {
+            should_write_screen |= monitor.record(Direction::Out, &bytes);
+        }
+
+        if should_write_lights {
+            let mut lights = context.lights.clone();
+            transport.compose_lights(&mut lights);
+            if let Some(sixteenth) = chase {
+                let color = settings.transport.chase_color.pad_color();
+                draw_pad_chase(&mut lights, sixteenth, settings.transport.meter(), color);
+            }
+            if settings.lights.pressure_meter {
+                pressure.compose_lights(&mut lights);
+            }
+            if let Some(level) = settings.lights.input_ack {
+                input_ack.compose_lights(&mut lights, level.brightness());
+            }
+            #[cfg(feature = "audio")]
+            if let Some(meter) = &audio_meter {
+                meter.compose_lights(&mut lights, &settings.audio);
+            }
+            scenes.compose(&mut lights, &settings);
+            quantizer.compose_lights(&mut lights);
+            arp.compose_lights(&mut lights);
+            fixed_velocity.compose_lights(&mut lights, &settings.fixed_velocity);
+            settings.lights.apply_button_levels(&mut lights);
+            if attract.is_running() {
+                attract.compose_lights(&mut lights);
+            }
+            fader.set_target(settings.pad_grid.lights_to_physical(&status.compose_lights(&lights)));
+        }
+        let fade = scene_fade.unwrap_or(Duration::from_millis(settings.lights.fade_ms));
+        if let Some(frame) = fader.next_frame(now, fade)
+            && frame.write(&device).is_err()
+        {
+            hid_errors = hid_errors.saturating_add(1);
+        }
+        if !fader.is_animating() {
+            scene_fade = None;
+        }
+        if should_write_screen {
+            let frame = if monitor.visible() { monitor.compose() } else { status.compose_screen(context.screen) };
+            let frame = overlay.compose(frame, &settings, &snapshots, &modes.available_names(&settings));
+            let frame = if attract.is_running() { attract.compose_screen(&settings.attract) } else { frame };
+            #[cfg(feature = "audio")]
+            let frame = match &audio_meter {
+                Some(meter) if settings.audio.meter => meter.compose_screen(frame),
+                _ => frame,
+            };
+            if frame.write(&device).is_err() {
+                hid_errors = hid_errors.saturating_add(1);
             }
         }
 
-        if !loop_activity {
-            thread::sleep(Duration::from_millis(1));
+        if loop_activity {
+            poll.activity(now);
         }
+        let animating = osc_replay.is_some()
+            || status.is_animating()
+            || transport.is_animating()
+            || chase.is_some()
+            || fader.is_animating()
+            || input_ack.is_animating()
+            || quantizer.is_animating()
+            || arp.is_animating()
+            || lock.is_animating()
+            || attract.is_running()
+            || modes.current().is_animating();
+        // The meter follows the audio, not the controller.
+        #[cfg(feature = "audio")]
+        let animating = animating || audio_meter.is_some();
+        hid_wait = poll.wait(context.clock.now(), animating, &settings.polling);
     }
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use crate::autosave;
+use crate::sequence::{NoteSequence, SeqNote};
+use crate::settings::Settings;
+
+/// Maschine plays Sound 1 of a group on C1 and the other 15 on the notes above.
+const SOUND_ROOT: u8 = 36;
+const DEFAULT_BPM: f64 = 120.0;
+
+/// One pattern as Maschine exported it: a group's sounds as notes, in ticks
+struct Exported {
+    /// 0-based, A to H
+    group: usize,
+    ppq: u32,
+    /// Microseconds per quarter note
+    tempo: u32,
+    /// Ticks in a bar
+    bar: u32,
+    end: u32,
+    /// Start, end, key and velocity
+    notes: Vec<(u32, u32, u8, u8)>,
+}
+
+/// `driver import-maschine`: reads the MIDI files of patterns exported from the Maschine
+/// software (File > Export MIDI, or patterns dragged out of it) and writes each as a
+/// loop to `out`, or `[autosave] dir`, for Play mode's loop browser. Each group plays on
+/// the pad bank of its letter, A the first, and each sound on the pad of its number, so
+/// a pattern plays the driver's kit the way it played Maschine's. Best effort: notes that
+/// aren't one of the 16 sounds keep their key.
+pub fn run(folder: &str, out: Option<&str>, settings: &Settings) -> Result<(), String> {
+    let out = out.or(settings.autosave.dir.as_deref()).ok_or("pass --out or set [autosave] dir for the loops to go to")?;
+    let mut files = Vec::new();
+    collect(Path::new(folder), &mut files).map_err(|e| format!("{folder}: {e}"))?;
+    if files.is_empty() {
+        return Err(format!("no MIDI files in {folder}"));
+    }
+    files.sort();
+    fs::create_dir_all(out).map_err(|e| format!("{out}: {e}"))?;
+    // The browser lists the newest first; the first file gets the newest stamp.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let mut imported = 0;
+    for (i, file) in files.iter().enumerate() {
+        let exported = match fs::read(file).map_err(|e| e.to_string()).and_then(|bytes| read(file, &bytes)) {
+            Ok(exported) => exported,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let (sequence, off_kit) = convert(&exported, settings);
+        if let Err(e) = sequence.validate() {
+            eprintln!("Skipping {}: {}", file.display(), e);
+            continue;
+        }
+        let path = autosave::path(Path::new(out), now - i as u128);
+        fs::write(&path, sequence.to_json()).map_err(|e| format!("{}: {e}", path.display()))?;
+        let bank = exported.group.min(settings.bank_count() - 1);
+        print!("{} -> {} (group {}, bank {}, {} notes", file.display(), path.display(), (b'A' + exported.group as u8) as char, bank + 1, sequence.notes.len());
+        if off_kit > 0 {
+            print!(", {off_kit} not on a sound's note");
+        }
+        println!(")");
+        imported += 1;
+    }
+    println!("Imported {imported} of {} patterns. In Play mode, push the encoder to pick one and push again to load it;", files.len());
+    println!("pick each group's pad bank first so its notes light their pads.");
+    Ok(())
+}
+
+/// The MIDI files under `dir`, in its subfolders too
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mid") || e.eq_ignore_ascii_case("midi")) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The group from the file's or its folder's name: Maschine names groups A1, B1..., and
+/// "Group A" works too. Without one, it's group A.
+fn group_of(path: &Path) -> usize {
+    let names = path.iter().rev().take(2).filter_map(|n| n.to_str()).map(|n| n.trim_end_matches(".mid").to_uppercase());
+    for name in names {
+        let words: Vec<&str> = name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()).collect();
+        for (i, word) in words.iter().enumerate() {
+            let mut chars = word.chars();
+            let letter = chars.next().filter(|c| ('A'..='H').contains(c));
+            let rest = chars.as_str();
+            let group = match letter {
+                Some(letter) if !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()) => Some(letter),
+                Some(letter) if rest.is_empty() && i > 0 && words[i - 1] == "GROUP" => Some(letter),
+                _ => None,
+            };
+            if let Some(letter) = group {
+                return (letter as u8 - b'A') as usize;
+            }
+        }
+    }
+    0
+}
+
+fn read(path: &Path, bytes: &[u8]) -> Result<Exported, String> {
+    let smf = Smf::parse(bytes).map_err(|e| e.to_string())?;
+    let Timing::Metrical(ppq) = smf.header.timing else {
+        return Err("the file counts time in SMPTE frames rather than beats".to_string());
+    };
+    let ppq = ppq.as_int() as u32;
+    let mut exported = Exported { group: group_of(path), ppq, tempo: (60_000_000.0 / DEFAULT_BPM) as u32, bar: ppq * 4, end: 0, notes: Vec::new() };
+    for track in &smf.tracks {
+        let mut tick = 0u32;
+        // Start and velocity of each key still down, by channel
+        let mut down: Vec<(u8, u8, u32, u8)> = Vec::new();
+        for event in track {
+            tick += event.delta.as_int();
+            match event.kind {
+                TrackEventKind::Midi { channel, message } => {
+                    let (key, velocity) = match message {
+                        MidiMessage::NoteOn { key, vel } => (key.as_int(), vel.as_int()),
+                        MidiMessage::NoteOff { key, .. } => (key.as_int(), 0),
+                        _ => continue,
+                    };
+                    let channel = channel.as_int();
+                    if let Some(i) = down.iter().position(|&(c, k, _, _)| c == channel && k == key) {
+                        let (_, _, start, vel) = down.remove(i);
+                        exported.notes.push((start, tick, key, vel));
+                    }
+                    if velocity > 0 {
+                        down.push((channel, key, tick, velocity));
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(tempo)) if tick == 0 => exported.tempo = tempo.as_int(),
+                TrackEventKind::Meta(MetaMessage::TimeSignature(beats, unit, _, _)) if tick == 0 => {
+                    exported.bar = (ppq * 4 * beats as u32) >> unit;
+                }
+                TrackEventKind::Meta(MetaMessage::EndOfTrack) => exported.end = exported.end.max(tick),
+                _ => {}
+            }
+        }
+        // Notes still down at the end last until then.
+        exported.notes.extend(down.into_iter().map(|(_, key, start, vel)| (start, tick, key, vel)));
+    }
+    if exported.notes.is_empty() {
+        return Err("the file has no notes".to_string());
+    }
+    Ok(exported)
+}
+
+/// The pattern as a loop of whole bars, its sounds moved to the notes of the pads in the
+/// group's bank. Returns it with the number of notes that weren't one of the sounds.
+fn convert(exported: &Exported, settings: &Settings) -> (NoteSequence, usize) {
+    let bank = exported.group.min(settings.bank_count() - 1);
+    let ms = |tick: u32| (tick as f64 * exported.tempo as f64 / exported.ppq as f64 / 1000.0).round() as u32;
+    let last = exported.notes.iter().map(|&(start, _, _, _)| start + 1).max().unwrap_or(0).max(exported.end);
+    let bar = exported.bar.max(1);
+    let length = last.div_ceil(bar).max(1) * bar;
+    let mut off_kit = 0;
+    let mut notes: Vec<SeqNote> = exported
+        .notes
+        .iter()
+        .map(|&(start, end, key, velocity)| {
+            let note = match key.checked_sub(SOUND_ROOT).filter(|sound| *sound < 16) {
+                Some(sound) => settings.pad_note(sound as usize, bank).1,
+                None => {
+                    off_kit += 1;
+                    key
+                }
+            };
+            let duration = ms(end.min(length)).saturating_sub(ms(start)).clamp(1, u16::MAX as u32) as u16;
+            SeqNote { start_ms: ms(start), duration_ms: duration, note, velocity }
+        })
+        .collect();
+    notes.sort_by_key(|n| n.start_ms);
+    (NoteSequence { length_ms: ms(length), notes }, off_kit)
+}
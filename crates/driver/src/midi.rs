@@ -0,0 +1,82 @@
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection, SendError};
+use midir::os::unix::{VirtualInput, VirtualOutput};
+use std::sync::mpsc::{self, Receiver};
+use crate::context::MidiSink;
+
+/// The driver's virtual MIDI output port. Keeps track of failing sends so the
+/// watchdog can notice a dead backend, and can recreate the port on request.
+pub struct VirtualMidiOut {
+    client_name: String,
+    port_name: String,
+    conn: Option<MidiOutputConnection>,
+    consecutive_errors: u32,
+}
+
+impl VirtualMidiOut {
+    pub fn open(client_name: &str, port_name: &str) -> Result<Self, String> {
+        let mut port = Self {
+            client_name: client_name.to_string(),
+            port_name: port_name.to_string(),
+            conn: None,
+            consecutive_errors: 0,
+        };
+        port.reconnect()?;
+        Ok(port)
+    }
+}
+
+impl MidiSink for VirtualMidiOut {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError> {
+        let res = match &mut self.conn {
+            Some(conn) => conn.send(message),
+            None => Err(SendError::Other("MIDI port is closed")),
+        };
+        match res {
+            Ok(()) => self.consecutive_errors = 0,
+            Err(_) => self.consecutive_errors = self.consecutive_errors.saturating_add(1),
+        }
+        res
+    }
+
+    fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        // Drop the old connection first so the virtual port name is free again.
+        if let Some(conn) = self.conn.take() {
+            conn.close();
+        }
+        let output = MidiOutput::new(&self.client_name).map_err(|e| format!("Couldn't open MIDI output: {e}"))?;
+        let conn = output
+            .create_virtual(&self.port_name)
+            .map_err(|e| format!("Couldn't create virtual port: {e}"))?;
+        self.conn = Some(conn);
+        self.consecutive_errors = 0;
+        Ok(())
+    }
+}
+
+/// The driver's virtual MIDI input port, for feedback from the host. Messages arrive on
+/// midir's thread and are queued until the main loop drains them.
+pub struct VirtualMidiIn {
+    _conn: MidiInputConnection<()>,
+    rx: Receiver<Vec<u8>>,
+}
+
+impl VirtualMidiIn {
+    pub fn open(client_name: &str, port_name: &str) -> Result<Self, String> {
+        let input = MidiInput::new(client_name).map_err(|e| format!("Couldn't open MIDI input: {e}"))?;
+        let (tx, rx) = mpsc::channel();
+        let conn = input
+            .create_virtual(port_name, move |_, message, _| {
+                let _ = tx.send(message.to_vec());
+            }, ())
+            .map_err(|e| format!("Couldn't create virtual input port: {e}"))?;
+        Ok(Self { _conn: conn, rx })
+    }
+
+    pub fn drain(&self) -> Vec<Vec<u8>> {
+        self.rx.try_iter().collect()
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use midly::num::{u15, u24, u28, u4, u7};
+use midly::{Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+use crate::transport::Meter;
+
+const TICKS_PER_BEAT: u16 = 480;
+
+/// A note of the loop, in time from its top
+pub struct FileNote {
+    pub start: Duration,
+    pub end: Duration,
+    /// 0-based
+    pub channel: u8,
+    pub note: u8,
+    pub velocity: u8,
+}
+
+/// The loop as a Standard MIDI File: one track with the tempo, the meter and the loop's
+/// name up front, then the notes, and End of Track exactly one pass after the top, so a
+/// DAW makes the clip as long as the loop.
+pub fn encode(notes: &[FileNote], length: Duration, bpm: f64, meter: Meter) -> Vec<u8> {
+    let ticks = |at: Duration| (at.as_secs_f64() * bpm / 60.0 * TICKS_PER_BEAT as f64).round() as u32;
+    let end = ticks(length);
+    let mut timed: Vec<(u32, TrackEventKind)> = Vec::new();
+    for note in notes {
+        let channel = u4::new(note.channel);
+        let (key, vel) = (u7::new(note.note), u7::new(note.velocity));
+        timed.push((ticks(note.start).min(end), TrackEventKind::Midi { channel, message: MidiMessage::NoteOn { key, vel } }));
+        timed.push((ticks(note.end).min(end), TrackEventKind::Midi { channel, message: MidiMessage::NoteOff { key, vel: u7::new(0) } }));
+    }
+    // Note offs go before note ons at the same time, so a repeated note isn't cut short.
+    timed.sort_by_key(|(at, kind)| (*at, matches!(kind, TrackEventKind::Midi { message: MidiMessage::NoteOn { .. }, .. })));
+
+    let tempo = (60_000_000.0 / bpm).round() as u32;
+    let denominator = meter.beat_unit.trailing_zeros() as u8;
+    let mut track = vec![
+        TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::TrackName(b"Maschine loop")) },
+        TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(tempo))) },
+        TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::TimeSignature(meter.beats_per_bar, denominator, 24, 8)) },
+    ];
+    let mut last = 0;
+    for (at, kind) in timed {
+        track.push(TrackEvent { delta: u28::new(at - last), kind });
+        last = at;
+    }
+    track.push(TrackEvent { delta: u28::new(end - last), kind: TrackEventKind::Meta(MetaMessage::EndOfTrack) });
+
+    let smf = Smf { header: Header::new(Format::SingleTrack, Timing::Metrical(u15::new(TICKS_PER_BEAT))), tracks: vec![track] };
+    let mut bytes = Vec::new();
+    // Writing to memory only fails on events midly can't represent, and these are all plain.
+    let _ = smf.write_std(&mut bytes);
+    bytes
+}
+
+/// Writes the file to `dir` as `loop-<unix ms>.mid` on a thread of its own. Returns the
+/// path it goes to; a failed write is reported and otherwise ignored.
+pub fn save(dir: &str, bytes: Vec<u8>) -> PathBuf {
+    let dir = PathBuf::from(dir);
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let path = dir.join(format!("loop-{stamp:013}.mid"));
+    let target = path.clone();
+    thread::spawn(move || {
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&target, bytes)) {
+            eprintln!("Writing {} failed: {}", target.display(), e);
+        }
+    });
+    path
+}
@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use midir::SendError;
+use serde::{Deserialize, Serialize};
+use crate::context::MidiSink;
+use crate::settings::MidiQueueSettings;
+
+/// What goes out first when the port can't keep up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Priority {
+    /// Notes, pressure and pitch bend: what you hear
+    Note = 0,
+    /// Clock, start/stop and song position
+    Clock = 1,
+    /// CCs, program changes and SysEx, mostly feedback for the host's controls
+    Feedback = 2,
+}
+
+impl Priority {
+    pub fn of(message: &[u8]) -> Priority {
+        match message.first().copied().unwrap_or(0) {
+            0x80..=0xAF | 0xD0..=0xEF => Priority::Note,
+            0xF2 | 0xF8..=0xFC => Priority::Clock,
+            _ => Priority::Feedback,
+        }
+    }
+}
+
+/// What happens to feedback once `capacity` messages wait.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// A newer value of the same CC replaces the one waiting; past that, the oldest goes.
+    Coalesce,
+    DropOldest,
+    DropNewest,
+}
+
+/// Counters for one priority class
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ClassStats {
+    pub sent: u64,
+    pub dropped: u64,
+    /// Replaced by a newer value before going out
+    pub coalesced: u64,
+    /// Waiting right now
+    pub queued: usize,
+    /// Longest wait of any message, in microseconds
+    pub max_latency_us: u64,
+}
+
+/// Counters of the send queue, by class, for `/maschine/metrics`
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct QueueStats {
+    pub notes: ClassStats,
+    pub clock: ClassStats,
+    pub feedback: ClassStats,
+}
+
+struct Queued {
+    bytes: Vec<u8>,
+    at: Instant,
+}
+
+/// Outgoing MIDI in priority classes. Notes and clock go straight out unless something of
+/// theirs is still waiting; feedback waits for `flush` at the end of each pass, which sends
+/// at most `max_per_pass` of it after everything else. A send the port refuses stays at
+/// the front of its queue for the next pass, so a flood of light feedback can neither
+/// delay nor drop a note off. Each class holds at most `capacity` messages; past that, a
+/// new note is dropped, clock loses its oldest and feedback follows `overflow`, but note
+/// offs and SysEx always wait their turn. Disabled, it sends everything at once, in order.
+pub struct MidiQueue<S: MidiSink> {
+    inner: S,
+    settings: MidiQueueSettings,
+    queues: [VecDeque<Queued>; 3],
+    stats: [ClassStats; 3],
+}
+
+impl<S: MidiSink> MidiQueue<S> {
+    pub fn new(inner: S, settings: &MidiQueueSettings) -> Self {
+        Self { inner, settings: settings.clone(), queues: Default::default(), stats: Default::default() }
+    }
+
+    /// Sends from the front of a class's queue until it's empty, the budget is spent or
+    /// the port refuses. Returns false on a refusal.
+    fn drain(&mut self, class: usize, budget: &mut usize, now: Instant) -> bool {
+        while let Some(front) = self.queues[class].front() {
+            if *budget == 0 {
+                return true;
+            }
+            if self.inner.send(&front.bytes).is_err() {
+                return false;
+            }
+            let waited = now.duration_since(front.at);
+            self.queues[class].pop_front();
+            self.record_sent(class, waited);
+            *budget -= 1;
+        }
+        true
+    }
+
+    fn record_sent(&mut self, class: usize, waited: Duration) {
+        let stats = &mut self.stats[class];
+        stats.sent += 1;
+        stats.max_latency_us = stats.max_latency_us.max(waited.as_micros() as u64);
+    }
+
+    fn enqueue(&mut self, class: Priority, message: &[u8], now: Instant) {
+        let queue = &mut self.queues[class as usize];
+        let stats = &mut self.stats[class as usize];
+        if class == Priority::Feedback && self.settings.overflow == OverflowPolicy::Coalesce && is_cc(message) {
+            // Only the latest value of a control matters to the host.
+            if let Some(waiting) = queue.iter_mut().find(|q| is_cc(&q.bytes) && q.bytes[..2] == message[..2]) {
+                waiting.bytes = message.to_vec();
+                stats.coalesced += 1;
+                return;
+            }
+        }
+        if queue.len() >= self.settings.capacity && !is_kept(message) {
+            stats.dropped += 1;
+            // A new note is worth less than the note offs waiting ahead of it.
+            let drop_newest = match class {
+                Priority::Note => true,
+                Priority::Clock => false,
+                Priority::Feedback => self.settings.overflow == OverflowPolicy::DropNewest,
+            };
+            if drop_newest {
+                return;
+            }
+            if let Some(oldest) = queue.iter().position(|q| !is_kept(&q.bytes)) {
+                queue.remove(oldest);
+            }
+        }
+        queue.push_back(Queued { bytes: message.to_vec(), at: now });
+    }
+}
+
+fn is_cc(message: &[u8]) -> bool {
+    message.len() == 3 && message[0] & 0xF0 == 0xB0
+}
+
+/// SysEx and note offs are never dropped: a backup missing a part is no backup, and a note
+/// whose off is lost hangs.
+fn is_kept(message: &[u8]) -> bool {
+    match message {
+        [0xF0, ..] => true,
+        [status, ..] if status & 0xF0 == 0x80 => true,
+        [status, _, 0] if status & 0xF0 == 0x90 => true,
+        _ => false,
+    }
+}
+
+impl<S: MidiSink> MidiSink for MidiQueue<S> {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError> {
+        if !self.settings.enabled {
+            return self.inner.send(message);
+        }
+        let class = Priority::of(message);
+        let now = Instant::now();
+        // Nothing of the same or a higher class may be waiting, or the order would change.
+        let clear = self.queues[..=class as usize].iter().all(VecDeque::is_empty);
+        if class != Priority::Feedback && clear && self.inner.send(message).is_ok() {
+            self.record_sent(class as usize, Duration::ZERO);
+            return Ok(());
+        }
+        self.enqueue(class, message, now);
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let mut unlimited = usize::MAX;
+        if !self.drain(Priority::Note as usize, &mut unlimited, now) || !self.drain(Priority::Clock as usize, &mut unlimited, now) {
+            return;
+        }
+        let mut budget = self.settings.max_per_pass;
+        self.drain(Priority::Feedback as usize, &mut budget, now);
+    }
+
+    fn consecutive_errors(&self) -> u32 {
+        self.inner.consecutive_errors()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.inner.reconnect()
+    }
+
+    fn take_sent(&mut self) -> Vec<Vec<u8>> {
+        self.inner.take_sent()
+    }
+
+    fn queue_stats(&self) -> Option<QueueStats> {
+        let [notes, clock, feedback] = std::array::from_fn(|i| ClassStats { queued: self.queues[i].len(), ..self.stats[i].clone() });
+        Some(QueueStats { notes, clock, feedback })
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use config::Config;
+use crate::settings::Settings;
+
+/// Schema version of configs written for this driver; `config_version` in the file.
+/// Files without one predate versioning and count as 0.
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+/// One schema upgrade: rewrites the config text from `to - 1` to `to`, returning what it changed.
+struct Migration {
+    to: u32,
+    apply: fn(&str) -> (String, Vec<String>),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration { to: 1, apply: stamp_version }];
+
+/// Version 1 only starts numbering the schema; the settings themselves didn't change.
+fn stamp_version(text: &str) -> (String, Vec<String>) {
+    let text = format!("config_version = 1\n{}", text);
+    (text, vec!["added config_version = 1".to_string()])
+}
+
+/// `config_version` from the top-level table, read from the text so that files the
+/// current settings can't load yet still work.
+fn file_version(text: &str) -> Result<u32, String> {
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "config_version"
+        {
+            let value = value.split('#').next().unwrap_or("").trim();
+            return value.parse().map_err(|_| format!("config_version should be a number, found {value}"));
+        }
+    }
+    Ok(0)
+}
+
+/// Upgrades `text` to the current schema. Returns the new text and one line per change.
+pub fn migrate(text: &str) -> Result<(String, Vec<String>), String> {
+    let version = file_version(text)?;
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(format!(
+            "the config is version {version}, newer than this driver (version {CURRENT_CONFIG_VERSION})"
+        ));
+    }
+    let mut text = text.to_string();
+    let mut report = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.to > version) {
+        let (upgraded, changes) = (migration.apply)(&text);
+        text = upgraded;
+        report.extend(changes.into_iter().map(|c| format!("v{}: {}", migration.to, c)));
+    }
+    Ok((text, report))
+}
+
+/// `driver migrate-config <path>`: reports what an upgrade changes and, with `write`,
+/// saves it, keeping the original next to it as `<path>.bak`.
+pub fn run(path: &str, write: bool) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let (upgraded, report) = migrate(&text)?;
+    if report.is_empty() {
+        println!("{} is already at config version {}", path, CURRENT_CONFIG_VERSION);
+        return Ok(());
+    }
+    for change in &report {
+        println!("{}", change);
+    }
+
+    let settings: Settings = Config::builder()
+        .add_source(config::File::from_str(&upgraded, config::FileFormat::Toml))
+        .build()
+        .and_then(|c| c.try_deserialize())
+        .map_err(|e| format!("the upgraded config doesn't load: {e}"))?;
+    settings.validate().map_err(|e| format!("the upgraded config doesn't validate: {e}"))?;
+
+    if write {
+        let backup = format!("{path}.bak");
+        fs::copy(path, &backup).map_err(|e| format!("{backup}: {e}"))?;
+        fs::write(path, upgraded).map_err(|e| format!("{path}: {e}"))?;
+        println!("Upgraded {} (the original is in {})", path, backup);
+    } else {
+        println!("Run again with --write to save this.");
+    }
+    Ok(())
+}
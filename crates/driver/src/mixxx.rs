@@ -0,0 +1,92 @@
+use std::fmt::Write as _;
+use crate::settings::Settings;
+
+/// What the mapping's script sends from `init`; same header as the SysEx backup.
+pub const HELLO: [u8; 5] = [0xF0, 0x7D, 0x4D, 0x40, 0xF7];
+
+/// `"[Channel1],play"` as group and key
+pub fn split_control(control: &str) -> Option<(&str, &str)> {
+    let (group, key) = control.split_once(',')?;
+    let (group, key) = (group.trim(), key.trim());
+    (group.starts_with('[') && group.ends_with(']') && !key.is_empty()).then_some((group, key))
+}
+
+/// The control Mixxx reports `key`'s state on: the indicator for play and cue, and
+/// `..._enabled` for hot cues and loops, since their `_activate` controls don't stay on.
+fn indicator(key: &str) -> String {
+    match key {
+        "play" => "play_indicator".to_string(),
+        "cue_default" | "cue_gotoandplay" => "cue_indicator".to_string(),
+        _ => match key.strip_suffix("_activate") {
+            Some(control) => format!("{control}_enabled"),
+            None => key.to_string(),
+        },
+    }
+}
+
+fn control(out: &mut String, group: &str, key: &str, status: u8, number: u8) {
+    let _ = write!(
+        out,
+        "            <control>\n                <group>{group}</group>\n                <key>{key}</key>\n                \
+         <status>0x{status:02X}</status>\n                <midino>0x{number:02X}</midino>\n                \
+         <options><normal/></options>\n            </control>\n"
+    );
+}
+
+fn output(out: &mut String, group: &str, key: &str, status: u8, number: u8, on: u8) {
+    let _ = write!(
+        out,
+        "            <output>\n                <group>{group}</group>\n                <key>{}</key>\n                \
+         <status>0x{status:02X}</status>\n                <midino>0x{number:02X}</midino>\n                \
+         <on>0x{on:02X}</on>\n                <off>0x00</off>\n                <minimum>0.5</minimum>\n            </output>\n",
+        indicator(key)
+    );
+}
+
+/// The Mixxx mapping for what Custom MIDI mode sends with this config: `[mixxx] pads` on
+/// the pads' notes, lit back through `[pad_feedback] channel`, and `[mixxx] buttons` on
+/// their `cc`, lit back on the same controller.
+pub fn mapping(settings: &Settings) -> Result<String, String> {
+    let mut controls = String::new();
+    let mut outputs = String::new();
+    let mut mapped: Vec<(u8, u8)> = Vec::new();
+    for (pad, name) in settings.mixxx.pads.iter().enumerate().take(16) {
+        let (group, key) = split_control(name).ok_or_else(|| format!("mixxx.pads: expected \"[Group],key\" (found {name})"))?;
+        let (channel, note) = settings.pad_note(pad, 0);
+        // Mixxx takes one control per message, so the first pad playing a note keeps it.
+        if mapped.contains(&(channel, note)) {
+            eprintln!("Pad {} plays the same note as an earlier pad; leaving it out of the mapping", pad + 1);
+            continue;
+        }
+        mapped.push((channel, note));
+        control(&mut controls, group, key, 0x90 | channel, note);
+        control(&mut controls, group, key, 0x80 | channel, note);
+        if let Some(feedback) = settings.pad_feedback.channel {
+            output(&mut outputs, group, key, 0x90 | (feedback - 1), note, settings.mixxx.pad_on);
+        }
+    }
+    let mut buttons: Vec<_> = settings.mixxx.buttons.iter().collect();
+    buttons.sort();
+    for (button, name) in buttons {
+        let (group, key) = split_control(name).ok_or_else(|| format!("mixxx.buttons.{button}: expected \"[Group],key\" (found {name})"))?;
+        let cc = settings
+            .button_configs
+            .get(button)
+            .and_then(|c| c.cc)
+            .ok_or_else(|| format!("mixxx.buttons.{button}: give the button a cc in [button_configs]"))?;
+        let status = 0xB0 | settings.button_channel(button);
+        control(&mut controls, group, key, status, cc);
+        output(&mut outputs, group, key, status, cc, 0x7F);
+    }
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <!-- Written by `driver export-mixxx-mapping` from the driver's config. -->\n\
+         <MixxxControllerPreset mixxxVersion=\"2.3.0+\" schemaVersion=\"1\">\n    \
+         <info>\n        <name>Maschinette</name>\n        \
+         <description>Maschine Mikro MK3 through the Maschinette driver's Custom MIDI mode</description>\n    </info>\n    \
+         <controller id=\"Maschinette\">\n        <scriptfiles>\n            \
+         <file filename=\"Maschinette.js\" functionprefix=\"Maschinette\"/>\n        </scriptfiles>\n        \
+         <controls>\n{controls}        </controls>\n        <outputs>\n{outputs}        </outputs>\n    \
+         </controller>\n</MixxxControllerPreset>\n"
+    ))
+}
@@ -0,0 +1,206 @@
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::status::CONTENT_ROW;
+use super::{draw_value_bar, ButtonEdges, MachineMode, ParamEncoder};
+
+/// Same header as the SysEx backup; the Bitwig script's messages use their own commands.
+const HEADER: [u8; 3] = [0xF0, 0x7D, 0x4D];
+const HELLO: u8 = 0x30;
+const PARAMETER: u8 = 0x31;
+const DEVICE: u8 = 0x32;
+const PAGE: u8 = 0x33;
+/// Sent on entering the mode; the script answers with everything it would otherwise send
+/// as it changes.
+const REFRESH: [u8; 5] = [0xF0, 0x7D, 0x4D, 0x3F, 0xF7];
+
+// Controllers the driver sends the script, on `[bitwig] channel`.
+/// 0-7: sets that remote control to the value
+const CC_PARAMETER: u8 = 0;
+/// Steps through the device's remote control pages by `value - 64`
+const CC_PAGE: u8 = 20;
+/// Steps through the track's devices by `value - 64`
+const CC_DEVICE: u8 = 21;
+
+const PARAMETERS: usize = 8;
+/// Below the value bar; status banners cover it while they're up
+const INFO_ROW: usize = CONTENT_ROW + 15;
+/// Encoder step per detent, in 0-127 units; one with Shift
+const COARSE_STEP: i32 = 4;
+
+/// What the Bitwig extension reports, as SysEx on the driver's MIDI input:
+/// `F0 7D 4D 30 <version> F7` when it starts (which brings the Bitwig page up),
+/// `F0 7D 4D 31 <parameter 0-7> <value> <name...> F7` for each remote control, and
+/// `F0 7D 4D 32 <name...> F7` / `F0 7D 4D 33 <name...> F7` for the device and page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitwigMessage {
+    Hello,
+    Parameter { index: usize, value: u8, name: String },
+    Device(String),
+    Page(String),
+}
+
+pub fn parse(bytes: &[u8]) -> Option<BitwigMessage> {
+    let body = bytes.strip_prefix(&HEADER[..])?.strip_suffix(&[0xF7])?;
+    let (&command, args) = body.split_first()?;
+    let text = |bytes: &[u8]| bytes.iter().map(|b| *b as char).collect::<String>();
+    match (command, args) {
+        (HELLO, [_version]) => Some(BitwigMessage::Hello),
+        (PARAMETER, [index @ 0..=7, value, name @ ..]) => {
+            Some(BitwigMessage::Parameter { index: *index as usize, value: *value, name: text(name) })
+        }
+        (DEVICE, name) => Some(BitwigMessage::Device(text(name))),
+        (PAGE, name) => Some(BitwigMessage::Page(text(name))),
+        _ => None,
+    }
+}
+
+/// Bitwig Studio through the companion controller extension (`driver
+/// install-bitwig-script`): the selected device's eight remote controls. Pads 1-8 pick
+/// one, the encoder turns it (finely with Shift) and the touch strip sets it outright.
+/// Left/Right step through the remote pages, Shift+Left/Right through the devices. The
+/// screen shows the device, page, and the picked control's name and value.
+pub struct BitwigMode {
+    connected: bool,
+    device: String,
+    page: String,
+    parameters: [(String, u8); PARAMETERS],
+    selected: usize,
+    encoder: ParamEncoder,
+    shift: bool,
+    /// Left and Right as of the last report, since button states repeat
+    buttons: ButtonEdges,
+}
+
+impl BitwigMode {
+    pub fn new() -> Self {
+        Self {
+            connected: false,
+            device: String::new(),
+            page: String::new(),
+            parameters: Default::default(),
+            selected: 0,
+            encoder: ParamEncoder::new(),
+            shift: false,
+            buttons: ButtonEdges::new(),
+        }
+    }
+
+    fn send_cc(&self, controller: u8, value: u8, ctx: &mut DriverContext) {
+        let message = MidiMessage::Controller { controller: controller.into(), value: value.into() };
+        let mut midibuf = Vec::new();
+        let channel = ctx.settings.bitwig.channel - 1;
+        if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+            let _ = ctx.midi_port.send(&midibuf[..]);
+        }
+    }
+
+    fn set_value(&mut self, value: u8, ctx: &mut DriverContext) {
+        if self.parameters[self.selected].1 != value {
+            self.parameters[self.selected].1 = value;
+            self.send_cc(CC_PARAMETER + self.selected as u8, value, ctx);
+            self.draw_screen(ctx);
+        }
+    }
+
+    fn update_pad_lights(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            if i == self.selected {
+                ctx.lights.set_pad(i, PadColors::Orange, Brightness::Bright);
+            } else if i < PARAMETERS && !self.parameters[i].0.is_empty() {
+                ctx.lights.set_pad(i, PadColors::Orange, Brightness::Dim);
+            } else {
+                ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+            }
+        }
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        if !self.connected {
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, "WAITING FOR BWS", 1);
+            return;
+        }
+        let (name, value) = &self.parameters[self.selected];
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{} {}", self.selected + 1, name), 1);
+        draw_value_bar(ctx.screen, *value);
+        Font::write_string(ctx.screen, INFO_ROW, 0, &format!("{} {}", self.device, self.page), 1);
+    }
+}
+
+impl MachineMode for BitwigMode {
+    fn name(&self) -> &'static str {
+        "BWS"
+    }
+
+    fn channel(&self, ctx: &DriverContext) -> u8 {
+        ctx.settings.bitwig.channel - 1
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        let _ = ctx.midi_port.send(&REFRESH);
+        self.update_pad_lights(ctx);
+        self.draw_screen(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match *event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value }
+                if value > 0 && index < PARAMETERS && index != self.selected =>
+            {
+                self.selected = index;
+                self.update_pad_lights(ctx);
+                self.draw_screen(ctx);
+            }
+            HardwareEvent::Button { index, pressed } => {
+                self.encoder.button(index, pressed);
+                let step = match index {
+                    Buttons::Shift => {
+                        self.shift = pressed;
+                        return;
+                    }
+                    Buttons::Left => 63,
+                    Buttons::Right => 65,
+                    _ => return,
+                };
+                if self.buttons.pressed(index, pressed) {
+                    self.send_cc(if self.shift { CC_DEVICE } else { CC_PAGE }, step, ctx);
+                }
+            }
+            HardwareEvent::Encoder { value } => {
+                let delta = self.encoder.delta(value, COARSE_STEP);
+                if delta != 0 {
+                    let next = (self.parameters[self.selected].1 as i32 + delta).clamp(0, 127) as u8;
+                    self.set_value(next, ctx);
+                }
+            }
+            // The strip reports 1-200 while touched and 0 on release.
+            HardwareEvent::Slider { value } if value > 0 => {
+                let next = ((value as u32 - 1) * 127 / 199) as u8;
+                self.set_value(next, ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        let HostEvent::Bitwig(message) = event else {
+            return false;
+        };
+        self.connected = true;
+        match message {
+            BitwigMessage::Hello => {}
+            BitwigMessage::Parameter { index, value, name } => {
+                self.parameters[*index] = (name.clone(), *value);
+                self.update_pad_lights(ctx);
+            }
+            BitwigMessage::Device(name) => self.device = name.clone(),
+            BitwigMessage::Page(name) => self.page = name.clone(),
+        }
+        self.draw_screen(ctx);
+        true
+    }
+}
@@ -0,0 +1,167 @@
+use midly::{live::LiveEvent, MidiMessage};
+use maschine_library::controls::PadEventType;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::monitor::note_name;
+use crate::status::CONTENT_ROW;
+use super::MachineMode;
+use super::voices::Voices;
+
+/// Semitones above the root of the chords `[chords]` knows by name
+pub fn builtin(name: &str) -> Option<&'static [u8]> {
+    Some(match name {
+        "major" => &[0, 4, 7],
+        "minor" => &[0, 3, 7],
+        "dim" => &[0, 3, 6],
+        "aug" => &[0, 4, 8],
+        "sus2" => &[0, 2, 7],
+        "sus4" => &[0, 5, 7],
+        "major7" => &[0, 4, 7, 11],
+        "minor7" => &[0, 3, 7, 10],
+        "dominant7" => &[0, 4, 7, 10],
+        "half_dim7" => &[0, 3, 6, 10],
+        "dim7" => &[0, 3, 6, 9],
+        "add9" => &[0, 4, 7, 14],
+        "major9" => &[0, 4, 7, 11, 14],
+        "minor9" => &[0, 3, 7, 10, 14],
+        _ => return None,
+    })
+}
+
+/// One chord per pad, from `[chords] pads`: a hit plays every note of the pad's chord
+/// on `[chords] channel`, or spread over `channels`. Pads with plain triads light blue and chords with extensions
+/// (sevenths and up) violet; the screen shows the last chord with its root apart from
+/// the rest.
+pub struct ChordMode {
+    /// Notes each held pad started, so its note offs match
+    sounding: [Vec<u8>; 16],
+    /// Pad of the chord on screen
+    shown: Option<usize>,
+    voices: Voices,
+}
+
+impl ChordMode {
+    pub fn new() -> Self {
+        Self { sounding: Default::default(), shown: None, voices: Voices::new() }
+    }
+
+    /// Notes of a pad's chord within MIDI's range, root first
+    fn notes(pad: usize, ctx: &DriverContext) -> Vec<u8> {
+        let settings = &ctx.settings.chords;
+        let Some(chord) = settings.pads.get(pad) else {
+            return Vec::new();
+        };
+        settings
+            .intervals(&chord.chord)
+            .unwrap_or_default()
+            .iter()
+            .map(|i| chord.root as u32 + *i as u32)
+            .filter(|n| *n < 128)
+            .map(|n| n as u8)
+            .collect()
+    }
+
+    fn update_pad_light(&self, pad: usize, ctx: &mut DriverContext) {
+        let notes = Self::notes(pad, ctx);
+        if notes.is_empty() {
+            ctx.lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            return;
+        }
+        let color = if notes.len() > 3 { PadColors::Violet } else { PadColors::Blue };
+        let brightness = if self.sounding[pad].is_empty() { Brightness::Dim } else { Brightness::Bright };
+        ctx.lights.set_pad(pad, color, brightness);
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let Some(pad) = self.shown else {
+            let text = if ctx.settings.chords.pads.is_empty() { "NO CHORDS" } else { "PLAY A PAD" };
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, text, 1);
+            return;
+        };
+        let chord = &ctx.settings.chords.pads[pad];
+        let notes = Self::notes(pad, ctx);
+        let label = format!("{} {}", note_name(chord.root), chord.chord.to_uppercase());
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &label, 1);
+        // The root on a line of its own, the notes stacked on it below.
+        let extensions: Vec<String> = notes.iter().skip(1).map(|n| note_name(*n)).collect();
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &format!("+ {}", extensions.join(" ")), 1);
+    }
+
+    fn send(channel: u8, message: MidiMessage, ctx: &mut DriverContext) {
+        let live_event = LiveEvent::Midi { channel: channel.into(), message };
+        let mut midibuf = Vec::new();
+        if live_event.write(&mut midibuf).is_ok() {
+            let _ = ctx.midi_port.send(&midibuf[..]);
+        }
+    }
+
+    fn release(&mut self, pad: usize, ctx: &mut DriverContext) {
+        for note in std::mem::take(&mut self.sounding[pad]) {
+            if let Some(channel) = self.voices.stop(pad, note) {
+                Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+            }
+        }
+    }
+}
+
+impl MachineMode for ChordMode {
+    fn name(&self) -> &'static str {
+        "CHRD"
+    }
+
+    /// The first of `channels` when the notes are spread over several
+    fn channel(&self, ctx: &DriverContext) -> u8 {
+        let settings = &ctx.settings.chords;
+        settings.channels.first().copied().unwrap_or(settings.channel) - 1
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        self.draw_screen(ctx);
+    }
+
+    fn on_exit(&mut self, ctx: &mut DriverContext) {
+        for pad in 0..16 {
+            self.release(pad, ctx);
+        }
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match *event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value } if value > 0 => {
+                let notes = Self::notes(index, ctx);
+                if notes.is_empty() {
+                    return;
+                }
+                self.release(index, ctx);
+                let velocity = ((value >> 5) as u8).max(1);
+                let settings = &ctx.settings.chords;
+                for note in &notes {
+                    let (channel, stolen) = self.voices.start(index, *note, settings.channel, &settings.channels, settings.allocation);
+                    if let Some((channel, note)) = stolen {
+                        Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+                    }
+                    Self::send(channel, MidiMessage::NoteOn { key: (*note).into(), vel: velocity.into() }, ctx);
+                }
+                self.sounding[index] = notes;
+                self.update_pad_light(index, ctx);
+                if self.shown != Some(index) {
+                    self.shown = Some(index);
+                    self.draw_screen(ctx);
+                }
+            }
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOff | PadEventType::PressOff, .. }
+                if !self.sounding[index].is_empty() =>
+            {
+                self.release(index, ctx);
+                self.update_pad_light(index, ctx);
+            }
+            _ => {}
+        }
+    }
+}
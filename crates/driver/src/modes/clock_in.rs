@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+use midly::live::{LiveEvent, SystemCommon, SystemRealtime};
+
+pub const CLOCKS_PER_BEAT: u32 = 24;
+const CLOCKS_PER_SIXTEENTH: u32 = 6;
+/// Weight of the newest tick in the smoothed tick length
+const TEMPO_SMOOTHING: f64 = 0.1;
+/// Ticks further apart than this mean the clock stopped; don't average across the gap.
+const CLOCK_GAP: Duration = Duration::from_millis(250);
+
+/// Transport and clock from the MIDI input, for Play mode to follow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockMessage {
+    Start,
+    Continue,
+    Stop,
+    Tick,
+    /// Song position pointer, in 16ths
+    SongPosition(u32),
+    /// Where a Link session's timeline is right now, and its tempo
+    Beat { beat: f64, bpm: f64 },
+}
+
+pub fn parse(bytes: &[u8]) -> Option<ClockMessage> {
+    match LiveEvent::parse(bytes).ok()? {
+        LiveEvent::Realtime(SystemRealtime::Start) => Some(ClockMessage::Start),
+        LiveEvent::Realtime(SystemRealtime::Continue) => Some(ClockMessage::Continue),
+        LiveEvent::Realtime(SystemRealtime::Stop) => Some(ClockMessage::Stop),
+        LiveEvent::Realtime(SystemRealtime::TimingClock) => Some(ClockMessage::Tick),
+        LiveEvent::Common(SystemCommon::SongPosition(sixteenths)) => Some(ClockMessage::SongPosition(sixteenths.as_int() as u32)),
+        _ => None,
+    }
+}
+
+/// The host's clock as Play mode follows it with `[clock_in]`, or a Link session's with
+/// `[link]`: where the song is, in clock ticks, and how long a tick takes.
+pub(crate) struct ClockIn {
+    running: bool,
+    /// Ticks since the start of the song; the next one falls on this position.
+    ticks: u32,
+    /// Whether a tick came since Start or a song position, so `ticks - 1` is where it is
+    ticked: bool,
+    last_tick: Option<Instant>,
+    tick_length: Option<Duration>,
+    /// Link's beat as of its last status, and when that came
+    link_beat: Option<(f64, Instant)>,
+}
+
+impl ClockIn {
+    pub fn new() -> Self {
+        Self { running: false, ticks: 0, ticked: false, last_tick: None, tick_length: None, link_beat: None }
+    }
+
+    pub fn handle(&mut self, message: ClockMessage, now: Instant) {
+        match message {
+            ClockMessage::Start => {
+                self.running = true;
+                self.ticks = 0;
+                self.ticked = false;
+            }
+            ClockMessage::Continue => self.running = true,
+            ClockMessage::Stop => self.running = false,
+            ClockMessage::SongPosition(sixteenths) => {
+                self.ticks = sixteenths * CLOCKS_PER_SIXTEENTH;
+                self.ticked = false;
+            }
+            // Link's timeline never stops.
+            ClockMessage::Beat { beat, bpm } => {
+                self.running = true;
+                self.link_beat = Some((beat, now));
+                self.tick_length = Some(Duration::from_secs_f64(60.0 / bpm / CLOCKS_PER_BEAT as f64));
+            }
+            ClockMessage::Tick => {
+                if let Some(last) = self.last_tick
+                    && now.duration_since(last) < CLOCK_GAP
+                {
+                    let length = now.duration_since(last).as_secs_f64();
+                    let smoothed = match self.tick_length {
+                        Some(avg) => avg.as_secs_f64() + (length - avg.as_secs_f64()) * TEMPO_SMOOTHING,
+                        None => length,
+                    };
+                    self.tick_length = Some(Duration::from_secs_f64(smoothed));
+                }
+                self.last_tick = Some(now);
+                if self.running {
+                    self.ticks += 1;
+                    self.ticked = true;
+                }
+            }
+        }
+    }
+
+    /// Length of a beat at the host's tempo, once two ticks came in
+    pub fn beat(&self) -> Option<Duration> {
+        self.tick_length.map(|tick| tick * CLOCKS_PER_BEAT)
+    }
+
+    /// Where the song is, in ticks and fractions of one, while the clock runs. Before the first tick after Start or a song position, that's just before the tick
+    /// to come; once ticks stop coming, there's no telling.
+    pub fn position(&self, now: Instant) -> Option<f64> {
+        if !self.running {
+            return None;
+        }
+        if let Some((beat, at)) = self.link_beat {
+            let beats = now.duration_since(at).as_secs_f64() / self.beat()?.as_secs_f64();
+            return Some((beat + beats) * CLOCKS_PER_BEAT as f64);
+        }
+        if !self.ticked {
+            return Some(self.ticks as f64 - 1.0);
+        }
+        let since = now.duration_since(self.last_tick?);
+        if since >= CLOCK_GAP {
+            return None;
+        }
+        let fraction = self.tick_length.map_or(0.0, |tick| (since.as_secs_f64() / tick.as_secs_f64()).min(0.999));
+        Some((self.ticks - 1) as f64 + fraction)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// The beat closest to now, as its tick and when it falls, while the clock runs
+    pub fn nearest_beat(&self, now: Instant) -> Option<(u32, Instant)> {
+        let position = self.position(now)?;
+        let beat = (position / CLOCKS_PER_BEAT as f64).round() as u32 * CLOCKS_PER_BEAT;
+        let tick = self.tick_length.unwrap_or_default();
+        let at = if beat as f64 >= position {
+            now + tick.mul_f64(beat as f64 - position)
+        } else {
+            now - tick.mul_f64(position - beat as f64)
+        };
+        Some((beat, at))
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+use crate::context::MidiSink;
+use crate::settings::ClockOutSettings;
+
+const CLOCKS_PER_BEAT: u32 = 24;
+/// Song position counts 16ths, six clocks each.
+const CLOCKS_PER_SIXTEENTH: u32 = 6;
+
+/// MIDI clock from Play mode's loop, for gear and DAWs to follow: `[clock_out]
+/// beats_per_loop` beats fill each pass of the loop, so the tempo is whatever the first
+/// take's length makes it. Start goes out when the loop plays from the top, Continue when
+/// it resumes from a pause and Stop when it stops or pauses.
+pub(crate) struct ClockOut {
+    running: bool,
+    /// Clocks sent since the top of the loop
+    sent: u32,
+}
+
+impl ClockOut {
+    pub fn new() -> Self {
+        Self { running: false, sent: 0 }
+    }
+
+    pub fn start(&mut self, settings: &ClockOutSettings, midi: &mut dyn MidiSink) {
+        if settings.enabled {
+            let _ = midi.send(&[0xFA]);
+            self.running = true;
+            self.sent = 0;
+        }
+    }
+
+    /// Picks up where the clock stopped.
+    pub fn resume(&mut self, settings: &ClockOutSettings, midi: &mut dyn MidiSink) {
+        if settings.enabled && !self.running {
+            let _ = midi.send(&[0xFB]);
+            self.running = true;
+        }
+    }
+
+    /// Picks up at `elapsed` into the loop, after the loop went on without the clock. The
+    /// song position is rounded up to the next 16th, where the clock carries on.
+    pub fn resume_at(&mut self, elapsed: Duration, loop_duration: Duration, settings: &ClockOutSettings, midi: &mut dyn MidiSink) {
+        if !settings.enabled || self.running {
+            return;
+        }
+        let sixteenth = Self::due(elapsed, loop_duration, settings).div_ceil(CLOCKS_PER_SIXTEENTH);
+        let _ = midi.send(&[0xF2, (sixteenth & 0x7F) as u8, ((sixteenth >> 7) & 0x7F) as u8]);
+        self.sent = sixteenth * CLOCKS_PER_SIXTEENTH;
+        self.resume(settings, midi);
+    }
+
+    pub fn stop(&mut self, midi: &mut dyn MidiSink) {
+        if std::mem::replace(&mut self.running, false) {
+            let _ = midi.send(&[0xFC]);
+        }
+    }
+
+    /// Clocks from the top of the loop to `elapsed`, the one at the top included
+    fn due(elapsed: Duration, loop_duration: Duration, settings: &ClockOutSettings) -> u32 {
+        let total = settings.beats_per_loop * CLOCKS_PER_BEAT;
+        if loop_duration.is_zero() {
+            return 0;
+        }
+        ((elapsed.as_nanos() * total as u128 / loop_duration.as_nanos()) as u32 + 1).min(total)
+    }
+
+    /// Sends the clocks due by `elapsed` into the loop.
+    pub fn run(&mut self, elapsed: Duration, loop_duration: Duration, settings: &ClockOutSettings, midi: &mut dyn MidiSink) {
+        if !self.running {
+            return;
+        }
+        let due = Self::due(elapsed, loop_duration, settings);
+        while self.sent < due {
+            let _ = midi.send(&[0xF8]);
+            self.sent += 1;
+        }
+    }
+
+    /// The loop came around: sends the rest of its clocks and counts from the top again.
+    pub fn wrap(&mut self, loop_duration: Duration, settings: &ClockOutSettings, midi: &mut dyn MidiSink) {
+        self.run(loop_duration, loop_duration, settings, midi);
+        self.sent = 0;
+    }
+}
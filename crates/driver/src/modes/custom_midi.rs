@@ -1,51 +1,66 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use midly::{live::LiveEvent, MidiMessage};
-use rosc::{OscMessage, OscPacket, OscType};
+use rosc::{OscMessage, OscType};
 use maschine_library::controls::{Buttons, PadEventType};
 use maschine_library::lights::{Brightness, PadColors};
-use crate::settings::{ButtonMode, Settings};
+use crate::settings::{ButtonMode, EncoderOutput, OscFormat};
+use evdev::KeyCode;
 use crate::context::DriverContext;
-use crate::input::HardwareEvent;
-use super::MachineMode;
+use crate::groups::GroupState;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::macros::run_macro;
+use crate::modular::Modular;
+use super::note_repeat::NoteRepeat;
+use super::pad_banks::PadBanks;
+use super::{encoder_direction, MachineMode, PadNotes};
 
 // Helper to look up buttons by name for exclusive groups
-fn button_from_name(name: &str) -> Option<Buttons> {
+pub(crate) fn button_from_name(name: &str) -> Option<Buttons> {
     for i in 0..41 {
-        if let Some(button) = num::FromPrimitive::from_usize(i) {
-            if format!("{:?}", button).to_string().eq_ignore_ascii_case(name) {
-                return Some(button);
-            }
+        if let Some(button) = <Buttons as num::FromPrimitive>::from_usize(i)
+            && format!("{:?}", button).eq_ignore_ascii_case(name)
+        {
+            return Some(button);
         }
     }
     None
 }
 
 pub struct CustomMidiMode {
-    toggle_states: HashMap<Buttons, bool>,
-    exclusive_groups: HashMap<u8, Vec<String>>,
+    // Toggle states themselves live in `ctx.groups`.
+    // HID reports repeat the state of every button, so a toggle only flips on the first report of a press.
+    // The press time drives the momentary-while-held gesture.
+    held_toggles: HashMap<Buttons, Instant>,
+    // Same for on_press macros, which fire for every button mode.
+    pressed_buttons: HashSet<Buttons>,
     last_encoder_val: u8,
     encoder_is_pressed: bool,
+    // Pad colors set by the host (pad_feedback); pads show these when not pressed.
+    pad_feedback: [(PadColors, Brightness); 16],
+    held_pads: [bool; 16],
+    pad_notes: PadNotes,
+    // Trigger buttons the host lights (e.g. Mixxx's play indicator), and whether it has them on.
+    host_lit: HashMap<Buttons, bool>,
+    note_repeat: NoteRepeat,
+    modular: Modular,
+    banks: PadBanks,
 }
 
 impl CustomMidiMode {
-    pub fn new(settings: &Settings) -> Self {
-        let mut exclusive_groups: HashMap<u8, Vec<String>> = HashMap::new();
-        for (button_name, config) in settings.button_configs.iter() {
-            if config.mode == ButtonMode::Toggle {
-                if let Some(group_id) = config.group_id {
-                    exclusive_groups
-                        .entry(group_id)
-                        .or_default()
-                        .push(button_name.clone());
-                }
-            }
-        }
-
+    pub fn new() -> Self {
         Self {
-            toggle_states: HashMap::new(),
-            exclusive_groups,
+            held_toggles: HashMap::new(),
+            pressed_buttons: HashSet::new(),
             last_encoder_val: 0,
             encoder_is_pressed: false,
+            pad_feedback: [(PadColors::Off, Brightness::Off); 16],
+            held_pads: [false; 16],
+            pad_notes: PadNotes::new(),
+            host_lit: HashMap::new(),
+            note_repeat: NoteRepeat::new(),
+            modular: Modular::new(),
+            banks: PadBanks::new(),
         }
     }
 
@@ -68,94 +83,189 @@ impl CustomMidiMode {
         let mut should_send_osc = false;
         let mut osc_value: i32 = 0;
         let mut target_light_brightness: Option<Brightness> = None;
+        let mut changed_group: Option<u8> = None;
 
         match mode {
             ButtonMode::Trigger => {
-                if is_pressed != current_light_state {
+                // Once the host lights the button, the light no longer says whether it's held.
+                let host_lit = self.host_lit.get(&button).copied();
+                let was_pressed = if host_lit.is_some() { self.pressed_buttons.contains(&button) } else { current_light_state };
+                if is_pressed != was_pressed {
                     should_send_osc = true;
                     osc_value = if is_pressed { 1 } else { 0 };
-                    target_light_brightness = Some(if is_pressed { Brightness::Normal } else { Brightness::Off });
+                    let released = if host_lit == Some(true) { Brightness::Bright } else { Brightness::Off };
+                    target_light_brightness = Some(if is_pressed { Brightness::Normal } else { released });
                 }
             }
             ButtonMode::Toggle => {
-                if is_pressed && ctx.lights.get_button(button) != Brightness::Bright {
-                    let new_toggle_state = !*self.toggle_states.entry(button).or_default();
-
-                    if new_toggle_state {
-                        if let Some(group_id) = config.and_then(|c| c.group_id) {
-                            if let Some(member_names) = self.exclusive_groups.get(&group_id) {
-                                for other_name in member_names {
-                                    if other_name != &button_name {
-                                        if let Some(other_button) = button_from_name(other_name) {
-                                            self.toggle_states.insert(other_button, false);
-                                            ctx.lights.set_button(other_button, Brightness::Off);
-                                            changed_lights = true;
-                                            self.send_osc(&format!("/maschine/{}", other_name.to_lowercase()), 0, ctx);
-                                        }
-                                    }
-                                }
-                            }
+                let group_id = config.and_then(|c| c.group_id);
+                let new_toggle_state = !ctx.groups.is_on(button);
+                // In a group that must keep a member selected, pressing the active one does nothing.
+                let locked = !new_toggle_state
+                    && group_id.is_some_and(|id| GroupState::config(ctx.settings, id).is_some_and(|g| !g.allow_none));
+
+                let first_report = is_pressed && !self.held_toggles.contains_key(&button);
+                if first_report {
+                    self.held_toggles.insert(button, ctx.clock.now());
+                }
+
+                if first_report && !locked {
+                    for other_name in ctx.groups.set(button, new_toggle_state, ctx.settings) {
+                        if let Some(other_button) = button_from_name(&other_name) {
+                            ctx.lights.set_button(other_button, Brightness::Off);
+                            changed_lights = true;
+                        }
+                        let format = ctx.settings.button_osc_format(&other_name);
+                        ctx.osc.send_formatted(&format!("/maschine/{}", other_name.to_lowercase()), 0, format);
+                        if ctx.settings.modular.enabled {
+                            self.modular.gate(&other_name, false, ctx);
                         }
                     }
-
-                    self.toggle_states.insert(button, new_toggle_state);
+                    changed_group = group_id;
                     should_send_osc = true;
                     osc_value = if new_toggle_state { 1 } else { 0 };
                     target_light_brightness = Some(Brightness::Bright);
                 }
 
-                if !is_pressed && current_light_state {
-                    target_light_brightness = Some(if *self.toggle_states.get(&button).unwrap_or(&false) { Brightness::Bright } else { Brightness::Off });
+                if !is_pressed && let Some(pressed_at) = self.held_toggles.remove(&button) {
+                    let held_long = config
+                        .and_then(|c| c.momentary_after_ms)
+                        .is_some_and(|ms| ctx.clock.now().duration_since(pressed_at) >= Duration::from_millis(ms));
+                    if held_long && !locked && ctx.groups.is_on(button) {
+                        ctx.groups.set(button, false, ctx.settings);
+                        changed_group = group_id;
+                        should_send_osc = true;
+                        osc_value = 0;
+                    }
+                    target_light_brightness = Some(if ctx.groups.is_on(button) { Brightness::Bright } else { Brightness::Off });
                 }
             }
         }
 
         if should_send_osc {
-            self.send_osc(&format!("/maschine/{}", button_name.to_lowercase()), osc_value, ctx);
+            let format = config.map_or(OscFormat::Int, |c| c.osc_format);
+            ctx.osc.send_formatted(&format!("/maschine/{}", button_name.to_lowercase()), osc_value, format);
+            if ctx.settings.modular.enabled {
+                match mode {
+                    ButtonMode::Trigger if osc_value == 1 => self.modular.trigger(&button_name, ctx),
+                    ButtonMode::Trigger => {}
+                    ButtonMode::Toggle => self.modular.gate(&button_name, osc_value == 1, ctx),
+                }
+            }
         }
 
-        if let Some(cc_num) = config.and_then(|c| c.cc) {
-            if should_send_osc {
-                let cc_val = if osc_value == 1 { 127 } else { 0 };
-                self.send_midi_cc(cc_num, cc_val, ctx);
-            }
+        if let Some(cc_num) = config.and_then(|c| c.cc)
+            && should_send_osc
+        {
+            let cc_val = if osc_value == 1 { 127 } else { 0 };
+            self.send_midi_cc(ctx.settings.button_channel(&button_name), cc_num, cc_val, ctx);
         }
 
-        if let Some(b) = target_light_brightness {
-            if ctx.lights.button_has_light(button) {
-                ctx.lights.set_button(button, b);
-                changed_lights = true;
-            }
+        if let Some(group_id) = changed_group {
+            GroupState::announce(ctx, group_id, false);
+        }
+
+        if is_pressed
+            && !self.pressed_buttons.contains(&button)
+            && let Some(steps) = config.map(|c| &c.on_press)
+        {
+            run_macro(steps, ctx);
+        }
+        if is_pressed {
+            self.pressed_buttons.insert(button);
+        } else {
+            self.pressed_buttons.remove(&button);
+        }
+
+        if let Some(b) = target_light_brightness
+            && ctx.lights.button_has_light(button)
+        {
+            ctx.lights.set_button(button, b);
+            changed_lights = true;
         }
 
         changed_lights
     }
 
-    fn process_pad(&self, index: usize, event_type: PadEventType, value: u16, ctx: &mut DriverContext) -> bool {
+    /// Applies a button state reported by the host, without echoing anything back: a
+    /// toggle takes it on. With `light_triggers` (Mixxx's indicators), a trigger button
+    /// just shows it, also once it's let go.
+    fn sync_button(&mut self, button: Buttons, on: bool, light_triggers: bool, ctx: &mut DriverContext) -> bool {
+        let button_name = format!("{:?}", button);
+        match ctx.settings.button_configs.get(&button_name).map(|c| c.mode) {
+            Some(ButtonMode::Toggle) => {}
+            Some(ButtonMode::Trigger) if light_triggers => {
+                self.host_lit.insert(button, on);
+                if !self.pressed_buttons.contains(&button) && ctx.lights.button_has_light(button) {
+                    ctx.lights.set_button(button, if on { Brightness::Bright } else { Brightness::Off });
+                }
+                return true;
+            }
+            _ => return false,
+        }
+
+        for other_name in ctx.groups.set(button, on, ctx.settings) {
+            if let Some(other_button) = button_from_name(&other_name) {
+                ctx.lights.set_button(other_button, Brightness::Off);
+            }
+        }
+        if ctx.lights.button_has_light(button) {
+            ctx.lights.set_button(button, if on { Brightness::Bright } else { Brightness::Off });
+        }
+        true
+    }
+
+    fn process_pad(&mut self, index: usize, event_type: PadEventType, value: u16, ctx: &mut DriverContext) -> bool {
         let mut changed_lights = false;
         
-        let (_, prev_b) = ctx.lights.get_pad(index);
-        let b = match event_type {
-            PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch if value > 0 => Brightness::Normal,
-            _ => Brightness::Off,
-        };
-        if prev_b != b {
-            ctx.lights.set_pad(index, PadColors::Blue, b);
+        let held = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+        if held != self.held_pads[index] {
+            self.held_pads[index] = held;
+            self.update_pad_light(index, ctx);
             changed_lights = true;
         }
 
-        let note = ctx.settings.notemaps[index];
         let mut velocity = (value >> 5) as u8;
         if value > 0 && velocity == 0 { velocity = 1; }
 
         let event = match event_type {
-            PadEventType::NoteOn | PadEventType::PressOn => Some(MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }),
-            PadEventType::NoteOff | PadEventType::PressOff => Some(MidiMessage::NoteOff { key: note.into(), vel: velocity.into() }),
+            PadEventType::NoteOn | PadEventType::PressOn => {
+                let (channel, note) = self.pad_notes.press(index, velocity, ctx);
+                Some((channel, MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }))
+            }
+            PadEventType::NoteOff | PadEventType::PressOff => {
+                let (channel, note) = self.pad_notes.release(index, ctx);
+                Some((channel, MidiMessage::NoteOff { key: note.into(), vel: velocity.into() }))
+            }
             _ => None,
         };
 
-        if let Some(evt) = event {
-            let l_ev = LiveEvent::Midi { channel: 0.into(), message: evt };
+        if ctx.settings.pad_osc.enabled
+            && let Some((_, message)) = &event
+        {
+            let velocity = match message {
+                MidiMessage::NoteOn { vel, .. } => vel.as_int() as i32,
+                _ => 0,
+            };
+            let mut args = vec![OscType::Int(index as i32 + 1), OscType::Int(velocity)];
+            if ctx.settings.pad_osc.names {
+                args.push(OscType::String(ctx.settings.pad_name(index).unwrap_or_default().to_string()));
+            }
+            ctx.osc.send(OscMessage { addr: "/maschine/pad".to_string(), args });
+        }
+
+        if ctx.settings.modular.enabled
+            && let Some((_, message)) = &event
+        {
+            let velocity = match message {
+                MidiMessage::NoteOn { vel, .. } => vel.as_int(),
+                _ => 0,
+            };
+            self.modular.pad(index, velocity, ctx);
+        }
+
+        if let Some((channel, evt)) = event {
+            let l_ev = LiveEvent::Midi { channel: channel.into(), message: evt };
             let mut midibuf = Vec::new();
             if l_ev.write(&mut midibuf).is_ok() {
                 let _ = ctx.midi_port.send(&midibuf[..]);
@@ -165,11 +275,37 @@ impl CustomMidiMode {
         changed_lights
     }
 
-    fn process_encoder(&mut self, val: u8, ctx: &DriverContext) {
-        if val != 0 && val != self.last_encoder_val {
-            let diff = val as i8 - self.last_encoder_val as i8;
-            let direction = if (diff > 0 && diff < 8) || (diff < -8) { 1 } else { -1 };
-            self.send_osc("/maschine/encoder", direction, ctx);
+    fn update_pad_light(&self, index: usize, ctx: &mut DriverContext) {
+        if self.held_pads[index] {
+            ctx.lights.set_pad(index, PadColors::Blue, Brightness::Normal);
+        } else if self.pad_feedback[index].0 != PadColors::Off {
+            let (color, brightness) = self.pad_feedback[index];
+            ctx.lights.set_pad(index, color, brightness);
+        } else {
+            ctx.lights.set_pad(index, ctx.settings.pad_zone_color(index, ctx.groups.bank()), Brightness::Dim);
+        }
+    }
+
+    fn process_encoder(&mut self, val: u8, ctx: &mut DriverContext) {
+        let direction = encoder_direction(self.last_encoder_val, val);
+        if direction != 0 {
+            let amount = ctx.settings.encoder.amount as i32;
+            match ctx.settings.encoder.output {
+                EncoderOutput::Osc => self.send_osc("/maschine/encoder", direction, ctx),
+                EncoderOutput::Wheel => ctx.uinput.scroll(direction * amount, 0),
+                EncoderOutput::HorizontalWheel => ctx.uinput.scroll(0, direction * amount),
+                EncoderOutput::UpDown | EncoderOutput::LeftRight => {
+                    let key = match (ctx.settings.encoder.output, direction > 0) {
+                        (EncoderOutput::UpDown, true) => KeyCode::KEY_UP,
+                        (EncoderOutput::UpDown, false) => KeyCode::KEY_DOWN,
+                        (_, true) => KeyCode::KEY_RIGHT,
+                        (_, false) => KeyCode::KEY_LEFT,
+                    };
+                    for _ in 0..amount {
+                        ctx.uinput.tap(key.0);
+                    }
+                }
+            }
         }
         if val != 0 {
             self.last_encoder_val = val;
@@ -179,6 +315,14 @@ impl CustomMidiMode {
     fn process_slider(&self, val: u8, ctx: &mut DriverContext) -> bool {
         if val != 0 {
             self.send_osc("/maschine/slider", val as i32, ctx);
+            if let Some(cc) = ctx.settings.slider.cc {
+                // The strip reads 1-200 while touched.
+                let value = ((val as u32 - 1) * 127 / 199) as u8;
+                self.send_midi_cc(ctx.settings.channel(ctx.settings.slider.channel), cc, value, ctx);
+            }
+            if ctx.settings.modular.enabled {
+                self.modular.cv("slider", val as u32 - 1, 199, ctx);
+            }
             
             let cnt = (val as i32 - 1 + 5) * 25 / 200 - 1;
             for i in 0..25 {
@@ -195,18 +339,12 @@ impl CustomMidiMode {
     }
 
     fn send_osc(&self, addr: &str, val: i32, ctx: &DriverContext) {
-        let msg = OscMessage {
-            addr: addr.to_string(),
-            args: vec![OscType::Int(val)],
-        };
-        if let Ok(encoded_buf) = rosc::encoder::encode(&OscPacket::Message(msg)) {
-            let _ = ctx.osc_socket.send_to(&encoded_buf, ctx.osc_addr);
-        }
+        ctx.osc.send_int(addr, val);
     }
 
-    fn send_midi_cc(&self, cc: u8, val: u8, ctx: &mut DriverContext) {
+    fn send_midi_cc(&self, channel: u8, cc: u8, val: u8, ctx: &mut DriverContext) {
         let cc_message = MidiMessage::Controller { controller: cc.into(), value: val.into() };
-        let live_event = LiveEvent::Midi { channel: 0.into(), message: cc_message };
+        let live_event = LiveEvent::Midi { channel: channel.into(), message: cc_message };
         let mut midibuf = Vec::new();
         if live_event.write(&mut midibuf).is_ok() {
             let _ = ctx.midi_port.send(&midibuf[..]);
@@ -215,17 +353,64 @@ impl CustomMidiMode {
 }
 
 impl MachineMode for CustomMidiMode {
+    fn name(&self) -> &'static str {
+        "MIDI"
+    }
+
     fn on_enter(&mut self, ctx: &mut DriverContext) {
-        for (button, is_active) in &self.toggle_states {
-            if *is_active {
+        ctx.groups.draw(ctx.lights);
+        for (button, on) in &self.host_lit {
+            if *on && ctx.lights.button_has_light(*button) {
                 ctx.lights.set_button(*button, Brightness::Bright);
-            } else {
-                ctx.lights.set_button(*button, Brightness::Off);
             }
         }
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        for group_id in ctx.groups.sorted_ids() {
+            if GroupState::config(ctx.settings, group_id).is_some_and(|g| g.announce) {
+                GroupState::announce(ctx, group_id, true);
+            }
+        }
+    }
+
+    fn on_exit(&mut self, ctx: &mut DriverContext) {
+        self.pad_notes.release_all(ctx);
+        self.held_pads = [false; 16];
+        self.note_repeat.release();
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        self.modular.tick(ctx);
+        let mut changed = false;
+        for (pad, velocity) in self.note_repeat.due(ctx) {
+            self.process_pad(pad, PadEventType::NoteOff, 0, ctx);
+            changed |= self.process_pad(pad, PadEventType::NoteOn, (velocity as u16) << 5, ctx);
+        }
+        changed
+    }
+
+    fn is_animating(&self) -> bool {
+        self.note_repeat.is_animating() || self.modular.is_pending()
     }
 
     fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        let bank = ctx.groups.bank();
+        if ctx.settings.pad_banks.enabled && self.banks.handle(event, ctx) {
+            // Host colors were for the old bank's notes.
+            if ctx.groups.bank() != bank {
+                self.pad_feedback = [(PadColors::Off, Brightness::Off); 16];
+            }
+            if !self.banks.is_picking() {
+                for i in 0..16 {
+                    self.update_pad_light(i, ctx);
+                }
+            }
+            return;
+        }
+        if ctx.settings.note_repeat.enabled && self.note_repeat.handle(event, ctx) {
+            return;
+        }
         match event {
             HardwareEvent::Button { index, pressed } => {
                 self.process_button(*index, *pressed, ctx);
@@ -240,5 +425,79 @@ impl MachineMode for CustomMidiMode {
                 self.process_slider(*value, ctx);
             }
         }
+        // Pads let go of while picking a bank relight underneath the banks.
+        if self.banks.is_picking() {
+            self.banks.draw(ctx);
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        match event {
+            // Host asks for every group to go back to its default member.
+            HostEvent::Osc { addr, value } if addr == "/maschine/reset" => {
+                if *value == 0 {
+                    return false;
+                }
+                ctx.groups.apply_defaults(ctx.settings);
+                ctx.groups.draw(ctx.lights);
+                for group_id in ctx.groups.sorted_ids() {
+                    if GroupState::config(ctx.settings, group_id).is_some_and(|g| g.default.is_some()) {
+                        GroupState::announce(ctx, group_id, true);
+                    }
+                }
+                true
+            }
+            HostEvent::Osc { addr, value } => {
+                match addr.strip_prefix("/maschine/").and_then(button_from_name) {
+                    Some(button) => self.sync_button(button, *value != 0, false, ctx),
+                    None => false,
+                }
+            }
+            HostEvent::ControlChange { channel, controller, value } => {
+                let mut buttons: Vec<Buttons> = ctx
+                    .settings
+                    .button_configs
+                    .iter()
+                    .filter(|(_, config)| config.cc == Some(*controller) && ctx.settings.channel(config.channel) == *channel)
+                    .filter_map(|(name, _)| button_from_name(name))
+                    .collect();
+                buttons.sort_by_key(|b| *b as usize);
+                let mut changed = false;
+                for button in buttons {
+                    changed |= self.sync_button(button, *value >= 64, ctx.settings.mixxx.enabled, ctx);
+                }
+                changed
+            }
+            HostEvent::Note { channel, note, velocity } => {
+                if ctx.settings.pad_feedback.channel.is_none_or(|c| c - 1 != *channel) {
+                    return false;
+                }
+                let color = ctx.settings.pad_feedback.color_for(*velocity);
+                let mut changed = false;
+                for i in 0..16 {
+                    if ctx.settings.pad_note(i, ctx.groups.bank()).1 == *note {
+                        self.pad_feedback[i] = color;
+                        self.update_pad_light(i, ctx);
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            HostEvent::LoadLoop(_)
+            | HostEvent::DumpLoop(_)
+            | HostEvent::Scene { .. }
+            | HostEvent::ProgramChange { .. }
+            | HostEvent::Namespace
+            | HostEvent::Metrics
+            | HostEvent::Macro { .. }
+            | HostEvent::LightFrame(_)
+            | HostEvent::Snapshot { .. }
+            | HostEvent::Live(_)
+            | HostEvent::Bitwig(_)
+            | HostEvent::Reaper(_)
+            | HostEvent::MixxxHello
+            | HostEvent::Clock(_)
+            | HostEvent::Hello => false,
+        }
     }
 }
\ No newline at end of file
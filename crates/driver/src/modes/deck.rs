@@ -0,0 +1,78 @@
+// crates/driver/src/modes/deck.rs
+use maschine_library::controls::PadEventType;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::macros::run_macro;
+use super::MachineMode;
+
+/// Each legend cell is 32 pixels wide: four characters of the 8x8 font.
+const LABEL_CHARS: usize = 4;
+
+/// Macro pad: every configured pad fires its macro, and the screen shows a 4x4
+/// legend laid out like the pads (top-left pad first).
+pub struct DeckMode {
+    held: [bool; 16],
+}
+
+impl DeckMode {
+    pub fn new() -> Self {
+        Self { held: [false; 16] }
+    }
+
+    fn update_pad_light(&self, index: usize, ctx: &mut DriverContext) {
+        if index >= ctx.settings.deck.len() {
+            ctx.lights.set_pad(index, PadColors::Off, Brightness::Off);
+        } else if self.held[index] {
+            ctx.lights.set_pad(index, PadColors::Turquoise, Brightness::Bright);
+        } else {
+            ctx.lights.set_pad(index, PadColors::Turquoise, Brightness::Dim);
+        }
+    }
+
+    fn draw_legend(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        for (i, pad) in ctx.settings.deck.iter().enumerate().take(16) {
+            let label: String = pad.label.chars().take(LABEL_CHARS).collect();
+            Font::write_string(ctx.screen, (i / 4) * 8, (i % 4) * 8 * LABEL_CHARS, &label, 1);
+        }
+    }
+}
+
+impl MachineMode for DeckMode {
+    fn name(&self) -> &'static str {
+        "DECK"
+    }
+
+    fn full_screen(&self) -> bool {
+        true
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        self.draw_legend(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        let HardwareEvent::Pad { index, event_type, value } = event else {
+            return;
+        };
+        let Some(pad) = ctx.settings.deck.get(*index) else {
+            return;
+        };
+        let pressed = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn) && *value > 0;
+        let released = matches!(event_type, PadEventType::NoteOff | PadEventType::PressOff);
+
+        if pressed && !self.held[*index] {
+            self.held[*index] = true;
+            run_macro(&pad.steps, ctx);
+            self.update_pad_light(*index, ctx);
+        } else if released && self.held[*index] {
+            self.held[*index] = false;
+            self.update_pad_light(*index, ctx);
+        }
+    }
+}
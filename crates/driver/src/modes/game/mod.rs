@@ -0,0 +1,122 @@
+// crates/driver/src/modes/game/mod.rs
+//! Small games on the pads and the screen. Besides being an easter egg, they are the
+//! shortest complete examples of a mode: input handling, timed redraws through `tick`,
+//! pad colors and full-screen drawing. Playing them for a while also exercises every pad.
+
+pub mod simon;
+pub mod snake;
+
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use super::MachineMode;
+
+/// One game hosted by `GameMode`. Games own the pads and the whole screen while selected.
+pub trait Game {
+    fn name(&self) -> &'static str;
+
+    /// Starts a fresh round and draws it
+    fn start(&mut self, rng: &mut Rng, ctx: &mut DriverContext);
+
+    /// Called for pad presses only; releases and other controls are filtered out
+    fn pad_pressed(&mut self, index: usize, rng: &mut Rng, ctx: &mut DriverContext);
+
+    /// Advances timers. Returns true when the lights or the screen changed.
+    fn tick(&mut self, rng: &mut Rng, ctx: &mut DriverContext) -> bool;
+}
+
+/// Xorshift generator; games don't need more, and a fixed seed keeps tests reproducible.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift is stuck at zero forever.
+        Self(seed.max(1))
+    }
+
+    /// A number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Hosts the games: Left and Right pick one, everything else goes to the game.
+pub struct GameMode {
+    games: Vec<Box<dyn Game>>,
+    current: usize,
+    rng: Rng,
+}
+
+impl GameMode {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            games: vec![Box::new(simon::Simon::new()), Box::new(snake::Snake::new())],
+            current: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    fn start_current(&mut self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+        }
+        ctx.screen.reset();
+        self.games[self.current].start(&mut self.rng, ctx);
+    }
+}
+
+impl MachineMode for GameMode {
+    fn name(&self) -> &'static str {
+        "GAME"
+    }
+
+    fn full_screen(&self) -> bool {
+        true
+    }
+
+    fn is_animating(&self) -> bool {
+        true
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        self.start_current(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match event {
+            HardwareEvent::Button { index: Buttons::Left, pressed: true } => {
+                self.current = (self.current + self.games.len() - 1) % self.games.len();
+                self.start_current(ctx);
+            }
+            HardwareEvent::Button { index: Buttons::Right, pressed: true } => {
+                self.current = (self.current + 1) % self.games.len();
+                self.start_current(ctx);
+            }
+            HardwareEvent::Pad { index, event_type, value }
+                if matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn) && *value > 0 =>
+            {
+                self.games[self.current].pad_pressed(*index, &mut self.rng, ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        self.games[self.current].tick(&mut self.rng, ctx)
+    }
+}
+
+/// Shared game-over screen: the score large, then how to continue.
+pub(crate) fn draw_game_over(score: usize, ctx: &mut DriverContext) {
+    ctx.screen.reset();
+    Font::write_string(ctx.screen, 0, 0, &format!("SCORE {score}"), 2);
+    Font::write_string(ctx.screen, 24, 0, "PAD TO RESTART", 1);
+    for i in 0..16 {
+        ctx.lights.set_pad(i, PadColors::Red, Brightness::Dim);
+    }
+}
@@ -0,0 +1,128 @@
+// crates/driver/src/modes/game/simon.rs
+use std::time::{Duration, Instant};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use super::{draw_game_over, Game, Rng};
+
+const COLORS: [PadColors; 4] = [PadColors::Red, PadColors::Green, PadColors::Blue, PadColors::Yellow];
+const SHOW_ON: Duration = Duration::from_millis(400);
+const SHOW_GAP: Duration = Duration::from_millis(200);
+/// Pause after a round is repeated correctly, before the longer sequence plays.
+const NEXT_ROUND: Duration = Duration::from_millis(800);
+/// Presses right after losing are most likely late answers, not a request to restart.
+const RESTART_GUARD: Duration = Duration::from_millis(1000);
+
+enum Phase {
+    /// Playing the sequence back; `step` counts lit and dark halves.
+    Showing { step: usize, since: Option<Instant> },
+    Input { pos: usize },
+    GameOver { since: Instant },
+}
+
+/// Memory game: the four 2x2 pad quadrants flash a growing sequence that the
+/// player repeats by tapping any pad of each quadrant.
+pub struct Simon {
+    sequence: Vec<usize>,
+    phase: Phase,
+}
+
+impl Simon {
+    pub fn new() -> Self {
+        Self { sequence: Vec::new(), phase: Phase::Showing { step: 0, since: None } }
+    }
+
+    fn quadrant(pad: usize) -> usize {
+        (pad / 8) * 2 + (pad % 4) / 2
+    }
+
+    fn light_quadrants(lit: Option<usize>, ctx: &mut DriverContext) {
+        for pad in 0..16 {
+            let q = Self::quadrant(pad);
+            let b = if lit == Some(q) { Brightness::Bright } else { Brightness::Dim };
+            ctx.lights.set_pad(pad, COLORS[q], b);
+        }
+    }
+
+    fn draw_status(&self, text: &str, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, 0, 0, self.name(), 1);
+        Font::write_string(ctx.screen, 8, 0, &format!("ROUND {}", self.sequence.len()), 1);
+        Font::write_string(ctx.screen, 24, 0, text, 1);
+    }
+
+    fn next_round(&mut self, rng: &mut Rng, ctx: &mut DriverContext) {
+        self.sequence.push(rng.below(COLORS.len()));
+        self.phase = Phase::Showing { step: 0, since: None };
+        Self::light_quadrants(None, ctx);
+        self.draw_status("WATCH", ctx);
+    }
+}
+
+impl Game for Simon {
+    fn name(&self) -> &'static str {
+        "SIMON"
+    }
+
+    fn start(&mut self, rng: &mut Rng, ctx: &mut DriverContext) {
+        self.sequence.clear();
+        self.next_round(rng, ctx);
+    }
+
+    fn pad_pressed(&mut self, index: usize, rng: &mut Rng, ctx: &mut DriverContext) {
+        match self.phase {
+            Phase::Input { pos } => {
+                let q = Self::quadrant(index);
+                if q != self.sequence[pos] {
+                    self.phase = Phase::GameOver { since: ctx.clock.now() };
+                    draw_game_over(self.sequence.len() - 1, ctx);
+                    return;
+                }
+                Self::light_quadrants(Some(q), ctx);
+                if pos + 1 == self.sequence.len() {
+                    // Wait before the next round so the last press stays visible.
+                    self.phase = Phase::Showing { step: 0, since: Some(ctx.clock.now() + NEXT_ROUND) };
+                    self.sequence.push(rng.below(COLORS.len()));
+                    self.draw_status("WELL DONE", ctx);
+                } else {
+                    self.phase = Phase::Input { pos: pos + 1 };
+                }
+            }
+            Phase::GameOver { since } if ctx.clock.now() >= since + RESTART_GUARD => {
+                self.start(rng, ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, _rng: &mut Rng, ctx: &mut DriverContext) -> bool {
+        let now = ctx.clock.now();
+        let Phase::Showing { step, since } = self.phase else {
+            return false;
+        };
+        let Some(since) = since else {
+            // Start the playback from the first tick, so it's timed from when it's visible.
+            self.phase = Phase::Showing { step: 0, since: Some(now) };
+            return false;
+        };
+        let length = if step % 2 == 0 { SHOW_GAP } else { SHOW_ON };
+        if now < since + length {
+            return false;
+        }
+
+        let step = step + 1;
+        if step / 2 >= self.sequence.len() && step % 2 == 0 {
+            Self::light_quadrants(None, ctx);
+            self.phase = Phase::Input { pos: 0 };
+            self.draw_status("YOUR TURN", ctx);
+        } else {
+            let lit = (step % 2 == 1).then(|| self.sequence[step / 2]);
+            Self::light_quadrants(lit, ctx);
+            if step == 1 {
+                self.draw_status("WATCH", ctx);
+            }
+            self.phase = Phase::Showing { step, since: Some(now) };
+        }
+        true
+    }
+}
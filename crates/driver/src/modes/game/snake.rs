@@ -0,0 +1,144 @@
+// crates/driver/src/modes/game/snake.rs
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use super::{draw_game_over, Game, Rng};
+
+/// Cells are 4x4 pixels, so the 128x32 screen is a 32x8 board.
+const CELL: usize = 4;
+const COLS: i32 = 32;
+const ROWS: i32 = 8;
+const STEP: Duration = Duration::from_millis(150);
+/// How long the title stays up before the snake starts moving.
+const INTRO: Duration = Duration::from_millis(1000);
+
+/// Pads laid out as a d-pad: (pad, row delta, column delta).
+const DPAD: [(usize, i32, i32); 4] = [(1, -1, 0), (4, 0, -1), (6, 0, 1), (9, 1, 0)];
+
+/// Snake on the screen, steered with a d-pad on the upper-left pads.
+pub struct Snake {
+    body: VecDeque<(i32, i32)>,
+    dir: (i32, i32),
+    /// Direction for the next step; turning twice within one step can't reverse the snake.
+    next_dir: (i32, i32),
+    food: (i32, i32),
+    last_step: Option<Instant>,
+    alive: bool,
+}
+
+impl Snake {
+    pub fn new() -> Self {
+        Self {
+            body: VecDeque::new(),
+            dir: (0, 1),
+            next_dir: (0, 1),
+            food: (0, 0),
+            last_step: None,
+            alive: false,
+        }
+    }
+
+    fn place_food(&mut self, rng: &mut Rng) {
+        let free: Vec<(i32, i32)> = (0..ROWS)
+            .flat_map(|r| (0..COLS).map(move |c| (r, c)))
+            .filter(|cell| !self.body.contains(cell))
+            .collect();
+        if !free.is_empty() {
+            self.food = free[rng.below(free.len())];
+        }
+    }
+
+    fn fill_cell(cell: (i32, i32), inset: usize, ctx: &mut DriverContext) {
+        let (top, left) = (cell.0 as usize * CELL, cell.1 as usize * CELL);
+        for i in top + inset..top + CELL - inset {
+            for j in left + inset..left + CELL - inset {
+                ctx.screen.set(i, j, true);
+            }
+        }
+    }
+
+    fn draw(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        for cell in &self.body {
+            Self::fill_cell(*cell, 0, ctx);
+        }
+        Self::fill_cell(self.food, 1, ctx);
+    }
+
+    fn light_dpad(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+        }
+        for (pad, dr, dc) in DPAD {
+            let b = if (dr, dc) == self.dir { Brightness::Bright } else { Brightness::Dim };
+            ctx.lights.set_pad(pad, PadColors::Green, b);
+        }
+    }
+}
+
+impl Game for Snake {
+    fn name(&self) -> &'static str {
+        "SNAKE"
+    }
+
+    fn start(&mut self, rng: &mut Rng, ctx: &mut DriverContext) {
+        self.body = (0..3).map(|c| (ROWS / 2, 4 - c)).collect();
+        self.dir = (0, 1);
+        self.next_dir = self.dir;
+        self.alive = true;
+        self.place_food(rng);
+        // The title shows until the first step redraws the board.
+        self.last_step = Some(ctx.clock.now() + INTRO - STEP);
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, 8, 24, self.name(), 2);
+        self.light_dpad(ctx);
+    }
+
+    fn pad_pressed(&mut self, index: usize, rng: &mut Rng, ctx: &mut DriverContext) {
+        if !self.alive {
+            // Ignore steering that was meant for the step that crashed.
+            if self.last_step.is_some_and(|last| ctx.clock.now() >= last + INTRO) {
+                self.start(rng, ctx);
+            }
+            return;
+        }
+        if let Some((_, dr, dc)) = DPAD.iter().find(|(pad, _, _)| *pad == index)
+            && (*dr, *dc) != (-self.dir.0, -self.dir.1)
+        {
+            self.next_dir = (*dr, *dc);
+        }
+    }
+
+    fn tick(&mut self, rng: &mut Rng, ctx: &mut DriverContext) -> bool {
+        let now = ctx.clock.now();
+        match self.last_step {
+            Some(last) if self.alive && now >= last + STEP => self.last_step = Some(now),
+            _ => return false,
+        }
+
+        self.dir = self.next_dir;
+        let head = self.body[0];
+        let next = (head.0 + self.dir.0, head.1 + self.dir.1);
+        let hits_wall = !(0..ROWS).contains(&next.0) || !(0..COLS).contains(&next.1);
+        // The tail moves away this step unless the snake eats, so it's not in the way.
+        let eats = next == self.food;
+        let body_len = if eats { self.body.len() } else { self.body.len() - 1 };
+        if hits_wall || self.body.iter().take(body_len).any(|c| *c == next) {
+            self.alive = false;
+            draw_game_over(self.body.len() - 3, ctx);
+            return true;
+        }
+
+        self.body.push_front(next);
+        if eats {
+            self.place_food(rng);
+        } else {
+            self.body.pop_back();
+        }
+        self.draw(ctx);
+        self.light_dpad(ctx);
+        true
+    }
+}
@@ -0,0 +1,209 @@
+use midly::{live::LiveEvent, MidiMessage};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::monitor::note_name;
+use crate::settings::{OutOfScale, Scale, Settings};
+use crate::status::CONTENT_ROW;
+use super::{encoder_direction, ButtonEdges, MachineMode};
+use super::voices::Voices;
+
+/// The pads as a keyboard in a scale, from the bottom-left pad up a row at a time like
+/// Maschine's own keyboard mode. The encoder changes the root or the scale, whichever
+/// the encoder press picked; Left/Right move an octave. With `out_of_scale = "skip"` the
+/// pads only play the scale, otherwise they stay chromatic and dim the notes outside it.
+/// Notes go out on `channel`, or spread over `channels`.
+pub struct KeyboardMode {
+    root: u8,
+    scale: Scale,
+    /// The encoder edits the scale rather than the root
+    editing_scale: bool,
+    /// Note each held pad started, so its note off matches after the layout changes
+    sounding: [Option<u8>; 16],
+    voices: Voices,
+    last_encoder_val: u8,
+    /// Buttons held as of the last report, since their states repeat
+    buttons: ButtonEdges,
+}
+
+impl KeyboardMode {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            root: settings.keyboard.root,
+            scale: settings.keyboard.scale,
+            editing_scale: false,
+            sounding: [None; 16],
+            voices: Voices::new(),
+            last_encoder_val: 0,
+            buttons: ButtonEdges::new(),
+        }
+    }
+
+    /// Note of a pad, counting from the bottom-left one; None above MIDI's range.
+    fn note(&self, pad: usize, settings: &Settings) -> Option<u8> {
+        // Pad 1 is top-left, so the bottom row is row 3.
+        let position = (3 - pad / 4) * 4 + pad % 4;
+        let note = match settings.keyboard.out_of_scale {
+            OutOfScale::Dim => self.root as usize + position,
+            OutOfScale::Skip => {
+                let intervals = self.scale.intervals();
+                self.root as usize + position / intervals.len() * 12 + intervals[position % intervals.len()] as usize
+            }
+        };
+        u8::try_from(note).ok().filter(|n| *n < 128)
+    }
+
+    fn in_scale(&self, note: u8) -> bool {
+        let degree = (note as i32 - self.root as i32).rem_euclid(12) as u8;
+        self.scale.intervals().contains(&degree)
+    }
+
+    fn update_pad_light(&self, pad: usize, ctx: &mut DriverContext) {
+        let Some(note) = self.note(pad, ctx.settings) else {
+            ctx.lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            return;
+        };
+        let color = if (note as i32 - self.root as i32).rem_euclid(12) == 0 { PadColors::Blue } else { PadColors::White };
+        let brightness = if self.sounding[pad].is_some() {
+            Brightness::Bright
+        } else if self.in_scale(note) {
+            Brightness::Normal
+        } else {
+            Brightness::Dim
+        };
+        ctx.lights.set_pad(pad, color, brightness);
+    }
+
+    fn draw(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        ctx.screen.reset();
+        let marker = |editing: bool| if editing { ">" } else { " " };
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{}ROOT {}", marker(!self.editing_scale), note_name(self.root)), 1);
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &format!("{}{}", marker(self.editing_scale), self.scale.name()), 1);
+    }
+
+    fn send(channel: u8, message: MidiMessage, ctx: &mut DriverContext) {
+        let live_event = LiveEvent::Midi { channel: channel.into(), message };
+        let mut midibuf = Vec::new();
+        if live_event.write(&mut midibuf).is_ok() {
+            let _ = ctx.midi_port.send(&midibuf[..]);
+        }
+    }
+
+    fn note_on(&mut self, pad: usize, note: u8, velocity: u8, ctx: &mut DriverContext) {
+        let settings = &ctx.settings.keyboard;
+        let (channel, stolen) = self.voices.start(pad, note, settings.channel, &settings.channels, settings.allocation);
+        if let Some((channel, note)) = stolen {
+            Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+        }
+        Self::send(channel, MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }, ctx);
+    }
+
+    fn note_off(&mut self, pad: usize, note: u8, ctx: &mut DriverContext) {
+        if let Some(channel) = self.voices.stop(pad, note) {
+            Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+        }
+    }
+
+    fn release_all(&mut self, ctx: &mut DriverContext) {
+        for pad in 0..16 {
+            if let Some(note) = self.sounding[pad].take() {
+                self.note_off(pad, note, ctx);
+            }
+        }
+    }
+
+    /// Moves the root by `semitones`, keeping the bottom-left pad within MIDI's range.
+    fn move_root(&mut self, semitones: i32, ctx: &mut DriverContext) {
+        let root = (self.root as i32 + semitones).clamp(0, 127) as u8;
+        if root != self.root {
+            self.root = root;
+            self.draw(ctx);
+        }
+    }
+}
+
+impl MachineMode for KeyboardMode {
+    fn name(&self) -> &'static str {
+        "KEYS"
+    }
+
+    /// The first of `channels` when the notes are spread over several
+    fn channel(&self, ctx: &DriverContext) -> u8 {
+        let settings = &ctx.settings.keyboard;
+        settings.channels.first().copied().unwrap_or(settings.channel) - 1
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        ctx.lights.set_button(Buttons::Left, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Right, Brightness::Dim);
+        self.draw(ctx);
+    }
+
+    fn on_exit(&mut self, ctx: &mut DriverContext) {
+        self.release_all(ctx);
+    }
+
+    /// The config's root and scale take over from the ones picked on the encoder.
+    fn on_settings_changed(&mut self, ctx: &mut DriverContext) {
+        self.root = ctx.settings.keyboard.root;
+        self.scale = ctx.settings.keyboard.scale;
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match *event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value } if value > 0 => {
+                let Some(note) = self.note(index, ctx.settings) else {
+                    return;
+                };
+                if let Some(previous) = self.sounding[index].replace(note) {
+                    self.note_off(index, previous, ctx);
+                }
+                let velocity = ((value >> 5) as u8).max(1);
+                self.note_on(index, note, velocity, ctx);
+                self.update_pad_light(index, ctx);
+            }
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOff | PadEventType::PressOff, .. } => {
+                if let Some(note) = self.sounding[index].take() {
+                    self.note_off(index, note, ctx);
+                    self.update_pad_light(index, ctx);
+                }
+            }
+            HardwareEvent::Button { index, pressed } => {
+                if !self.buttons.pressed(index, pressed) {
+                    return;
+                }
+                match index {
+                    Buttons::EncoderPress => {
+                        self.editing_scale = !self.editing_scale;
+                        self.draw(ctx);
+                    }
+                    Buttons::Left => self.move_root(-12, ctx),
+                    Buttons::Right => self.move_root(12, ctx),
+                    _ => {}
+                }
+            }
+            HardwareEvent::Encoder { value } => {
+                let direction = encoder_direction(self.last_encoder_val, value);
+                if value != 0 {
+                    self.last_encoder_val = value;
+                }
+                if direction == 0 {
+                    return;
+                }
+                if self.editing_scale {
+                    let current = Scale::ALL.iter().position(|s| *s == self.scale).unwrap_or(0) as i32;
+                    self.scale = Scale::ALL[(current + direction).clamp(0, Scale::ALL.len() as i32 - 1) as usize];
+                    self.draw(ctx);
+                } else {
+                    self.move_root(direction, ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+}
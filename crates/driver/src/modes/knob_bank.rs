@@ -0,0 +1,145 @@
+// crates/driver/src/modes/knob_bank.rs
+use midly::{live::LiveEvent, MidiMessage};
+use maschine_library::controls::PadEventType;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::settings::Settings;
+use crate::status::CONTENT_ROW;
+use super::{draw_value_bar, MachineMode, ParamEncoder};
+
+/// Up to 16 virtual parameters for the single encoder: a pad tap picks which one
+/// the encoder edits, and each keeps its own value between selections.
+pub struct KnobBankMode {
+    values: Vec<u8>,
+    selected: usize,
+    encoder: ParamEncoder,
+}
+
+impl KnobBankMode {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            values: settings.knobs.iter().map(|k| k.initial).collect(),
+            selected: 0,
+            encoder: ParamEncoder::new(),
+        }
+    }
+
+    fn update_pad_lights(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            if i == self.selected && i < self.values.len() {
+                ctx.lights.set_pad(i, PadColors::White, Brightness::Bright);
+            } else if i < self.values.len() {
+                ctx.lights.set_pad(i, PadColors::Blue, Brightness::Dim);
+            } else {
+                ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+            }
+        }
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let Some(knob) = ctx.settings.knobs.get(self.selected) else {
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, "NO KNOBS", 1);
+            return;
+        };
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{:02} {}", self.selected + 1, knob.name), 1);
+        draw_value_bar(ctx.screen, self.values[self.selected]);
+    }
+
+    fn send_value(&self, index: usize, ctx: &mut DriverContext) {
+        let knob = &ctx.settings.knobs[index];
+        let value = self.values[index];
+        if let Some(cc) = knob.cc {
+            let live_event = LiveEvent::Midi {
+                channel: ctx.settings.channel(knob.channel).into(),
+                message: MidiMessage::Controller { controller: cc.into(), value: value.into() },
+            };
+            let mut midibuf = Vec::new();
+            if live_event.write(&mut midibuf).is_ok() {
+                let _ = ctx.midi_port.send(&midibuf[..]);
+            }
+        }
+        if let Some(addr) = &knob.osc {
+            ctx.osc.send_formatted(addr, value as i32, knob.osc_format);
+        }
+    }
+
+    /// Takes a value reported by the host; returns true when the selected knob changed.
+    fn sync_value(&mut self, index: usize, value: i32, ctx: &mut DriverContext) -> bool {
+        self.values[index] = value.clamp(0, 127) as u8;
+        if index == self.selected {
+            self.draw_screen(ctx);
+            return true;
+        }
+        false
+    }
+}
+
+impl MachineMode for KnobBankMode {
+    fn name(&self) -> &'static str {
+        "KNOB"
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        self.update_pad_lights(ctx);
+        self.draw_screen(ctx);
+    }
+
+    /// Knobs that are still there keep their values; new ones start at `initial`.
+    fn on_settings_changed(&mut self, ctx: &mut DriverContext) {
+        let knobs = &ctx.settings.knobs;
+        self.values.truncate(knobs.len());
+        self.values.extend(knobs[self.values.len()..].iter().map(|k| k.initial));
+        self.selected = self.selected.min(knobs.len().saturating_sub(1));
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value }
+                if *value > 0 && *index < self.values.len() && *index != self.selected =>
+            {
+                self.selected = *index;
+                self.update_pad_lights(ctx);
+                self.draw_screen(ctx);
+            }
+            HardwareEvent::Button { index, pressed } => self.encoder.button(*index, *pressed),
+            HardwareEvent::Encoder { value } => {
+                let step = ctx.settings.knobs.get(self.selected).map_or(1, |k| k.step as i32);
+                let delta = self.encoder.delta(*value, step);
+                if delta == 0 || self.selected >= self.values.len() {
+                    return;
+                }
+                let current = self.values[self.selected] as i32;
+                let next = (current + delta).clamp(0, 127) as u8;
+                if next as i32 != current {
+                    self.values[self.selected] = next;
+                    self.send_value(self.selected, ctx);
+                    self.draw_screen(ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        let knobs = &ctx.settings.knobs;
+        let index = match event {
+            HostEvent::ControlChange { channel, controller, .. } => {
+                knobs.iter().position(|k| k.cc == Some(*controller) && ctx.settings.channel(k.channel) == *channel)
+            }
+            HostEvent::Osc { addr, .. } => knobs.iter().position(|k| k.osc.as_deref() == Some(addr.as_str())),
+            _ => None,
+        };
+        let value = match event {
+            HostEvent::ControlChange { value, .. } => *value as i32,
+            HostEvent::Osc { value, .. } => *value,
+            _ => return false,
+        };
+        match index {
+            Some(index) => self.sync_value(index, value, ctx),
+            None => false,
+        }
+    }
+}
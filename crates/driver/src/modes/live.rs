@@ -0,0 +1,256 @@
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::status::CONTENT_ROW;
+use super::{encoder_direction, ButtonEdges, MachineMode};
+
+/// Same header as the SysEx backup; the Live script's messages use their own commands.
+const HEADER: [u8; 3] = [0xF0, 0x7D, 0x4D];
+const TRACK_NAME: u8 = 0x20;
+const CLIP: u8 = 0x21;
+const TRANSPORT: u8 = 0x22;
+const RING: u8 = 0x23;
+/// Sent on entering the mode; the script answers with everything it would otherwise send
+/// as it changes.
+const HELLO: [u8; 5] = [0xF0, 0x7D, 0x4D, 0x2F, 0xF7];
+
+// Controllers the driver sends the script, on `[live] channel`.
+/// 0-15: launch the clip slot under the pad
+const CC_LAUNCH: u8 = 0;
+/// 16-19: stop the clips of the track in that column
+const CC_STOP_TRACK: u8 = 16;
+/// Moves the session ring sideways by `value - 64` tracks
+const CC_RING_TRACKS: u8 = 20;
+/// Moves the session ring by `value - 64` scenes
+const CC_RING_SCENES: u8 = 21;
+const CC_PLAY: u8 = 24;
+const CC_STOP: u8 = 25;
+const CC_RECORD: u8 = 26;
+const CC_STOP_ALL: u8 = 27;
+
+/// Name characters that fit a pad column of the screen
+const NAME_CHARS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipState {
+    Empty,
+    Stopped,
+    Playing,
+    /// Launched, waiting for the global quantization
+    Triggered,
+    Recording,
+}
+
+/// What the Live script reports, as SysEx on the driver's MIDI input:
+/// `F0 7D 4D 20 <column 0-3> <name...> F7` names the ring's tracks,
+/// `F0 7D 4D 21 <pad 0-15> <color 0-17> <state 0-4> F7` describes a clip slot,
+/// `F0 7D 4D 22 <playing> <recording> F7` the transport and
+/// `F0 7D 4D 23 <track> <scene> F7` where the ring is (14-bit each, high byte first).
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveMessage {
+    TrackName { column: usize, name: String },
+    Clip { pad: usize, color: PadColors, state: ClipState },
+    Transport { playing: bool, recording: bool },
+    Ring { track: u16, scene: u16 },
+}
+
+pub fn parse(bytes: &[u8]) -> Option<LiveMessage> {
+    let body = bytes.strip_prefix(&HEADER[..])?.strip_suffix(&[0xF7])?;
+    let (&command, args) = body.split_first()?;
+    match (command, args) {
+        (TRACK_NAME, [column @ 0..=3, name @ ..]) => Some(LiveMessage::TrackName {
+            column: *column as usize,
+            name: name.iter().map(|b| *b as char).collect(),
+        }),
+        (CLIP, [pad @ 0..=15, color, state]) => {
+            let state = match state {
+                0 => ClipState::Empty,
+                1 => ClipState::Stopped,
+                2 => ClipState::Playing,
+                3 => ClipState::Triggered,
+                4 => ClipState::Recording,
+                _ => return None,
+            };
+            Some(LiveMessage::Clip { pad: *pad as usize, color: num::FromPrimitive::from_u8(*color)?, state })
+        }
+        (TRANSPORT, [playing, recording]) => Some(LiveMessage::Transport { playing: *playing != 0, recording: *recording != 0 }),
+        (RING, [track_hi, track_lo, scene_hi, scene_lo]) => Some(LiveMessage::Ring {
+            track: ((*track_hi as u16) << 7) | *track_lo as u16,
+            scene: ((*scene_hi as u16) << 7) | *scene_lo as u16,
+        }),
+        _ => None,
+    }
+}
+
+/// Ableton Live through the companion remote script (`driver install-live-script`): the
+/// pads are a 4x4 session ring, lit in the clip colors, and launch their clip slot
+/// (Shift+pad stops that column's track). Left/Right move the ring by a track (four with
+/// Shift), the encoder by a scene. Play starts and stops Live, Stop stops it (Shift+Stop
+/// stops all clips) and Rec toggles session recording. The screen shows the ring's track
+/// names and position.
+pub struct LiveMode {
+    connected: bool,
+    names: [String; 4],
+    clips: [(PadColors, ClipState); 16],
+    playing: bool,
+    recording: bool,
+    ring: (u16, u16),
+    /// Pads and buttons held as of the last report, since their states repeat
+    held_pads: [bool; 16],
+    buttons: ButtonEdges,
+    last_encoder: u8,
+}
+
+impl LiveMode {
+    pub fn new() -> Self {
+        Self {
+            connected: false,
+            names: Default::default(),
+            clips: [(PadColors::Off, ClipState::Empty); 16],
+            playing: false,
+            recording: false,
+            ring: (0, 0),
+            held_pads: [false; 16],
+            buttons: ButtonEdges::new(),
+            last_encoder: 0,
+        }
+    }
+
+    fn send_cc(&self, controller: u8, value: u8, ctx: &mut DriverContext) {
+        let message = MidiMessage::Controller { controller: controller.into(), value: value.into() };
+        let mut midibuf = Vec::new();
+        let channel = ctx.settings.live.channel - 1;
+        if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+            let _ = ctx.midi_port.send(&midibuf[..]);
+        }
+    }
+
+    fn draw_pad(&self, index: usize, ctx: &mut DriverContext) {
+        let (color, state) = self.clips[index];
+        match state {
+            ClipState::Empty => ctx.lights.set_pad(index, PadColors::Off, Brightness::Off),
+            ClipState::Stopped => ctx.lights.set_pad(index, color, Brightness::Dim),
+            ClipState::Triggered => ctx.lights.set_pad(index, color, Brightness::Normal),
+            ClipState::Playing => ctx.lights.set_pad(index, color, Brightness::Bright),
+            ClipState::Recording => ctx.lights.set_pad(index, PadColors::Red, Brightness::Bright),
+        }
+    }
+
+    fn draw_transport(&self, ctx: &mut DriverContext) {
+        let lit = |on: bool| if on { Brightness::Bright } else { Brightness::Dim };
+        ctx.lights.set_button(Buttons::Play, lit(self.playing));
+        ctx.lights.set_button(Buttons::Rec, lit(self.recording));
+        ctx.lights.set_button(Buttons::Stop, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Left, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Right, Brightness::Dim);
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        if !self.connected {
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, "WAITING FOR LIVE", 1);
+            return;
+        }
+        for (column, name) in self.names.iter().enumerate() {
+            let label: String = name.chars().take(NAME_CHARS).collect();
+            Font::write_string(ctx.screen, CONTENT_ROW, column * 8 * NAME_CHARS, &label, 1);
+        }
+        let transport = match (self.playing, self.recording) {
+            (_, true) => "REC",
+            (true, false) => "PLAY",
+            (false, false) => "STOP",
+        };
+        let position = format!("T{} S{} {}", self.ring.0 + 1, self.ring.1 + 1, transport);
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &position, 1);
+    }
+}
+
+impl MachineMode for LiveMode {
+    fn name(&self) -> &'static str {
+        "LIVE"
+    }
+
+    fn channel(&self, ctx: &DriverContext) -> u8 {
+        ctx.settings.live.channel - 1
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        let _ = ctx.midi_port.send(&HELLO);
+        for i in 0..16 {
+            self.draw_pad(i, ctx);
+        }
+        self.draw_transport(ctx);
+        self.draw_screen(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        let shift = self.buttons.is_held(Buttons::Shift);
+        match *event {
+            HardwareEvent::Pad { index, event_type, value } => {
+                let held = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+                if held && !self.held_pads[index] {
+                    if shift {
+                        self.send_cc(CC_STOP_TRACK + (index % 4) as u8, 127, ctx);
+                    } else {
+                        self.send_cc(CC_LAUNCH + index as u8, 127, ctx);
+                    }
+                }
+                self.held_pads[index] = held;
+            }
+            HardwareEvent::Button { index, pressed } => {
+                if !self.buttons.pressed(index, pressed) {
+                    return;
+                }
+                let step = if shift { 4 } else { 1 };
+                match index {
+                    Buttons::Left => self.send_cc(CC_RING_TRACKS, 64 - step, ctx),
+                    Buttons::Right => self.send_cc(CC_RING_TRACKS, 64 + step, ctx),
+                    Buttons::Play => self.send_cc(CC_PLAY, 127, ctx),
+                    Buttons::Stop if shift => self.send_cc(CC_STOP_ALL, 127, ctx),
+                    Buttons::Stop => self.send_cc(CC_STOP, 127, ctx),
+                    Buttons::Rec => self.send_cc(CC_RECORD, 127, ctx),
+                    _ => {}
+                }
+            }
+            HardwareEvent::Encoder { value } => {
+                let direction = encoder_direction(self.last_encoder, value);
+                if value != 0 {
+                    self.last_encoder = value;
+                }
+                if direction != 0 {
+                    let step = if shift { 4 } else { 1 };
+                    self.send_cc(CC_RING_SCENES, (64 + direction * step) as u8, ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        let HostEvent::Live(message) = event else {
+            return false;
+        };
+        let was_connected = std::mem::replace(&mut self.connected, true);
+        match message {
+            LiveMessage::TrackName { column, name } => self.names[*column] = name.clone(),
+            LiveMessage::Clip { pad, color, state } => {
+                self.clips[*pad] = (*color, *state);
+                self.draw_pad(*pad, ctx);
+            }
+            LiveMessage::Transport { playing, recording } => {
+                self.playing = *playing;
+                self.recording = *recording;
+                self.draw_transport(ctx);
+            }
+            LiveMessage::Ring { track, scene } => self.ring = (*track, *scene),
+        }
+        // Clips only show on the pads, once the screen has left "waiting".
+        if !was_connected || !matches!(message, LiveMessage::Clip { .. }) {
+            self.draw_screen(ctx);
+        }
+        true
+    }
+}
@@ -0,0 +1,213 @@
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::settings::Settings;
+use super::{BitwigMode, ButtonEdges, ChordMode, CustomMidiMode, DeckMode, GameMode, KeyboardMode, KnobBankMode, LiveMode, MachineMode, PlayMode, PracticeMode, ReaperMode, ShowMode, VisualizerMode};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DriverMode {
+    CustomMidi,
+    Playability,
+    Knobs,
+    Deck,
+    Practice,
+    Show,
+    Visualizer,
+    Game,
+    Live,
+    Bitwig,
+    Reaper,
+    Keyboard,
+    Chord,
+}
+
+/// The buttons that pick modes, each lit bright while one of its modes is up
+const SELECTORS: [Buttons; 3] = [Buttons::Maschine, Buttons::Star, Buttons::Browse];
+
+/// Owns every mode and knows which one is up. Maschine, Star and Browse pick modes as
+/// before; holding Maschine and hitting a pad jumps straight to the mode in that slot of
+/// `available`.
+pub struct ModeManager {
+    current: DriverMode,
+    selectors: ButtonEdges,
+    pub custom_midi: CustomMidiMode,
+    pub play: PlayMode,
+    pub knobs: KnobBankMode,
+    pub deck: DeckMode,
+    pub practice: PracticeMode,
+    pub show: ShowMode,
+    pub visualizer: VisualizerMode,
+    pub game: GameMode,
+    pub live: LiveMode,
+    pub bitwig: BitwigMode,
+    pub reaper: ReaperMode,
+    pub keyboard: KeyboardMode,
+    pub chord: ChordMode,
+}
+
+impl ModeManager {
+    pub fn new(settings: &Settings, seed: u64) -> Self {
+        Self {
+            current: DriverMode::CustomMidi,
+            selectors: ButtonEdges::new(),
+            custom_midi: CustomMidiMode::new(),
+            play: PlayMode::new(settings, seed),
+            knobs: KnobBankMode::new(settings),
+            deck: DeckMode::new(),
+            practice: PracticeMode::new(),
+            show: ShowMode::new(),
+            visualizer: VisualizerMode::new(),
+            game: GameMode::new(seed),
+            live: LiveMode::new(),
+            bitwig: BitwigMode::new(),
+            reaper: ReaperMode::new(),
+            keyboard: KeyboardMode::new(settings),
+            chord: ChordMode::new(),
+        }
+    }
+
+    pub fn current_id(&self) -> DriverMode {
+        self.current
+    }
+
+    pub fn current(&mut self) -> &mut dyn MachineMode {
+        self.get(self.current)
+    }
+
+    pub fn get(&mut self, id: DriverMode) -> &mut dyn MachineMode {
+        match id {
+            DriverMode::CustomMidi => &mut self.custom_midi,
+            DriverMode::Playability => &mut self.play,
+            DriverMode::Knobs => &mut self.knobs,
+            DriverMode::Deck => &mut self.deck,
+            DriverMode::Practice => &mut self.practice,
+            DriverMode::Show => &mut self.show,
+            DriverMode::Visualizer => &mut self.visualizer,
+            DriverMode::Game => &mut self.game,
+            DriverMode::Live => &mut self.live,
+            DriverMode::Bitwig => &mut self.bitwig,
+            DriverMode::Reaper => &mut self.reaper,
+            DriverMode::Keyboard => &mut self.keyboard,
+            DriverMode::Chord => &mut self.chord,
+        }
+    }
+
+    /// Every mode, for host events that all of them follow
+    pub fn all(&mut self) -> [(DriverMode, &mut dyn MachineMode); 13] {
+        [
+            (DriverMode::CustomMidi, &mut self.custom_midi),
+            (DriverMode::Playability, &mut self.play),
+            (DriverMode::Knobs, &mut self.knobs),
+            (DriverMode::Deck, &mut self.deck),
+            (DriverMode::Practice, &mut self.practice),
+            (DriverMode::Show, &mut self.show),
+            (DriverMode::Visualizer, &mut self.visualizer),
+            (DriverMode::Game, &mut self.game),
+            (DriverMode::Live, &mut self.live),
+            (DriverMode::Bitwig, &mut self.bitwig),
+            (DriverMode::Reaper, &mut self.reaper),
+            (DriverMode::Keyboard, &mut self.keyboard),
+            (DriverMode::Chord, &mut self.chord),
+        ]
+    }
+
+    /// Utility pages Browse cycles through, in order; only the configured ones.
+    fn pages(settings: &Settings) -> Vec<DriverMode> {
+        [
+            (DriverMode::Knobs, !settings.knobs.is_empty()),
+            (DriverMode::Deck, !settings.deck.is_empty()),
+            (DriverMode::Practice, settings.practice.enabled),
+            (DriverMode::Show, settings.show.enabled),
+            (DriverMode::Visualizer, settings.visualizer.enabled),
+            (DriverMode::Live, settings.live.enabled),
+            (DriverMode::Bitwig, settings.bitwig.enabled),
+            (DriverMode::Reaper, settings.reaper.enabled),
+            (DriverMode::Keyboard, settings.keyboard.enabled),
+            (DriverMode::Chord, settings.chords.enabled),
+        ]
+        .into_iter()
+        .filter_map(|(page, configured)| configured.then_some(page))
+        .collect()
+    }
+
+    /// Modes this config can reach, in the order of the pads that pick them while
+    /// Maschine is held.
+    pub fn available(settings: &Settings) -> Vec<DriverMode> {
+        let mut modes = vec![DriverMode::CustomMidi, DriverMode::Playability];
+        modes.extend(Self::pages(settings));
+        modes.push(DriverMode::Game);
+        modes
+    }
+
+    /// Names of `available`, for the legend shown while Maschine is held.
+    pub fn available_names(&mut self, settings: &Settings) -> Vec<&'static str> {
+        Self::available(settings).into_iter().map(|id| self.get(id).name()).collect()
+    }
+
+    /// Mode a selector going down in `event` leads to. Maschine, Star and Browse repeat
+    /// their state in every button report, so only the report where one goes down picks.
+    pub fn selection(&mut self, event: &HardwareEvent, shift_held: bool, settings: &Settings) -> Option<DriverMode> {
+        let HardwareEvent::Button { index: selector @ (Buttons::Maschine | Buttons::Star | Buttons::Browse), pressed } = *event else {
+            return None;
+        };
+        self.selectors.pressed(selector, pressed).then(|| self.selected(selector, shift_held, settings))
+    }
+
+    /// Mode a selector press leads to; Browse steps to the next configured page.
+    fn selected(&self, selector: Buttons, shift_held: bool, settings: &Settings) -> DriverMode {
+        match selector {
+            Buttons::Maschine => DriverMode::CustomMidi,
+            Buttons::Star => DriverMode::Playability,
+            _ if shift_held => DriverMode::Game,
+            _ => {
+                let pages = Self::pages(settings);
+                match pages.iter().position(|p| *p == self.current) {
+                    Some(i) => pages[(i + 1) % pages.len()],
+                    None => pages.first().copied().unwrap_or(DriverMode::Knobs),
+                }
+            }
+        }
+    }
+
+    /// Passes a reloaded config on to every mode and enters the current one again, so its
+    /// lights and screen follow the new settings, or Custom MIDI mode when the config no
+    /// longer has it.
+    pub fn settings_changed(&mut self, ctx: &mut DriverContext) {
+        for (_, mode) in self.all() {
+            mode.on_settings_changed(ctx);
+        }
+        let id = if Self::available(ctx.settings).contains(&self.current) { self.current } else { DriverMode::CustomMidi };
+        self.enter(id, ctx);
+    }
+
+    /// Switches to `id`; picking the mode that's up already does nothing. Returns true
+    /// when the mode changed.
+    pub fn switch(&mut self, id: DriverMode, ctx: &mut DriverContext) -> bool {
+        if id == self.current {
+            return false;
+        }
+        self.enter(id, ctx);
+        true
+    }
+
+    /// Leaves the current mode and enters `id`, with the pads and screen cleared for it,
+    /// since they belong to the mode.
+    fn enter(&mut self, id: DriverMode, ctx: &mut DriverContext) {
+        self.current().on_exit(ctx);
+        self.current = id;
+        let selector = match id {
+            DriverMode::CustomMidi => Buttons::Maschine,
+            DriverMode::Playability => Buttons::Star,
+            _ => Buttons::Browse,
+        };
+        for button in SELECTORS {
+            ctx.lights.set_button(button, if button == selector { Brightness::Bright } else { Brightness::Dim });
+        }
+        for i in 0..16 {
+            ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+        }
+        ctx.screen.reset();
+        self.current().on_enter(ctx);
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+use maschine_library::lights::Brightness;
+use crate::context::DriverContext;
+use super::custom_midi::button_from_name;
+
+const FLASH: Duration = Duration::from_millis(80);
+/// A metronome starting partway into a beat waits for the next one.
+const LATE_START: f64 = 0.1;
+
+/// Play mode's metronome ([metronome]): on every beat of the meter while the loop is
+/// armed, recording or playing, it flashes its button (and pad) and sends its click, the
+/// first beat of the bar accented. Play mode says where it is; the metronome only counts.
+pub(crate) struct Metronome {
+    /// Beat of the meter clicked last, while running
+    last: Option<i64>,
+    flash_until: Option<Instant>,
+}
+
+impl Metronome {
+    pub fn new() -> Self {
+        Self { last: None, flash_until: None }
+    }
+
+    /// Beat of the meter at `quarters` quarter notes, and where in it that is
+    fn beat(quarters: f64, ctx: &DriverContext) -> (i64, f64) {
+        let beats = quarters * 4.0 / ctx.settings.transport.meter().beat_sixteenths() as f64;
+        (beats.floor() as i64, beats - beats.floor())
+    }
+
+    /// Goes on counting from `quarters` without a click, where the beats carry on from a
+    /// different place.
+    pub fn skip_to(&mut self, quarters: Option<f64>, ctx: &DriverContext) {
+        if self.last.is_some() {
+            self.last = quarters.map(|q| Self::beat(q, ctx).0);
+        }
+    }
+
+    /// Clicks when `quarters`, quarter notes from the top of the song, loop or count, is on
+    /// a new beat; `None` stops it. Returns true when the flash started or ended.
+    pub fn run(&mut self, quarters: Option<f64>, now: Instant, ctx: &mut DriverContext) -> bool {
+        let settings = &ctx.settings.metronome;
+        let button = button_from_name(&settings.button);
+        let mut changed = false;
+        let Some(quarters) = quarters else {
+            if self.last.take().is_some() || self.flash_until.take().is_some() {
+                if let Some(button) = button {
+                    ctx.lights.set_button(button, Brightness::Off);
+                }
+                changed = true;
+            }
+            return changed;
+        };
+        let (beat, into) = Self::beat(quarters, ctx);
+        let due = match self.last {
+            Some(last) => beat != last,
+            None => into < LATE_START,
+        };
+        if self.last.is_some() || due {
+            self.last = Some(beat);
+        }
+        if due {
+            if let Some(note) = settings.click_note {
+                let accent = beat.rem_euclid(ctx.settings.transport.meter().beats() as i64) == 0;
+                let velocity = if accent { settings.accent_velocity } else { settings.velocity };
+                let channel = settings.click_channel - 1;
+                let _ = ctx.midi_port.send(&[0x90 | channel, note, velocity]);
+                let _ = ctx.midi_port.send(&[0x80 | channel, note, 0]);
+            }
+            if let Some(button) = button {
+                ctx.lights.set_button(button, Brightness::Bright);
+            }
+            self.flash_until = Some(now + FLASH);
+            changed = true;
+        } else if self.flash_until.is_some_and(|until| now >= until) {
+            self.flash_until = None;
+            if let Some(button) = button {
+                ctx.lights.set_button(button, Brightness::Dim);
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    /// The pad lit on the beat, while it's lit
+    pub fn flashing_pad(&self, ctx: &DriverContext) -> Option<usize> {
+        self.flash_until.and(ctx.settings.metronome.pad).map(|pad| pad as usize - 1)
+    }
+}
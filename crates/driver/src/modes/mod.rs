@@ -1,17 +1,294 @@
 // crates/driver/src/modes/mod.rs
+pub mod bitwig;
+pub mod chord;
+pub mod clock_in;
+pub mod clock_out;
 pub mod custom_midi;
 pub mod play_mode;
+pub mod knob_bank;
+pub mod deck;
+pub mod game;
+pub mod keyboard;
+pub mod live;
+pub mod manager;
+pub mod metronome;
+pub mod note_repeat;
+pub mod pad_banks;
+pub mod practice;
+pub mod reaper;
+pub mod show;
+pub mod visualizer;
+pub mod voices;
 
+pub use bitwig::BitwigMode;
+pub use chord::ChordMode;
 pub use custom_midi::CustomMidiMode;
 pub use play_mode::PlayMode;
+pub use knob_bank::KnobBankMode;
+pub use deck::DeckMode;
+pub use game::GameMode;
+pub use keyboard::KeyboardMode;
+pub use live::LiveMode;
+pub use manager::{DriverMode, ModeManager};
+pub use practice::PracticeMode;
+pub use reaper::ReaperMode;
+pub use show::ShowMode;
+pub use visualizer::VisualizerMode;
 
+use std::time::{Duration, Instant};
+use midly::{live::LiveEvent, MidiMessage};
+use maschine_library::controls::Buttons;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
 use crate::context::DriverContext;
-use crate::input::HardwareEvent;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::settings::{ConfirmGesture, ConfirmSettings};
+use crate::status::CONTENT_ROW;
+
+const WARNING_BLINK: Duration = Duration::from_millis(125);
+const BAR_ROW: usize = CONTENT_ROW + 8;
+const BAR_WIDTH: usize = 100;
 
 pub trait MachineMode {
+    /// Short name shown in the screen's status bar
+    fn name(&self) -> &'static str;
+
     /// Called when the user switches to this mode
     fn on_enter(&mut self, ctx: &mut DriverContext);
 
+    /// Called when the user switches to another mode, before it enters
+    fn on_exit(&mut self, _ctx: &mut DriverContext) {}
+
+    /// Called on every mode after the config file was reloaded, with the new settings in
+    /// `ctx`, to bring what the mode took from the old ones in line. The active mode is
+    /// entered again afterwards, so this needn't draw.
+    fn on_settings_changed(&mut self, _ctx: &mut DriverContext) {}
+
     /// Called for every hardware event (button, pad, etc)
     fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext);
-}
\ No newline at end of file
+
+    /// Called for feedback from the host, whether or not the mode is active.
+    /// Returns true when the lights or the screen changed.
+    fn handle_host_event(&mut self, _event: &HostEvent, _ctx: &mut DriverContext) -> bool {
+        false
+    }
+
+    /// Called on every pass of the main loop while the mode is active.
+    /// Returns true when the lights or the screen changed.
+    fn tick(&mut self, _ctx: &mut DriverContext) -> bool {
+        false
+    }
+
+    /// Position of the mode's own clock in sixteenth notes, while one runs
+    fn clock_position(&self, _now: std::time::Instant) -> Option<u32> {
+        None
+    }
+
+    /// 0-based MIDI channel the mode plays on, for the status bar
+    fn channel(&self, ctx: &DriverContext) -> u8 {
+        ctx.settings.channel(None)
+    }
+
+    /// True when the mode needs the whole screen, status bar included
+    fn full_screen(&self) -> bool {
+        false
+    }
+
+    /// True while the mode redraws or schedules output on its own and needs frequent ticks
+    fn is_animating(&self) -> bool {
+        false
+    }
+}
+
+/// Remembers the note each held pad started, so its note off matches even when a
+/// velocity layer picked something other than the pad's usual note.
+pub(crate) struct PadNotes([Option<(u8, u8)>; 16]);
+
+impl PadNotes {
+    pub fn new() -> Self {
+        Self([None; 16])
+    }
+
+    /// Channel and note for a hit
+    pub fn press(&mut self, index: usize, velocity: u8, ctx: &DriverContext) -> (u8, u8) {
+        let played = ctx.settings.pad_note_at(index, ctx.groups.bank(), velocity);
+        self.0[index] = Some(played);
+        played
+    }
+
+    /// Channel and note to release
+    pub fn release(&mut self, index: usize, ctx: &DriverContext) -> (u8, u8) {
+        self.0[index].take().unwrap_or_else(|| ctx.settings.pad_note(index, ctx.groups.bank()))
+    }
+
+    /// Sends note off for every pad still sounding, so leaving a mode mid-hit doesn't
+    /// leave a note hanging.
+    pub fn release_all(&mut self, ctx: &mut DriverContext) {
+        for (channel, note) in self.0.iter_mut().filter_map(Option::take) {
+            let message = MidiMessage::NoteOff { key: note.into(), vel: 0.into() };
+            let mut midibuf = Vec::new();
+            if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+                let _ = ctx.midi_port.send(&midibuf[..]);
+            }
+        }
+    }
+}
+
+/// Holds a destructive action back until it's confirmed with a second press or a long
+/// press, depending on the action's gesture.
+pub(crate) struct Confirm {
+    held: bool,
+    /// Start of the pending gesture: the first press, or the start of the hold
+    started: Option<Instant>,
+}
+
+impl Confirm {
+    pub fn new() -> Self {
+        Self { held: false, started: None }
+    }
+
+    /// Follows the button, whose state reports repeat; true when the action should run now.
+    pub fn button(&mut self, pressed: bool, gesture: ConfirmGesture, now: Instant, settings: &ConfirmSettings) -> bool {
+        let was_held = std::mem::replace(&mut self.held, pressed);
+        let new_press = pressed && !was_held;
+        match gesture {
+            ConfirmGesture::None => new_press,
+            ConfirmGesture::Double if new_press => {
+                let timeout = Duration::from_millis(settings.timeout_ms);
+                if self.started.take().is_some_and(|first| now.duration_since(first) <= timeout) {
+                    true
+                } else {
+                    self.started = Some(now);
+                    false
+                }
+            }
+            ConfirmGesture::Hold if new_press => {
+                self.started = Some(now);
+                false
+            }
+            ConfirmGesture::Hold if !pressed => {
+                self.started = None;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs out the pending gesture; true when a hold just completed.
+    pub fn tick(&mut self, gesture: ConfirmGesture, now: Instant, settings: &ConfirmSettings) -> bool {
+        let Some(started) = self.started else {
+            return false;
+        };
+        let elapsed = now.duration_since(started);
+        match gesture {
+            ConfirmGesture::Hold if elapsed >= Duration::from_millis(settings.hold_ms) => {
+                self.started = None;
+                true
+            }
+            ConfirmGesture::Double if elapsed > Duration::from_millis(settings.timeout_ms) => {
+                self.started = None;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Blinks every pad red and the Erase light while an action waits for confirmation.
+    pub fn draw_warning(&self, lights: &mut Lights, now: Instant) {
+        let Some(started) = self.started else {
+            return;
+        };
+        let on = (now.duration_since(started).as_millis() / WARNING_BLINK.as_millis()).is_multiple_of(2);
+        let (color, level) = if on { (PadColors::Red, Brightness::Bright) } else { (PadColors::Off, Brightness::Off) };
+        for i in 0..16 {
+            lights.set_pad(i, color, level);
+        }
+        lights.set_button(Buttons::Erase, level);
+    }
+}
+
+/// Draws a 0-127 value under the first content line: an outlined bar, filled in
+/// proportion, with the number to its right.
+pub(crate) fn draw_value_bar(screen: &mut Screen, value: u8) {
+    Font::write_string(screen, BAR_ROW - 1, BAR_WIDTH + 4, &format!("{:3}", value), 1);
+    let fill = value as usize * (BAR_WIDTH - 1) / 127;
+    for j in 0..=BAR_WIDTH {
+        screen.set(BAR_ROW, j, true);
+        screen.set(BAR_ROW + 4, j, true);
+    }
+    for i in BAR_ROW + 1..BAR_ROW + 4 {
+        screen.set(i, 0, true);
+        screen.set(i, BAR_WIDTH, true);
+        for j in 1..=fill {
+            screen.set(i, j, true);
+        }
+    }
+}
+
+/// Turns a new absolute encoder reading into a step: 1, -1, or 0 when it didn't move.
+/// The encoder reports a 4-bit position that wraps around.
+pub(crate) fn encoder_direction(last: u8, val: u8) -> i32 {
+    if val == 0 || val == last {
+        return 0;
+    }
+    let diff = val as i8 - last as i8;
+    if (diff > 0 && diff < 8) || (diff < -8) { 1 } else { -1 }
+}
+
+/// Which buttons are down, followed through reports that repeat every button's state, to
+/// tell the report where a button goes down or comes up from the repeats.
+pub(crate) struct ButtonEdges([bool; 41]);
+
+impl ButtonEdges {
+    pub fn new() -> Self {
+        Self([false; 41])
+    }
+
+    /// Records the button's state; true when it differs from the last report's.
+    pub fn changed(&mut self, button: Buttons, pressed: bool) -> bool {
+        std::mem::replace(&mut self.0[button as usize], pressed) != pressed
+    }
+
+    /// Records the button's state; true on the report where it goes down.
+    pub fn pressed(&mut self, button: Buttons, pressed: bool) -> bool {
+        self.changed(button, pressed) && pressed
+    }
+
+    pub fn is_held(&self, button: Buttons) -> bool {
+        self.0[button as usize]
+    }
+}
+
+/// Encoder for editing a value: each detent moves it by a coarse step, or by one unit
+/// while Shift is held, for fine adjustment.
+pub(crate) struct ParamEncoder {
+    last_val: u8,
+    fine: bool,
+}
+
+impl ParamEncoder {
+    pub fn new() -> Self {
+        Self { last_val: 0, fine: false }
+    }
+
+    /// Follows Shift; pass it every button event the mode sees.
+    pub fn button(&mut self, button: Buttons, pressed: bool) {
+        if button == Buttons::Shift {
+            self.fine = pressed;
+        }
+    }
+
+    /// Change for a new encoder reading: `coarse` units per detent, 1 with Shift held.
+    pub fn delta(&mut self, value: u8, coarse: i32) -> i32 {
+        let direction = encoder_direction(self.last_val, value);
+        if value != 0 {
+            self.last_val = value;
+        }
+        direction * if self.fine { 1 } else { coarse }
+    }
+}
@@ -0,0 +1,134 @@
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::step_clock::{StepClock, RATES};
+use super::encoder_direction;
+
+const SLIDER_LEDS: usize = 25;
+
+/// Note repeat for the pad modes: while Note Repeat is held (or latched on, with
+/// `[note_repeat] latch`), held pads retrigger every step at the rate picked on the touch
+/// strip or the encoder, which the strip shows meanwhile. The first repeat comes a step
+/// after the hit that started it, on an internal clock at `[note_repeat] bpm`.
+pub(crate) struct NoteRepeat {
+    button_held: bool,
+    latched: bool,
+    /// Velocity of the hit on each held pad
+    pads: [Option<u8>; 16],
+    /// Picked on the strip or the encoder; `[note_repeat] rate` until then
+    rate: Option<u32>,
+    clock: StepClock,
+    running: bool,
+    last_encoder_val: u8,
+}
+
+impl NoteRepeat {
+    pub fn new() -> Self {
+        Self {
+            button_held: false,
+            latched: false,
+            pads: [None; 16],
+            rate: None,
+            clock: StepClock::new(),
+            running: false,
+            last_encoder_val: 0,
+        }
+    }
+
+    fn active(&self) -> bool {
+        self.button_held || self.latched
+    }
+
+    fn rate(&self, ctx: &DriverContext) -> u32 {
+        self.rate.unwrap_or(ctx.settings.note_repeat.rate)
+    }
+
+    /// Follows the Note Repeat button and the pads; while the button is held the strip
+    /// and the encoder pick the rate. Returns true when the event was note repeat's and
+    /// shouldn't reach the mode; pad events always go on.
+    pub fn handle(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) -> bool {
+        match *event {
+            HardwareEvent::Button { index: Buttons::NoteRepeat, pressed } => {
+                if pressed && !self.button_held && ctx.settings.note_repeat.latch {
+                    self.latched = !self.latched;
+                }
+                self.button_held = pressed;
+                self.draw(ctx.lights, self.rate(ctx));
+                true
+            }
+            HardwareEvent::Pad { index, event_type, value } => {
+                match event_type {
+                    PadEventType::NoteOn | PadEventType::PressOn if value > 0 => {
+                        self.pads[index].get_or_insert(((value >> 5) as u8).max(1));
+                    }
+                    PadEventType::NoteOff | PadEventType::PressOff => self.pads[index] = None,
+                    _ => {}
+                }
+                false
+            }
+            // The strip reports 1-200 while touched and 0 on release.
+            HardwareEvent::Slider { value } if self.button_held => {
+                if value > 0 {
+                    self.rate = Some(RATES[(value as usize - 1) * RATES.len() / 200]);
+                    self.draw(ctx.lights, self.rate(ctx));
+                }
+                true
+            }
+            HardwareEvent::Encoder { value } if self.button_held => {
+                let direction = encoder_direction(self.last_encoder_val, value);
+                if value != 0 {
+                    self.last_encoder_val = value;
+                }
+                let current = RATES.iter().position(|&r| r == self.rate(ctx)).unwrap_or(0);
+                let next = (current as i32 + direction).clamp(0, RATES.len() as i32 - 1) as usize;
+                self.rate = Some(RATES[next]);
+                self.draw(ctx.lights, RATES[next]);
+                true
+            }
+            HardwareEvent::Encoder { value } => {
+                if value != 0 {
+                    self.last_encoder_val = value;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Pads to retrigger now, with the velocity they were hit at.
+    pub fn due(&mut self, ctx: &DriverContext) -> Vec<(usize, u8)> {
+        if !self.active() || self.pads.iter().all(Option::is_none) {
+            self.running = false;
+            self.clock.reset();
+            return Vec::new();
+        }
+        let settings = &ctx.settings.note_repeat;
+        let step = self.clock.step(None, settings.bpm, self.rate(ctx), ctx.clock.now());
+        // The hit that started it already played.
+        if !std::mem::replace(&mut self.running, true) || !step {
+            return Vec::new();
+        }
+        self.pads.iter().enumerate().filter_map(|(pad, velocity)| velocity.map(|v| (pad, v))).collect()
+    }
+
+    /// Forgets the held pads, for when the mode is left.
+    pub fn release(&mut self) {
+        self.pads = [None; 16];
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.active() && self.pads.iter().any(Option::is_some)
+    }
+
+    /// Lights the button while note repeat is on, and the rate on the strip while the
+    /// button is held: the six rates from the bottom up.
+    fn draw(&self, lights: &mut Lights, rate: u32) {
+        lights.set_button(Buttons::NoteRepeat, if self.active() { Brightness::Bright } else { Brightness::Dim });
+        let zone = RATES.iter().position(|&r| r == rate).unwrap_or(0);
+        for i in 0..SLIDER_LEDS {
+            let lit = self.button_held && i * RATES.len() / SLIDER_LEDS == zone;
+            lights.set_slider(i, if lit { Brightness::Normal } else { Brightness::Off });
+        }
+    }
+}
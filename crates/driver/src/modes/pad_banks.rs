@@ -0,0 +1,74 @@
+use maschine_library::controls::PadEventType;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use super::custom_midi::button_from_name;
+
+/// Pad bank picking for the pad modes: while `[pad_banks] button` is held, the first
+/// pads stand for the banks, each in its color and the one in use bright, and a hit picks
+/// one. The bank is kept in `ctx.groups`, so every mode plays it until the next pick.
+pub(crate) struct PadBanks {
+    button_held: bool,
+    /// Pads down as of the last report, since their reports repeat
+    down: [bool; 16],
+    /// Pads hit while the button was held, taken until they're let go
+    taken: [bool; 16],
+}
+
+impl PadBanks {
+    pub fn new() -> Self {
+        Self { button_held: false, down: [false; 16], taken: [false; 16] }
+    }
+
+    /// True while the pads show the banks rather than the mode's lights
+    pub fn is_picking(&self) -> bool {
+        self.button_held
+    }
+
+    /// Takes the bank button and the pads hit while it's held. Returns true when the
+    /// event was taken and shouldn't reach the mode; once the button is let go, the mode
+    /// should light its pads again. Pads already down when the button goes down play on.
+    pub fn handle(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) -> bool {
+        match *event {
+            HardwareEvent::Button { index, pressed } if button_from_name(&ctx.settings.pad_banks.button) == Some(index) => {
+                self.button_held = pressed;
+                ctx.lights.set_button(index, if pressed { Brightness::Bright } else { Brightness::Off });
+                if pressed {
+                    self.draw(ctx);
+                }
+                true
+            }
+            HardwareEvent::Pad { index, event_type, value } => {
+                let down = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && value > 0;
+                let was_down = std::mem::replace(&mut self.down[index], down);
+                if self.taken[index] {
+                    self.taken[index] = down;
+                    return true;
+                }
+                if !down || was_down || !self.button_held {
+                    return false;
+                }
+                self.taken[index] = true;
+                if index < ctx.settings.bank_count() && index != ctx.groups.bank() {
+                    ctx.groups.set_bank(index);
+                    self.draw(ctx);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lights the banks on the pads; modes call it again after lighting pads themselves
+    /// while picking.
+    pub fn draw(&self, ctx: &mut DriverContext) {
+        for pad in 0..16 {
+            if pad < ctx.settings.bank_count() {
+                let brightness = if pad == ctx.groups.bank() { Brightness::Bright } else { Brightness::Dim };
+                ctx.lights.set_pad(pad, ctx.settings.bank_color(pad), brightness);
+            } else {
+                ctx.lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            }
+        }
+    }
+}
@@ -1,15 +1,87 @@
 // crates/driver/src/modes/play_mode.rs
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use midly::{live::LiveEvent, MidiMessage};
+use rosc::{OscMessage, OscType};
+use maschine_library::font::Font;
 use maschine_library::lights::{Brightness, PadColors};
 use maschine_library::controls::{Buttons, PadEventType};
+use crate::autosave;
+use crate::groove::{self, Groove, MAX_SWING};
 use crate::context::DriverContext;
-use crate::input::HardwareEvent;
-use super::MachineMode;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::midi_file::{self, FileNote};
+use crate::sequence::{LoopFormat, NoteSequence, SeqNote};
+use crate::settings::{Settings, WrapHold};
+use crate::status::CONTENT_ROW;
+use super::clock_in::{ClockIn, ClockMessage, CLOCKS_PER_BEAT};
+use super::clock_out::ClockOut;
+use super::game::Rng;
+use super::metronome::Metronome;
+use super::note_repeat::NoteRepeat;
+use super::pad_banks::PadBanks;
+use super::{encoder_direction, ButtonEdges, Confirm, MachineMode, PadNotes};
+
+/// Overdub passes Shift+Erase can take back
+const UNDO_LEVELS: usize = 32;
+/// Launches a scene, 1-16
+const SCENE: &str = "/maschine/play/scene";
+/// Most passes a follow action can wait
+const MAX_PASSES: u32 = 64;
+/// Controller the step view starts on, filter cutoff on most synths
+const DEFAULT_LANE: u8 = 74;
+
+/// A channel of the loop that comes around in a length of its own ([polymeter]), so it
+/// phases against the rest
+struct PolyTrack {
+    /// 0-based
+    channel: u8,
+    beats: u32,
+    length: Duration,
+    events: Vec<SeqEvent>,
+    cursor: usize,
+    /// Where the track was when it last played
+    position: Duration,
+}
+
+/// One of the loops the pads pick while Pattern is held. The one playing lives in Play
+/// mode's own fields; these are the others.
+#[derive(Clone, Default)]
+struct Pattern {
+    events: Vec<SeqEvent>,
+    loop_duration: Duration,
+    loop_beats: Option<u32>,
+    groove: Option<Groove>,
+    locks: Vec<Lock>,
+}
+
+/// A CC value locked to a step, sent before the step's notes on `channel` when it plays
+#[derive(Clone, Debug)]
+struct Lock {
+    /// Where the step starts in the loop
+    offset: Duration,
+    /// 0-based
+    channel: u8,
+    controller: u8,
+    value: u8,
+}
+
+/// The pattern (pad, 0-based) each of the 16 MIDI channels plays: what a scene keeps,
+/// and what the loop plays when its tracks come from more than one pattern
+pub type Mix = [usize; 16];
+
+/// After `passes` passes, a pattern goes on to one of `next`, at random. Off with no passes.
+#[derive(Clone, Default)]
+struct Follow {
+    passes: u32,
+    next: Vec<usize>,
+}
 
 #[derive(Clone, Debug)]
 struct SeqEvent {
     offset: Duration,
+    /// 0-based; the pad's zone channel when the pads are split
+    channel: u8,
     note: u8,
     velocity: u8,
     is_note_on: bool,
@@ -26,6 +98,7 @@ pub struct PlayMode {
     playback_start: Option<Instant>,  // Start of the current loop iteration
     loop_duration: Duration,
     paused_position: Option<Duration>, // Saved offset for resume
+    blink_anchor: Option<Instant>,     // Phase reference for the Rec blink
 
     // Data
     events: Vec<SeqEvent>,
@@ -34,14 +107,91 @@ pub struct PlayMode {
     // Visuals
     user_holding: [bool; 16], // Tracks pads physically held by user
     seq_holding: [bool; 16],  // Tracks pads held by sequencer
+    sounding: Vec<(u8, u8, u8)>, // Notes the loop holds: channel, note, velocity
+    tracks: Vec<PolyTrack>,       // Channels with a length of their own
+    passes: u32,                  // Times the loop came around since its top, for the tracks' phase
+    shown_beats: Vec<u32>,        // Beat of the loop and each track, as on the screen
+    pad_notes: PadNotes,
+    note_repeat: NoteRepeat,
+    banks: PadBanks,
+    clock_out: ClockOut,
+
+    // Host clock ([clock_in])
+    clock_in: ClockIn,
+    loop_beats: Option<u32>,          // Beats in the loop, once it has been measured against the clock
+    loop_anchor: u32,                 // Tick of the host's clock where the loop starts
+    take_anchor: Option<u32>,         // Beat the first take started on
+    locked: bool,                     // Playing from the host's clock rather than our own time
+    last_position: Duration,
     
     // Button States (for momentary lights)
+    buttons: ButtonEdges,             // As of the last report, which repeats every button's state
     is_restart_pressed: bool,
     is_erase_pressed: bool,
+    shift_held: bool,
+    confirm_clear: Confirm,
+
+    // Autosave
+    autosave_at: Option<Instant>,       // Last autosave check
+    autosaved: Option<NoteSequence>,   // What the last autosave wrote
+    browser: Option<AutosaveBrowser>,  // Open while picking an autosave to reload
+    last_encoder_val: u8,
+
+    // Groove ([groove])
+    groove: Option<Groove>,              // Feel the loop plays with
+    grooves: Option<Vec<Groove>>,        // Loaded while Swing is held, to pick from
+    swing: Option<u8>,                   // Set on the encoder; until then [groove] swing
+    swing_held: bool,
+
+    // Patterns
+    patterns: Vec<Pattern>,              // One per pad; the current one's slot stays empty
+    pattern: usize,                      // Pad of the pattern in use
+    queued: Option<usize>,               // Pattern to switch to when the loop comes around
+    pattern_held: bool,                  // The pads pick patterns
+    pattern_taken: [bool; 16],           // Pads hit while Pattern, Scene or Follow was held, until they're let go
+
+    // Scenes
+    scenes: Vec<Option<Mix>>,            // One per pad, stored with Shift
+    mix: Option<Mix>,                    // The tracks' patterns, when they aren't all the one in use
+    scene: Option<usize>,                // Scene playing, until something else is picked
+    queued_mix: Option<(Mix, Option<usize>)>, // Mix, and its scene, to switch to when the loop comes around
+    scene_held: bool,                    // The pads launch scenes
+    track: u8,                           // Channel of the last pad played, the track Scene+Pattern sets
+
+    // Follow actions
+    follows: Vec<Follow>,                // One per pattern
+    follow_held: bool,                   // The encoder sets the passes and the pads the next patterns
+    rng: Rng,                            // Picks among a follow action's patterns
+
+    // Parameter locks
+    locks: Vec<Lock>,                    // The pattern in use's
+    step_view: bool,                     // The pads are the loop's steps, for the encoder to lock values to
+    step_page: usize,                    // Which 16 steps the pads show
+    steps_held: [bool; 16],              // Step pads down
+    lane: u8,                            // Controller the step view locks, on the track last played
+
+    // Undo
+    overdub_start: Option<Pattern>,      // The pattern as the overdub found it, until it records a note
+    undo: Vec<Pattern>,                  // The pattern before each overdub pass, the last one last
+    redo: Vec<Pattern>,                  // Passes taken back, to put back in turn
+
+    // Metronome ([metronome])
+    metronome: Metronome,
+    count_from: Option<Instant>,         // Rec armed a first take with no clock: the count's top
+}
+
+/// On-screen list of the autosaves, newest first.
+struct AutosaveBrowser {
+    files: Vec<PathBuf>,
+    selected: usize,
 }
 
 impl PlayMode {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings, seed: u64) -> Self {
+        let mut follows = vec![Follow::default(); 16];
+        for follow in &settings.patterns.follow {
+            follows[follow.pattern as usize - 1] = Follow { passes: follow.passes, next: follow.next.iter().map(|&p| p as usize - 1).collect() };
+        }
         Self {
             armed: false,
             recording: false,
@@ -50,88 +200,93 @@ impl PlayMode {
             playback_start: None,
             loop_duration: Duration::from_millis(0),
             paused_position: None,
+            blink_anchor: None,
             events: Vec::new(),
             playback_cursor: 0,
             user_holding: [false; 16],
             seq_holding: [false; 16],
+            sounding: Vec::new(),
+            tracks: Vec::new(),
+            passes: 0,
+            shown_beats: Vec::new(),
+            pad_notes: PadNotes::new(),
+            note_repeat: NoteRepeat::new(),
+            banks: PadBanks::new(),
+            clock_out: ClockOut::new(),
+            clock_in: ClockIn::new(),
+            loop_beats: None,
+            loop_anchor: 0,
+            take_anchor: None,
+            locked: false,
+            last_position: Duration::ZERO,
+            buttons: ButtonEdges::new(),
             is_restart_pressed: false,
             is_erase_pressed: false,
+            shift_held: false,
+            confirm_clear: Confirm::new(),
+            autosave_at: None,
+            autosaved: None,
+            browser: None,
+            last_encoder_val: 0,
+            groove: None,
+            grooves: None,
+            swing: None,
+            swing_held: false,
+            patterns: vec![Pattern::default(); 16],
+            pattern: 0,
+            queued: None,
+            pattern_held: false,
+            pattern_taken: [false; 16],
+            scenes: vec![None; 16],
+            mix: None,
+            scene: None,
+            queued_mix: None,
+            scene_held: false,
+            locks: Vec::new(),
+            step_view: false,
+            step_page: 0,
+            steps_held: [false; 16],
+            lane: DEFAULT_LANE,
+            track: 0,
+            follows,
+            follow_held: false,
+            rng: Rng::new(seed),
+            overdub_start: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            metronome: Metronome::new(),
+            count_from: None,
         }
     }
 
-    pub fn tick(&mut self, ctx: &mut DriverContext) -> bool {
-        let mut changed = false;
-        let now = Instant::now();
-
-        // --- 1. SEQUENCER PLAYBACK & LOOPING ---
-        if self.playing && self.loop_duration > Duration::ZERO {
-            // Initialize playback anchor if missing
-            if self.playback_start.is_none() {
-                self.playback_start = Some(now);
-            }
-
-            let start = self.playback_start.unwrap();
-            let mut elapsed = now.duration_since(start);
-
-            // Loop Wrap
-            if elapsed >= self.loop_duration {
-                self.playback_start = Some(now);
-                self.playback_cursor = 0;
-                elapsed = Duration::from_millis(0);
-            }
-
-            // Fire Events
-            while self.playback_cursor < self.events.len() {
-                let event = &self.events[self.playback_cursor];
-                if event.offset <= elapsed {
-                    // Send MIDI
-                    let midi_msg = if event.is_note_on {
-                        MidiMessage::NoteOn { key: event.note.into(), vel: event.velocity.into() }
-                    } else {
-                        MidiMessage::NoteOff { key: event.note.into(), vel: event.velocity.into() }
-                    };
-                    
-                    let live_event = LiveEvent::Midi { channel: 0.into(), message: midi_msg };
-                    let mut buf = Vec::new();
-                    if live_event.write(&mut buf).is_ok() {
-                        let _ = ctx.midi_port.send(&buf);
-                    }
-
-                    // Update Sequence State & Lights
-                    if let Some(pad_index) = ctx.settings.notemaps.iter().position(|&n| n == event.note) {
-                        self.seq_holding[pad_index] = event.is_note_on;
-                        self.update_pad_light(ctx, pad_index);
-                        changed = true;
-                    }
-
-                    self.playback_cursor += 1;
-                } else {
-                    break;
-                }
-            }
+    fn update_pad_light(&self, ctx: &mut DriverContext, pad_index: usize) {
+        if self.pattern_held {
+            self.draw_pattern_pad(ctx, pad_index);
+            return;
         }
-
-        // --- 2. RECORDING BUTTON BLINK ---
-        // Blink logic: On for 500ms, Off for 500ms
-        if self.recording {
-            let blink_on = (now.elapsed().as_millis() / 500) % 2 == 0;
-            // When blinking off, use Dim to match "half lit when off" request
-            let brightness = if blink_on { Brightness::Bright } else { Brightness::Dim };
-            ctx.lights.set_button(Buttons::Rec, brightness);
-            changed = true;
+        if self.scene_held {
+            self.draw_scene_pad(ctx, pad_index);
+            return;
+        }
+        if self.follow_held {
+            self.draw_follow_pad(ctx, pad_index);
+            return;
+        }
+        if self.step_view {
+            self.draw_step_pad(ctx, pad_index);
+            return;
+        }
+        if self.metronome.flashing_pad(ctx) == Some(pad_index) {
+            ctx.lights.set_pad(pad_index, PadColors::White, Brightness::Bright);
+            return;
         }
-
-        changed
-    }
-
-    fn update_pad_light(&self, ctx: &mut DriverContext, pad_index: usize) {
         // Priority: User Input (White) > Sequencer (Orange) > Off
         if self.user_holding[pad_index] {
             ctx.lights.set_pad(pad_index, PadColors::White, Brightness::Bright);
         } else if self.seq_holding[pad_index] {
             ctx.lights.set_pad(pad_index, PadColors::Orange, Brightness::Normal);
         } else {
-            ctx.lights.set_pad(pad_index, PadColors::Off, Brightness::Off);
+            ctx.lights.set_pad(pad_index, ctx.settings.pad_zone_color(pad_index, ctx.groups.bank()), Brightness::Dim);
         }
     }
 
@@ -139,7 +294,10 @@ impl PlayMode {
         // Rec Button Logic:
         // Always active logic because it's the entry point for creating a loop.
         // If recording, tick() handles blinking. If not, we set static state here.
-        if !self.recording {
+        // A mix of patterns can't be recorded into.
+        if self.mix.is_some() {
+            ctx.lights.set_button(Buttons::Rec, Brightness::Off);
+        } else if !self.recording {
             if self.armed {
                 ctx.lights.set_button(Buttons::Rec, Brightness::Bright);
             } else {
@@ -147,6 +305,27 @@ impl PlayMode {
             }
         }
 
+        let swing = if self.groove.is_some() || self.swing(ctx) > 0 {
+            Brightness::Bright
+        } else if ctx.settings.groove.dir.is_some() {
+            Brightness::Dim
+        } else {
+            Brightness::Off
+        };
+        ctx.lights.set_button(Buttons::Swing, swing);
+        let follow = if self.follow_held {
+            Brightness::Bright
+        } else if self.follows(self.pattern).is_some() {
+            Brightness::Dim
+        } else {
+            Brightness::Off
+        };
+        ctx.lights.set_button(Buttons::Follow, follow);
+        ctx.lights.set_button(Buttons::Step, if self.step_view { Brightness::Bright } else { Brightness::Off });
+        let paging = if self.step_view && self.step_pages(ctx) > 1 { Brightness::Dim } else { Brightness::Off };
+        ctx.lights.set_button(Buttons::Left, paging);
+        ctx.lights.set_button(Buttons::Right, paging);
+
         // Other Transport Buttons Logic:
         if self.loop_duration == Duration::ZERO {
             // NO LOOP STORED: Everything else OFF
@@ -187,155 +366,1545 @@ impl PlayMode {
         }
     }
     
+    /// Replaces the loop with one sent by the host. A running loop carries on from the top
+    /// of the new one; otherwise it waits for Play.
+    fn load_sequence(&mut self, sequence: &NoteSequence, ctx: &mut DriverContext) {
+        // Notes the old loop was holding would never get their note off.
+        for (channel, note, _) in std::mem::take(&mut self.sounding) {
+            self.sound(ctx, channel, note, 0, false);
+        }
+        self.seq_holding = [false; 16];
+        // Over a mix, the loop goes to the pattern last in use.
+        if self.mix.take().is_some() {
+            self.patterns[self.pattern] = Pattern::default();
+        }
+        self.scene = None;
+
+        let length = sequence.length();
+        self.forget_history();
+        self.count_from = None;
+        self.events.clear();
+        self.locks.clear();
+        for note in &sequence.notes {
+            // Sequences carry no channel; a note plays on the channel of the pad it's mapped to.
+            let channel = (0..16)
+                .map(|i| ctx.settings.pad_note(i, ctx.groups.bank()))
+                .find(|(_, n)| *n == note.note)
+                .map_or(0, |(c, _)| c);
+            let start = Duration::from_millis(note.start_ms as u64);
+            let end = (start + Duration::from_millis(note.duration_ms as u64))
+                .clamp(start + Duration::from_millis(1), length - Duration::from_millis(1));
+            self.events.push(SeqEvent { offset: start, channel, note: note.note, velocity: note.velocity, is_note_on: true });
+            self.events.push(SeqEvent { offset: end, channel, note: note.note, velocity: 0, is_note_on: false });
+        }
+        // Note offs first, so a note repeated right where the previous one ends isn't cut.
+        self.events.sort_by_key(|e| (e.offset, e.is_note_on));
+
+        self.armed = false;
+        self.recording = false;
+        self.start_time = None;
+        self.loop_duration = length;
+        self.loop_beats = None;
+        self.playback_cursor = 0;
+        self.split_tracks(ctx);
+        if self.playing {
+            self.playback_start = Some(ctx.clock.now());
+            self.restart_on_beat(ctx);
+            self.clock_out.start(&ctx.settings.clock_out, ctx.midi_port);
+        } else {
+            self.paused_position = Some(Duration::ZERO);
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+        self.update_transport_lights(ctx);
+    }
+
+    /// The note ons of the loop, each with how long it lasts
+    fn notes(&self) -> Vec<(&SeqEvent, Duration)> {
+        let length = self.loop_duration;
+        let mut notes = Vec::new();
+        for (i, on) in self.events.iter().enumerate().filter(|(_, e)| e.is_note_on) {
+            // The note off may have been recorded past the loop end, so it comes around again.
+            let off = self.events[i + 1..]
+                .iter()
+                .chain(&self.events[..i])
+                .find(|e| !e.is_note_on && e.channel == on.channel && e.note == on.note);
+            let duration = match off {
+                Some(off) if off.offset >= on.offset => off.offset - on.offset,
+                Some(off) => length.saturating_sub(on.offset) + off.offset,
+                None => length.saturating_sub(on.offset),
+            };
+            notes.push((on, duration));
+        }
+        notes
+    }
+
+    /// The current loop as notes with durations; empty while nothing is recorded.
+    pub fn to_sequence(&self) -> NoteSequence {
+        let notes = self
+            .notes()
+            .into_iter()
+            .map(|(on, duration)| SeqNote {
+                start_ms: on.offset.as_millis() as u32,
+                duration_ms: duration.as_millis().min(u16::MAX as u128) as u16,
+                note: on.note,
+                velocity: on.velocity,
+            })
+            .collect();
+        NoteSequence { length_ms: self.loop_duration.as_millis() as u32, notes }
+    }
+
+    /// Beats the loop holds: measured against the clock it followed, `[clock_out]
+    /// beats_per_loop` when it sends one, or else one bar of `[transport]`'s meter
+    fn beats(&self, ctx: &DriverContext) -> f64 {
+        match self.loop_beats {
+            Some(beats) => beats as f64,
+            None if ctx.settings.clock_out.enabled => ctx.settings.clock_out.beats_per_loop as f64,
+            None => ctx.settings.transport.meter().bar_sixteenths() as f64 / 4.0,
+        }
+    }
+
+    /// Moves the notes on `[polymeter]` channels out of the loop into tracks of their own
+    /// length, folding whatever lies past a track's end to its top, and starts them all
+    /// from the top.
+    fn split_tracks(&mut self, ctx: &DriverContext) {
+        self.tracks.clear();
+        self.passes = 0;
+        if self.loop_duration == Duration::ZERO {
+            return;
+        }
+        let beats = self.beats(ctx);
+        for track in &ctx.settings.polymeter.tracks {
+            let channel = track.channel - 1;
+            let length = self.loop_duration.mul_f64(track.beats as f64 / beats);
+            let (mut events, rest): (Vec<_>, Vec<_>) = self.events.drain(..).partition(|e| e.channel == channel);
+            self.events = rest;
+            for event in &mut events {
+                event.offset = Duration::from_nanos((event.offset.as_nanos() % length.as_nanos().max(1)) as u64);
+            }
+            events.sort_by_key(|e| (e.offset, e.is_note_on));
+            self.tracks.push(PolyTrack { channel, beats: track.beats, length, events, cursor: 0, position: Duration::ZERO });
+        }
+    }
+
+    /// Puts each track where it is `song` after the top of the loop's first pass.
+    fn seek_tracks(&mut self, song: Duration) {
+        for track in &mut self.tracks {
+            track.position = Duration::from_nanos((song.as_nanos() % track.length.as_nanos().max(1)) as u64);
+            track.cursor = track.events.iter().position(|e| e.offset >= track.position).unwrap_or(track.events.len());
+        }
+    }
+
+    /// Writes the loop to `[midi_export] dir` as a MIDI file, at the tempo its beats make.
+    fn export(&self, ctx: &DriverContext) {
+        let Some(dir) = &ctx.settings.midi_export.dir else {
+            return;
+        };
+        if self.loop_duration == Duration::ZERO {
+            return;
+        }
+        let bpm = self.beats(ctx) * 60.0 / self.loop_duration.as_secs_f64();
+        let notes: Vec<FileNote> = self
+            .notes()
+            .into_iter()
+            .map(|(on, duration)| FileNote {
+                start: on.offset,
+                end: on.offset + duration,
+                channel: on.channel,
+                note: on.note,
+                velocity: on.velocity,
+            })
+            .collect();
+        let path = midi_file::save(dir, midi_file::encode(&notes, self.loop_duration, bpm, ctx.settings.transport.meter()));
+        println!("Exporting the loop to {} ({:.1} BPM)", path.display(), bpm);
+    }
+
+    /// Saves the loop every `autosave.interval_s`, unless it hasn't changed since the last save.
+    fn autosave(&mut self, now: Instant, ctx: &mut DriverContext) {
+        let settings = &ctx.settings.autosave;
+        let Some(dir) = &settings.dir else {
+            return;
+        };
+        let last = *self.autosave_at.get_or_insert(now);
+        if now.duration_since(last) < Duration::from_secs(settings.interval_s) {
+            return;
+        }
+        self.autosave_at = Some(now);
+        // A first take still being recorded has no length yet.
+        if self.loop_duration == Duration::ZERO {
+            return;
+        }
+        let sequence = self.to_sequence();
+        if self.autosaved.as_ref() != Some(&sequence) {
+            autosave::save(dir, settings.keep, &sequence);
+            self.autosaved = Some(sequence);
+        }
+    }
+
+    fn draw_browser(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let Some(path) = browser.files.get(browser.selected) else {
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, "NO AUTOSAVES", 1);
+            return;
+        };
+        let age = autosave::age(path).map_or("?".to_string(), format_age);
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{:02} {} AGO", browser.selected + 1, age), 1);
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, "PUSH: LOAD", 1);
+    }
+
+    /// Encoder press opens the browser, and in it loads the selected autosave.
+    fn browser_press(&mut self, ctx: &mut DriverContext) {
+        let Some(dir) = &ctx.settings.autosave.dir else {
+            return;
+        };
+        match self.browser.take() {
+            None => {
+                self.browser = Some(AutosaveBrowser { files: autosave::list(Path::new(dir)), selected: 0 });
+            }
+            Some(browser) => {
+                if let Some(path) = browser.files.get(browser.selected) {
+                    match autosave::load(path) {
+                        Ok(sequence) => {
+                            self.load_sequence(&sequence, ctx);
+                            // Reloading isn't a change worth another save.
+                            self.autosaved = Some(sequence);
+                        }
+                        Err(e) => eprintln!("Can't load {}: {}", path.display(), e),
+                    }
+                }
+            }
+        }
+        self.draw_browser(ctx);
+    }
+
+    fn swing(&self, ctx: &DriverContext) -> u8 {
+        self.swing.unwrap_or(ctx.settings.groove.swing)
+    }
+
+    /// Holding Swing shows the swing to set with the encoder, and loads the grooves in
+    /// `[groove] dir` to pick one from with Shift and the encoder; letting go keeps both
+    /// and gives the screen back.
+    fn swing_button(&mut self, pressed: bool, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        self.swing_held = pressed;
+        if pressed {
+            self.grooves = ctx.settings.groove.dir.as_deref().map(groove::load_all);
+            self.draw_groove(ctx);
+        } else {
+            self.grooves = None;
+            self.shown_beats.clear();
+        }
+    }
+
+    /// Off-beat 16ths move by 1% a detent, up to `MAX_SWING`.
+    fn turn_swing(&mut self, direction: i32, ctx: &mut DriverContext) {
+        if direction == 0 {
+            return;
+        }
+        self.swing = Some((self.swing(ctx) as i32 + direction).clamp(0, MAX_SWING as i32) as u8);
+        self.draw_groove(ctx);
+        self.update_transport_lights(ctx);
+    }
+
+    /// Steps through no groove and then each one in the folder.
+    fn pick_groove(&mut self, direction: i32, ctx: &mut DriverContext) {
+        let Some(grooves) = &self.grooves else {
+            return;
+        };
+        if direction == 0 {
+            return;
+        }
+        let current = self.groove.as_ref().and_then(|g| grooves.iter().position(|o| o.name == g.name)).map_or(0, |i| i + 1);
+        let picked = (current as i32 + direction).clamp(0, grooves.len() as i32) as usize;
+        self.groove = picked.checked_sub(1).map(|i| grooves[i].clone());
+        self.draw_groove(ctx);
+        self.update_transport_lights(ctx);
+    }
+
+    fn draw_groove(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("SWING {}%", self.swing(ctx)), 1);
+        if self.grooves.is_some() {
+            let name = self.groove.as_ref().map_or("OFF", |g| g.name.as_str());
+            Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &format!("GROOVE {name}"), 1);
+        }
+    }
+
+    /// Sends one of the loop's notes and lights its pad. Returns true when a light changed.
+    fn sound(&mut self, ctx: &mut DriverContext, channel: u8, note: u8, velocity: u8, on: bool) -> bool {
+        // Send MIDI
+        let midi_msg = if on {
+            MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }
+        } else {
+            MidiMessage::NoteOff { key: note.into(), vel: velocity.into() }
+        };
+        
+        let live_event = LiveEvent::Midi { channel: channel.into(), message: midi_msg };
+        let mut buf = Vec::new();
+        if live_event.write(&mut buf).is_ok() {
+            let _ = ctx.midi_port.send(&buf);
+        }
+
+        // Update Sequence State & Lights
+        if let Some(pad_index) = ctx.settings.pad_for_note(channel, note, ctx.groups.bank()) {
+            self.seq_holding[pad_index] = on;
+            self.update_pad_light(ctx, pad_index);
+            return true;
+        }
+        false
+    }
+
+    /// Sends the loop's events up to `elapsed` into it. A note still sounding when it
+    /// starts again ends first, and a note off for one that isn't sounding (cut at the
+    /// loop's end, or started before playback picked up) stays quiet. Returns true when a
+    /// pad light changed.
+    fn play_until(&mut self, elapsed: Duration, ctx: &mut DriverContext) -> bool {
+        let events = std::mem::take(&mut self.events);
+        let mut cursor = self.playback_cursor;
+        let (length, beats) = (self.loop_duration, self.beats(ctx));
+        let changed = self.play_events(&events, &mut cursor, elapsed, length, beats, ctx);
+        self.events = events;
+        self.playback_cursor = cursor;
+        changed
+    }
+
+    /// Sends `events` from `cursor` up to `elapsed`, for the loop or one of its tracks,
+    /// `length` long and `beats` beats. The groove and then the swing move each event off
+    /// its recorded time, though never past the end. The locks of a note on's step go out
+    /// just before it.
+    fn play_events(&mut self, events: &[SeqEvent], cursor: &mut usize, elapsed: Duration, length: Duration, beats: f64, ctx: &mut DriverContext) -> bool {
+        let mut changed = false;
+        let step = length.div_f64(beats.max(1.0) * 4.0);
+        let amount = ctx.settings.groove.amount as f64 / 100.0;
+        let groove = self.groove.take();
+        let swing = self.swing(ctx);
+        let feel = |offset: Duration| {
+            let offset = groove.as_ref().map_or(offset, |g| g.shift(offset, step, amount));
+            groove::swing(offset, step, swing).min(length)
+        };
+        // Steps whose locks went out, so a chord sends them once
+        let mut locked = Vec::new();
+        while let Some(mut event) = events.get(*cursor).filter(|e| feel(e.offset) <= elapsed).cloned() {
+            *cursor += 1;
+            if event.is_note_on {
+                let at = step_of(event.offset, step, length);
+                if !locked.contains(&(event.channel, at)) {
+                    locked.push((event.channel, at));
+                    for lock in self.locks.iter().filter(|l| l.channel == event.channel && step_of(l.offset, step, length) == at) {
+                        send_cc(ctx, lock.channel, lock.controller, lock.value);
+                    }
+                }
+            }
+            if let Some(groove) = &groove
+                && event.is_note_on
+            {
+                event.velocity = groove.velocity(event.offset, step, event.velocity, amount);
+            }
+            let held = self.sounding.iter().position(|&(c, n, _)| c == event.channel && n == event.note);
+            if let Some(i) = held {
+                self.sounding.remove(i);
+                changed |= self.sound(ctx, event.channel, event.note, if event.is_note_on { 0 } else { event.velocity }, false);
+            }
+            if event.is_note_on {
+                self.sounding.push((event.channel, event.note, event.velocity));
+                changed |= self.sound(ctx, event.channel, event.note, event.velocity, true);
+            }
+        }
+        self.groove = groove;
+        changed
+    }
+
+    /// The loop came around: plays what's left of the pass, settles the notes it holds
+    /// and goes back to its top. Returns true when a pad light changed.
+    fn wrap(&mut self, ctx: &mut DriverContext) -> bool {
+        let mut changed = self.play_until(self.loop_duration, ctx);
+        let events = std::mem::take(&mut self.events);
+        let tracks: Vec<u8> = self.tracks.iter().map(|t| t.channel).collect();
+        changed |= self.wrap_held(&events, |channel| !tracks.contains(&channel), ctx);
+        self.events = events;
+        self.playback_cursor = 0;
+        self.passes += 1;
+        self.follow();
+        if let Some(next) = self.queued {
+            self.load_pattern(next, ctx);
+            self.restart_on_beat(ctx);
+            changed = true;
+        } else if let Some((mix, scene)) = self.queued_mix.take() {
+            self.load_mix(mix, scene, ctx);
+            self.restart_on_beat(ctx);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Plays the tracks up to where they are `elapsed` into this pass of the loop, each
+    /// coming around at its own end. Returns true when a pad light changed.
+    fn play_tracks(&mut self, elapsed: Duration, ctx: &mut DriverContext) -> bool {
+        let song = self.loop_duration * self.passes + elapsed;
+        let mut tracks = std::mem::take(&mut self.tracks);
+        let mut changed = false;
+        for track in &mut tracks {
+            let position = Duration::from_nanos((song.as_nanos() % track.length.as_nanos().max(1)) as u64);
+            if position < track.position {
+                changed |= self.play_events(&track.events, &mut track.cursor, track.length, track.length, track.beats as f64, ctx);
+                changed |= self.wrap_held(&track.events, |channel| channel == track.channel, ctx);
+                track.cursor = 0;
+            }
+            changed |= self.play_events(&track.events, &mut track.cursor, position, track.length, track.beats as f64, ctx);
+            track.position = position;
+        }
+        self.tracks = tracks;
+        changed
+    }
+
+    /// Shows the beat the loop and each track are on, when there are tracks. Returns true
+    /// when the screen changed.
+    fn draw_tracks(&mut self, elapsed: Duration, ctx: &mut DriverContext) -> bool {
+        if self.tracks.is_empty() || self.browser.is_some() || self.swing_held || self.step_view {
+            return false;
+        }
+        let beats = (self.beats(ctx).round() as u32).max(1);
+        let beat_of = |position: Duration, length: Duration, beats: u32| {
+            ((position.as_secs_f64() / length.as_secs_f64() * beats as f64) as u32).min(beats - 1)
+        };
+        let mut shown = vec![beat_of(elapsed, self.loop_duration, beats)];
+        shown.extend(self.tracks.iter().map(|t| beat_of(t.position, t.length, t.beats)));
+        if shown == self.shown_beats {
+            return false;
+        }
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("LOOP {}/{}", shown[0] + 1, beats), 1);
+        let tracks: Vec<String> = self.tracks.iter().zip(&shown[1..]).map(|(t, beat)| format!("{}:{}/{}", t.channel + 1, beat + 1, t.beats)).collect();
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &tracks.join(" "), 1);
+        self.shown_beats = shown;
+        true
+    }
+
+    /// The notes the loop holds on the channels `channels` picks ring on, end or start
+    /// again, as `[loop_wrap]` says for their channel, now that `events` came around.
+    /// Returns true when a pad light changed.
+    fn wrap_held(&mut self, events: &[SeqEvent], channels: impl Fn(u8) -> bool, ctx: &mut DriverContext) -> bool {
+        let mut changed = false;
+        for (channel, note, velocity) in std::mem::take(&mut self.sounding) {
+            if !channels(channel) {
+                self.sounding.push((channel, note, velocity));
+                continue;
+            }
+            // A note starting right at the top plays again anyway.
+            let restarts = events.iter().take_while(|e| e.offset.is_zero()).any(|e| e.is_note_on && e.channel == channel && e.note == note);
+            match ctx.settings.loop_wrap.held(channel) {
+                WrapHold::Cut => changed |= self.sound(ctx, channel, note, 0, false),
+                WrapHold::Retrigger if !restarts => {
+                    self.sound(ctx, channel, note, 0, false);
+                    changed |= self.sound(ctx, channel, note, velocity, true);
+                    self.sounding.push((channel, note, velocity));
+                }
+                _ => self.sounding.push((channel, note, velocity)),
+            }
+        }
+        changed
+    }
+
+    /// The first event at or after `position`, where playback from there picks up
+    fn cursor_at(&self, position: Duration) -> usize {
+        self.events.iter().position(|e| e.offset >= position).unwrap_or(self.events.len())
+    }
+
+    /// Where the loop is by the host's clock, while it runs and `[clock_in]` or `[link]` is
+    /// on. A loop that wasn't recorded to the clock gets the nearest whole number of beats.
+    fn synced_position(&mut self, now: Instant, ctx: &DriverContext) -> Option<Duration> {
+        if !follows_clock(ctx) {
+            return None;
+        }
+        let ticks = self.clock_in.position(now)?;
+        self.loop_position(ticks)
+    }
+
+    /// Where the loop is at `ticks` into the song
+    fn loop_position(&mut self, ticks: f64) -> Option<Duration> {
+        if self.loop_beats.is_none() {
+            let beat = self.clock_in.beat()?;
+            self.loop_beats = Some((self.loop_duration.as_secs_f64() / beat.as_secs_f64()).round().max(1.0) as u32);
+        }
+        let loop_ticks = (self.loop_beats? * CLOCKS_PER_BEAT) as f64;
+        let in_loop = (ticks - self.loop_anchor as f64).rem_euclid(loop_ticks);
+        Some(self.loop_duration.mul_f64(in_loop / loop_ticks))
+    }
+
+    /// Moves the top of the loop to the beat nearest now, while the host's clock runs.
+    fn restart_on_beat(&mut self, ctx: &DriverContext) {
+        if follows_clock(ctx)
+            && let Some((beat, _)) = self.clock_in.nearest_beat(ctx.clock.now())
+        {
+            self.loop_anchor = beat;
+            // The next tick finds its place in the loop again.
+            self.locked = false;
+        }
+    }
+
+    /// Ends the first take. Recorded to the host's clock, it's rounded to whole beats from
+    /// the beat it started on, or whole bars of the Link session's quantum, and notes played
+    /// past the end go to the top.
+    fn end_take(&mut self, ctx: &mut DriverContext) {
+        let now = ctx.clock.now();
+        if let Some(start) = self.start_time {
+            self.loop_duration = now.duration_since(start);
+        }
+        self.playback_start = Some(now);
+        if let Some(anchor) = self.take_anchor.take()
+            && let Some(beat) = self.clock_in.beat().filter(|_| self.clock_in.is_running())
+        {
+            let unit = if ctx.settings.link.enabled { ctx.settings.link.quantum } else { 1 };
+            let bars = (self.loop_duration.as_secs_f64() / (beat * unit).as_secs_f64()).round().max(1.0) as u32;
+            let beats = bars * unit;
+            self.loop_duration = beat * beats;
+            self.loop_beats = Some(beats);
+            self.loop_anchor = anchor;
+            self.fold_take(ctx);
+            // What was just played live doesn't play again.
+            if let Some(position) = self.synced_position(now, ctx) {
+                self.locked = true;
+                self.last_position = position;
+                self.playback_cursor = self.cursor_at(position);
+                self.seek_tracks(position);
+            }
+        } else if self.count_from.take().is_some()
+            && let Some(start) = self.start_time
+        {
+            // Counted in by the metronome: whole beats at its tempo, the second pass already
+            // under way on its clicks.
+            let beat = Duration::from_secs_f64(60.0 / ctx.settings.metronome.bpm as f64);
+            let beats = (self.loop_duration.as_secs_f64() / beat.as_secs_f64()).round().max(1.0) as u32;
+            self.loop_duration = beat * beats;
+            self.loop_beats = Some(beats);
+            self.fold_take(ctx);
+            let top = start + self.loop_duration;
+            self.playback_start = Some(top);
+            self.playback_cursor = self.cursor_at(now.saturating_duration_since(top));
+            let into = now.saturating_duration_since(top).as_secs_f64() - top.saturating_duration_since(now).as_secs_f64();
+            self.metronome.skip_to(Some(into / beat.as_secs_f64()), ctx);
+        } else {
+            self.split_tracks(ctx);
+        }
+    }
+
+    /// Moves the tracks out of a first take cut to length, and brings the notes played
+    /// past its end to the top.
+    fn fold_take(&mut self, ctx: &DriverContext) {
+        self.split_tracks(ctx);
+        let length = self.loop_duration.as_nanos();
+        for event in &mut self.events {
+            event.offset = Duration::from_nanos((event.offset.as_nanos() % length) as u64);
+        }
+        self.events.sort_by_key(|e| e.offset);
+    }
+
+    /// Where the metronome is, in quarter notes: on the host's clock while it runs, along
+    /// the loop while it plays, and from Rec at `[metronome] bpm` while a first take has no
+    /// clock to follow
+    fn metronome_position(&self, now: Instant, ctx: &DriverContext) -> Option<f64> {
+        if !(self.armed || self.recording || self.playing) {
+            return None;
+        }
+        if follows_clock(ctx)
+            && let Some(ticks) = self.clock_in.position(now)
+        {
+            return Some(ticks / CLOCKS_PER_BEAT as f64);
+        }
+        if self.playing && self.loop_duration > Duration::ZERO {
+            // A take rounded up to the next beat comes around a moment from now.
+            let ahead = self.playback_start.map_or(Duration::ZERO, |start| start.saturating_duration_since(now));
+            let pass = (self.position(now).as_secs_f64() - ahead.as_secs_f64()) / self.loop_duration.as_secs_f64();
+            return Some((self.passes as f64 + pass) * self.beats(ctx));
+        }
+        let from = self.count_from?;
+        Some(now.saturating_duration_since(from).as_secs_f64() * ctx.settings.metronome.bpm as f64 / 60.0)
+    }
+
+    fn pause(&mut self, ctx: &mut DriverContext) {
+        self.clock_out.stop(ctx.midi_port);
+        self.playing = false;
+        self.recording = false; // Stop recording if we pause
+        
+        // Calculate where we paused relative to loop start
+        if std::mem::take(&mut self.locked) {
+            self.paused_position = Some(self.last_position);
+        } else if let Some(start) = self.playback_start {
+            let elapsed = ctx.clock.now().duration_since(start);
+            let pos = if self.loop_duration > Duration::ZERO {
+                let nanos = elapsed.as_nanos() % self.loop_duration.as_nanos();
+                Duration::from_nanos(nanos as u64)
+            } else {
+                Duration::ZERO
+            };
+            self.paused_position = Some(pos);
+        }
+        
+        // Turn off sequencer lights as we paused
+        self.seq_holding = [false; 16];
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    fn resume(&mut self, ctx: &mut DriverContext) {
+        self.playing = true;
+        
+        let offset = self.paused_position.unwrap_or(Duration::ZERO);
+        // From the top after Stop or Restart; where it paused otherwise.
+        if offset.is_zero() {
+            self.clock_out.start(&ctx.settings.clock_out, ctx.midi_port);
+        } else {
+            self.clock_out.resume(&ctx.settings.clock_out, ctx.midi_port);
+        }
+        // Set playback start in the past so that (now - start) == offset
+        self.playback_start = Some(ctx.clock.now() - offset);
+        
+        // Re-sync cursor
+        self.playback_cursor = self.cursor_at(offset);
+        self.seek_tracks(self.loop_duration * self.passes + offset);
+    }
+
+    /// Follows the host's transport: Start plays the loop from its top, on the song's first
+    /// beat; Stop pauses it and Continue picks up where the clock is. Returns true when
+    /// the loop started or stopped.
+    fn handle_clock(&mut self, message: ClockMessage, ctx: &mut DriverContext) -> bool {
+        self.clock_in.handle(message, ctx.clock.now());
+        if self.loop_duration == Duration::ZERO {
+            return false;
+        }
+        match message {
+            ClockMessage::Start => {
+                self.loop_anchor = 0;
+                self.locked = false;
+                self.passes = 0;
+                if !self.playing {
+                    self.paused_position = Some(Duration::ZERO);
+                    self.resume(ctx);
+                }
+            }
+            ClockMessage::Continue if !self.playing => self.resume(ctx),
+            ClockMessage::Stop if self.playing => self.pause(ctx),
+            _ => return false,
+        }
+        self.update_transport_lights(ctx);
+        true
+    }
+
+    /// The pattern in use, its tracks' notes back in the loop
+    fn current_pattern(&self) -> Pattern {
+        let mut events = self.events.clone();
+        events.extend(self.tracks.iter().flat_map(|t| t.events.iter().cloned()));
+        events.sort_by_key(|e| (e.offset, e.is_note_on));
+        Pattern { events, loop_duration: self.loop_duration, loop_beats: self.loop_beats, groove: self.groove.clone(), locks: self.locks.clone() }
+    }
+
+    fn has_pattern(&self, index: usize) -> bool {
+        let length = if self.in_use(index) { self.loop_duration } else { self.patterns[index].loop_duration };
+        length > Duration::ZERO
+    }
+
+    /// Takes back the last overdub pass that recorded anything, ending the overdub.
+    fn undo(&mut self, ctx: &mut DriverContext) {
+        if let Some(before) = self.undo.pop() {
+            self.redo.push(self.current_pattern());
+            self.restore_notes(before, ctx);
+        }
+    }
+
+    /// Puts back the last pass taken back.
+    fn redo(&mut self, ctx: &mut DriverContext) {
+        if let Some(after) = self.redo.pop() {
+            self.undo.push(self.current_pattern());
+            self.restore_notes(after, ctx);
+        }
+    }
+
+    /// Gives the loop the notes `pattern` has, carrying on from where it is.
+    fn restore_notes(&mut self, pattern: Pattern, ctx: &mut DriverContext) {
+        for (channel, note, _) in std::mem::take(&mut self.sounding) {
+            self.sound(ctx, channel, note, 0, false);
+        }
+        self.seq_holding = [false; 16];
+        self.recording = false;
+        self.overdub_start = None;
+        let passes = self.passes;
+        self.events = pattern.events;
+        self.split_tracks(ctx);
+        self.passes = passes;
+        let position = self.position(ctx.clock.now());
+        self.playback_cursor = self.cursor_at(position);
+        self.seek_tracks(self.loop_duration * passes + position);
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    /// Undo goes back over the overdubs of the loop in use only.
+    fn forget_history(&mut self) {
+        self.overdub_start = None;
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Where the loop is in its pass: playing, or where it'll pick up from
+    fn position(&self, now: Instant) -> Duration {
+        if self.locked {
+            return self.last_position;
+        }
+        match self.playback_start {
+            Some(start) if self.playing => Duration::from_nanos((now.duration_since(start).as_nanos() % self.loop_duration.as_nanos().max(1)) as u64),
+            _ => self.paused_position.unwrap_or_default(),
+        }
+    }
+
+    /// Pattern `index`'s follow action, when it has one
+    fn follows(&self, index: usize) -> Option<&Follow> {
+        Some(&self.follows[index]).filter(|f| f.passes > 0 && !f.next.is_empty())
+    }
+
+    /// Queues the next pattern when the one in use has played the passes its follow
+    /// action asks for, unless one was picked already, it's overdubbing or a mix plays.
+    /// Patterns without notes are passed over.
+    fn follow(&mut self) {
+        if self.queued.is_some() || self.queued_mix.is_some() || self.recording || self.mix.is_some() {
+            return;
+        }
+        let Some(follow) = self.follows(self.pattern) else {
+            return;
+        };
+        if !self.passes.is_multiple_of(follow.passes) {
+            return;
+        }
+        let next: Vec<usize> = follow.next.iter().copied().filter(|&p| p != self.pattern && self.has_pattern(p)).collect();
+        if !next.is_empty() {
+            self.queued = Some(next[self.rng.below(next.len())]);
+        }
+    }
+
+    /// Holding Follow shows the follow action of the pattern in use: the encoder sets the
+    /// passes it waits, down to off, and the pads pick the patterns it goes on to.
+    fn follow_button(&mut self, pressed: bool, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        self.follow_held = pressed;
+        if pressed {
+            self.draw_follow(ctx);
+        } else {
+            self.shown_beats.clear();
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    fn turn_passes(&mut self, direction: i32, ctx: &mut DriverContext) {
+        if direction == 0 {
+            return;
+        }
+        let follow = &mut self.follows[self.pattern];
+        follow.passes = (follow.passes as i32 + direction).clamp(0, MAX_PASSES as i32) as u32;
+        self.draw_follow(ctx);
+    }
+
+    /// A pad hit while Follow is held adds that pattern to the ones the pattern in use goes
+    /// on to, or takes it out.
+    fn follow_pad(&mut self, index: usize, ctx: &mut DriverContext) {
+        let next = &mut self.follows[self.pattern].next;
+        match next.iter().position(|&p| p == index) {
+            Some(i) => {
+                next.remove(i);
+            }
+            None => next.push(index),
+        }
+        self.draw_follow(ctx);
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    fn draw_follow(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let follow = &self.follows[self.pattern];
+        let passes = match follow.passes {
+            0 => "OFF".to_string(),
+            1 => "1 PASS".to_string(),
+            n => format!("{n} PASSES"),
+        };
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("FOLLOW P{}: {}", self.pattern + 1, passes), 1);
+        let mut next: Vec<usize> = follow.next.iter().map(|p| p + 1).collect();
+        next.sort();
+        let next = if next.is_empty() { "NONE".to_string() } else { next.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(" ") };
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &format!("TO {next}"), 1);
+        self.update_transport_lights(ctx);
+    }
+
+    /// While Follow is held: the pattern in use green, the ones it goes on to yellow, the
+    /// others with notes blue
+    fn draw_follow_pad(&self, ctx: &mut DriverContext, index: usize) {
+        let (color, brightness) = if index == self.pattern {
+            (PadColors::Green, Brightness::Normal)
+        } else if self.follows[self.pattern].next.contains(&index) {
+            (PadColors::Yellow, Brightness::Normal)
+        } else if self.has_pattern(index) {
+            (PadColors::Blue, Brightness::Dim)
+        } else {
+            (PadColors::Off, Brightness::Off)
+        };
+        ctx.lights.set_pad(index, color, brightness);
+    }
+
+    /// Whether pattern `index` is the one playing, out of its slot. A mix leaves every
+    /// pattern in its slot.
+    fn in_use(&self, index: usize) -> bool {
+        self.mix.is_none() && index == self.pattern
+    }
+
+    /// The pattern each track plays now
+    fn current_mix(&self) -> Mix {
+        self.mix.unwrap_or([self.pattern; 16])
+    }
+
+    /// Ends the loop's notes and puts the pattern in use back in its slot.
+    fn put_away(&mut self, ctx: &mut DriverContext) {
+        for (channel, note, _) in std::mem::take(&mut self.sounding) {
+            self.sound(ctx, channel, note, 0, false);
+        }
+        self.seq_holding = [false; 16];
+        if self.mix.take().is_none() {
+            self.patterns[self.pattern] = self.current_pattern();
+        }
+        self.queued = None;
+        self.queued_mix = None;
+        self.forget_history();
+    }
+
+    /// Puts the pattern in use away and plays pattern `index` from its top instead, or
+    /// stops when it's empty.
+    fn load_pattern(&mut self, index: usize, ctx: &mut DriverContext) {
+        self.put_away(ctx);
+        let next = std::mem::take(&mut self.patterns[index]);
+        self.pattern = index;
+        self.scene = None;
+        self.play_pattern(next, ctx);
+    }
+
+    /// The pattern each track plays now, for a performance snapshot
+    pub fn mix(&self) -> Mix {
+        self.current_mix()
+    }
+
+    /// Switches to a performance snapshot's patterns as a scene would, at the end of the
+    /// pass when playing.
+    pub fn recall_mix(&mut self, mix: Mix, ctx: &mut DriverContext) {
+        if mix != self.current_mix() {
+            self.launch(mix, None, ctx);
+        }
+    }
+
+    /// Plays each track from the pattern `mix` picks for it, from the top; a mix of one
+    /// pattern is just that pattern.
+    fn load_mix(&mut self, mix: Mix, scene: Option<usize>, ctx: &mut DriverContext) {
+        if mix.iter().all(|&p| p == mix[0]) {
+            if !self.in_use(mix[0]) {
+                self.load_pattern(mix[0], ctx);
+            }
+            self.scene = scene;
+            return;
+        }
+        self.put_away(ctx);
+        let next = self.mixed(&mix);
+        self.mix = Some(mix);
+        self.scene = scene;
+        self.play_pattern(next, ctx);
+    }
+
+    /// The loop `mix` makes of the patterns: each track's notes from its own pattern, as
+    /// long as the longest of them, with shorter ones repeating to fill it. The longest
+    /// also gives the beats and the groove.
+    fn mixed(&self, mix: &Mix) -> Pattern {
+        let longest = mix.iter().copied().max_by_key(|&p| self.patterns[p].loop_duration).unwrap_or(0);
+        let lead = &self.patterns[longest];
+        let length = lead.loop_duration;
+        let mut events = Vec::new();
+        let mut locks = Vec::new();
+        for (index, pattern) in self.patterns.iter().enumerate().filter(|(i, p)| mix.contains(i) && p.loop_duration > Duration::ZERO) {
+            let mut from = Duration::ZERO;
+            while from < length {
+                let repeat = pattern.events.iter().filter(|e| mix[e.channel as usize] == index).map(|e| SeqEvent { offset: from + e.offset, ..e.clone() });
+                // Past the first time through, whatever runs over the end is left out.
+                events.extend(repeat.filter(|e| from.is_zero() || e.offset < length));
+                let repeat = pattern.locks.iter().filter(|l| mix[l.channel as usize] == index).map(|l| Lock { offset: from + l.offset, ..l.clone() });
+                locks.extend(repeat.filter(|l| l.offset < length));
+                from += pattern.loop_duration;
+            }
+        }
+        events.sort_by_key(|e| (e.offset, e.is_note_on));
+        Pattern { events, loop_duration: length, loop_beats: lead.loop_beats, groove: lead.groove.clone(), locks }
+    }
+
+    /// Switches to `mix`, from `scene` when it's one: right away when stopped, at the end of
+    /// the pass when playing. A first take stays in the pattern it started in.
+    fn launch(&mut self, mix: Mix, scene: Option<usize>, ctx: &mut DriverContext) {
+        if self.armed || (self.recording && self.loop_duration == Duration::ZERO) {
+            return;
+        }
+        self.queued = None;
+        if self.playing {
+            self.queued_mix = Some((mix, scene));
+        } else {
+            self.load_mix(mix, scene, ctx);
+        }
+    }
+
+    /// Plays `next` from its top, or stops when it's empty.
+    fn play_pattern(&mut self, next: Pattern, ctx: &mut DriverContext) {
+        self.events = next.events;
+        self.loop_duration = next.loop_duration;
+        self.loop_beats = next.loop_beats;
+        self.groove = next.groove;
+        self.locks = next.locks;
+        self.playback_cursor = 0;
+        self.recording = false;
+        self.split_tracks(ctx);
+        self.shown_beats.clear();
+        if self.loop_duration == Duration::ZERO {
+            self.clock_out.stop(ctx.midi_port);
+            self.playing = false;
+            self.locked = false;
+            self.paused_position = None;
+            ctx.screen.reset();
+        } else if !self.playing {
+            self.paused_position = Some(Duration::ZERO);
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+        self.update_transport_lights(ctx);
+    }
+
+    /// A pad hit while Pattern is held picks that pattern: right away when stopped, at the
+    /// end of the pass when playing. With Shift, it gets a copy of the pattern in use
+    /// instead; with Erase, it's cleared. With Scene held too, only the track last played
+    /// on the pads switches to it.
+    fn pattern_pad(&mut self, index: usize, ctx: &mut DriverContext) {
+        if self.is_erase_pressed {
+            if self.in_use(index) {
+                self.clear_all(ctx);
+            } else {
+                self.patterns[index] = Pattern::default();
+                if self.queued == Some(index) {
+                    self.queued = None;
+                }
+            }
+        } else if self.shift_held {
+            if !self.in_use(index) {
+                self.patterns[index] = self.current_pattern();
+            }
+        } else if self.scene_held {
+            // With Scene held too, only the track last played takes the pattern.
+            let mut mix = self.current_mix();
+            mix[self.track as usize] = index;
+            self.launch(mix, None, ctx);
+        } else if self.armed || (self.recording && self.loop_duration == Duration::ZERO) {
+            // The first take goes to the pattern it started in.
+            return;
+        } else if self.playing && !self.in_use(index) {
+            self.queued = Some(index);
+            self.queued_mix = None;
+        } else if !self.in_use(index) {
+            self.load_pattern(index, ctx);
+        } else {
+            self.queued = None;
+            self.queued_mix = None;
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    /// While Pattern is held: the patterns in use bright green, the ones queued yellow,
+    /// the others with notes blue
+    fn draw_pattern_pad(&self, ctx: &mut DriverContext, index: usize) {
+        let (color, brightness) = if self.current_mix().contains(&index) {
+            (PadColors::Green, if self.playing { Brightness::Bright } else { Brightness::Normal })
+        } else if self.queued == Some(index) || self.queued_mix.is_some_and(|(mix, _)| mix.contains(&index)) {
+            (PadColors::Yellow, Brightness::Normal)
+        } else if self.has_pattern(index) {
+            (PadColors::Blue, Brightness::Dim)
+        } else {
+            (PadColors::Off, Brightness::Off)
+        };
+        ctx.lights.set_pad(index, color, brightness);
+    }
+
+    /// A pad hit while Scene is held launches that scene, as a pattern is picked: right
+    /// away when stopped, at the end of the pass when playing. With Shift, the scene keeps
+    /// the pattern each track plays now instead; with Erase, it's cleared.
+    fn scene_pad(&mut self, index: usize, ctx: &mut DriverContext) {
+        if self.is_erase_pressed {
+            self.scenes[index] = None;
+            if self.scene == Some(index) {
+                self.scene = None;
+            }
+            if self.queued_mix.is_some_and(|(_, scene)| scene == Some(index)) {
+                self.queued_mix = None;
+            }
+        } else if self.shift_held {
+            self.scenes[index] = Some(self.current_mix());
+            self.scene = Some(index);
+        } else if let Some(mix) = self.scenes[index] {
+            self.launch(mix, Some(index), ctx);
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    /// The loop's 16ths, as the groove and swing count them
+    fn step_length(&self, ctx: &DriverContext) -> Duration {
+        self.loop_duration.div_f64(self.beats(ctx).max(1.0) * 4.0)
+    }
+
+    fn step_pages(&self, ctx: &DriverContext) -> usize {
+        ((self.beats(ctx).max(1.0) * 4.0).round() as usize).div_ceil(16)
+    }
+
+    /// The step pad `index` shows, when the loop has one there
+    fn step_at(&self, index: usize, ctx: &DriverContext) -> Option<usize> {
+        let step = self.step_page * 16 + index;
+        let steps = (self.beats(ctx).max(1.0) * 4.0).round() as usize;
+        Some(step).filter(|&s| s < steps && self.loop_duration > Duration::ZERO)
+    }
+
+    /// Whether a note starts on `step` on the track last played, for a lock to go with
+    fn has_step_note(&self, step: usize, ctx: &DriverContext) -> bool {
+        let length = self.step_length(ctx);
+        self.events.iter().any(|e| e.is_note_on && e.channel == self.track && step_of(e.offset, length, self.loop_duration) == step)
+    }
+
+    /// The lock `step` has on the lane, on the track last played
+    fn lock_at(&self, step: usize, ctx: &DriverContext) -> Option<usize> {
+        let length = self.step_length(ctx);
+        self.locks
+            .iter()
+            .position(|l| l.channel == self.track && l.controller == self.lane && step_of(l.offset, length, self.loop_duration) == step)
+    }
+
+    /// Step turns the step view on and off. In it, the pads are 16 of the loop's steps,
+    /// Left and Right page through the rest, and the encoder picks the lane, or locks a
+    /// value to the steps held.
+    fn step_button(&mut self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        self.step_view = !self.step_view;
+        self.steps_held = [false; 16];
+        if self.step_view {
+            self.browser = None;
+            self.step_page = 0;
+            self.draw_steps(ctx);
+        } else {
+            self.shown_beats.clear();
+        }
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    fn turn_page(&mut self, direction: i32, ctx: &mut DriverContext) {
+        let last = self.step_pages(ctx) as i32 - 1;
+        self.step_page = (self.step_page as i32 + direction).clamp(0, last.max(0)) as usize;
+        self.draw_steps(ctx);
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+    }
+
+    /// A step pad held picks that step for the encoder; with Erase, its lock goes.
+    fn step_pad(&mut self, index: usize, down: bool, ctx: &mut DriverContext) {
+        self.steps_held[index] = down;
+        if down
+            && self.is_erase_pressed
+            && let Some(lock) = self.step_at(index, ctx).and_then(|s| self.lock_at(s, ctx))
+        {
+            self.locks.remove(lock);
+        }
+        self.draw_steps(ctx);
+        self.update_pad_light(ctx, index);
+    }
+
+    /// With steps held, moves the value locked to each of them that has a note, starting
+    /// from the middle; without, moves to another controller.
+    fn turn_step(&mut self, direction: i32, ctx: &mut DriverContext) {
+        if direction == 0 {
+            return;
+        }
+        let steps: Vec<(usize, usize)> = (0..16)
+            .filter(|&i| self.steps_held[i])
+            .filter_map(|i| self.step_at(i, ctx).map(|s| (i, s)))
+            .filter(|&(_, s)| self.has_step_note(s, ctx))
+            .collect();
+        if !self.steps_held.contains(&true) {
+            self.lane = (self.lane as i32 + direction).clamp(0, 127) as u8;
+            for i in 0..16 {
+                self.update_pad_light(ctx, i);
+            }
+        }
+        let length = self.step_length(ctx);
+        for (index, step) in steps {
+            match self.lock_at(step, ctx) {
+                Some(lock) => {
+                    let lock = &mut self.locks[lock];
+                    lock.value = (lock.value as i32 + direction).clamp(0, 127) as u8;
+                }
+                None => self.locks.push(Lock {
+                    offset: length * step as u32,
+                    channel: self.track,
+                    controller: self.lane,
+                    value: (64 + direction).clamp(0, 127) as u8,
+                }),
+            }
+            self.update_pad_light(ctx, index);
+        }
+        self.draw_steps(ctx);
+    }
+
+    fn draw_steps(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let pages = self.step_pages(ctx);
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("CC{} CH{} {}/{}", self.lane, self.track + 1, self.step_page + 1, pages), 1);
+        let held = (0..16).find(|&i| self.steps_held[i]);
+        let text = match held.map(|i| (i, self.step_at(i, ctx))) {
+            None => format!("{} LOCKED", self.locks.iter().filter(|l| l.channel == self.track && l.controller == self.lane).count()),
+            Some((i, None)) => format!("STEP {} NONE", self.step_page * 16 + i + 1),
+            Some((_, Some(step))) if !self.has_step_note(step, ctx) => format!("STEP {} NO NOTE", step + 1),
+            Some((_, Some(step))) => match self.lock_at(step, ctx) {
+                Some(lock) => format!("STEP {} = {}", step + 1, self.locks[lock].value),
+                None => format!("STEP {} OFF", step + 1),
+            },
+        };
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &text, 1);
+    }
+
+    /// In the step view: steps held white, steps with a lock on the lane yellow, the
+    /// others with a note on the track blue
+    fn draw_step_pad(&self, ctx: &mut DriverContext, index: usize) {
+        let (color, brightness) = match self.step_at(index, ctx) {
+            None => (PadColors::Off, Brightness::Off),
+            Some(_) if self.steps_held[index] => (PadColors::White, Brightness::Bright),
+            Some(step) if self.lock_at(step, ctx).is_some() => (PadColors::Yellow, Brightness::Normal),
+            Some(step) if self.has_step_note(step, ctx) => (PadColors::Blue, Brightness::Dim),
+            Some(_) => (PadColors::Off, Brightness::Off),
+        };
+        ctx.lights.set_pad(index, color, brightness);
+    }
+
+    /// A scene launched by the host: `/maschine/play/scene` 1-16, or a program change 0-15
+    /// with `[patterns] program_change`. Returns true when there was one to launch.
+    fn host_scene(&mut self, index: i32, ctx: &mut DriverContext) -> bool {
+        let Some((index, mix)) = usize::try_from(index).ok().and_then(|i| Some((i, self.scenes.get(i).copied().flatten()?))) else {
+            return false;
+        };
+        self.launch(mix, Some(index), ctx);
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
+        self.update_transport_lights(ctx);
+        true
+    }
+
+    /// While Scene is held: the scene playing bright green, the one queued yellow, the
+    /// others stored blue
+    fn draw_scene_pad(&self, ctx: &mut DriverContext, index: usize) {
+        let (color, brightness) = if self.scene == Some(index) {
+            (PadColors::Green, if self.playing { Brightness::Bright } else { Brightness::Normal })
+        } else if self.queued_mix.is_some_and(|(_, scene)| scene == Some(index)) {
+            (PadColors::Yellow, Brightness::Normal)
+        } else if self.scenes[index].is_some() {
+            (PadColors::Blue, Brightness::Dim)
+        } else {
+            (PadColors::Off, Brightness::Off)
+        };
+        ctx.lights.set_pad(index, color, brightness);
+    }
+
     fn clear_all(&mut self, ctx: &mut DriverContext) {
+        // A mix only plays the patterns: clearing it leaves them be and stops on the
+        // pattern last in use.
+        if self.mix.is_some() {
+            self.clock_out.stop(ctx.midi_port);
+            self.playing = false;
+            self.locked = false;
+            self.load_pattern(self.pattern, ctx);
+            return;
+        }
+        self.clock_out.stop(ctx.midi_port);
         self.playing = false;
         self.recording = false;
         self.armed = false;
+        self.locked = false;
         self.start_time = None;
         self.playback_start = None;
         self.paused_position = None;
         self.loop_duration = Duration::from_millis(0);
+        self.loop_beats = None;
+        self.take_anchor = None;
+        self.count_from = None;
+        self.queued = None;
+        self.queued_mix = None;
+        self.forget_history();
         self.events.clear();
+        self.locks.clear();
         self.playback_cursor = 0;
         self.seq_holding = [false; 16];
+        self.sounding.clear();
+        self.tracks.clear();
+        self.passes = 0;
+        self.shown_beats.clear();
         self.user_holding = [false; 16];
         
-        // Clear all pad lights
         for i in 0..16 {
-            ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+            self.update_pad_light(ctx, i);
         }
         self.update_transport_lights(ctx);
     }
 }
 
 impl MachineMode for PlayMode {
+    fn name(&self) -> &'static str {
+        "PLAY"
+    }
+
     fn on_enter(&mut self, ctx: &mut DriverContext) {
+        // The screen was cleared on the way in; start over from the loop itself.
+        self.browser = None;
+        for i in 0..16 {
+            self.update_pad_light(ctx, i);
+        }
         self.update_transport_lights(ctx);
+        // The loop went on while another mode had the pads.
+        if self.playing
+            && let Some(start) = self.playback_start
+        {
+            let elapsed = ctx.clock.now().duration_since(start);
+            let loop_ns = self.loop_duration.as_nanos().max(1);
+            let position = Duration::from_nanos((elapsed.as_nanos() % loop_ns) as u64);
+            self.clock_out.resume_at(position, self.loop_duration, &ctx.settings.clock_out, ctx.midi_port);
+        }
+    }
+
+    fn on_exit(&mut self, ctx: &mut DriverContext) {
+        self.pad_notes.release_all(ctx);
+        self.note_repeat.release();
+        self.clock_out.stop(ctx.midi_port);
+        self.pattern_held = false;
+        self.steps_held = [false; 16];
+        self.scene_held = false;
+        self.follow_held = false;
+        self.metronome.run(None, ctx.clock.now(), ctx);
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        let mut changed = false;
+        let now = ctx.clock.now();
+
+        // --- 1. SEQUENCER PLAYBACK & LOOPING ---
+        if self.playing && self.loop_duration > Duration::ZERO {
+            let elapsed = match self.synced_position(now, ctx) {
+                // Locked to the host's clock: it says where the loop is and when it comes around.
+                Some(position) => {
+                    if !self.locked {
+                        // From the tick just gone, so a note right on it still plays.
+                        self.locked = true;
+                        let tick = self.clock_in.position(now).map_or(0.0, f64::floor);
+                        let from = self.loop_position(tick).unwrap_or(position);
+                        self.playback_cursor = self.cursor_at(from);
+                        // The tracks' phase counts from the song's top too.
+                        let loop_ticks = (self.loop_beats.unwrap_or(1) * CLOCKS_PER_BEAT) as f64;
+                        self.passes = ((tick - self.loop_anchor as f64) / loop_ticks).floor().max(0.0) as u32;
+                        self.seek_tracks(self.loop_duration * self.passes + from);
+                    } else if position < self.last_position {
+                        // Whatever the clock skipped at the end still goes out, note offs included.
+                        changed |= self.wrap(ctx);
+                    }
+                    self.last_position = position;
+                    position
+                }
+                None => {
+                    // Without the clock, the loop carries on in its own time from where it was.
+                    if std::mem::take(&mut self.locked) {
+                        self.playback_start = Some(now - self.last_position);
+                    }
+
+                    // Initialize playback anchor if missing
+                    if self.playback_start.is_none() {
+                        self.playback_start = Some(now);
+                    }
+
+                    let start = self.playback_start.unwrap();
+                    let mut elapsed = now.duration_since(start);
+
+                    // Loop Wrap
+                    if elapsed >= self.loop_duration {
+                        // Events in the last moment before the end still go out, note offs included.
+                        changed |= self.wrap(ctx);
+                        self.clock_out.wrap(self.loop_duration, &ctx.settings.clock_out, ctx.midi_port);
+                        self.playback_start = Some(now);
+                        elapsed = Duration::from_millis(0);
+                    }
+                    self.clock_out.run(elapsed, self.loop_duration, &ctx.settings.clock_out, ctx.midi_port);
+                    elapsed
+                }
+            };
+
+            // Fire Events
+            changed |= self.play_until(elapsed, ctx);
+            changed |= self.play_tracks(elapsed, ctx);
+            changed |= self.draw_tracks(elapsed, ctx);
+        }
+
+        // --- 2. METRONOME ---
+        if ctx.settings.metronome.enabled {
+            let position = self.metronome_position(now, ctx);
+            if self.metronome.run(position, now, ctx) {
+                if let Some(pad) = ctx.settings.metronome.pad {
+                    self.update_pad_light(ctx, pad as usize - 1);
+                }
+                changed = true;
+            }
+        }
+
+        // --- 3. RECORDING BUTTON BLINK ---
+        // Blink logic: On for 500ms, Off for 500ms
+        if self.recording {
+            let anchor = *self.blink_anchor.get_or_insert(now);
+            let blink_on = (now.duration_since(anchor).as_millis() / 500).is_multiple_of(2);
+            // When blinking off, use Dim to match "half lit when off" request
+            let brightness = if blink_on { Brightness::Bright } else { Brightness::Dim };
+            ctx.lights.set_button(Buttons::Rec, brightness);
+            changed = true;
+        } else {
+            self.blink_anchor = None;
+        }
+
+        // --- 4. ERASE CONFIRMATION ---
+        let confirm = &ctx.settings.confirm;
+        let was_pending = self.confirm_clear.is_pending();
+        if self.confirm_clear.tick(confirm.clear_loop, now, confirm) {
+            self.clear_all(ctx);
+            changed = true;
+        }
+        if self.confirm_clear.is_pending() {
+            self.confirm_clear.draw_warning(ctx.lights, now);
+            changed = true;
+        } else if was_pending {
+            // Timed out: give the pads and Erase their own lights back.
+            for i in 0..16 {
+                self.update_pad_light(ctx, i);
+            }
+            self.update_transport_lights(ctx);
+            changed = true;
+        }
+
+        // --- 5. AUTOSAVE ---
+        self.autosave(now, ctx);
+
+        // --- 6. NOTE REPEAT ---
+        // Retriggers go through the pad handler, so they're recorded like hits; they wait
+        // while a bank is being picked, where they'd look like picks.
+        if !self.banks.is_picking() {
+            for (pad, velocity) in self.note_repeat.due(ctx) {
+                self.handle_event(&HardwareEvent::Pad { index: pad, event_type: PadEventType::NoteOff, value: 0 }, ctx);
+                self.handle_event(&HardwareEvent::Pad { index: pad, event_type: PadEventType::NoteOn, value: (velocity as u16) << 5 }, ctx);
+                changed = true;
+            }
+        }
+
+        // The banks stay up over the loop's lights while picking.
+        if self.banks.is_picking() {
+            self.banks.draw(ctx);
+        }
+
+        changed
+    }
+
+    fn is_animating(&self) -> bool {
+        self.playing || self.recording || self.confirm_clear.is_pending() || self.note_repeat.is_animating()
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        match event {
+            HostEvent::LoadLoop(sequence) => {
+                self.load_sequence(sequence, ctx);
+                true
+            }
+            HostEvent::Clock(message) if follows_clock(ctx) => self.handle_clock(*message, ctx),
+            HostEvent::DumpLoop(format) => {
+                let sequence = self.to_sequence();
+                let arg = match format {
+                    LoopFormat::Json => OscType::String(sequence.to_json()),
+                    LoopFormat::Blob => OscType::Blob(sequence.to_blob()),
+                };
+                ctx.osc.send(OscMessage { addr: "/maschine/loop/data".to_string(), args: vec![arg] });
+                false
+            }
+            HostEvent::Osc { addr, value } if addr == SCENE => self.host_scene(value - 1, ctx),
+            HostEvent::ProgramChange { program } if ctx.settings.patterns.program_change => self.host_scene(*program as i32, ctx),
+            _ => false,
+        }
     }
 
     fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        let bank = ctx.groups.bank();
+        if ctx.settings.pad_banks.enabled && self.banks.handle(event, ctx) {
+            // The loop's notes light the pads of the new bank from here on.
+            if ctx.groups.bank() != bank {
+                self.seq_holding = [false; 16];
+            }
+            if !self.banks.is_picking() {
+                for i in 0..16 {
+                    self.update_pad_light(ctx, i);
+                }
+            }
+            return;
+        }
+        if ctx.settings.note_repeat.enabled && self.note_repeat.handle(event, ctx) {
+            return;
+        }
         match event {
             HardwareEvent::Button { index, pressed } => {
+                let changed = self.buttons.changed(*index, *pressed);
                 match index {
-                    Buttons::Rec => {
-                        if *pressed {
-                            if self.recording {
-                                // STOP RECORDING (Finish Initial or Stop Overdub) -> KEEP PLAYING
-                                if self.loop_duration == Duration::ZERO {
-                                    // Finish Initial Recording
-                                    if let Some(start) = self.start_time {
-                                        self.loop_duration = Instant::now().duration_since(start);
-                                    }
-                                    self.playback_start = Some(Instant::now()); // Align loop start
-                                }
-                                self.recording = false;
-                                self.playing = true;
-                            } else if self.playing {
-                                // START OVERDUB
-                                self.recording = true;
-                            } else if self.armed {
-                                // DISARM
-                                self.armed = false;
-                            } else {
-                                // ARM (for initial recording)
-                                self.armed = true;
-                            }
+                    Buttons::EncoderPress if changed && *pressed && !self.step_view => self.browser_press(ctx),
+                    Buttons::Swing if changed => self.swing_button(*pressed, ctx),
+                    Buttons::Follow if changed => self.follow_button(*pressed, ctx),
+                    Buttons::Step if changed && *pressed => self.step_button(ctx),
+                    Buttons::Left if changed && *pressed && self.step_view => self.turn_page(-1, ctx),
+                    Buttons::Right if changed && *pressed && self.step_view => self.turn_page(1, ctx),
+                    Buttons::Pattern => {
+                        self.pattern_held = *pressed;
+                        ctx.lights.set_button(Buttons::Pattern, if *pressed { Brightness::Bright } else { Brightness::Off });
+                        for i in 0..16 {
+                            self.update_pad_light(ctx, i);
                         }
                     },
-                    Buttons::Play => {
-                        if *pressed {
-                            if self.recording && self.loop_duration == Duration::ZERO {
-                                // Finish Initial Rec -> Play
-                                if let Some(start) = self.start_time {
-                                    self.loop_duration = Instant::now().duration_since(start);
-                                }
-                                self.recording = false;
-                                self.playing = true;
-                                self.playback_start = Some(Instant::now());
-                                self.paused_position = None;
-                            } else if self.playing {
-                                // PAUSE
-                                self.playing = false;
-                                self.recording = false; // Stop recording if we pause
-                                
-                                // Calculate where we paused relative to loop start
-                                if let Some(start) = self.playback_start {
-                                    let elapsed = Instant::now().duration_since(start);
-                                    let pos = if self.loop_duration > Duration::ZERO {
-                                        let millis = elapsed.as_millis() % self.loop_duration.as_millis();
-                                        Duration::from_millis(millis as u64)
-                                    } else {
-                                        Duration::ZERO
-                                    };
-                                    self.paused_position = Some(pos);
-                                }
-                                
-                                // Turn off sequencer lights as we paused
-                                self.seq_holding = [false; 16];
-                                for i in 0..16 {
-                                    self.update_pad_light(ctx, i);
-                                }
-                            } else if self.loop_duration > Duration::ZERO {
-                                // RESUME
-                                self.playing = true;
-                                
-                                let offset = self.paused_position.unwrap_or(Duration::ZERO);
-                                // Set playback start in the past so that (now - start) == offset
-                                self.playback_start = Some(Instant::now() - offset);
-                                
-                                // Re-sync cursor
-                                self.playback_cursor = 0;
-                                for (i, event) in self.events.iter().enumerate() {
-                                    // We look for the first event that hasn't happened yet relative to offset
-                                    if event.offset > offset {
-                                        self.playback_cursor = i;
-                                        break;
-                                    }
-                                    // Handle exact match if necessary, mostly covered by loop logic
-                                    if event.offset == offset {
-                                        self.playback_cursor = i;
-                                        break;
-                                    }
-                                    // If we are past the event, move cursor forward
-                                    self.playback_cursor = i + 1;
-                                }
+                    Buttons::Scene => {
+                        self.scene_held = *pressed;
+                        ctx.lights.set_button(Buttons::Scene, if *pressed { Brightness::Bright } else { Brightness::Off });
+                        for i in 0..16 {
+                            self.update_pad_light(ctx, i);
+                        }
+                    },
+                    // Held with Pattern or Scene, Erase clears the pattern or scene of the pad hit,
+                    // and in the step view the lock of the step hit.
+                    Buttons::Erase if self.pattern_held || self.scene_held || self.step_view => self.is_erase_pressed = *pressed,
+                    Buttons::Erase if self.shift_held => {
+                        self.is_erase_pressed = *pressed;
+                        if changed && *pressed {
+                            self.undo(ctx);
+                        }
+                    },
+                    Buttons::Restart if self.shift_held => {
+                        self.is_restart_pressed = *pressed;
+                        if changed && *pressed {
+                            self.redo(ctx);
+                        }
+                    },
+                    Buttons::Shift => self.shift_held = *pressed,
+                    Buttons::Rec if changed && *pressed && self.shift_held && ctx.settings.midi_export.dir.is_some() => self.export(ctx),
+                    // Reports repeating Shift+Rec don't start a take.
+                    Buttons::Rec if self.shift_held && ctx.settings.midi_export.dir.is_some() => {},
+                    // Overdubs go into a pattern; a mix of them has nowhere to keep one.
+                    Buttons::Rec if self.mix.is_some() => {},
+                    // Stop also closes the browser without loading anything.
+                    Buttons::Stop if *pressed && self.browser.is_some() => {
+                        self.browser = None;
+                        self.draw_browser(ctx);
+                    },
+                    Buttons::Rec if *pressed => {
+                        if self.recording {
+                            // STOP RECORDING (Finish Initial or Stop Overdub) -> KEEP PLAYING
+                            if self.loop_duration == Duration::ZERO {
+                                // Finish Initial Recording
+                                self.end_take(ctx);
+                                self.clock_out.start(&ctx.settings.clock_out, ctx.midi_port);
+                            }
+                            self.recording = false;
+                            self.playing = true;
+                        } else if self.playing {
+                            // START OVERDUB
+                            self.recording = true;
+                            self.overdub_start = Some(self.current_pattern());
+                        } else if self.armed {
+                            // DISARM
+                            self.armed = false;
+                            self.count_from = None;
+                        } else {
+                            // ARM (for initial recording)
+                            self.armed = true;
+                            if ctx.settings.metronome.enabled {
+                                self.count_from = Some(ctx.clock.now());
                             }
                         }
                     },
-                    Buttons::Stop => {
-                        if *pressed {
-                             self.playing = false;
-                             self.recording = false;
-                             self.armed = false;
-                             
-                             // Reset position to Start
-                             self.paused_position = Some(Duration::ZERO);
-                             self.playback_cursor = 0;
-                             
-                             self.seq_holding = [false; 16];
-                             for i in 0..16 {
-                                self.update_pad_light(ctx, i);
-                             }
+                    Buttons::Play if *pressed => {
+                        if self.recording && self.loop_duration == Duration::ZERO {
+                            // Finish Initial Rec -> Play
+                            self.end_take(ctx);
+                            self.recording = false;
+                            self.playing = true;
+                            self.paused_position = None;
+                            self.clock_out.start(&ctx.settings.clock_out, ctx.midi_port);
+                        } else if self.playing {
+                            self.pause(ctx);
+                        } else if self.loop_duration > Duration::ZERO {
+                            self.resume(ctx);
                         }
                     },
+                    Buttons::Stop if *pressed => {
+                         self.clock_out.stop(ctx.midi_port);
+                         self.playing = false;
+                         self.recording = false;
+                         self.armed = false;
+                         self.count_from = None;
+                         self.locked = false;
+                         
+                         // Reset position to Start
+                         self.paused_position = Some(Duration::ZERO);
+                         self.playback_cursor = 0;
+                         self.passes = 0;
+                         self.seek_tracks(Duration::ZERO);
+                         
+                         self.seq_holding = [false; 16];
+                         for i in 0..16 {
+                            self.update_pad_light(ctx, i);
+                         }
+                    },
                     Buttons::Restart => {
                         self.is_restart_pressed = *pressed;
                         if *pressed {
                             // Restart Loop logic
                             if self.playing {
-                                self.playback_start = Some(Instant::now());
+                                self.playback_start = Some(ctx.clock.now());
                                 self.playback_cursor = 0;
+                                self.clock_out.start(&ctx.settings.clock_out, ctx.midi_port);
+                                self.restart_on_beat(ctx);
                             }
                             // Reset position regardless
                             self.paused_position = Some(Duration::ZERO);
                             if !self.playing {
                                 self.playback_cursor = 0;
                             }
+                            self.passes = 0;
+                            self.seek_tracks(Duration::ZERO);
                         }
                     },
                     Buttons::Erase => {
                         self.is_erase_pressed = *pressed;
-                        if *pressed {
+                        let confirm = &ctx.settings.confirm;
+                        if self.confirm_clear.button(*pressed, confirm.clear_loop, ctx.clock.now(), confirm) {
                             self.clear_all(ctx);
                         }
                     },
@@ -343,13 +1912,68 @@ impl MachineMode for PlayMode {
                 }
                 self.update_transport_lights(ctx);
             },
+            HardwareEvent::Encoder { value } => {
+                let direction = encoder_direction(self.last_encoder_val, *value);
+                if *value != 0 {
+                    self.last_encoder_val = *value;
+                }
+                if self.swing_held && self.shift_held && self.grooves.is_some() {
+                    self.pick_groove(direction, ctx);
+                } else if self.swing_held {
+                    self.turn_swing(direction, ctx);
+                } else if self.follow_held {
+                    self.turn_passes(direction, ctx);
+                } else if self.step_view {
+                    self.turn_step(direction, ctx);
+                } else if let Some(browser) = &mut self.browser
+                    && direction != 0
+                    && !browser.files.is_empty()
+                {
+                    let last = browser.files.len() - 1;
+                    browser.selected = (browser.selected as i32 + direction).clamp(0, last as i32) as usize;
+                    self.draw_browser(ctx);
+                }
+            },
             HardwareEvent::Pad { index, event_type, value } => {
-                let note = ctx.settings.notemaps[*index];
-                
+                let down = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn | PadEventType::Aftertouch) && *value > 0;
+                if self.pattern_taken[*index] {
+                    self.pattern_taken[*index] = down;
+                    return;
+                }
+                // A pad already down when Pattern went down plays on.
+                if self.pattern_held && down && !self.user_holding[*index] {
+                    self.pattern_taken[*index] = true;
+                    self.pattern_pad(*index, ctx);
+                    return;
+                }
+                if self.scene_held && down && !self.user_holding[*index] {
+                    self.pattern_taken[*index] = true;
+                    self.scene_pad(*index, ctx);
+                    return;
+                }
+                if self.follow_held && down && !self.user_holding[*index] {
+                    self.pattern_taken[*index] = true;
+                    self.follow_pad(*index, ctx);
+                    return;
+                }
+                // A pad already down when the step view came up plays on.
+                if self.step_view && !self.user_holding[*index] {
+                    self.step_pad(*index, down, ctx);
+                    return;
+                }
+                let velocity = (value >> 5) as u8;
+                let (channel, note) = match event_type {
+                    PadEventType::NoteOn | PadEventType::PressOn => self.pad_notes.press(*index, velocity, ctx),
+                    PadEventType::NoteOff | PadEventType::PressOff => self.pad_notes.release(*index, ctx),
+                    _ => ctx.settings.pad_note(*index, ctx.groups.bank()),
+                };
+
+
                 // 1. Track User State
                 match event_type {
                     PadEventType::NoteOn | PadEventType::PressOn if *value > 0 => {
                         self.user_holding[*index] = true;
+                        self.track = channel;
                     },
                     PadEventType::NoteOff | PadEventType::PressOff => {
                         self.user_holding[*index] = false;
@@ -361,7 +1985,6 @@ impl MachineMode for PlayMode {
                 self.update_pad_light(ctx, *index);
 
                 // 3. MIDI Thru
-                let velocity = (value >> 5) as u8;
                 let midi_msg = match event_type {
                     PadEventType::NoteOn | PadEventType::PressOn => Some(MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }),
                     PadEventType::NoteOff | PadEventType::PressOff => Some(MidiMessage::NoteOff { key: note.into(), vel: velocity.into() }),
@@ -369,7 +1992,7 @@ impl MachineMode for PlayMode {
                 };
 
                 if let Some(msg) = midi_msg {
-                    let live_event = LiveEvent::Midi { channel: 0.into(), message: msg };
+                    let live_event = LiveEvent::Midi { channel: channel.into(), message: msg };
                     let mut buf = Vec::new();
                     if live_event.write(&mut buf).is_ok() {
                         let _ = ctx.midi_port.send(&buf);
@@ -381,14 +2004,29 @@ impl MachineMode for PlayMode {
                         self.armed = false;
                         self.recording = true;
                         self.events.clear();
-                        self.start_time = Some(Instant::now());
+                        self.locks.clear();
+                        self.start_time = Some(ctx.clock.now());
+                        self.take_anchor = None;
+                        // With the host's clock running, the take starts on the nearest beat.
+                        if follows_clock(ctx)
+                            && let Some((beat, at)) = self.clock_in.nearest_beat(ctx.clock.now())
+                        {
+                            self.start_time = Some(at);
+                            self.take_anchor = Some(beat);
+                            self.count_from = None;
+                        } else if let Some(from) = self.count_from {
+                            // Counted in by the metronome, it starts on the nearest click.
+                            let beat = 60.0 / ctx.settings.metronome.bpm as f64;
+                            let beats = (ctx.clock.now().duration_since(from).as_secs_f64() / beat).round();
+                            self.start_time = Some(from + Duration::from_secs_f64(beats * beat));
+                        }
                         self.loop_duration = Duration::ZERO; // Mark as Initial Recording
                         self.update_transport_lights(ctx);
                     }
 
                     // B. Capture Events
                     if self.recording {
-                        let now = Instant::now();
+                        let now = ctx.clock.now();
                         let offset = if self.loop_duration == Duration::ZERO {
                             // Initial Recording: Offset from Start Time
                             if let Some(start) = self.start_time {
@@ -398,7 +2036,9 @@ impl MachineMode for PlayMode {
                             }
                         } else {
                             // Overdub: Offset from Playback Start (Modulo Loop Duration)
-                            if let Some(start) = self.playback_start {
+                            if self.locked {
+                                self.synced_position(now, ctx).unwrap_or(self.last_position)
+                            } else if let Some(start) = self.playback_start {
                                 let raw = now.duration_since(start);
                                 // Simple modulo simulation if we drifted past loop end before tick reset it
                                 if raw > self.loop_duration {
@@ -413,20 +2053,72 @@ impl MachineMode for PlayMode {
                         
                         let is_note_on = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn);
                         if is_note_on || matches!(event_type, PadEventType::NoteOff | PadEventType::PressOff) {
-                            self.events.push(SeqEvent {
-                                offset,
-                                note,
-                                velocity,
-                                is_note_on,
-                            });
-                            
-                            // Optimization: Keep events sorted by offset for the tick loop
-                            self.events.sort_by(|a, b| a.offset.cmp(&b.offset));
+                            // The pass's first note makes it one to undo.
+                            if self.loop_duration > Duration::ZERO
+                                && let Some(before) = self.overdub_start.take()
+                            {
+                                self.undo.push(before);
+                                if self.undo.len() > UNDO_LEVELS {
+                                    self.undo.remove(0);
+                                }
+                                self.redo.clear();
+                            }
+                            let event = SeqEvent { offset, channel, note, velocity, is_note_on };
+                            // A track records where it is in its own length.
+                            if self.loop_duration > Duration::ZERO
+                                && let Some(track) = self.tracks.iter_mut().find(|t| t.channel == channel)
+                            {
+                                let song = self.loop_duration * self.passes + offset;
+                                let offset = Duration::from_nanos((song.as_nanos() % track.length.as_nanos().max(1)) as u64);
+                                let at = track.events.partition_point(|e| e.offset <= offset);
+                                track.events.insert(at, SeqEvent { offset, ..event });
+                                // It was just played live; it plays again next time around.
+                                if at < track.cursor || offset <= track.position {
+                                    track.cursor += 1;
+                                }
+                            } else {
+                                self.events.push(event);
+
+                                // Optimization: Keep events sorted by offset for the tick loop
+                                self.events.sort_by_key(|e| e.offset);
+                            }
                         }
                     }
                 }
             },
             _ => {}
         }
+        // Pads let go of while picking a bank relight underneath the banks.
+        if self.banks.is_picking() {
+            self.banks.draw(ctx);
+        }
+    }
+}
+/// `42S`, `5M`, `3H`: coarse enough to fit next to the index.
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s if s < 60 => format!("{}S", s),
+        s if s < 3600 => format!("{}M", s / 60),
+        s => format!("{}H", s / 3600),
     }
-}
\ No newline at end of file
+}
+
+/// The step, `step` long, that an event `offset` into a loop `length` long falls on: the
+/// nearest one, the last coming around to the first
+fn step_of(offset: Duration, step: Duration, length: Duration) -> usize {
+    let steps = (length.as_secs_f64() / step.as_secs_f64()).round().max(1.0);
+    ((offset.as_secs_f64() / step.as_secs_f64()).round() % steps) as usize
+}
+
+fn send_cc(ctx: &mut DriverContext, channel: u8, controller: u8, value: u8) {
+    let live_event = LiveEvent::Midi { channel: channel.into(), message: MidiMessage::Controller { controller: controller.into(), value: value.into() } };
+    let mut buf = Vec::new();
+    if live_event.write(&mut buf).is_ok() {
+        let _ = ctx.midi_port.send(&buf);
+    }
+}
+
+/// Whether the loop follows an outside clock, MIDI's or a Link session's
+fn follows_clock(ctx: &DriverContext) -> bool {
+    ctx.settings.clock_in.enabled || ctx.settings.link.enabled
+}
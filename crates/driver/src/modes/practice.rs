@@ -0,0 +1,341 @@
+// crates/driver/src/modes/practice.rs
+use std::time::{Duration, Instant};
+use midly::{live::LiveEvent, MidiMessage};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::analytics::{grid_offset_ms, PadStats, PracticeStats, VELOCITY_BUCKETS};
+use crate::context::DriverContext;
+use crate::input::HardwareEvent;
+use crate::status::CONTENT_ROW;
+use crate::tap_tempo::TapTempo;
+use super::{encoder_direction, Confirm, MachineMode, PadNotes};
+
+const CLICK_VELOCITY: u8 = 100;
+/// The click on the first beat of each bar
+const ACCENT_VELOCITY: u8 = 127;
+const BEAT_FLASH: Duration = Duration::from_millis(80);
+/// Timing a pad's light shows as good (green) or fair (yellow); anything looser is red.
+const TIGHT_MS: f64 = 15.0;
+const LOOSE_MS: f64 = 30.0;
+const HISTOGRAM_COL: usize = 96;
+const HISTOGRAM_HEIGHT: usize = 14;
+
+/// Finger-drumming practice: pads play their notes while every hit is measured against
+/// a metronome. Play starts and stops the metronome, Tap sets its tempo, Erase clears the
+/// statistics and Duplicate exports them as CSV. Turning the encoder while holding Shift
+/// and pressing it nudges the beat earlier or later, to line it up with a band by ear.
+pub struct PracticeMode {
+    stats: PracticeStats,
+    /// Start of the grid and the next beat, while the metronome runs
+    anchor: Option<Instant>,
+    next_beat: Option<Instant>,
+    /// Beats clicked since the metronome started, for the accent on each bar
+    beat_count: u32,
+    /// Length of a sixteenth at the metronome's tempo, for the pad chase
+    sixteenth: Duration,
+    flash_until: Option<Instant>,
+    last_pad: Option<usize>,
+    held: [bool; 16],
+    pad_notes: PadNotes,
+    confirm_reset: Confirm,
+    /// Tapped tempo, in place of `[practice] bpm`
+    bpm: Option<f32>,
+    tap: TapTempo,
+    tap_held: bool,
+    shift_held: bool,
+    encoder_held: bool,
+    last_encoder_val: u8,
+}
+
+impl PracticeMode {
+    pub fn new() -> Self {
+        Self {
+            stats: PracticeStats::new(),
+            anchor: None,
+            next_beat: None,
+            beat_count: 0,
+            sixteenth: Duration::ZERO,
+            flash_until: None,
+            last_pad: None,
+            held: [false; 16],
+            pad_notes: PadNotes::new(),
+            confirm_reset: Confirm::new(),
+            bpm: None,
+            tap: TapTempo::new(),
+            tap_held: false,
+            shift_held: false,
+            encoder_held: false,
+            last_encoder_val: 0,
+        }
+    }
+
+    fn reset_stats(&mut self, ctx: &mut DriverContext) {
+        self.stats.reset();
+        self.last_pad = None;
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        ctx.lights.set_button(Buttons::Erase, Brightness::Off);
+        self.draw_summary(ctx);
+    }
+
+    fn bpm(&self, ctx: &DriverContext) -> f32 {
+        self.bpm.unwrap_or(ctx.settings.practice.bpm)
+    }
+
+    fn beat(&self, ctx: &DriverContext) -> Duration {
+        Duration::from_secs_f64(60.0 / self.bpm(ctx) as f64)
+    }
+
+    /// Time between clicks: a beat of `[transport]`'s meter, e.g. an eighth in 7/8
+    fn click_interval(&self, ctx: &DriverContext) -> Duration {
+        self.beat(ctx) * ctx.settings.transport.meter().beat_sixteenths() / 4
+    }
+
+    /// A tap sets the tempo once there are two in a row, tapped in the meter's beats; a
+    /// running metronome also takes the tap as the top of a bar.
+    fn tap(&mut self, ctx: &mut DriverContext) {
+        let now = ctx.clock.now();
+        let Some(bpm) = self.tap.tap(now, ctx.settings.practice.tap_average) else {
+            return;
+        };
+        self.bpm = Some(bpm * 4.0 / ctx.settings.transport.meter().beat_sixteenths() as f32);
+        self.sixteenth = self.beat(ctx) / 4;
+        if self.anchor.is_some() {
+            self.anchor = Some(now);
+            self.next_beat = Some(now + self.click_interval(ctx));
+            self.beat_count = 1;
+        }
+        self.draw_summary(ctx);
+    }
+
+    /// Moves the beat `nudge_ms` earlier (direction > 0) or later, keeping the tempo.
+    fn nudge(&mut self, direction: i32, ctx: &DriverContext) {
+        let nudge = Duration::from_millis(ctx.settings.practice.nudge_ms);
+        let shift = |t: Instant| if direction > 0 { t - nudge } else { t + nudge };
+        self.anchor = self.anchor.map(shift);
+        self.next_beat = self.next_beat.map(shift);
+    }
+
+    fn send(channel: u8, message: MidiMessage, ctx: &mut DriverContext) {
+        let mut midibuf = Vec::new();
+        if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+            let _ = ctx.midi_port.send(&midibuf);
+        }
+    }
+
+    fn update_pad_light(&self, index: usize, ctx: &mut DriverContext) {
+        let stats = self.stats.pad(index);
+        let color = match stats.mean_abs_offset_ms() {
+            Some(ms) if ms < TIGHT_MS => PadColors::Green,
+            Some(ms) if ms < LOOSE_MS => PadColors::Yellow,
+            Some(_) => PadColors::Red,
+            None => PadColors::Blue,
+        };
+        let b = if self.held[index] { Brightness::Bright } else { Brightness::Dim };
+        ctx.lights.set_pad(index, color, b);
+    }
+
+    fn draw_summary(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        let (label, stats) = match self.last_pad {
+            Some(pad) => (format!("P{}", pad + 1), self.stats.pad(pad).clone()),
+            None => ("ALL".to_string(), self.stats.total()),
+        };
+        let velocity = stats.mean_velocity().map(|v| format!(" V{:.0}", v)).unwrap_or_default();
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{} {}{}", label, stats.hits, velocity), 1);
+
+        let timing = match (stats.mean_offset_ms(), stats.mean_abs_offset_ms()) {
+            (Some(offset), Some(abs)) => format!("{:+.0} ~{:.0}MS", offset, abs),
+            _ if self.anchor.is_none() => format!("PLAY: CLICK {:.0}", self.bpm(ctx)),
+            _ => "-".to_string(),
+        };
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &timing, 1);
+        Self::draw_histogram(&stats, ctx);
+    }
+
+    /// Velocity spread as bars, soft on the left
+    fn draw_histogram(stats: &PadStats, ctx: &mut DriverContext) {
+        let max = stats.velocities.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return;
+        }
+        let bottom = CONTENT_ROW + 8 + HISTOGRAM_HEIGHT;
+        for (b, count) in stats.velocities.iter().enumerate() {
+            let height = (*count as usize * HISTOGRAM_HEIGHT).div_ceil(max as usize);
+            let left = HISTOGRAM_COL + b * (128 - HISTOGRAM_COL) / VELOCITY_BUCKETS;
+            for i in bottom - height..bottom {
+                for j in left..left + 3 {
+                    ctx.screen.set(i, j, true);
+                }
+            }
+        }
+    }
+
+    fn toggle_metronome(&mut self, ctx: &mut DriverContext) {
+        if self.anchor.is_some() {
+            self.anchor = None;
+            self.next_beat = None;
+            ctx.lights.set_button(Buttons::Play, Brightness::Dim);
+        } else {
+            let now = ctx.clock.now();
+            self.anchor = Some(now);
+            self.next_beat = Some(now);
+            self.beat_count = 0;
+            self.sixteenth = self.beat(ctx) / 4;
+            ctx.lights.set_button(Buttons::Play, Brightness::Bright);
+        }
+    }
+
+    fn process_pad(&mut self, index: usize, event_type: PadEventType, value: u16, ctx: &mut DriverContext) {
+        let pressed = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn) && value > 0;
+        let released = matches!(event_type, PadEventType::NoteOff | PadEventType::PressOff);
+
+        if pressed && !self.held[index] {
+            self.held[index] = true;
+            let velocity = ((value >> 5) as u8).max(1);
+            let (channel, note) = self.pad_notes.press(index, velocity, ctx);
+            Self::send(channel, MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }, ctx);
+
+            let step = self.beat(ctx) / ctx.settings.practice.subdivision as u32;
+            let offset = self.anchor.map(|anchor| grid_offset_ms(anchor, step, ctx.clock.now()));
+            self.stats.record(index, velocity, offset);
+            self.last_pad = Some(index);
+            self.update_pad_light(index, ctx);
+            self.draw_summary(ctx);
+        } else if released && self.held[index] {
+            self.held[index] = false;
+            let (channel, note) = self.pad_notes.release(index, ctx);
+            Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+            self.update_pad_light(index, ctx);
+        }
+    }
+}
+
+impl MachineMode for PracticeMode {
+    fn name(&self) -> &'static str {
+        "PRAC"
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            self.update_pad_light(i, ctx);
+        }
+        let b = if self.anchor.is_some() { Brightness::Bright } else { Brightness::Dim };
+        ctx.lights.set_button(Buttons::Play, b);
+        ctx.lights.set_button(Buttons::Tap, Brightness::Dim);
+        self.draw_summary(ctx);
+    }
+
+    fn on_exit(&mut self, ctx: &mut DriverContext) {
+        self.pad_notes.release_all(ctx);
+        self.held = [false; 16];
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match event {
+            HardwareEvent::Pad { index, event_type, value } => {
+                self.process_pad(*index, *event_type, *value, ctx);
+            }
+            HardwareEvent::Button { index: Buttons::Play, pressed: true } => {
+                self.toggle_metronome(ctx);
+                self.draw_summary(ctx);
+            }
+            HardwareEvent::Button { index: Buttons::Erase, pressed } => {
+                let confirm = &ctx.settings.confirm;
+                if self.confirm_reset.button(*pressed, confirm.clear_stats, ctx.clock.now(), confirm) {
+                    self.reset_stats(ctx);
+                }
+            }
+            // Button reports repeat every held button, so only the first report of a press taps.
+            HardwareEvent::Button { index: Buttons::Tap, pressed } => {
+                if *pressed && !self.tap_held {
+                    self.tap(ctx);
+                }
+                self.tap_held = *pressed;
+            }
+            HardwareEvent::Button { index: Buttons::Shift, pressed } => self.shift_held = *pressed,
+            HardwareEvent::Button { index: Buttons::EncoderPress, pressed } => self.encoder_held = *pressed,
+            HardwareEvent::Encoder { value } => {
+                let direction = encoder_direction(self.last_encoder_val, *value);
+                if *value != 0 {
+                    self.last_encoder_val = *value;
+                }
+                if direction != 0 && self.shift_held && self.encoder_held {
+                    self.nudge(direction, ctx);
+                }
+            }
+            HardwareEvent::Button { index: Buttons::Duplicate, pressed: true } => {
+                let path = &ctx.settings.practice.export_path;
+                match self.stats.export(path) {
+                    Ok(()) => println!("Practice statistics written to {}", path),
+                    Err(e) => eprintln!("Can't write practice statistics to {}: {}", path, e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        let now = ctx.clock.now();
+        let mut changed = false;
+
+        if let Some(next) = self.next_beat
+            && now >= next
+        {
+            let beats = ctx.settings.transport.meter().beats();
+            if let Some(note) = ctx.settings.practice.click_note {
+                let channel = ctx.settings.practice.click_channel - 1;
+                let velocity = if self.beat_count.is_multiple_of(beats) { ACCENT_VELOCITY } else { CLICK_VELOCITY };
+                Self::send(channel, MidiMessage::NoteOn { key: note.into(), vel: velocity.into() }, ctx);
+                Self::send(channel, MidiMessage::NoteOff { key: note.into(), vel: 0.into() }, ctx);
+            }
+            ctx.lights.set_button(Buttons::Tap, Brightness::Bright);
+            self.flash_until = Some(now + BEAT_FLASH);
+            // Skip beats missed while the loop was stalled rather than clicking them all at once.
+            let beat = self.click_interval(ctx);
+            let mut next = next + beat;
+            self.beat_count += 1;
+            while next <= now {
+                next += beat;
+                self.beat_count += 1;
+            }
+            self.next_beat = Some(next);
+            changed = true;
+        }
+
+        if self.flash_until.is_some_and(|until| now >= until) {
+            self.flash_until = None;
+            ctx.lights.set_button(Buttons::Tap, Brightness::Dim);
+            changed = true;
+        }
+
+        let confirm = &ctx.settings.confirm;
+        let was_pending = self.confirm_reset.is_pending();
+        if self.confirm_reset.tick(confirm.clear_stats, now, confirm) {
+            self.reset_stats(ctx);
+            changed = true;
+        }
+        if self.confirm_reset.is_pending() {
+            self.confirm_reset.draw_warning(ctx.lights, now);
+            changed = true;
+        } else if was_pending {
+            for i in 0..16 {
+                self.update_pad_light(i, ctx);
+            }
+            ctx.lights.set_button(Buttons::Erase, Brightness::Off);
+            changed = true;
+        }
+        changed
+    }
+
+    fn is_animating(&self) -> bool {
+        self.anchor.is_some() || self.flash_until.is_some() || self.confirm_reset.is_pending()
+    }
+
+    fn clock_position(&self, now: Instant) -> Option<u32> {
+        let anchor = self.anchor?;
+        Some((now.duration_since(anchor).as_secs_f64() / self.sixteenth.as_secs_f64()) as u32)
+    }
+}
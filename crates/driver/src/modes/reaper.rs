@@ -0,0 +1,217 @@
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use rosc::{OscMessage, OscType};
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::status::CONTENT_ROW;
+use super::{draw_value_bar, ButtonEdges, MachineMode};
+
+/// Root of the addresses in the pattern file `driver export-reaper-osc` writes
+pub const PREFIX: &str = "/maschine/reaper";
+/// Tracks in Reaper's device bank, one per pad
+pub const TRACKS: usize = 16;
+/// Transport goes out on the same addresses Custom MIDI mode's buttons use, so the
+/// pattern file makes those work too; Reaper reports the play and record state back on them.
+const PLAY: &str = "/maschine/play";
+const STOP: &str = "/maschine/stop";
+const RECORD: &str = "/maschine/rec";
+/// Below the value bar; status banners cover it while they're up
+const INFO_ROW: usize = CONTENT_ROW + 15;
+
+/// What Reaper sends back for the tracks of the bank, numbered 1-16 in the addresses:
+/// `<prefix>/track/<n>/name s`, `.../select f`, `.../volume f` (0-1) and
+/// `.../volume/str s` (in dB).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReaperMessage {
+    Name { track: usize, name: String },
+    Selected { track: usize, selected: bool },
+    Volume { track: usize, volume: f32 },
+    VolumeText { track: usize, text: String },
+}
+
+pub fn parse(msg: &OscMessage) -> Option<ReaperMessage> {
+    let rest = msg.addr.strip_prefix(PREFIX)?.strip_prefix("/track/")?;
+    let (track, field) = rest.split_once('/')?;
+    let track = track.parse::<usize>().ok().filter(|t| (1..=TRACKS).contains(t))? - 1;
+    let number = match msg.args.first()? {
+        OscType::Float(f) => Some(*f),
+        OscType::Int(i) => Some(*i as f32),
+        _ => None,
+    };
+    match (field, msg.args.first()?) {
+        ("name", OscType::String(name)) => Some(ReaperMessage::Name { track, name: name.clone() }),
+        ("volume/str", OscType::String(text)) => Some(ReaperMessage::VolumeText { track, text: text.clone() }),
+        ("select", _) => Some(ReaperMessage::Selected { track, selected: number? > 0.0 }),
+        ("volume", _) => Some(ReaperMessage::Volume { track, volume: number?.clamp(0.0, 1.0) }),
+        _ => None,
+    }
+}
+
+/// Reaper over OSC, with the pattern file from `driver export-reaper-osc`: the pads are
+/// the sixteen tracks of Reaper's bank and select one, the touch strip sets the selected
+/// track's volume, and Left/Right move the bank. Play, Stop and Rec drive the transport.
+/// The screen shows the selected track's name and volume.
+pub struct ReaperMode {
+    connected: bool,
+    /// Name, volume (0-1) and volume in dB of each track in the bank
+    tracks: Vec<(String, f32, String)>,
+    selected: usize,
+    playing: bool,
+    recording: bool,
+    /// Buttons held as of the last report, since their states repeat
+    buttons: ButtonEdges,
+}
+
+impl ReaperMode {
+    pub fn new() -> Self {
+        Self {
+            connected: false,
+            tracks: vec![Default::default(); TRACKS],
+            selected: 0,
+            playing: false,
+            recording: false,
+            buttons: ButtonEdges::new(),
+        }
+    }
+
+    fn track_address(track: usize, field: &str) -> String {
+        format!("{PREFIX}/track/{}/{field}", track + 1)
+    }
+
+    fn update_pad_lights(&self, ctx: &mut DriverContext) {
+        for i in 0..TRACKS {
+            if i == self.selected {
+                ctx.lights.set_pad(i, PadColors::Green, Brightness::Bright);
+            } else if !self.tracks[i].0.is_empty() {
+                ctx.lights.set_pad(i, PadColors::Green, Brightness::Dim);
+            } else {
+                ctx.lights.set_pad(i, PadColors::Off, Brightness::Off);
+            }
+        }
+    }
+
+    fn draw_transport(&self, ctx: &mut DriverContext) {
+        let lit = |on: bool| if on { Brightness::Bright } else { Brightness::Dim };
+        ctx.lights.set_button(Buttons::Play, lit(self.playing));
+        ctx.lights.set_button(Buttons::Rec, lit(self.recording));
+        ctx.lights.set_button(Buttons::Stop, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Left, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Right, Brightness::Dim);
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        if !self.connected {
+            Font::write_string(ctx.screen, CONTENT_ROW, 0, "WAITING FOR REAPER", 1);
+            return;
+        }
+        let (name, volume, text) = &self.tracks[self.selected];
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("{} {}", self.selected + 1, name), 1);
+        draw_value_bar(ctx.screen, (volume * 127.0).round() as u8);
+        Font::write_string(ctx.screen, INFO_ROW, 0, text, 1);
+    }
+}
+
+impl MachineMode for ReaperMode {
+    fn name(&self) -> &'static str {
+        "RPR"
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        self.update_pad_lights(ctx);
+        self.draw_transport(ctx);
+        self.draw_screen(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match *event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value }
+                if value > 0 && index != self.selected =>
+            {
+                self.selected = index;
+                ctx.osc.send_int(&Self::track_address(index, "select"), 1);
+                self.update_pad_lights(ctx);
+                self.draw_screen(ctx);
+            }
+            HardwareEvent::Button { index, pressed } => {
+                if !self.buttons.pressed(index, pressed) {
+                    return;
+                }
+                match index {
+                    Buttons::Left => ctx.osc.send_int(&format!("{PREFIX}/bank/-"), 1),
+                    Buttons::Right => ctx.osc.send_int(&format!("{PREFIX}/bank/+"), 1),
+                    Buttons::Play => ctx.osc.send_int(PLAY, 1),
+                    Buttons::Stop => ctx.osc.send_int(STOP, 1),
+                    Buttons::Rec => ctx.osc.send_int(RECORD, 1),
+                    _ => {}
+                }
+            }
+            // The strip reports 1-200 while touched and 0 on release.
+            HardwareEvent::Slider { value } if value > 0 => {
+                let volume = (value - 1) as f32 / 199.0;
+                self.tracks[self.selected].1 = volume;
+                ctx.osc.send(OscMessage {
+                    addr: Self::track_address(self.selected, "volume"),
+                    args: vec![OscType::Float(volume)],
+                });
+                self.draw_screen(ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        match event {
+            HostEvent::Osc { addr, value } if addr == PLAY || addr == RECORD => {
+                if addr == PLAY {
+                    self.playing = *value > 0;
+                } else {
+                    self.recording = *value > 0;
+                }
+                self.draw_transport(ctx);
+                return true;
+            }
+            HostEvent::Reaper(message) => {
+                self.connected = true;
+                match message {
+                    ReaperMessage::Name { track, name } => {
+                        self.tracks[*track].0 = name.clone();
+                        self.update_pad_lights(ctx);
+                    }
+                    ReaperMessage::Selected { track, selected: true } => {
+                        self.selected = *track;
+                        self.update_pad_lights(ctx);
+                    }
+                    ReaperMessage::Selected { .. } => {}
+                    ReaperMessage::Volume { track, volume } => self.tracks[*track].1 = *volume,
+                    ReaperMessage::VolumeText { track, text } => self.tracks[*track].2 = text.clone(),
+                }
+            }
+            _ => return false,
+        }
+        self.draw_screen(ctx);
+        true
+    }
+}
+
+/// The `.ReaperOSC` pattern file for this page: Reaper's actions mapped onto the addresses
+/// above, so picking it in Reaper's OSC control surface settings is all the setup needed.
+pub fn pattern_file() -> String {
+    let mut out = String::new();
+    out.push_str("# Maschinette: Reaper OSC pattern config for the Maschine Mikro MK3 driver's Reaper page.\n");
+    out.push_str("# Written by `driver export-reaper-osc`. In Reaper's Preferences > Control/OSC/web, add an\n");
+    out.push_str("# OSC device with this pattern config, the driver's osc_listen_port as the device port and\n");
+    out.push_str("# its osc_port as the local listen port.\n\n");
+    out.push_str(&format!("DEVICE_TRACK_COUNT {TRACKS}\n"));
+    out.push_str(&format!("DEVICE_PREV_TRACK_BANK t{PREFIX}/bank/-\n"));
+    out.push_str(&format!("DEVICE_NEXT_TRACK_BANK t{PREFIX}/bank/+\n\n"));
+    out.push_str(&format!("TRACK_NAME s{PREFIX}/track/@/name\n"));
+    out.push_str(&format!("TRACK_SELECT b{PREFIX}/track/@/select\n"));
+    out.push_str(&format!("TRACK_VOLUME n{PREFIX}/track/@/volume\n"));
+    out.push_str(&format!("TRACK_VOLUME_STR s{PREFIX}/track/@/volume/str\n\n"));
+    out.push_str(&format!("PLAY t{PLAY}\n"));
+    out.push_str(&format!("STOP t{STOP}\n"));
+    out.push_str(&format!("RECORD t{RECORD}\n"));
+    out
+}
@@ -0,0 +1,116 @@
+// crates/driver/src/modes/show.rs
+use std::time::{Duration, Instant};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, PadColors};
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::status::CONTENT_ROW;
+use super::{MachineMode, ParamEncoder};
+
+const CONFIRM_FLASH: Duration = Duration::from_millis(150);
+
+/// Show control for lighting and cue software (Chataigne, QLC+): Play is GO and Restart
+/// is BACK, each flashing to confirm; pads pick the cue stack and the encoder sets the
+/// intensity master. Everything is sent under `[show] prefix`:
+/// `<prefix>/go 1`, `<prefix>/back 1`, `<prefix>/stack 1-16`, `<prefix>/master 0-100`.
+/// The host can send the last two back to keep the page in step.
+pub struct ShowMode {
+    stack: usize,
+    master: i32,
+    encoder: ParamEncoder,
+    /// Button showing a confirm flash, and when it ends
+    flash: Option<(Buttons, Instant)>,
+}
+
+impl ShowMode {
+    pub fn new() -> Self {
+        Self { stack: 0, master: 100, encoder: ParamEncoder::new(), flash: None }
+    }
+
+    fn address(name: &str, ctx: &DriverContext) -> String {
+        format!("{}/{}", ctx.settings.show.prefix, name)
+    }
+
+    fn draw(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            let b = if i == self.stack { Brightness::Bright } else { Brightness::Dim };
+            ctx.lights.set_pad(i, PadColors::Yellow, b);
+        }
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, &format!("STACK {}", self.stack + 1), 1);
+        Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, &format!("MASTER {}%", self.master), 1);
+    }
+
+    fn fire(&mut self, button: Buttons, name: &str, ctx: &mut DriverContext) {
+        ctx.osc.send_int(&Self::address(name, ctx), 1);
+        ctx.lights.set_button(button, Brightness::Bright);
+        self.flash = Some((button, ctx.clock.now() + CONFIRM_FLASH));
+    }
+}
+
+impl MachineMode for ShowMode {
+    fn name(&self) -> &'static str {
+        "SHOW"
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        ctx.lights.set_button(Buttons::Play, Brightness::Dim);
+        ctx.lights.set_button(Buttons::Restart, Brightness::Dim);
+        self.draw(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        match event {
+            HardwareEvent::Button { index: Buttons::Play, pressed: true } => self.fire(Buttons::Play, "go", ctx),
+            HardwareEvent::Button { index: Buttons::Restart, pressed: true } => self.fire(Buttons::Restart, "back", ctx),
+            HardwareEvent::Pad { index, event_type, value } => {
+                let pressed = matches!(event_type, PadEventType::NoteOn | PadEventType::PressOn) && *value > 0;
+                if pressed && *index != self.stack {
+                    self.stack = *index;
+                    ctx.osc.send_int(&Self::address("stack", ctx), *index as i32 + 1);
+                    self.draw(ctx);
+                }
+            }
+            HardwareEvent::Button { index, pressed } => self.encoder.button(*index, *pressed),
+            HardwareEvent::Encoder { value } => {
+                let delta = self.encoder.delta(*value, ctx.settings.show.master_step as i32);
+                let master = (self.master + delta).clamp(0, 100);
+                if master != self.master {
+                    self.master = master;
+                    ctx.osc.send_int(&Self::address("master", ctx), master);
+                    self.draw(ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        let HostEvent::Osc { addr, value } = event else {
+            return false;
+        };
+        match addr.strip_prefix(ctx.settings.show.prefix.as_str()) {
+            Some("/stack") if (1..=16).contains(value) => self.stack = *value as usize - 1,
+            Some("/master") => self.master = (*value).clamp(0, 100),
+            _ => return false,
+        }
+        self.draw(ctx);
+        true
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        match self.flash {
+            Some((button, until)) if ctx.clock.now() >= until => {
+                ctx.lights.set_button(button, Brightness::Dim);
+                self.flash = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.flash.is_some()
+    }
+}
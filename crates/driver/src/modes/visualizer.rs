@@ -0,0 +1,127 @@
+// crates/driver/src/modes/visualizer.rs
+use std::time::{Duration, Instant};
+use maschine_library::font::Font;
+use maschine_library::lights::Lights;
+use crate::context::DriverContext;
+use crate::input::{HardwareEvent, HostEvent};
+use crate::status::CONTENT_ROW;
+use super::{CustomMidiMode, MachineMode};
+
+/// Brightness steps between off and bright, the most a light moves between two frames
+const LEVEL_STEPS: u32 = 3;
+const SLIDER_LEDS: usize = 25;
+
+/// The pads and slider as a display for `/maschine/lights/frame`: each frame becomes the
+/// target, and the LEDs walk towards it in brightness steps spread over the time until
+/// the next frame is expected, so a slow sender still looks smooth. Updates never go out
+/// faster than `[visualizer] max_fps`. Buttons, pads and encoder keep their Custom MIDI
+/// mappings; the button lights in a frame are left to them.
+pub struct VisualizerMode {
+    inner: CustomMidiMode,
+    shown: Lights,
+    target: Lights,
+    last_frame: Option<Instant>,
+    /// Time between the last two frames
+    frame_interval: Option<Duration>,
+    next_update: Option<Instant>,
+}
+
+impl VisualizerMode {
+    pub fn new() -> Self {
+        Self {
+            inner: CustomMidiMode::new(),
+            shown: Lights::new(),
+            target: Lights::new(),
+            last_frame: None,
+            frame_interval: None,
+            next_update: None,
+        }
+    }
+
+    /// Copies the frame being shown over the pads and slider.
+    fn draw(&self, ctx: &mut DriverContext) {
+        for i in 0..16 {
+            let (color, brightness) = self.shown.get_pad(i);
+            ctx.lights.set_pad(i, color, brightness);
+        }
+        for i in 0..SLIDER_LEDS {
+            ctx.lights.set_slider(i, self.shown.get_slider(i));
+        }
+    }
+
+    fn draw_screen(&self, ctx: &mut DriverContext) {
+        ctx.screen.reset();
+        Font::write_string(ctx.screen, CONTENT_ROW, 0, "VISUALIZER", 1);
+        if self.last_frame.is_none() {
+            Font::write_string(ctx.screen, CONTENT_ROW + 8, 0, "WAITING FOR OSC", 1);
+        }
+    }
+}
+
+impl MachineMode for VisualizerMode {
+    fn name(&self) -> &'static str {
+        "VIS"
+    }
+
+    fn on_enter(&mut self, ctx: &mut DriverContext) {
+        self.inner.on_enter(ctx);
+        self.draw(ctx);
+        self.draw_screen(ctx);
+    }
+
+    fn handle_event(&mut self, event: &HardwareEvent, ctx: &mut DriverContext) {
+        self.inner.handle_event(event, ctx);
+        // Pad presses and the slider would light their own LEDs; the frame owns them.
+        if matches!(event, HardwareEvent::Pad { .. } | HardwareEvent::Slider { .. }) {
+            self.draw(ctx);
+        }
+    }
+
+    fn handle_host_event(&mut self, event: &HostEvent, ctx: &mut DriverContext) -> bool {
+        let HostEvent::LightFrame(frame) = event else {
+            let changed = self.inner.handle_host_event(event, ctx);
+            self.draw(ctx);
+            return changed;
+        };
+        let now = ctx.clock.now();
+        for (i, (color, brightness)) in frame.pads.iter().enumerate() {
+            self.target.set_pad(i, *color, *brightness);
+        }
+        for (i, brightness) in frame.slider.iter().flatten().enumerate() {
+            self.target.set_slider(i, *brightness);
+        }
+        let first = self.last_frame.is_none();
+        self.frame_interval = self.last_frame.map(|last| now.duration_since(last));
+        self.last_frame = Some(now);
+        if first {
+            self.draw_screen(ctx);
+        }
+        first
+    }
+
+    fn tick(&mut self, ctx: &mut DriverContext) -> bool {
+        let now = ctx.clock.now();
+        if self.shown == self.target || self.next_update.is_some_and(|at| now < at) {
+            return false;
+        }
+        let min_period = Duration::from_secs(1) / ctx.settings.visualizer.max_fps;
+        match self.frame_interval.map(|interval| interval / LEVEL_STEPS) {
+            // Room for the steps between frames: fade.
+            Some(step) if step >= min_period => {
+                self.shown.step_towards(&self.target);
+                self.next_update = Some(now + step);
+            }
+            // First frame, or frames too close together to fade: show it as it is.
+            _ => {
+                self.shown = self.target.clone();
+                self.next_update = Some(now + min_period);
+            }
+        }
+        self.draw(ctx);
+        true
+    }
+
+    fn is_animating(&self) -> bool {
+        self.shown != self.target
+    }
+}
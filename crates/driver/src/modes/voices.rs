@@ -0,0 +1,51 @@
+use crate::settings::VoiceAllocation;
+
+/// Hands out channels to the notes of Keyboard and Chord modes, spread over a list of
+/// them (`channels`) to play multi-timbral instruments or a stack of mono synths as one.
+/// Keeps which channel each sounding note went to, so its note off follows it there.
+pub(crate) struct Voices {
+    /// Position in the list of the next round-robin channel
+    next: usize,
+    /// Pad, note and 0-based channel of each sounding note, oldest first
+    sounding: Vec<(usize, u8, u8)>,
+}
+
+impl Voices {
+    pub fn new() -> Self {
+        Self { next: 0, sounding: Vec::new() }
+    }
+
+    /// The 0-based channel for `note`, started by `pad`, with the note it takes over from
+    /// as (channel, note) when there was no free one. Without `channels`, every note goes
+    /// to `channel` (1-16).
+    pub fn start(&mut self, pad: usize, note: u8, channel: u8, channels: &[u8], allocation: VoiceAllocation) -> (u8, Option<(u8, u8)>) {
+        let default = [channel];
+        let channels = if channels.is_empty() { &default[..] } else { channels };
+        let mut stolen = None;
+        let channel = match allocation {
+            VoiceAllocation::RoundRobin => {
+                let channel = channels[self.next % channels.len()] - 1;
+                self.next = (self.next + 1) % channels.len();
+                channel
+            }
+            VoiceAllocation::FirstFree => match channels.iter().map(|c| c - 1).find(|c| !self.sounding.iter().any(|s| s.2 == *c)) {
+                Some(channel) => channel,
+                None => {
+                    let oldest = self.sounding.iter().position(|s| channels.contains(&(s.2 + 1))).unwrap_or(0);
+                    let (_, note, channel) = self.sounding.remove(oldest);
+                    stolen = Some((channel, note));
+                    channel
+                }
+            },
+        };
+        self.sounding.push((pad, note, channel));
+        (channel, stolen)
+    }
+
+    /// The 0-based channel of the note `pad` started, to send its note off to; None when
+    /// another note took the channel over since.
+    pub fn stop(&mut self, pad: usize, note: u8) -> Option<u8> {
+        let i = self.sounding.iter().position(|s| s.0 == pad && s.1 == note)?;
+        Some(self.sounding.remove(i).2)
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+use rosc::{OscMessage, OscType};
+use crate::context::DriverContext;
+
+/// Custom MIDI mode's controls as a modular rig expects them, next to the usual messages:
+/// every value a float in 0-1 under `[modular] prefix`, the way VCV Rack's OSC modules
+/// (trowaSoft cvOSCcv, MindMeld's OSC bridge) turn addresses into voltages.
+///
+/// - `<prefix>/gate/<pad 1-16>` 1 while the pad is held, 0 on release, after
+///   `<prefix>/velocity/<pad>` with the hit's velocity
+/// - `<prefix>/gate/<button>` the state of a toggle button
+/// - `<prefix>/trig/<button>` a 1 bang on every press of a trigger button; with
+///   `trigger_ms` a 0 follows, for OSC-to-CV bridges that hold the last value
+/// - `<prefix>/cv/slider` the touch strip
+pub struct Modular {
+    /// Trigger addresses waiting for their 0, and when it's due
+    pending: Vec<(String, Instant)>,
+}
+
+impl Modular {
+    pub fn new() -> Self {
+        Self { pending: Vec::new() }
+    }
+
+    fn send(address: String, value: f32, ctx: &DriverContext) {
+        ctx.osc.send(OscMessage { addr: address, args: vec![OscType::Float(value)] });
+    }
+
+    /// Pad gate, with its velocity (0-127) first when it opens
+    pub fn pad(&self, index: usize, velocity: u8, ctx: &DriverContext) {
+        let prefix = &ctx.settings.modular.prefix;
+        if velocity > 0 {
+            Self::send(format!("{prefix}/velocity/{}", index + 1), velocity as f32 / 127.0, ctx);
+        }
+        Self::send(format!("{prefix}/gate/{}", index + 1), if velocity > 0 { 1.0 } else { 0.0 }, ctx);
+    }
+
+    pub fn gate(&self, name: &str, on: bool, ctx: &DriverContext) {
+        Self::send(format!("{}/gate/{}", ctx.settings.modular.prefix, name.to_lowercase()), if on { 1.0 } else { 0.0 }, ctx);
+    }
+
+    pub fn trigger(&mut self, name: &str, ctx: &DriverContext) {
+        let address = format!("{}/trig/{}", ctx.settings.modular.prefix, name.to_lowercase());
+        Self::send(address.clone(), 1.0, ctx);
+        let trigger_ms = ctx.settings.modular.trigger_ms;
+        if trigger_ms > 0 {
+            self.pending.retain(|(a, _)| *a != address);
+            self.pending.push((address, ctx.clock.now() + Duration::from_millis(trigger_ms)));
+        }
+    }
+
+    /// `value` in 0-`full_scale`
+    pub fn cv(&self, name: &str, value: u32, full_scale: u32, ctx: &DriverContext) {
+        Self::send(format!("{}/cv/{name}", ctx.settings.modular.prefix), value as f32 / full_scale as f32, ctx);
+    }
+
+    /// Ends the trigger pulses that are due.
+    pub fn tick(&mut self, ctx: &DriverContext) {
+        let now = ctx.clock.now();
+        self.pending.retain(|(address, due)| {
+            if now < *due {
+                return true;
+            }
+            Self::send(address.clone(), 0.0, ctx);
+            false
+        });
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use maschine_library::font::Font;
+use maschine_library::screen::Screen;
+use midir::SendError;
+use midly::live::{LiveEvent, SystemCommon, SystemRealtime};
+use midly::MidiMessage;
+use crate::context::MidiSink;
+use crate::midi_queue::QueueStats;
+
+/// Four 8-pixel text lines fill the screen.
+const LINES: usize = 4;
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// Screen page listing the latest MIDI messages in and out, newest at the bottom, for
+/// diagnosing mappings without a MIDI monitor on the computer. It overlays whatever
+/// mode is active, so what that mode sends stays visible.
+pub struct MidiMonitor {
+    lines: VecDeque<String>,
+    visible: bool,
+}
+
+impl MidiMonitor {
+    pub fn new() -> Self {
+        Self { lines: VecDeque::with_capacity(LINES), visible: false }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Logs a message; returns true when that changes the page while it's shown.
+    pub fn record(&mut self, direction: Direction, bytes: &[u8]) -> bool {
+        // Clock and active sensing would push everything else off the page at once.
+        if matches!(bytes, [0xF8] | [0xFE]) {
+            return false;
+        }
+        if self.lines.len() == LINES {
+            self.lines.pop_front();
+        }
+        let arrow = match direction {
+            Direction::In => '<',
+            Direction::Out => '>',
+        };
+        self.lines.push_back(format!("{}{}", arrow, describe(bytes)));
+        self.visible
+    }
+
+    pub fn compose(&self) -> Screen {
+        let mut frame = Screen::new();
+        if self.lines.is_empty() {
+            Font::write_string(&mut frame, 0, 0, "MIDI MONITOR", 1);
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            Font::write_string(&mut frame, i * 8, 0, line, 1);
+        }
+        frame
+    }
+}
+
+/// Note name with octave, middle C (60) being C3.
+pub fn note_name(note: u8) -> String {
+    format!("{}{}", NOTE_NAMES[note as usize % 12], note as i32 / 12 - 2)
+}
+
+/// Short human-readable form of a MIDI message that fits a screen line, channel first.
+pub fn describe(bytes: &[u8]) -> String {
+    let (channel, message) = match LiveEvent::parse(bytes) {
+        Ok(LiveEvent::Midi { channel, message }) => (channel, message),
+        Ok(LiveEvent::Realtime(SystemRealtime::Start)) => return "START".to_string(),
+        Ok(LiveEvent::Realtime(SystemRealtime::Continue)) => return "CONTINUE".to_string(),
+        Ok(LiveEvent::Realtime(SystemRealtime::Stop)) => return "STOP".to_string(),
+        Ok(LiveEvent::Common(SystemCommon::SongPosition(position))) => return format!("SPP {}", position),
+        _ => return bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "),
+    };
+    let text = match message {
+        MidiMessage::NoteOn { key, vel } if vel == 0 => format!("OFF {}", note_name(key.into())),
+        MidiMessage::NoteOn { key, vel } => format!("ON {} v{}", note_name(key.into()), vel),
+        MidiMessage::NoteOff { key, .. } => format!("OFF {}", note_name(key.into())),
+        MidiMessage::Controller { controller, value } => format!("CC{}={}", controller, value),
+        MidiMessage::Aftertouch { key, vel } => format!("AT {} {}", note_name(key.into()), vel),
+        MidiMessage::ChannelAftertouch { vel } => format!("AT {}", vel),
+        MidiMessage::ProgramChange { program } => format!("PC {}", program),
+        MidiMessage::PitchBend { bend } => format!("PB {}", bend.as_int()),
+    };
+    format!("{:02} {}", channel.as_int() + 1, text)
+}
+
+/// Wraps the MIDI output and keeps what went out until the main loop collects it
+/// for the monitor.
+pub struct MidiTap<S: MidiSink> {
+    inner: S,
+    sent: Vec<Vec<u8>>,
+}
+
+impl<S: MidiSink> MidiTap<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner, sent: Vec::new() }
+    }
+}
+
+impl<S: MidiSink> MidiSink for MidiTap<S> {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError> {
+        self.sent.push(message.to_vec());
+        self.inner.send(message)
+    }
+
+    fn consecutive_errors(&self) -> u32 {
+        self.inner.consecutive_errors()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.inner.reconnect()
+    }
+
+    fn take_sent(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.sent)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+
+    fn queue_stats(&self) -> Option<QueueStats> {
+        self.inner.queue_stats()
+    }
+}
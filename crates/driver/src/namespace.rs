@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use maschine_library::controls::Buttons;
+use serde::Serialize;
+use crate::livecoding;
+use crate::macros::MacroStep;
+use crate::modes::reaper;
+use crate::settings::{ButtonMode, EncoderOutput, OscFormat, Settings};
+
+/// One OSC address in the reply to `/maschine/namespace`.
+#[derive(Serialize, Debug)]
+pub struct OscEndpoint {
+    pub address: String,
+    /// "out" for what the driver sends, "in" for what it accepts
+    pub direction: &'static str,
+    /// OSC type tags of the arguments, e.g. "i"; alternatives are separated by "|"
+    pub types: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<[i32; 2]>,
+    pub description: String,
+    /// What the address's `<...>` part can be, for tools that need every address spelled out
+    #[serde(skip)]
+    pub instances: Vec<String>,
+}
+
+impl OscEndpoint {
+    fn with_instances(mut self, instances: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.instances = instances.into_iter().map(|i| i.to_string()).collect();
+        self
+    }
+
+    /// The concrete addresses: the address itself, or one per instance of its `<...>` part
+    pub fn addresses(&self) -> Vec<String> {
+        match self.address.split_once('<').and_then(|(start, rest)| Some((start, rest.split_once('>')?.1))) {
+            Some((start, end)) => self.instances.iter().map(|i| format!("{start}{i}{end}")).collect(),
+            None => vec![self.address.clone()],
+        }
+    }
+}
+
+/// An outgoing value sent in the mapping's `osc_format`; address-embedded values show as `<value>`.
+fn formatted(address: &str, format: OscFormat, range: Option<[i32; 2]>, description: &str) -> OscEndpoint {
+    match format {
+        OscFormat::Address => endpoint(&format!("{}/<value>", address), "out", format.type_tags(), range, description)
+            .with_instances(range.map(|[min, max]| min..=max).into_iter().flatten()),
+        _ => endpoint(address, "out", format.type_tags(), range, description),
+    }
+}
+
+fn endpoint(address: &str, direction: &'static str, types: &'static str, range: Option<[i32; 2]>, description: &str) -> OscEndpoint {
+    OscEndpoint { address: address.to_string(), direction, types, range, description: description.to_string(), instances: Vec::new() }
+}
+
+/// Every address the driver sends and accepts with the current config, so client authors
+/// don't have to read the source.
+pub fn endpoints(settings: &Settings) -> Vec<OscEndpoint> {
+    let mut out = Vec::new();
+    let (mut toggles, mut triggers) = (Vec::new(), Vec::new());
+
+    for i in 0..Buttons::EncoderPress as usize {
+        let Some(button) = <Buttons as num::FromPrimitive>::from_usize(i) else {
+            continue;
+        };
+        // These pick the mode and never reach the host.
+        if matches!(button, Buttons::Maschine | Buttons::Star | Buttons::Browse) {
+            continue;
+        }
+        let name = format!("{:?}", button);
+        let address = format!("/maschine/{}", name.to_lowercase());
+        let format = settings.button_osc_format(&name);
+        match settings.button_configs.get(&name).map(|c| c.mode).unwrap_or_default() {
+            ButtonMode::Trigger => {
+                out.push(formatted(&address, format, Some([0, 1]), "1 on press, 0 on release"));
+                triggers.push(name.to_lowercase());
+            }
+            ButtonMode::Toggle => {
+                out.push(formatted(&address, format, Some([0, 1]), "toggle state"));
+                toggles.push(name.to_lowercase());
+                out.push(endpoint(&address, "in", "i", Some([0, 1]), "sets the toggle state"));
+            }
+        }
+    }
+    out.push(endpoint("/maschine/encoderPress", "out", "i", Some([0, 1]), "1 on press, 0 on release"));
+    if settings.encoder.output == EncoderOutput::Osc {
+        out.push(endpoint("/maschine/encoder", "out", "i", Some([-1, 1]), "one encoder detent"));
+    }
+    out.push(endpoint("/maschine/slider", "out", "i", Some([1, 200]), "touch strip position"));
+    if settings.pad_osc.enabled {
+        let types = if settings.pad_osc.names { "iis" } else { "ii" };
+        out.push(endpoint("/maschine/pad", "out", types, None, "pad 1-16 and velocity (0 on release), then its name"));
+    }
+
+    if settings.modular.enabled {
+        let prefix = &settings.modular.prefix;
+        out.push(endpoint(&format!("{prefix}/gate/<pad>"), "out", "f", None, "1 while pad 1-16 is held, 0 on release").with_instances(1..=16));
+        out.push(endpoint(&format!("{prefix}/velocity/<pad>"), "out", "f", None, "velocity of the hit on pad 1-16, 0-1").with_instances(1..=16));
+        out.push(endpoint(&format!("{prefix}/gate/<button>"), "out", "f", None, "toggle button state, 0 or 1").with_instances(toggles));
+        let trigger = if settings.modular.trigger_ms > 0 { "1 on press, 0 after trigger_ms" } else { "1 on press" };
+        out.push(endpoint(&format!("{prefix}/trig/<button>"), "out", "f", None, trigger).with_instances(triggers));
+        out.push(endpoint(&format!("{prefix}/cv/slider"), "out", "f", None, "touch strip position, 0-1"));
+    }
+
+    if settings.livecoding.enabled {
+        out.push(endpoint("/maschine/hello", "in", "|i|s", None, "subscribes like /maschine/subscribe; ints after the port pick pad groups"));
+        out.push(endpoint(livecoding::STATE, "out", "s", None, "surface state as JSON, in reply to /maschine/hello"));
+        out.push(endpoint("/maschine/pattern", "in", "s|sf", None, "Pbind-like keys and values loaded as the loop"));
+        out.push(endpoint(livecoding::TAGGED_PAD, "out", "iii", None, "pad group, pad 1-16 and velocity (0 on release)"));
+    }
+
+    let mut groups: BTreeMap<u8, i32> = BTreeMap::new();
+    for config in settings.button_configs.values() {
+        if let Some(id) = config.group_id {
+            *groups.entry(id).or_default() += 1;
+        }
+    }
+    for (id, members) in groups {
+        let address = format!("/maschine/group/{}", id);
+        out.push(endpoint(&address, "out", "i", Some([0, members]), "selected member, 0 for none"));
+    }
+
+    for knob in &settings.knobs {
+        if let Some(address) = &knob.osc {
+            out.push(formatted(address, knob.osc_format, Some([0, 127]), &format!("knob {}", knob.name)));
+            out.push(endpoint(address, "in", "i", Some([0, 127]), &format!("sets knob {}", knob.name)));
+        }
+    }
+    for step in settings.macro_steps() {
+        if let MacroStep::Osc { address, value } = step {
+            let types = if value.is_some() { "i" } else { "" };
+            out.push(endpoint(address, "out", types, value.map(|v| [v, v]), "macro step"));
+        }
+    }
+
+    if settings.show.enabled {
+        let prefix = &settings.show.prefix;
+        out.push(endpoint(&format!("{prefix}/go"), "out", "i", Some([1, 1]), "cue GO"));
+        out.push(endpoint(&format!("{prefix}/back"), "out", "i", Some([1, 1]), "cue BACK"));
+        for direction in ["out", "in"] {
+            out.push(endpoint(&format!("{prefix}/stack"), direction, "i", Some([1, 16]), "selected cue stack"));
+            out.push(endpoint(&format!("{prefix}/master"), direction, "i", Some([0, 100]), "intensity master in percent"));
+        }
+    }
+
+    if settings.reaper.enabled {
+        let track = format!("{}/track/<n>", reaper::PREFIX);
+        out.push(endpoint(&format!("{track}/select"), "out", "i", Some([1, 1]), "selects track 1-16 of Reaper's bank").with_instances(1..=reaper::TRACKS));
+        out.push(endpoint(&format!("{track}/volume"), "out", "f", None, "selected track's volume, 0-1").with_instances(1..=reaper::TRACKS));
+        out.push(endpoint(&format!("{}/bank/-", reaper::PREFIX), "out", "i", Some([1, 1]), "previous bank of tracks"));
+        out.push(endpoint(&format!("{}/bank/+", reaper::PREFIX), "out", "i", Some([1, 1]), "next bank of tracks"));
+        out.push(endpoint(&format!("{track}/name"), "in", "s", None, "track name").with_instances(1..=reaper::TRACKS));
+        out.push(endpoint(&format!("{track}/select"), "in", "f", None, "track selection").with_instances(1..=reaper::TRACKS));
+        out.push(endpoint(&format!("{track}/volume"), "in", "f", None, "track volume, 0-1").with_instances(1..=reaper::TRACKS));
+        out.push(endpoint(&format!("{track}/volume/str"), "in", "s", None, "track volume in dB").with_instances(1..=reaper::TRACKS));
+    }
+
+    out.push(endpoint("/maschine/loop/data", "out", "s|b", None, "the looper's loop, in reply to /maschine/loop/dump"));
+    out.push(endpoint("/maschine/namespace/data", "out", "s", None, "this list as JSON"));
+
+    out.push(endpoint("/maschine/reset", "in", "i", Some([0, 1]), "1 puts every group back to its default"));
+    out.push(endpoint("/maschine/screen/text", "in", "s", None, "text for the screen"));
+    out.push(endpoint("/maschine/scene", "in", "s|si", None, "recalls a light scene, \"off\" clears it; optional fade in ms"));
+    out.push(endpoint("/maschine/loop/load", "in", "s|b", None, "loop for the looper, as JSON or blob"));
+    out.push(endpoint("/maschine/loop/dump", "in", "|s", None, "asks for /maschine/loop/data; \"blob\" for the blob form"));
+    out.push(endpoint("/maschine/namespace", "in", "", None, "asks for /maschine/namespace/data"));
+    out.push(endpoint("/maschine/metrics", "in", "", None, "asks for /maschine/metrics/data"));
+    out.push(endpoint("/maschine/metrics/data", "out", "s", None, "MIDI send queue counters as JSON: sent, dropped, coalesced, queued and max_latency_us per class"));
+    out.push(endpoint("/maschine/macro", "in", "s", None, "runs a named macro from [macros]"));
+    out.push(endpoint("/maschine/subscribe", "in", "|i|s", None, "sends output here (or to port / \"host:port\") until not renewed; more strings filter by prefix"));
+    out.push(endpoint("/maschine/unsubscribe", "in", "|i|s", None, "ends a subscription"));
+    out.push(endpoint("/maschine/subscribed", "out", "i", None, "confirms a subscription, with the seconds until it has to be renewed"));
+    out.push(endpoint("/maschine/handshake", "in", "|i", None, "asks for /maschine/handshake/reply; optionally the client's protocol version"));
+    out.push(endpoint("/maschine/handshake/reply", "out", "is", None, "OSC protocol version and driver version"));
+    if settings.osc.failover.enabled {
+        out.push(endpoint("/maschine/heartbeat", "out", "i", None, "count, sent to the primary destination every interval_ms"));
+        out.push(endpoint("/maschine/heartbeat/ack", "in", "|i", None, "the primary is alive; without it the backup takes over"));
+    }
+    if settings.attract.enabled {
+        out.push(endpoint("/maschine/attract", "out", "i", Some([0, 1]), "1 when the attract loop starts, 0 when a touch ends it"));
+        if settings.attract.report_touches {
+            out.push(endpoint("/maschine/touch", "out", "s", None, "control touched: pad1-pad16, encoder, slider or a button name"));
+        }
+    }
+    out.push(endpoint("/maschine/snapshot", "in", "i", Some([1, 4]), "recalls a performance snapshot"));
+    out.push(endpoint("/maschine/play/scene", "in", "i", Some([1, 16]), "launches a Play mode scene at the end of the pass"));
+    out.push(endpoint("/maschine/lights/frame", "in", "b", None, "whole LED frame: pads, then buttons and slider (see README)"));
+    out
+}
+
+pub fn to_json(settings: &Settings) -> String {
+    serde_json::to_string(&endpoints(settings)).expect("the namespace always serializes")
+}
@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use midir::SendError;
+use crate::clock::Clock;
+use crate::context::MidiSink;
+use crate::midi_queue::QueueStats;
+use crate::settings::NoteGuardSettings;
+
+/// Receivers give up on a sender after 300 ms of silence; this leaves room for a slow pass.
+const ACTIVE_SENSING: Duration = Duration::from_millis(250);
+
+/// Keeps track of every note the driver turns on, so none can hang when its release never
+/// comes: a pad report lost over USB, a mode that forgot one, the driver stopping. A note
+/// still on `window_ms` after its NoteOn gets its NoteOff from here, and `release_notes`
+/// ends all of them on a mode switch or at shutdown. With `active_sensing`, it also sends
+/// Active Sensing whenever the port was quiet for a while, so gear that honours it turns
+/// its notes off by itself if the driver dies without a word. Disabled, it passes
+/// everything straight through. Time comes from the modes' clock.
+pub struct NoteGuard<S: MidiSink, C: Clock> {
+    inner: S,
+    settings: NoteGuardSettings,
+    clock: C,
+    /// Status byte, key and NoteOn time of each note on
+    on: Vec<(u8, u8, Instant)>,
+    last_sent: Option<Instant>,
+}
+
+impl<S: MidiSink, C: Clock> NoteGuard<S, C> {
+    pub fn new(inner: S, settings: &NoteGuardSettings, clock: C) -> Self {
+        Self { inner, settings: settings.clone(), clock, on: Vec::new(), last_sent: None }
+    }
+
+    fn track(&mut self, message: &[u8], now: Instant) {
+        let [status, key, velocity] = *message else {
+            return;
+        };
+        let channel = status & 0x0F;
+        // A NoteOn again restarts the window; any off ends the note.
+        self.on.retain(|&(s, k, _)| !(s & 0x0F == channel && k == key));
+        if status & 0xF0 == 0x90 && velocity > 0 {
+            self.on.push((status, key, now));
+        }
+    }
+
+    fn note_off(&mut self, status: u8, key: u8) {
+        let _ = self.inner.send(&[0x80 | (status & 0x0F), key, 0]);
+    }
+}
+
+impl<S: MidiSink, C: Clock> MidiSink for NoteGuard<S, C> {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError> {
+        if self.settings.enabled {
+            let now = self.clock.now();
+            self.last_sent = Some(now);
+            if matches!(message.first(), Some(0x80..=0x9F)) {
+                self.track(message, now);
+            }
+        }
+        self.inner.send(message)
+    }
+
+    fn flush(&mut self) {
+        if self.settings.enabled {
+            let now = self.clock.now();
+            let window = Duration::from_millis(self.settings.window_ms);
+            let (expired, on): (Vec<_>, Vec<_>) = self.on.drain(..).partition(|&(_, _, at)| now.duration_since(at) >= window);
+            self.on = on;
+            for (status, key, _) in expired {
+                self.note_off(status, key);
+            }
+            if self.settings.active_sensing && self.last_sent.is_none_or(|at| now.duration_since(at) >= ACTIVE_SENSING) {
+                let _ = self.inner.send(&[0xFE]);
+                self.last_sent = Some(now);
+            }
+        }
+        self.inner.flush()
+    }
+
+    fn release_notes(&mut self) {
+        for (status, key, _) in std::mem::take(&mut self.on) {
+            self.note_off(status, key);
+        }
+    }
+
+    fn consecutive_errors(&self) -> u32 {
+        self.inner.consecutive_errors()
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.inner.reconnect()
+    }
+
+    fn take_sent(&mut self) -> Vec<Vec<u8>> {
+        self.inner.take_sent()
+    }
+
+    fn queue_stats(&self) -> Option<QueueStats> {
+        self.inner.queue_stats()
+    }
+}
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::Relaxed);
+}
+
+/// Turns Ctrl-C and SIGTERM into a clean stop, so the notes still on can be ended first.
+pub fn catch_shutdown() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+pub fn shutting_down() -> bool {
+    SHUTDOWN.load(Ordering::Relaxed)
+}
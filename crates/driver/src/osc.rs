@@ -0,0 +1,435 @@
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
+use crate::context::OscSink;
+use crate::livecoding::TAGGED_PAD;
+
+const SESSION_HEADER: &str = "# maschinette osc session v1";
+
+/// Version of the OSC protocol, reported in reply to `/maschine/handshake`. Goes up when
+/// an address or its arguments change in a way existing clients would trip over.
+pub const PROTOCOL_VERSION: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OscDirection {
+    In,
+    Out,
+}
+
+impl OscDirection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OscDirection::In => "in",
+            OscDirection::Out => "out",
+        }
+    }
+}
+
+/// One place outgoing messages are sent to, with optional address-prefix filters.
+pub struct OscTarget {
+    addr: SocketAddr,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    send_errors: Cell<u32>,
+    /// Where messages go instead while failed over (see `failover`)
+    backup: Option<SocketAddr>,
+    failed_over: Cell<bool>,
+}
+
+impl OscTarget {
+    /// Filters are address prefixes; a trailing `*` is accepted and ignored.
+    pub fn new(addr: SocketAddr, include: &[String], exclude: &[String]) -> Self {
+        let prefixes = |list: &[String]| list.iter().map(|p| p.trim_end_matches('*').to_string()).collect();
+        Self {
+            addr,
+            include: prefixes(include),
+            exclude: prefixes(exclude),
+            send_errors: Cell::new(0),
+            backup: None,
+            failed_over: Cell::new(false),
+        }
+    }
+
+    pub fn with_backup(mut self, backup: SocketAddr) -> Self {
+        self.backup = Some(backup);
+        self
+    }
+
+    /// The backup while failed over, the target's own address otherwise
+    fn current_addr(&self) -> SocketAddr {
+        match self.backup {
+            Some(backup) if self.failed_over.get() => backup,
+            _ => self.addr,
+        }
+    }
+
+    fn accepts(&self, osc_addr: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| osc_addr.starts_with(p.as_str())))
+            && !self.exclude.iter().any(|p| osc_addr.starts_with(p.as_str()))
+    }
+}
+
+/// A client that asked for messages with `/maschine/subscribe` or `/maschine/hello`,
+/// until it stops renewing.
+struct Subscriber {
+    target: OscTarget,
+    expires: Instant,
+    /// Groups of tagged pad hits it wants; all of them when empty
+    pad_groups: Vec<i32>,
+}
+
+impl Subscriber {
+    fn wants(&self, addr: &str, tag: Option<i32>) -> bool {
+        self.target.accepts(addr) && (self.pad_groups.is_empty() || tag.is_none_or(|g| self.pad_groups.contains(&g)))
+    }
+}
+
+/// Outgoing OSC endpoint shared by all modes. Every packet sent (and, via
+/// `record_incoming`, every packet received) goes through here so sessions can be recorded
+/// and OSCQuery can follow the values at each address. Messages fan out to the
+/// configured targets and to every live subscriber.
+pub struct OscIo {
+    socket: UdpSocket,
+    /// For IPv6 targets and subscribers, when the host has IPv6
+    socket6: Option<UdpSocket>,
+    targets: Vec<OscTarget>,
+    subscribers: RefCell<Vec<Subscriber>>,
+    recorder: Option<OscRecorder>,
+    watchers: RefCell<Vec<Sender<OscMessage>>>,
+    /// When set, messages go out in bundles timetagged with the moment the input that
+    /// caused them was read, so receivers can measure its true age.
+    timetags: bool,
+    input_time: Cell<Option<Instant>>,
+}
+
+impl OscIo {
+    pub fn new(socket: UdpSocket, targets: Vec<OscTarget>, recorder: Option<OscRecorder>, timetags: bool) -> Self {
+        Self {
+            socket,
+            socket6: None,
+            targets,
+            subscribers: RefCell::new(Vec::new()),
+            recorder,
+            watchers: RefCell::new(Vec::new()),
+            timetags,
+            input_time: Cell::new(None),
+        }
+    }
+
+    pub fn with_ipv6(mut self, socket: UdpSocket) -> Self {
+        self.socket6 = Some(socket);
+        self
+    }
+
+    fn send_raw(&self, buf: &[u8], to: SocketAddr) -> io::Result<usize> {
+        match (&self.socket6, to) {
+            (Some(socket), SocketAddr::V6(_)) => socket.send_to(buf, to),
+            _ => self.socket.send_to(buf, to),
+        }
+    }
+
+    /// Handles `/maschine/subscribe [port|"host:port"] [prefix ...]` and
+    /// `/maschine/unsubscribe [port|"host:port"]` from `from`; returns false for any other
+    /// packet. Without a port the reply goes to the sending socket. Subscribing again
+    /// renews the subscription and replaces its filters; it is confirmed with
+    /// `/maschine/subscribed <ttl seconds>`. With `hello` on, `/maschine/hello
+    /// [port|"host:port"] [group ...]` subscribes too, to everything but the tagged pad
+    /// hits of other groups, and returns false so the state goes out in reply.
+    pub fn handle_subscription(&self, packet: &[u8], from: SocketAddr, now: Instant, ttl: Duration, hello: bool) -> bool {
+        let Ok((_, OscPacket::Message(msg))) = rosc::decoder::decode_udp(packet) else {
+            return false;
+        };
+        let subscribe = match msg.addr.as_str() {
+            "/maschine/subscribe" => true,
+            "/maschine/unsubscribe" => false,
+            "/maschine/hello" if hello => true,
+            _ => return false,
+        };
+        let mut args = msg.args.iter().peekable();
+        let addr = match args.peek() {
+            Some(OscType::Int(port)) => {
+                args.next();
+                SocketAddr::new(from.ip(), *port as u16)
+            }
+            Some(OscType::String(s)) if s.parse::<SocketAddr>().is_ok() => {
+                args.next();
+                s.parse().unwrap_or(from)
+            }
+            _ => from,
+        };
+        let (include, pad_groups) = if msg.addr == "/maschine/hello" {
+            (Vec::new(), args.filter_map(|a| a.clone().int()).collect())
+        } else {
+            (args.filter_map(|a| a.clone().string()).collect(), Vec::new())
+        };
+
+        let mut subscribers = self.subscribers.borrow_mut();
+        subscribers.retain(|s| s.target.addr != addr);
+        if subscribe {
+            subscribers.push(Subscriber { target: OscTarget::new(addr, &include, &[]), expires: now + ttl, pad_groups });
+            let reply = OscMessage { addr: "/maschine/subscribed".to_string(), args: vec![OscType::Int(ttl.as_secs() as i32)] };
+            if let Ok(buf) = rosc::encoder::encode(&OscPacket::Message(reply)) {
+                let _ = self.send_raw(&buf, addr);
+            }
+        }
+        msg.addr != "/maschine/hello"
+    }
+
+    /// Answers `/maschine/handshake [protocol]` from `from` with `/maschine/handshake/reply
+    /// <protocol> <driver version>`, so a patch can check it speaks the same protocol;
+    /// returns false for any other packet. A client on another protocol is warned about.
+    pub fn handle_handshake(&self, packet: &[u8], from: SocketAddr) -> bool {
+        let Ok((_, OscPacket::Message(msg))) = rosc::decoder::decode_udp(packet) else {
+            return false;
+        };
+        if msg.addr != "/maschine/handshake" {
+            return false;
+        }
+        if let Some(OscType::Int(version)) = msg.args.first()
+            && *version != PROTOCOL_VERSION
+        {
+            eprintln!("OSC client {} speaks protocol {}; this driver speaks {}", from, version, PROTOCOL_VERSION);
+        }
+        let args = vec![OscType::Int(PROTOCOL_VERSION), OscType::String(env!("CARGO_PKG_VERSION").to_string())];
+        let reply = OscMessage { addr: "/maschine/handshake/reply".to_string(), args };
+        if let Ok(buf) = rosc::encoder::encode(&OscPacket::Message(reply)) {
+            let _ = self.send_raw(&buf, from);
+        }
+        true
+    }
+
+    /// Sends to `target`'s own address even while it's failed over, for heartbeats.
+    pub fn send_to_target(&self, target: usize, msg: OscMessage) {
+        let Some(target) = self.targets.get(target) else {
+            return;
+        };
+        if let Ok(buf) = rosc::encoder::encode(&OscPacket::Message(msg)) {
+            if let Some(recorder) = &self.recorder {
+                recorder.record(OscDirection::Out, &buf);
+            }
+            let _ = self.send_raw(&buf, target.addr);
+        }
+    }
+
+    /// Sends `target`'s messages to its backup, or back to it.
+    pub fn set_failed_over(&self, target: usize, failed_over: bool) {
+        if let Some(target) = self.targets.get(target) {
+            target.failed_over.set(failed_over);
+            target.send_errors.set(0);
+        }
+    }
+
+    /// Drops subscribers that didn't renew in time.
+    pub fn expire_subscribers(&self, now: Instant) {
+        self.subscribers.borrow_mut().retain(|s| now < s.expires);
+    }
+
+    /// Read time of the input being handled; None for output that no input caused.
+    pub fn set_input_time(&self, at: Option<Instant>) {
+        self.input_time.set(at);
+    }
+
+    fn wrap(&self, msg: OscMessage) -> OscPacket {
+        let Some(at) = self.input_time.get().filter(|_| self.timetags) else {
+            return OscPacket::Message(msg);
+        };
+        let read_at = SystemTime::now() - at.elapsed();
+        match OscTime::try_from(read_at) {
+            Ok(timetag) => OscPacket::Bundle(OscBundle { timetag, content: vec![OscPacket::Message(msg)] }),
+            Err(_) => OscPacket::Message(msg),
+        }
+    }
+
+    /// Number of sends that failed in a row on the worst target; 0 while all are reachable.
+    pub fn consecutive_errors(&self) -> u32 {
+        self.targets.iter().map(|t| t.send_errors.get()).max().unwrap_or(0)
+    }
+
+    pub fn record_incoming(&self, packet: &[u8]) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(OscDirection::In, packet);
+        }
+        if let Ok((_, OscPacket::Message(msg))) = rosc::decoder::decode_udp(packet) {
+            self.remember(&msg);
+        }
+    }
+
+    /// Every message sent or received from now on, for another thread to follow
+    pub fn watch(&self) -> Receiver<OscMessage> {
+        let (sender, receiver) = mpsc::channel();
+        self.watchers.borrow_mut().push(sender);
+        receiver
+    }
+
+    fn remember(&self, msg: &OscMessage) {
+        self.watchers.borrow_mut().retain(|w| w.send(msg.clone()).is_ok());
+    }
+}
+
+impl OscSink for OscIo {
+    fn send(&self, msg: OscMessage) {
+        self.remember(&msg);
+        let msg_addr = msg.addr.clone();
+        let tag = match msg.args.first() {
+            Some(OscType::Int(group)) if msg.addr == TAGGED_PAD => Some(*group),
+            _ => None,
+        };
+        let targets: Vec<&OscTarget> = self.targets.iter().filter(|t| t.accepts(&msg.addr)).collect();
+        if let Ok(encoded_buf) = rosc::encoder::encode(&self.wrap(msg)) {
+            if let Some(recorder) = &self.recorder {
+                recorder.record(OscDirection::Out, &encoded_buf);
+            }
+            for target in targets {
+                match self.send_raw(&encoded_buf, target.current_addr()) {
+                    Ok(_) => target.send_errors.set(0),
+                    Err(_) => target.send_errors.set(target.send_errors.get().saturating_add(1)),
+                }
+            }
+            // A subscriber that went away shows up by not renewing, not as an unreachable target.
+            for subscriber in self.subscribers.borrow().iter().filter(|s| s.wants(&msg_addr, tag)) {
+                let _ = self.send_raw(&encoded_buf, subscriber.target.addr);
+            }
+        }
+    }
+}
+
+/// Address to listen on from an `[osc] listen` entry: an IPv4 or IPv6 address, optionally
+/// with a port (`"192.168.1.20:9000"`, `"[::1]:9000"`); `port` without one.
+pub fn listen_addr(entry: &str, port: u16) -> Result<SocketAddr, String> {
+    if let Ok(addr) = entry.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    match entry.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => Ok(SocketAddr::new(ip, port)),
+        Err(_) => Err(format!("expected an IP address, optionally with a port (found {entry})")),
+    }
+}
+
+/// Binds a non-blocking listener on `addr`, with a hint for the usual reasons it fails.
+pub fn bind_listener(addr: SocketAddr) -> Result<UdpSocket, String> {
+    let hint = |e: io::Error| {
+        let hint = match e.kind() {
+            io::ErrorKind::AddrInUse => " (another program, or another driver, has the port; on Linux \"::\" covers IPv4 too)",
+            io::ErrorKind::AddrNotAvailable => " (no interface of this machine has that address; try 0.0.0.0 or ::)",
+            io::ErrorKind::PermissionDenied => " (ports below 1024 need root)",
+            _ => "",
+        };
+        format!("Can't listen for OSC on {addr}: {e}{hint}")
+    };
+    let socket = UdpSocket::bind(addr).map_err(hint)?;
+    socket.set_nonblocking(true).map_err(hint)?;
+    Ok(socket)
+}
+
+/// Writes a timestamped log of the OSC dialog, one packet per line:
+/// `<seconds> <in|out> <address> <hex bytes>`. The address is informational only.
+pub struct OscRecorder {
+    start: Instant,
+    out: RefCell<BufWriter<File>>,
+}
+
+impl OscRecorder {
+    pub fn create(path: &str) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", SESSION_HEADER)?;
+        Ok(Self {
+            start: Instant::now(),
+            out: RefCell::new(out),
+        })
+    }
+
+    pub fn record(&self, direction: OscDirection, packet: &[u8]) {
+        let elapsed = self.start.elapsed();
+        let addr = match rosc::decoder::decode_udp(packet) {
+            Ok((_, OscPacket::Message(msg))) => msg.addr,
+            Ok((_, OscPacket::Bundle(_))) => "#bundle".to_string(),
+            Err(_) => "?".to_string(),
+        };
+        let hex: String = packet.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let mut out = self.out.borrow_mut();
+        let res = writeln!(out, "{}.{:06} {} {} {}", elapsed.as_secs(), elapsed.subsec_micros(), direction.as_str(), addr, hex)
+            .and_then(|_| out.flush());
+        if let Err(e) = res {
+            eprintln!("OSC recorder error: {}", e);
+        }
+    }
+}
+
+/// Replays the incoming half of a recorded session with the original timing.
+pub struct OscReplay {
+    entries: Vec<(Duration, Vec<u8>)>,
+    start: Option<Instant>,
+    cursor: usize,
+}
+
+impl OscReplay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Can't open {path}: {e}"))?;
+        let mut entries = Vec::new();
+
+        for (line_no, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(format!("{path}:{}: expected 4 fields", line_no + 1));
+            }
+            if fields[1] != OscDirection::In.as_str() {
+                continue;
+            }
+
+            // Negative, NaN and out-of-range offsets are as bad as unparsable ones.
+            let offset = fields[0]
+                .parse()
+                .ok()
+                .and_then(|secs: f64| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| format!("{path}:{}: bad timestamp", line_no + 1))?;
+            let packet = decode_hex(fields[3])
+                .ok_or_else(|| format!("{path}:{}: bad packet data", line_no + 1))?;
+            entries.push((offset, packet));
+        }
+
+        entries.sort_by_key(|(offset, _)| *offset);
+        Ok(Self { entries, start: None, cursor: 0 })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.entries.len()
+    }
+
+    /// Returns every packet whose recorded offset has elapsed since the first poll.
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        let start = *self.start.get_or_insert(now);
+        let elapsed = now.duration_since(start);
+
+        let mut due = Vec::new();
+        while let Some((offset, packet)) = self.entries.get(self.cursor) {
+            if *offset > elapsed {
+                break;
+            }
+            due.push(packet.clone());
+            self.cursor += 1;
+        }
+        due
+    }
+}
+
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    // Slicing by bytes would split a multibyte character, and a sign would parse.
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,111 @@
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use rosc::{OscMessage, OscPacket, OscType};
+use crate::osc::PROTOCOL_VERSION;
+
+/// How long each step waits for the driver to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// One color per pad, so a frame that arrives is easy to recognize
+const TEST_COLORS: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+/// `driver osc-test`: talks to a running driver the way an OSC client would and reports
+/// each step, so the network and firewall can be checked without another OSC tool. It
+/// subscribes (proving replies get back), checks the protocol version, writes to the
+/// screen, lights the pads with one light frame, asks for the namespace and the loop, and
+/// unsubscribes.
+pub fn run(driver: &str) -> Result<(), String> {
+    let addr = driver
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut a| a.next())
+        .ok_or_else(|| format!("can't resolve {driver}"))?;
+    let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).map_err(|e| e.to_string())?;
+    socket.set_read_timeout(Some(Duration::from_millis(100))).map_err(|e| e.to_string())?;
+    println!("Testing the driver at {} from {}", addr, socket.local_addr().map_err(|e| e.to_string())?);
+
+    send(&socket, addr, "/maschine/subscribe", vec![])?;
+    match wait_for(&socket, "/maschine/subscribed") {
+        Some(reply) => match reply.args.first() {
+            Some(OscType::Int(ttl)) => println!("ok   subscribed, renew within {} s", ttl),
+            _ => println!("ok   subscribed"),
+        },
+        None => {
+            return Err(format!(
+                "no reply to /maschine/subscribe within {}s: is the driver running, listening on {} and reachable (firewall)?",
+                REPLY_TIMEOUT.as_secs(),
+                addr
+            ));
+        }
+    }
+
+    send(&socket, addr, "/maschine/handshake", vec![OscType::Int(PROTOCOL_VERSION)])?;
+    match wait_for(&socket, "/maschine/handshake/reply").map(|reply| reply.args) {
+        Some(args) => match (args.first(), args.get(1)) {
+            (Some(OscType::Int(PROTOCOL_VERSION)), Some(OscType::String(version))) => {
+                println!("ok   driver {} speaks protocol {}", version, PROTOCOL_VERSION)
+            }
+            (Some(OscType::Int(protocol)), _) => println!("WARN the driver speaks protocol {}, this tool {}", protocol, PROTOCOL_VERSION),
+            _ => println!("WARN unexpected handshake reply"),
+        },
+        None => println!("WARN no handshake reply; the driver predates /maschine/handshake"),
+    }
+
+    send(&socket, addr, "/maschine/screen/text", vec![OscType::String("OSC TEST".to_string())])?;
+    println!("ok   sent screen text; the screen should read OSC TEST");
+
+    let frame: Vec<u8> = TEST_COLORS.iter().flat_map(|color| [*color, 2]).collect();
+    send(&socket, addr, "/maschine/lights/frame", vec![OscType::Blob(frame)])?;
+    println!("ok   sent a light frame; every pad should show a different color until the mode redraws");
+
+    let mut failed = false;
+    for (query, reply) in [("/maschine/namespace", "/maschine/namespace/data"), ("/maschine/loop/dump", "/maschine/loop/data")] {
+        send(&socket, addr, query, vec![])?;
+        match wait_for(&socket, reply) {
+            Some(msg) => println!("ok   {} answered with {} ({} bytes)", query, reply, payload_len(&msg)),
+            None => {
+                println!("FAIL {} got no {} reply", query, reply);
+                failed = true;
+            }
+        }
+    }
+
+    send(&socket, addr, "/maschine/unsubscribe", vec![])?;
+    println!("ok   unsubscribed");
+    if failed {
+        return Err("some queries went unanswered".to_string());
+    }
+    println!("All OSC checks passed.");
+    Ok(())
+}
+
+fn send(socket: &UdpSocket, to: SocketAddr, addr: &str, args: Vec<OscType>) -> Result<(), String> {
+    let packet = OscPacket::Message(OscMessage { addr: addr.to_string(), args });
+    let buf = rosc::encoder::encode(&packet).map_err(|e| e.to_string())?;
+    socket.send_to(&buf, to).map_err(|e| format!("can't send {addr}: {e}"))?;
+    Ok(())
+}
+
+/// Reads until a message at `addr` arrives; everything else the subscription brings is skipped.
+fn wait_for(socket: &UdpSocket, addr: &str) -> Option<OscMessage> {
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    let mut buf = [0u8; 65536];
+    while Instant::now() < deadline {
+        let Ok((size, _)) = socket.recv_from(&mut buf) else {
+            continue;
+        };
+        if let Ok((_, OscPacket::Message(msg))) = rosc::decoder::decode_udp(&buf[..size])
+            && msg.addr == addr
+        {
+            return Some(msg);
+        }
+    }
+    None
+}
+
+fn payload_len(msg: &OscMessage) -> usize {
+    match msg.args.first() {
+        Some(OscType::String(s)) => s.len(),
+        Some(OscType::Blob(b)) => b.len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use rosc::{OscMessage, OscPacket, OscType};
+use serde_json::{json, Map, Value};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::error::{Error, ProtocolError};
+use tungstenite::{Message, WebSocket};
+use crate::namespace::{endpoints, OscEndpoint};
+use crate::settings::Settings;
+
+/// Requests are tiny; a client that takes longer than this to send one is let go.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a WebSocket waits for a command before sending the values that changed
+const LISTEN_POLL: Duration = Duration::from_millis(20);
+/// Connections served at once; past that, new ones are closed straight away.
+const MAX_CONNECTIONS: usize = 32;
+/// A WebSocket gets a ping this often, so one whose client went away without closing it
+/// is noticed
+const PING_EVERY: Duration = Duration::from_secs(10);
+/// A WebSocket that hasn't answered for this long is closed, freeing its connection.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest request head read
+const MAX_HEAD: usize = 8192;
+// OSCQuery ACCESS bits
+const ACCESS_READ: u64 = 1;
+const ACCESS_WRITE: u64 = 2;
+
+/// What connections are answered from, kept off the main loop
+struct State {
+    host_info: Value,
+    /// The address space, without values
+    tree: Value,
+    /// Latest value sent or received at each address
+    values: HashMap<String, OscType>,
+    /// WebSocket connections, handed every message to pick the ones they listen to from
+    listeners: Vec<Sender<OscMessage>>,
+}
+
+/// OSCQuery over HTTP and WebSocket: the address space from `/maschine/namespace` as the
+/// JSON tree the spec describes, with types, ranges and the latest values, so tools like
+/// Chataigne or OSCQueryBrowser pointed at the port can browse the controls. It isn't
+/// advertised over mDNS. A WebSocket on the same port
+/// takes `LISTEN` and `IGNORE` commands and gets the messages at the addresses listened
+/// to as they're sent or received. It all runs on threads of its own, one a connection.
+pub struct OscQueryServer {
+    state: Arc<Mutex<State>>,
+}
+
+impl OscQueryServer {
+    /// Starts serving, following the values through `messages` (see `OscIo::watch`).
+    pub fn bind(port: u16, settings: &Settings, messages: Receiver<OscMessage>) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let state = Arc::new(Mutex::new(State {
+            host_info: host_info(settings),
+            tree: tree(settings),
+            values: HashMap::new(),
+            listeners: Vec::new(),
+        }));
+        let shared = state.clone();
+        thread::spawn(move || follow(&shared, messages));
+        let shared = state.clone();
+        thread::spawn(move || accept(listener, shared));
+        Ok(Self { state })
+    }
+
+    /// Takes the address space from a reloaded config.
+    pub fn settings_changed(&self, settings: &Settings) {
+        let mut state = self.state.lock().unwrap();
+        state.host_info = host_info(settings);
+        state.tree = tree(settings);
+    }
+}
+
+fn follow(state: &Mutex<State>, messages: Receiver<OscMessage>) {
+    for msg in messages {
+        let mut state = state.lock().unwrap();
+        if let Some(arg) = msg.args.first() {
+            state.values.insert(msg.addr.clone(), arg.clone());
+        }
+        state.listeners.retain(|listener| listener.send(msg.clone()).is_ok());
+    }
+}
+
+fn accept(listener: TcpListener, state: Arc<Mutex<State>>) {
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming().flatten() {
+        if open.load(Ordering::Relaxed) >= MAX_CONNECTIONS {
+            continue;
+        }
+        open.fetch_add(1, Ordering::Relaxed);
+        let (state, open) = (state.clone(), open.clone());
+        thread::spawn(move || {
+            if let Err(e) = serve(stream, &state) {
+                eprintln!("OSCQuery error: {}", e);
+            }
+            open.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+fn serve(mut stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let head = read_head(&mut stream)?;
+    let target = match head.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", target, ..] => target,
+        _ => return respond(&mut stream, "405 Method Not Allowed", None),
+    };
+    if let Some(key) = header(&head, "Sec-WebSocket-Key") {
+        return listen(stream, key, state);
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let body = {
+        let state = state.lock().unwrap();
+        if query == "HOST_INFO" {
+            Some(state.host_info.clone())
+        } else {
+            find(&state.tree, path).and_then(|node| {
+                let mut node = node.clone();
+                add_values(&mut node, &state.values);
+                match query {
+                    "" => Some(node),
+                    attribute => node.get(attribute).map(|v| json!({ attribute: v })),
+                }
+            })
+        }
+    };
+    match body {
+        Some(body) => respond(&mut stream, "200 OK", Some(body)),
+        None => respond(&mut stream, "404 Not Found", None),
+    }
+}
+
+/// Everything up to the blank line ending the request's headers
+fn read_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        let size = stream.read(&mut buf)?;
+        if size == 0 || head.len() + size > MAX_HEAD {
+            return Err(io::Error::new(ErrorKind::InvalidData, "incomplete request"));
+        }
+        head.extend_from_slice(&buf[..size]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: Option<Value>) -> io::Result<()> {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Upgrades the connection and sends it, as OSC packets, the messages at the addresses
+/// it listens to, until it closes or stops answering pings.
+fn listen(mut stream: TcpStream, key: &str, state: &Mutex<State>) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    )?;
+    stream.set_read_timeout(Some(LISTEN_POLL))?;
+    let (sender, messages) = mpsc::channel();
+    state.lock().unwrap().listeners.push(sender);
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let mut paths = HashSet::new();
+    let (mut heard, mut pinged) = (Instant::now(), Instant::now());
+    loop {
+        match socket.read() {
+            Ok(message) => {
+                heard = Instant::now();
+                if let Message::Text(text) = message {
+                    command(text.as_str(), &mut paths);
+                }
+            }
+            Err(Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(Error::ConnectionClosed | Error::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => return Ok(()),
+            Err(e) => return Err(io::Error::other(e)),
+        }
+        if heard.elapsed() >= IDLE_TIMEOUT {
+            let _ = socket.close(None);
+            return Ok(());
+        }
+        if pinged.elapsed() >= PING_EVERY {
+            socket.send(Message::Ping(Default::default())).map_err(io::Error::other)?;
+            pinged = Instant::now();
+        }
+        for msg in messages.try_iter().filter(|msg| paths.contains(&msg.addr)) {
+            let packet = rosc::encoder::encode(&OscPacket::Message(msg)).map_err(io::Error::other)?;
+            socket.send(Message::binary(packet)).map_err(io::Error::other)?;
+        }
+    }
+}
+
+/// `{"COMMAND": "LISTEN" | "IGNORE", "DATA": "<address>"}`; anything else is left alone.
+fn command(text: &str, paths: &mut HashSet<String>) {
+    let Ok(command) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    let Some(path) = command.get("DATA").and_then(Value::as_str) else {
+        return;
+    };
+    match command.get("COMMAND").and_then(Value::as_str) {
+        Some("LISTEN") => {
+            paths.insert(path.to_string());
+        }
+        Some("IGNORE") => {
+            paths.remove(path);
+        }
+        _ => {}
+    }
+}
+
+fn host_info(settings: &Settings) -> Value {
+    json!({
+        "NAME": settings.client_name,
+        "OSC_PORT": settings.osc_listen_port,
+        "OSC_TRANSPORT": "UDP",
+        "EXTENSIONS": {
+            "ACCESS": true,
+            "VALUE": true,
+            "RANGE": true,
+            "DESCRIPTION": true,
+            "TYPE": true,
+            "LISTEN": true,
+        },
+    })
+}
+
+/// The whole address space as nested OSCQuery nodes, placeholders like `<pad>` spelled out
+/// into an address each
+fn tree(settings: &Settings) -> Value {
+    let mut root = json!({ "FULL_PATH": "/", "CONTENTS": {} });
+    for endpoint in endpoints(settings) {
+        for address in endpoint.addresses() {
+            add_node(&mut root, &address, &endpoint);
+        }
+    }
+    root
+}
+
+/// Adds `address` for `endpoint` to the tree, with the nodes above it.
+fn add_node(root: &mut Value, address: &str, endpoint: &OscEndpoint) {
+    let mut node = root;
+    let mut full_path = String::new();
+    for part in address.split('/').filter(|p| !p.is_empty()) {
+        full_path = format!("{}/{}", full_path, part);
+        let contents = node
+            .as_object_mut()
+            .unwrap()
+            .entry("CONTENTS")
+            .or_insert_with(|| Value::Object(Map::new()));
+        node = contents
+            .as_object_mut()
+            .unwrap()
+            .entry(part)
+            .or_insert_with(|| json!({ "FULL_PATH": full_path }));
+    }
+    let leaf = node.as_object_mut().unwrap();
+    let access = leaf.get("ACCESS").and_then(Value::as_u64).unwrap_or(0)
+        | if endpoint.direction == "out" { ACCESS_READ } else { ACCESS_WRITE };
+    leaf.insert("ACCESS".to_string(), json!(access));
+    // Where a message takes alternative forms, the first one is described.
+    leaf.insert("TYPE".to_string(), json!(endpoint.types.split('|').next().unwrap_or("")));
+    leaf.entry("DESCRIPTION").or_insert_with(|| json!(endpoint.description));
+    if let Some([min, max]) = endpoint.range {
+        leaf.insert("RANGE".to_string(), json!([{ "MIN": min, "MAX": max }]));
+    }
+}
+
+/// Fills in the latest value of every address under `node`.
+fn add_values(node: &mut Value, values: &HashMap<String, OscType>) {
+    let Some(node) = node.as_object_mut() else {
+        return;
+    };
+    if node.contains_key("ACCESS")
+        && let Some(value) = node.get("FULL_PATH").and_then(Value::as_str).and_then(|path| values.get(path)).cloned().and_then(json_value)
+    {
+        node.insert("VALUE".to_string(), json!([value]));
+    }
+    if let Some(Value::Object(contents)) = node.get_mut("CONTENTS") {
+        contents.values_mut().for_each(|child| add_values(child, values));
+    }
+}
+
+fn json_value(arg: OscType) -> Option<Value> {
+    match arg {
+        OscType::Int(i) => Some(json!(i)),
+        OscType::Float(f) => Some(json!(f)),
+        OscType::String(s) => Some(json!(s)),
+        OscType::Bool(b) => Some(json!(b)),
+        _ => None,
+    }
+}
+
+fn find<'a>(tree: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('/').filter(|p| !p.is_empty()).try_fold(tree, |node, part| node.get("CONTENTS")?.get(part))
+}
@@ -0,0 +1,90 @@
+use maschine_library::controls::Buttons;
+use maschine_library::font::Font;
+use maschine_library::screen::Screen;
+use crate::input::HardwareEvent;
+use crate::modes::custom_midi::button_from_name;
+use crate::monitor::note_name;
+use crate::settings::Settings;
+use crate::snapshots::{Snapshots, SLOTS};
+
+/// Characters per pad label: four cells of 32 pixels across the screen
+const LABEL_CHARS: usize = 4;
+
+/// While a button with an `[overlays.<button>]` entry is held, the screen shows what each
+/// pad does, laid out like the pads with every other cell inverted so the labels read
+/// apart. Unless configured otherwise, Maschine shows the modes its pads jump to, Group
+/// the snapshot slots, Notes the `pad_names` and the `[pad_banks]` button the lowest note
+/// of each bank. The mode's screen comes back on release.
+pub struct ScreenOverlay {
+    held: Option<Buttons>,
+}
+
+impl ScreenOverlay {
+    pub fn new() -> Self {
+        Self { held: None }
+    }
+
+    /// Returns true when the overlay came up or went away.
+    pub fn handle(&mut self, event: &HardwareEvent, settings: &Settings) -> bool {
+        let HardwareEvent::Button { index, pressed } = *event else {
+            return false;
+        };
+        match self.held {
+            Some(held) if held == index && !pressed => {
+                self.held = None;
+                true
+            }
+            None if pressed && Self::has_overlay(index, settings) => {
+                self.held = Some(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn has_overlay(button: Buttons, settings: &Settings) -> bool {
+        button == Buttons::Maschine
+            || (button == Buttons::Group && settings.snapshots.enabled)
+            || (button == Buttons::Notes && !settings.pad_names.is_empty())
+            || Self::is_bank_button(button, settings)
+            || Self::configured(button, settings).is_some()
+    }
+
+    fn is_bank_button(button: Buttons, settings: &Settings) -> bool {
+        settings.pad_banks.enabled && button_from_name(&settings.pad_banks.button) == Some(button)
+    }
+
+    fn configured(button: Buttons, settings: &Settings) -> Option<&Vec<String>> {
+        settings.overlays.iter().find(|(name, _)| button_from_name(name) == Some(button)).map(|(_, o)| &o.pads)
+    }
+
+    pub fn compose(&self, frame: Screen, settings: &Settings, snapshots: &Snapshots, modes: &[&str]) -> Screen {
+        let Some(button) = self.held else {
+            return frame;
+        };
+        let labels: Vec<String> = match Self::configured(button, settings) {
+            Some(pads) => pads.clone(),
+            None if button == Buttons::Maschine => modes.iter().map(|name| name.to_string()).collect(),
+            None if button == Buttons::Notes => settings.pad_names.clone(),
+            None if Self::is_bank_button(button, settings) => (0..settings.bank_count())
+                .map(|bank| note_name((0..16).map(|pad| settings.pad_note(pad, bank).1).min().unwrap_or_default()))
+                .collect(),
+            None => (0..SLOTS).map(|i| if snapshots.is_stored(i) { format!("SET{}", i + 1) } else { "---".to_string() }).collect(),
+        };
+        let mut frame = Screen::new();
+        for pad in 0..16 {
+            let (row, col) = (pad / 4 * 8, pad % 4 * 32);
+            let label: String = labels.get(pad).map_or("", String::as_str).chars().take(LABEL_CHARS).collect();
+            Font::write_string(&mut frame, row, col, &label.to_uppercase(), 1);
+            if (pad / 4 + pad % 4) % 2 == 1 {
+                for i in row..row + 8 {
+                    for j in col..col + 32 {
+                        let on = frame.get(i, j);
+                        frame.set(i, j, !on);
+                    }
+                }
+            }
+        }
+        frame
+    }
+}
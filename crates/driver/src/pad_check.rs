@@ -0,0 +1,182 @@
+use hidapi::{HidDevice, HidResult};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use crate::input::{parse_hid_report, HardwareEvent};
+use crate::modes::ButtonEdges;
+use crate::settings::RemapSettings;
+
+const READ_TIMEOUT_MS: i32 = 20;
+
+/// The stand-ins the pad check settled on, by pad number (1-16).
+#[derive(Debug, Default)]
+pub(crate) struct PadWorkaround {
+    /// Working pad to the dead pad it now plays
+    pub pads: Vec<(u8, u8)>,
+    /// Button name to the dead pad it now plays
+    pub buttons: Vec<(String, u8)>,
+}
+
+impl PadWorkaround {
+    pub fn is_empty(&self) -> bool {
+        self.pads.is_empty() && self.buttons.is_empty()
+    }
+
+    pub fn apply(&self, remap: &mut RemapSettings) {
+        for (from, dead) in &self.pads {
+            remap.pads.insert(from.to_string(), *dead);
+        }
+        for (button, dead) in &self.buttons {
+            remap.button_pads.insert(button.clone(), *dead);
+        }
+    }
+
+    /// The workaround as a `[remap]` table.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("[remap]\n");
+        if !self.pads.is_empty() {
+            let pads: Vec<String> = self.pads.iter().map(|(from, dead)| format!("\"{from}\" = {dead}")).collect();
+            let _ = writeln!(out, "pads = {{ {} }}", pads.join(", "));
+        }
+        if !self.buttons.is_empty() {
+            let buttons: Vec<String> = self.buttons.iter().map(|(name, dead)| format!("{name} = {dead}")).collect();
+            let _ = writeln!(out, "button_pads = {{ {} }}", buttons.join(", "));
+        }
+        out
+    }
+
+}
+
+/// Appends a table written by one of the startup assistants to the config file, unless
+/// it already has that table (TOML won't take a second one); otherwise prints the lines
+/// to merge by hand.
+pub(crate) fn save_table(config: Option<&str>, table: &str, text: &str, flag: &str) {
+    let Some(path) = config else {
+        println!("Add this to your config to keep it:\n{}", text);
+        return;
+    };
+    match fs::read_to_string(path) {
+        Ok(current) if current.lines().any(|l| l.trim_start().starts_with(&format!("[{table}"))) => {
+            println!("{} already has a [{}] table; merge these lines into it:\n{}", path, table, text);
+        }
+        Ok(_) => match append(path, text, flag) {
+            Ok(()) => println!("Saved [{}] to {}", table, path),
+            Err(e) => eprintln!("Can't save [{}] to {}: {}\n{}", table, path, e, text),
+        },
+        Err(e) => eprintln!("Can't read {}: {}; add this to it:\n{}", path, e, text),
+    }
+}
+
+fn append(path: &str, text: &str, flag: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    write!(file, "\n# Written by {}\n{}", flag, text)
+}
+
+/// Interactive check for units with a failed pad: every pad has to be hit once, and each
+/// one that never answers can be handed to another pad or a button.
+pub(crate) fn pad_check(device: &HidDevice, screen: &mut Screen, lights: &mut Lights) -> HidResult<PadWorkaround> {
+    let mut buttons = ButtonEdges::new();
+    let mut hit = [false; 16];
+    let mut workaround = PadWorkaround::default();
+
+    lights.reset();
+    for i in 0..16 {
+        lights.set_pad(i, PadColors::Red, Brightness::Dim);
+    }
+    lights.set_button(Buttons::Play, Brightness::Normal);
+    lights.write(device)?;
+    prompt(device, screen, &["PAD CHECK", "HIT EVERY PAD", "PLAY: DONE"])?;
+
+    'hits: loop {
+        for input in read_inputs(device, &mut buttons)? {
+            match input {
+                Input::Pad(index) if !hit[index] => {
+                    hit[index] = true;
+                    lights.set_pad(index, PadColors::Green, Brightness::Bright);
+                    lights.write(device)?;
+                }
+                Input::Button(Buttons::Play) => break 'hits,
+                _ => {}
+            }
+        }
+    }
+
+    let dead: Vec<usize> = (0..16).filter(|i| !hit[*i]).collect();
+    println!("Pad check: {} dead pad(s) {:?}", dead.len(), dead.iter().map(|i| i + 1).collect::<Vec<_>>());
+    // Pads and buttons already standing in for a dead pad can't take a second one.
+    let mut taken = [false; 16];
+    for &pad in &dead {
+        lights.set_pad(pad, PadColors::Red, Brightness::Bright);
+        lights.set_button(Buttons::Play, Brightness::Off);
+        lights.set_button(Buttons::Stop, Brightness::Normal);
+        lights.write(device)?;
+        prompt(device, screen, &[&format!("PAD {} IS DEAD", pad + 1), "HIT A PAD OR BTN", "STOP: SKIP"])?;
+
+        let dead_pad = pad as u8 + 1;
+        'choice: loop {
+            for input in read_inputs(device, &mut buttons)? {
+                match input {
+                    Input::Pad(index) if hit[index] && !taken[index] => {
+                        taken[index] = true;
+                        workaround.pads.push((index as u8 + 1, dead_pad));
+                        lights.set_pad(index, PadColors::Blue, Brightness::Bright);
+                        break 'choice;
+                    }
+                    Input::Button(Buttons::Stop) => break 'choice,
+                    Input::Button(button) => {
+                        let name = format!("{:?}", button);
+                        if !workaround.buttons.iter().any(|(b, _)| *b == name) {
+                            workaround.buttons.push((name, dead_pad));
+                            break 'choice;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        lights.set_pad(pad, PadColors::Red, Brightness::Dim);
+    }
+
+    lights.reset();
+    lights.write(device)?;
+    screen.reset();
+    screen.write(device)?;
+    Ok(workaround)
+}
+
+pub(crate) fn prompt(device: &HidDevice, screen: &mut Screen, lines: &[&str]) -> HidResult<()> {
+    screen.reset();
+    for (i, line) in lines.iter().enumerate() {
+        Font::write_string(screen, i * 11, 0, line, 1);
+    }
+    screen.write(device)
+}
+
+enum Input {
+    Pad(usize),
+    Button(Buttons),
+}
+
+/// Pad hits and button presses from the reports waiting, with the repeated button
+/// states reduced to the moment each one goes down.
+fn read_inputs(device: &HidDevice, buttons: &mut ButtonEdges) -> HidResult<Vec<Input>> {
+    let mut buf = [0u8; 64];
+    let size = device.read_timeout(&mut buf, READ_TIMEOUT_MS)?;
+    let mut inputs = Vec::new();
+    for event in parse_hid_report(&buf[..size]) {
+        match event {
+            HardwareEvent::Pad { index, event_type: PadEventType::NoteOn | PadEventType::PressOn, value } if value > 0 => {
+                inputs.push(Input::Pad(index));
+            }
+            HardwareEvent::Button { index, pressed } if buttons.pressed(index, pressed) => {
+                inputs.push(Input::Button(index));
+            }
+            _ => {}
+        }
+    }
+    Ok(inputs)
+}
@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+use crate::settings::PollingSettings;
+
+/// Decides how long the main loop may block waiting for input. While anything is
+/// happening it polls quickly; once the controller has been idle for a while the
+/// wait grows so an untouched driver costs next to no CPU.
+pub struct AdaptivePoll {
+    last_activity: Option<Instant>,
+}
+
+impl AdaptivePoll {
+    pub fn new() -> Self {
+        Self { last_activity: None }
+    }
+
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+    }
+
+    /// How long to block on the next HID read. `animating` keeps the fast rate for
+    /// anything that has to redraw on its own (blinks, sequencer playback, replays).
+    pub fn wait(&self, now: Instant, animating: bool, settings: &PollingSettings) -> Duration {
+        let active = Duration::from_millis(settings.active_interval_ms);
+        if animating {
+            return active;
+        }
+        match self.last_activity {
+            Some(t) if now.duration_since(t) < Duration::from_millis(settings.idle_after_ms) => active,
+            _ => Duration::from_millis(settings.idle_interval_ms),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use maschine_library::controls::PadEventType;
+use maschine_library::lights::{Brightness, Lights};
+use crate::input::HardwareEvent;
+
+const SLIDER_LEDS: usize = 25;
+/// Pad values are 12 bits.
+const MAX_PRESSURE: u32 = 4096;
+
+/// Turns the slider LEDs into a meter of how hard the last struck pad is pressed, to see
+/// aftertouch while shaping a sound.
+pub struct PressureMeter {
+    pad: Option<usize>,
+    pressure: u16,
+}
+
+impl PressureMeter {
+    pub fn new() -> Self {
+        Self { pad: None, pressure: 0 }
+    }
+
+    /// Follows pad reports; returns true when the meter moved.
+    pub fn handle(&mut self, event: &HardwareEvent) -> bool {
+        let HardwareEvent::Pad { index, event_type, value } = *event else {
+            return false;
+        };
+        let pressure = match event_type {
+            PadEventType::NoteOn | PadEventType::PressOn if value > 0 => {
+                self.pad = Some(index);
+                value
+            }
+            PadEventType::NoteOff | PadEventType::PressOff => 0,
+            _ => value,
+        };
+        if self.pad != Some(index) || self.pressure == pressure {
+            return false;
+        }
+        let before = self.level();
+        self.pressure = pressure;
+        self.level() != before
+    }
+
+    fn level(&self) -> usize {
+        (self.pressure as u32 * SLIDER_LEDS as u32).div_ceil(MAX_PRESSURE) as usize
+    }
+
+    /// Lit from the bottom, the top of the column brightest
+    pub fn compose_lights(&self, lights: &mut Lights) {
+        let level = self.level();
+        for i in 0..SLIDER_LEDS {
+            let b = if i + 1 == level {
+                Brightness::Bright
+            } else if i < level {
+                Brightness::Dim
+            } else {
+                Brightness::Off
+            };
+            lights.set_slider(i, b);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::mem::discriminant;
+use std::time::{Duration, Instant};
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+use crate::input::{HardwareEvent, HostEvent};
+use crate::settings::{Quantize, Settings};
+use crate::transport::Meter;
+
+const BLINK: Duration = Duration::from_millis(125);
+
+enum Action {
+    /// A press, and its release if the button was let go before the press landed
+    Button { button: Buttons, released: bool },
+    /// A scene change or snapshot recall
+    Host(HostEvent),
+}
+
+struct Pending {
+    action: Action,
+    /// Sixteenth the clock was on when the action came in
+    from: u32,
+    to: Quantize,
+    since: Instant,
+}
+
+/// Holds back presses of buttons with a `quantize` setting, scene changes with
+/// `[transport] scene_quantize` and snapshot recalls with `[snapshots] quantize`, until
+/// the next beat or bar of the running clock (the host's, or the practice metronome).
+/// A waiting button blinks. Without a clock, or when it stops, everything goes through
+/// at once.
+pub struct InputQuantizer {
+    pending: Vec<Pending>,
+    /// Buttons whose press went through and are still held; their repeated reports pass.
+    through: Vec<Buttons>,
+    blink_on: bool,
+}
+
+impl InputQuantizer {
+    pub fn new() -> Self {
+        Self { pending: Vec::new(), through: Vec::new(), blink_on: false }
+    }
+
+    /// Returns true when the event was held back for later.
+    pub fn hold(&mut self, event: &HardwareEvent, clock: Option<u32>, settings: &Settings, now: Instant) -> bool {
+        let HardwareEvent::Button { index: button, pressed } = *event else {
+            return false;
+        };
+        let waiting = self.pending.iter_mut().find(|p| matches!(p.action, Action::Button { button: b, .. } if b == button));
+        if let Some(Pending { action: Action::Button { released, .. }, .. }) = waiting {
+            *released |= !pressed;
+            return true;
+        }
+        if !pressed {
+            self.through.retain(|b| *b != button);
+            return false;
+        }
+        let quantize = settings.button_configs.get(&format!("{:?}", button)).and_then(|c| c.quantize);
+        match (quantize, clock) {
+            (Some(to), Some(from)) if !self.through.contains(&button) => {
+                self.pending.push(Pending { action: Action::Button { button, released: false }, from, to, since: now });
+                true
+            }
+            _ => {
+                if !self.through.contains(&button) {
+                    self.through.push(button);
+                }
+                false
+            }
+        }
+    }
+
+    /// Returns true when the host event was held back; a newer one of the same kind
+    /// replaces it.
+    pub fn hold_host(&mut self, event: &HostEvent, to: Option<Quantize>, clock: Option<u32>, now: Instant) -> bool {
+        let (Some(to), Some(from)) = (to, clock) else {
+            return false;
+        };
+        self.pending.retain(|p| !matches!(&p.action, Action::Host(e) if discriminant(e) == discriminant(event)));
+        self.pending.push(Pending { action: Action::Host(event.clone()), from, to, since: now });
+        true
+    }
+
+    /// Takes out what is due at `clock`: button events to hand to the mode, in order,
+    /// and host events.
+    pub fn due(&mut self, clock: Option<u32>, meter: Meter) -> (Vec<HardwareEvent>, Vec<HostEvent>) {
+        let mut events = Vec::new();
+        let mut host = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            let p = &self.pending[i];
+            let unit = match p.to {
+                Quantize::Beat => meter.beat_sixteenths(),
+                Quantize::Bar => meter.bar_sixteenths(),
+            };
+            if clock.is_some_and(|now| now / unit == p.from / unit) {
+                i += 1;
+                continue;
+            }
+            match self.pending.remove(i).action {
+                Action::Button { button, released } => {
+                    events.push(HardwareEvent::Button { index: button, pressed: true });
+                    if released {
+                        events.push(HardwareEvent::Button { index: button, pressed: false });
+                    } else {
+                        self.through.push(button);
+                    }
+                }
+                Action::Host(event) => host.push(event),
+            }
+        }
+        (events, host)
+    }
+
+    /// Moves the blink along; returns true when the lights changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let Some(first) = self.pending.iter().map(|p| p.since).min() else {
+            return std::mem::take(&mut self.blink_on);
+        };
+        let on = (now.duration_since(first).as_millis() / BLINK.as_millis()).is_multiple_of(2);
+        on != std::mem::replace(&mut self.blink_on, on)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn compose_lights(&self, lights: &mut Lights) {
+        for p in &self.pending {
+            if let Action::Button { button, .. } = p.action
+                && lights.button_has_light(button)
+            {
+                lights.set_button(button, if self.blink_on { Brightness::Bright } else { Brightness::Off });
+            }
+        }
+    }
+}
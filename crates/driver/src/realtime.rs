@@ -0,0 +1,57 @@
+use std::io;
+use crate::settings::RealtimeSettings;
+
+/// Requests realtime scheduling for the calling thread (the one running the MIDI loop)
+/// and optionally locks memory. Every step degrades gracefully: a denied request is
+/// logged with a hint and the driver keeps running with normal scheduling.
+pub(crate) fn apply(settings: &RealtimeSettings) {
+    if !settings.enabled {
+        return;
+    }
+
+    match set_fifo_priority(settings.priority) {
+        Ok(()) => println!("Realtime scheduling enabled (SCHED_FIFO, priority {}).", settings.priority),
+        Err(e) => {
+            eprintln!("Realtime scheduling denied: {}", e);
+            if e.raw_os_error() == Some(libc::EPERM) {
+                eprintln!(
+                    "  Grant an rtprio limit (e.g. add your user to the 'audio' group with \
+                     '@audio - rtprio 95' in /etc/security/limits.conf) or run under rtkit."
+                );
+            }
+            match raise_niceness() {
+                Ok(()) => println!("Falling back to a raised process priority (nice -10)."),
+                Err(_) => println!("Continuing with normal scheduling."),
+            }
+        }
+    }
+
+    if settings.lock_memory {
+        // SAFETY: mlockall only takes flags and has no memory-safety preconditions.
+        if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } == 0 {
+            println!("Memory locked.");
+        } else {
+            eprintln!(
+                "Memory locking denied: {} (raise the memlock limit to allow it)",
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+fn set_fifo_priority(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param { sched_priority: priority };
+    // SAFETY: pthread_self() is always a valid handle for the calling thread and
+    // `param` outlives the call.
+    let res = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if res == 0 { Ok(()) } else { Err(io::Error::from_raw_os_error(res)) }
+}
+
+fn raise_niceness() -> io::Result<()> {
+    // SAFETY: plain syscall on the current process.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, -10) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use crate::modes::custom_midi::button_from_name;
+use crate::settings::{SceneConfig, Settings};
+
+/// Light scenes from the config, for lighting the controller to match song sections.
+/// While one is up it covers whatever the mode shows; recalling "off" (or an empty name)
+/// hands the lights back.
+pub struct LightScenes {
+    active: Option<String>,
+}
+
+impl LightScenes {
+    pub fn new() -> Self {
+        Self { active: None }
+    }
+
+    /// Switches scene; returns the crossfade to use, or None when nothing changed.
+    pub fn recall(&mut self, name: &str, fade_ms: Option<u64>, settings: &Settings) -> Option<Duration> {
+        let next = if name.is_empty() || name.eq_ignore_ascii_case("off") {
+            None
+        } else if settings.scenes.contains_key(name) {
+            Some(name.to_string())
+        } else {
+            eprintln!("No light scene called {}", name);
+            return None;
+        };
+        if next == self.active {
+            return None;
+        }
+        // Fading out of a scene takes as long as fading into it.
+        let scene_fade = next.as_ref().or(self.active.as_ref()).map_or(0, |n| settings.scenes[n].fade_ms);
+        self.active = next;
+        Some(Duration::from_millis(fade_ms.unwrap_or(scene_fade)))
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn recall_program(&mut self, program: u8, settings: &Settings) -> Option<Duration> {
+        let mut names: Vec<&String> = settings.scenes.iter().filter(|(_, s)| s.program == Some(program)).map(|(n, _)| n).collect();
+        names.sort();
+        let name = names.first()?.to_string();
+        self.recall(&name, None, settings)
+    }
+
+    pub fn compose(&self, lights: &mut Lights, settings: &Settings) {
+        if let Some(name) = &self.active {
+            *lights = scene_lights(&settings.scenes[name]);
+        }
+    }
+}
+
+fn scene_lights(scene: &SceneConfig) -> Lights {
+    let mut lights = Lights::new();
+    for (i, color) in scene.pads.iter().enumerate() {
+        let color = color.pad_color();
+        let b = if color == PadColors::Off { Brightness::Off } else { scene.brightness.brightness() };
+        lights.set_pad(i, color, b);
+    }
+    for (name, level) in &scene.buttons {
+        if let Some(button) = button_from_name(name) {
+            lights.set_button(button, level.brightness());
+        }
+    }
+    lights
+}
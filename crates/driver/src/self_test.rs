@@ -2,18 +2,48 @@ use hidapi::{HidDevice, HidResult};
 use maschine_library::font::Font;
 use maschine_library::lights::{Brightness, Lights, PadColors};
 use maschine_library::screen::Screen;
-use std::{thread, time};
+use std::{fs, thread, time};
+use crate::settings::{StartupAnimation, StartupSettings};
 
 pub(crate) fn self_test(
     device: &HidDevice,
     screen: &mut Screen,
     lights: &mut Lights,
+    startup: &StartupSettings,
 ) -> HidResult<()> {
-    Font::write_string(screen, 0, 0, "MASCHINE", 2);
+    draw_splash(screen, startup);
     screen.write(device)?;
-    thread::sleep(time::Duration::from_millis(1000));
+    thread::sleep(time::Duration::from_millis(startup.duration_ms));
 
+    match startup.animation {
+        StartupAnimation::Sweep => sweep(device, lights)?,
+        StartupAnimation::Rainbow => rainbow(device, lights)?,
+        StartupAnimation::None => {}
+    }
+    lights.reset();
+    lights.write(device)?;
+
+    screen.reset();
+    screen.write(device)?;
 
+    Ok(())
+}
+
+fn draw_splash(screen: &mut Screen, startup: &StartupSettings) {
+    if let Some(path) = &startup.image {
+        match load_pbm(path) {
+            Ok(image) => {
+                *screen = image;
+                return;
+            }
+            Err(e) => eprintln!("Can't load startup image: {}", e),
+        }
+    }
+    Font::write_string(screen, 0, 0, &startup.text, startup.text_scale);
+}
+
+/// Lights every control in turn through all brightness levels (the original self test).
+fn sweep(device: &HidDevice, lights: &mut Lights) -> HidResult<()> {
     for i in 0..39 {
         lights.set_button(num::FromPrimitive::from_u32(i).unwrap(), Brightness::Bright);
         lights.write(device)?;
@@ -21,10 +51,8 @@ pub(crate) fn self_test(
         lights.write(device)?;
         lights.set_button(num::FromPrimitive::from_u32(i).unwrap(), Brightness::Dim);
         lights.write(device)?;
-        // thread::sleep(time::Duration::from_millis(100));
     }
     for i in 0..16 {
-        // let color: PadColors = PadColors::Blue;
         let color: PadColors = num::FromPrimitive::from_usize(i + 2).unwrap();
         lights.set_pad(i, color, Brightness::Bright);
         lights.write(device)?;
@@ -34,7 +62,6 @@ pub(crate) fn self_test(
         let color: PadColors = num::FromPrimitive::from_usize(i + 1).unwrap();
         lights.set_pad(i, color, Brightness::Dim);
         lights.write(device)?;
-        // thread::sleep(time::Duration::from_millis(1000));
     }
     for i in 0..25 {
         lights.set_slider(i, Brightness::Bright);
@@ -43,13 +70,95 @@ pub(crate) fn self_test(
         lights.write(device)?;
         lights.set_slider(i, Brightness::Dim);
         lights.write(device)?;
-        // thread::sleep(time::Duration::from_millis(1000));
     }
-    lights.reset();
-    lights.write(device)?;
+    Ok(())
+}
 
-    screen.reset();
-    screen.write(device)?;
+/// The pad at `row`, `col` on the color wheel rolling diagonally across the pads, `frame`
+/// steps along.
+pub fn rainbow_color(row: usize, col: usize, frame: usize) -> PadColors {
+    num::FromPrimitive::from_usize((row + col + frame) % 16 + 1).unwrap()
+}
 
+/// Rolls the color wheel diagonally across the pads while the slider fills up.
+fn rainbow(device: &HidDevice, lights: &mut Lights) -> HidResult<()> {
+    for frame in 0..24 {
+        for i in 0..16 {
+            lights.set_pad(i, rainbow_color(i / 4, i % 4, frame), Brightness::Bright);
+        }
+        for i in 0..25 {
+            let b = if i <= frame { Brightness::Normal } else { Brightness::Off };
+            lights.set_slider(i, b);
+        }
+        lights.write(device)?;
+        thread::sleep(time::Duration::from_millis(40));
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Loads a 128x32 monochrome PBM (plain P1 or raw P4); black pixels are lit.
+fn load_pbm(path: &str) -> Result<Screen, String> {
+    let data = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+
+    // Header: magic, width, height, separated by whitespace, with '#' comments.
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < 3 {
+        while pos < data.len() && data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos < data.len() && data[pos] == b'#' {
+            while pos < data.len() && data[pos] != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(format!("{path}: truncated PBM header"));
+        }
+        fields.push(String::from_utf8_lossy(&data[start..pos]).to_string());
+    }
+    // Exactly one whitespace byte separates the header from raw data.
+    pos += 1;
+
+    let (width, height): (usize, usize) = match (fields[1].parse(), fields[2].parse()) {
+        (Ok(w), Ok(h)) => (w, h),
+        _ => return Err(format!("{path}: bad PBM size")),
+    };
+    if width != 128 || height != 32 {
+        return Err(format!("{path}: image must be 128x32 (found {width}x{height})"));
+    }
+
+    let mut screen = Screen::new();
+    match fields[0].as_str() {
+        "P1" => {
+            let bits: Vec<bool> = data[pos.min(data.len())..]
+                .iter()
+                .filter(|b| **b == b'0' || **b == b'1')
+                .map(|b| *b == b'1')
+                .collect();
+            if bits.len() < width * height {
+                return Err(format!("{path}: not enough pixel data"));
+            }
+            for (idx, bit) in bits.iter().take(width * height).enumerate() {
+                screen.set(idx / width, idx % width, *bit);
+            }
+        }
+        "P4" => {
+            let row_bytes = width.div_ceil(8);
+            let pixels = data.get(pos..pos + row_bytes * height).ok_or(format!("{path}: not enough pixel data"))?;
+            for i in 0..height {
+                for j in 0..width {
+                    let byte = pixels[i * row_bytes + j / 8];
+                    screen.set(i, j, byte & (0x80 >> (j % 8)) != 0);
+                }
+            }
+        }
+        other => return Err(format!("{path}: unsupported PBM format {other}")),
+    }
+    Ok(screen)
+}
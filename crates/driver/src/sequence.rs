@@ -0,0 +1,102 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Bytes per note in the blob form: start (u32 ms), duration (u16 ms), note, velocity.
+const BLOB_NOTE_SIZE: usize = 8;
+
+/// Encoding asked for in `/maschine/loop/dump`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopFormat {
+    Json,
+    Blob,
+}
+
+/// A loop as exchanged with the host over OSC, either as JSON text:
+///
+/// ```json
+/// {"length_ms": 2000, "notes": [{"start_ms": 0, "duration_ms": 120, "note": 36, "velocity": 100}]}
+/// ```
+///
+/// or as a blob: the loop length as a big-endian u32 in milliseconds, then 8 bytes per note
+/// (big-endian u32 start, u16 duration, note, velocity).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NoteSequence {
+    pub length_ms: u32,
+    pub notes: Vec<SeqNote>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SeqNote {
+    pub start_ms: u32,
+    pub duration_ms: u16,
+    pub note: u8,
+    #[serde(default = "default_velocity")]
+    pub velocity: u8,
+}
+
+fn default_velocity() -> u8 {
+    100
+}
+
+impl NoteSequence {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let sequence: Self = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        sequence.validate()?;
+        Ok(sequence)
+    }
+
+    pub fn from_blob(data: &[u8]) -> Result<Self, String> {
+        let (header, body) = data.split_first_chunk::<4>().ok_or("blob is too short")?;
+        if body.len() % BLOB_NOTE_SIZE != 0 {
+            return Err(format!("blob notes must be {BLOB_NOTE_SIZE} bytes each"));
+        }
+        let notes = body
+            .chunks_exact(BLOB_NOTE_SIZE)
+            .map(|n| SeqNote {
+                start_ms: u32::from_be_bytes([n[0], n[1], n[2], n[3]]),
+                duration_ms: u16::from_be_bytes([n[4], n[5]]),
+                note: n[6],
+                velocity: n[7],
+            })
+            .collect();
+        let sequence = Self { length_ms: u32::from_be_bytes(*header), notes };
+        sequence.validate()?;
+        Ok(sequence)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a note sequence always serializes")
+    }
+
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4 + self.notes.len() * BLOB_NOTE_SIZE);
+        data.extend_from_slice(&self.length_ms.to_be_bytes());
+        for note in &self.notes {
+            data.extend_from_slice(&note.start_ms.to_be_bytes());
+            data.extend_from_slice(&note.duration_ms.to_be_bytes());
+            data.push(note.note);
+            data.push(note.velocity);
+        }
+        data
+    }
+
+    pub fn length(&self) -> Duration {
+        Duration::from_millis(self.length_ms as u64)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.length_ms == 0 {
+            return Err("the loop needs a length".to_string());
+        }
+        for note in &self.notes {
+            // The note off needs at least a millisecond before the loop wraps.
+            if note.start_ms.saturating_add(1) >= self.length_ms {
+                return Err(format!("note at {}ms starts too late for a {}ms loop", note.start_ms, self.length_ms));
+            }
+            if note.note >= 128 || note.velocity >= 128 {
+                return Err(format!("note at {}ms: MIDI values should be 0 to 127", note.start_ms));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,163 @@
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
 use serde::Deserialize;
 use std::collections::HashMap;
+use crate::calibration::PAD_FULL_SCALE;
+use crate::groove::MAX_SWING;
+use crate::midi_queue::OverflowPolicy;
+use crate::migrate::CURRENT_CONFIG_VERSION;
+use crate::mixxx;
+use crate::macros::MacroStep;
+use crate::modes::chord;
+use crate::modes::custom_midi::button_from_name;
+use crate::step_clock::RATES;
+use crate::transport::Meter;
 
-#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ButtonMode {
+    #[default]
     Trigger, // 1 on press, 0 on release
     Toggle,  // Switch between 1 and 0 on press, ignores release
 }
 
-// FIX: Explicitly implement Default for ButtonMode, defaulting to Trigger
-impl Default for ButtonMode {
-    fn default() -> Self {
-        ButtonMode::Trigger
+/// Boundary a quantized action waits for.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantize {
+    Beat,
+    Bar,
+}
+
+/// Order the arpeggiator plays the held notes in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArpPattern {
+    #[default]
+    Up,
+    Down,
+    UpDown,
+    Random,
+}
+
+/// Scale Keyboard mode lays the pads out in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    Chromatic,
+    #[default]
+    Major,
+    Minor,
+    HarmonicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+}
+
+impl Scale {
+    /// In the order the encoder steps through them
+    pub const ALL: [Scale; 12] = [
+        Scale::Chromatic,
+        Scale::Major,
+        Scale::Minor,
+        Scale::HarmonicMinor,
+        Scale::Dorian,
+        Scale::Phrygian,
+        Scale::Lydian,
+        Scale::Mixolydian,
+        Scale::Locrian,
+        Scale::MajorPentatonic,
+        Scale::MinorPentatonic,
+        Scale::Blues,
+    ];
+
+    /// Semitones above the root of each degree in an octave
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+
+    /// Fits the screen
+    pub fn name(self) -> &'static str {
+        match self {
+            Scale::Chromatic => "CHROMATIC",
+            Scale::Major => "MAJOR",
+            Scale::Minor => "MINOR",
+            Scale::HarmonicMinor => "HARM MINOR",
+            Scale::Dorian => "DORIAN",
+            Scale::Phrygian => "PHRYGIAN",
+            Scale::Lydian => "LYDIAN",
+            Scale::Mixolydian => "MIXOLYDIAN",
+            Scale::Locrian => "LOCRIAN",
+            Scale::MajorPentatonic => "MAJOR PENTA",
+            Scale::MinorPentatonic => "MINOR PENTA",
+            Scale::Blues => "BLUES",
+        }
+    }
+}
+
+/// What Keyboard mode does with the notes outside the scale.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutOfScale {
+    /// The pads only play the scale, one degree after another
+    #[default]
+    Skip,
+    /// The pads stay chromatic, with the notes outside the scale dimmed
+    Dim,
+}
+
+/// How Keyboard and Chord modes spread their notes over `channels`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceAllocation {
+    /// Each note goes to the next channel in turn, busy or not
+    #[default]
+    RoundRobin,
+    /// Each note goes to the first channel with nothing sounding, or else takes over the
+    /// one whose note started first: one note per channel, for stacks of mono synths
+    FirstFree,
+}
+
+/// Argument layout of an outgoing OSC value; receivers disagree on what they expect.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OscFormat {
+    #[default]
+    Int,     // /maschine/play 1
+    Float,   // /maschine/play 1.0
+    Bool,    // /maschine/play true (T/F type tags)
+    String,  // /maschine/play "1"
+    Address, // /maschine/play/1, no arguments
+}
+
+impl OscFormat {
+    /// OSC type tags of the value, as listed in `/maschine/namespace`.
+    pub fn type_tags(self) -> &'static str {
+        match self {
+            OscFormat::Int => "i",
+            OscFormat::Float => "f",
+            OscFormat::Bool => "T|F",
+            OscFormat::String => "s",
+            OscFormat::Address => "",
+        }
     }
 }
 
@@ -25,6 +171,25 @@ pub(crate) struct ButtonConfig {
 
     #[serde(default)]
     pub cc: Option<u8>, 
+
+    /// MIDI channel (1-16) of the CC; `midi_channel` without it
+    #[serde(default)]
+    pub channel: Option<u8>,
+
+    #[serde(default)]
+    pub osc_format: OscFormat,
+
+    /// Toggle only: held longer than this, the button acts momentarily and switches off on release.
+    #[serde(default)]
+    pub momentary_after_ms: Option<u64>,
+
+    /// Macro run on every press, after the button's own OSC/MIDI.
+    #[serde(default)]
+    pub on_press: Vec<MacroStep>,
+
+    /// While a clock runs, presses take effect on the next beat or bar.
+    #[serde(default)]
+    pub quantize: Option<Quantize>,
 }
 
 // FIX: Implement Default for ButtonConfig
@@ -34,18 +199,1379 @@ impl Default for ButtonConfig {
             mode: ButtonMode::Trigger,
             group_id: None, // Default: no group
             cc: None, // Default: no CC message
+            channel: None,
+            osc_format: OscFormat::Int,
+            momentary_after_ms: None,
+            on_press: Vec::new(),
+            quantize: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct AccessibilitySettings {
+    /// Large text and high-contrast layout on the screen, brighter LEDs.
+    pub enabled: bool,
+    /// Play a short MIDI note on mode changes and status messages.
+    pub audio_cues: bool,
+    /// 1-16; cues go out on the main MIDI port.
+    pub cue_channel: u8,
+    pub cue_velocity: u8,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            audio_cues: false,
+            cue_channel: 16,
+            cue_velocity: 100,
+        }
+    }
+}
+
+/// Pad colors as written in the config; same names and order as `PadColors`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorName {
+    Off,
+    Red,
+    Orange,
+    LightOrange,
+    WarmYellow,
+    Yellow,
+    Lime,
+    Green,
+    Mint,
+    Cyan,
+    Turquoise,
+    Blue,
+    Plum,
+    Violet,
+    Purple,
+    Magenta,
+    Fuchsia,
+    White,
+}
+
+impl ColorName {
+    pub fn pad_color(self) -> PadColors {
+        num::FromPrimitive::from_u8(self as u8).unwrap()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LightLevel {
+    Off,
+    Dim,
+    #[default]
+    Normal,
+    Bright,
+}
+
+impl LightLevel {
+    pub fn brightness(self) -> Brightness {
+        match self {
+            LightLevel::Off => Brightness::Off,
+            LightLevel::Dim => Brightness::Dim,
+            LightLevel::Normal => Brightness::Normal,
+            LightLevel::Bright => Brightness::Bright,
         }
     }
 }
 
+/// Colors a pad takes from a velocity of `velocity` upwards.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct VelocityColor {
+    pub velocity: u8,
+    pub color: ColorName,
+    #[serde(default)]
+    pub brightness: LightLevel,
+}
+
+/// Pad lighting from the host: notes on `channel` light the pad mapped to that note
+/// (Custom MIDI mode), the way DAWs light drum rack pads.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PadFeedbackSettings {
+    /// 1-16; unset turns pad feedback off.
+    pub channel: Option<u8>,
+    /// Velocity to color table; empty uses the built-in palette.
+    pub colors: Vec<VelocityColor>,
+}
+
+impl PadFeedbackSettings {
+    /// Color for a note's velocity; 0 (note off) turns the pad off.
+    pub fn color_for(&self, velocity: u8) -> (PadColors, Brightness) {
+        if velocity == 0 {
+            return (PadColors::Off, Brightness::Off);
+        }
+        if !self.colors.is_empty() {
+            return self
+                .colors
+                .iter()
+                .filter(|c| c.velocity <= velocity)
+                .max_by_key(|c| c.velocity)
+                .map_or((PadColors::Off, Brightness::Off), |c| (c.color.pad_color(), c.brightness.brightness()));
+        }
+        // Built-in palette: seven velocities per color in PadColors order (three dim, two
+        // normal, two bright), from red at 1 to white; 120 and up is bright white.
+        let (color, shade) = ((velocity - 1) / 7 + 1, (velocity - 1) % 7);
+        let color: PadColors = num::FromPrimitive::from_u8(color.min(17)).unwrap();
+        let brightness = match shade {
+            0..=2 => Brightness::Dim,
+            3..=4 => Brightness::Normal,
+            _ => Brightness::Bright,
+        };
+        (color, if velocity >= 120 { Brightness::Bright } else { brightness })
+    }
+}
+
+/// Backup and restore over MIDI SysEx.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct SysexSettings {
+    pub enabled: bool,
+    /// Let a restored backup overwrite the config file given with --config.
+    pub write_config: bool,
+}
+
+impl Default for SysexSettings {
+    fn default() -> Self {
+        Self { enabled: true, write_config: false }
+    }
+}
+
+/// Ableton Live page, talking to the companion remote script.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct LiveSettings {
+    pub enabled: bool,
+    /// MIDI channel (1-16) the pads and buttons go out on; `install-live-script` writes
+    /// it into the script.
+    pub channel: u8,
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self { enabled: false, channel: 16 }
+    }
+}
+
+/// Bitwig Studio page, talking to the companion controller extension.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct BitwigSettings {
+    pub enabled: bool,
+    /// MIDI channel (1-16) the controls go out on; `install-bitwig-script` writes it
+    /// into the script.
+    pub channel: u8,
+}
+
+impl Default for BitwigSettings {
+    fn default() -> Self {
+        Self { enabled: false, channel: 15 }
+    }
+}
+
+/// Reaper page, over OSC with the pattern config from `export-reaper-osc`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct ReaperSettings {
+    pub enabled: bool,
+}
+
+/// Mixxx mapping written by `export-mixxx-mapping`, for Custom MIDI mode.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct MixxxSettings {
+    /// Mixxx loading the mapping brings Custom MIDI mode up, and controllers coming back
+    /// light trigger buttons too, the way Mixxx's indicators expect.
+    pub enabled: bool,
+    /// Mixxx control for each pad, in `notemaps` order, as "[Group],key"
+    pub pads: Vec<String>,
+    /// Mixxx control for buttons with a `cc`, by button name
+    pub buttons: HashMap<String, String>,
+    /// Velocity a pad's light is sent with while its control is on (see `[pad_feedback]`)
+    pub pad_on: u8,
+}
+
+impl Default for MixxxSettings {
+    fn default() -> Self {
+        // Hot cues 1-8 of deck 1 on the first eight pads, and of deck 2 on the rest.
+        let pads = (1..=2).flat_map(|deck| (1..=8).map(move |cue| format!("[Channel{deck}],hotcue_{cue}_activate"))).collect();
+        Self { enabled: false, pads, buttons: HashMap::new(), pad_on: 127 }
+    }
+}
+
+/// Arpeggiator over the pads held in Custom MIDI mode.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ArpSettings {
+    /// A press of `button` turns the arpeggiator on and off.
+    pub enabled: bool,
+    pub button: String,
+    pub pattern: ArpPattern,
+    /// Steps per bar of 4/4: 4, 8 or 16, 32, or triplets at 12 and 24
+    pub rate: u32,
+    /// Octaves the pattern climbs through, 1-4
+    pub octaves: u8,
+    /// Share of each step the note sounds for, in percent
+    pub gate: u8,
+    /// Tempo of the internal clock, used while no MIDI clock comes in
+    pub bpm: f32,
+}
+
+impl Default for ArpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            button: "NoteRepeat".to_string(),
+            pattern: ArpPattern::Up,
+            rate: 16,
+            octaves: 1,
+            gate: 50,
+            bpm: 120.0,
+        }
+    }
+}
+
+/// Note repeat in Custom MIDI and Play modes, on the Note Repeat button.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct NoteRepeatSettings {
+    /// Held pads retrigger while Note Repeat is held; the strip or encoder picks the rate meanwhile.
+    pub enabled: bool,
+    /// A press turns note repeat on until the next one, instead of only while held
+    pub latch: bool,
+    /// Steps per bar to start at, as `[arp] rate`
+    pub rate: u32,
+    /// Tempo of the internal clock, used while no MIDI clock comes in
+    pub bpm: f32,
+}
+
+impl Default for NoteRepeatSettings {
+    fn default() -> Self {
+        Self { enabled: false, latch: false, rate: 16, bpm: 120.0 }
+    }
+}
+
+/// MIDI clock from Play mode's loop.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ClockOutSettings {
+    /// Clock, Start, Stop and Continue follow the loop's transport
+    pub enabled: bool,
+    /// Beats in one pass of the loop, which sets the tempo
+    pub beats_per_loop: u32,
+}
+
+impl Default for ClockOutSettings {
+    fn default() -> Self {
+        Self { enabled: false, beats_per_loop: 4 }
+    }
+}
+
+/// Ableton Link, through Carabiner.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct LinkSettings {
+    /// Play mode's loop follows the session's tempo and beat.
+    pub enabled: bool,
+    /// Where Carabiner listens
+    pub carabiner: String,
+    /// Beats in a bar of the session; first takes are rounded to whole bars.
+    pub quantum: u32,
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self { enabled: false, carabiner: "127.0.0.1:17000".to_string(), quantum: 4 }
+    }
+}
+
+/// Play mode's metronome, on the beats of `[transport]`'s meter.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct MetronomeSettings {
+    /// Counts while the loop is armed, recording or playing.
+    pub enabled: bool,
+    /// Tempo of the count for a first take with no clock to follow; the take is rounded
+    /// to whole beats of it.
+    pub bpm: f32,
+    /// Button that flashes on the beat
+    pub button: String,
+    /// Pad (1-16) that flashes too
+    pub pad: Option<u8>,
+    /// Note sent on every beat; none just flashes.
+    pub click_note: Option<u8>,
+    /// 1-16
+    pub click_channel: u8,
+    pub velocity: u8,
+    /// Velocity of the bar's first beat
+    pub accent_velocity: u8,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bpm: 120.0,
+            button: "Tap".to_string(),
+            pad: None,
+            click_note: None,
+            click_channel: 10,
+            velocity: 100,
+            accent_velocity: 127,
+        }
+    }
+}
+
+/// What happens to a loop note still held when the loop comes around.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapHold {
+    /// It rings on until its note off comes around.
+    #[default]
+    Extend,
+    /// It ends at the loop's end.
+    Cut,
+    /// It starts again at the loop's top.
+    Retrigger,
+}
+
+/// Notes held across the end of Play mode's loop, for all of it or by channel.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct LoopWrapSettings {
+    pub held: WrapHold,
+    pub tracks: Vec<LoopWrapTrack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct LoopWrapTrack {
+    /// MIDI channel (1-16) of the loop's notes
+    pub channel: u8,
+    pub held: WrapHold,
+}
+
+impl LoopWrapSettings {
+    /// How held notes on `channel` (0-based) cross the loop's end
+    pub fn held(&self, channel: u8) -> WrapHold {
+        self.tracks.iter().find(|t| t.channel == channel + 1).map_or(self.held, |t| t.held)
+    }
+}
+
+/// Channels of Play mode's loop that loop in lengths of their own.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PolymeterSettings {
+    pub tracks: Vec<PolymeterTrack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct PolymeterTrack {
+    /// MIDI channel (1-16) of the track's notes
+    pub channel: u8,
+    /// Length of the track, in beats of the loop
+    pub beats: u32,
+}
+
+/// Play mode following the host's MIDI clock.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct ClockInSettings {
+    /// The loop locks to incoming clock and follows Start, Stop and Continue.
+    pub enabled: bool,
+}
+
+/// A pad's chord on the Chord page.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ChordPad {
+    /// Lowest note, before any voicing below it
+    pub root: u8,
+    /// A built-in chord ("major", "minor7", ...) or one of `[chords.voicings]`
+    pub chord: String,
+}
+
+/// Chord page: a chord per pad.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ChordSettings {
+    pub enabled: bool,
+    /// MIDI channel the chords play on, 1-16
+    pub channel: u8,
+    /// Channels (1-16) to spread the chords' notes over instead of `channel`
+    pub channels: Vec<u8>,
+    pub allocation: VoiceAllocation,
+    /// In pad order, like `notemaps`; pads past the end stay dark
+    pub pads: Vec<ChordPad>,
+    /// User voicings by name, in semitones above the root
+    pub voicings: HashMap<String, Vec<u8>>,
+}
+
+impl Default for ChordSettings {
+    /// The triads of C major on the top two rows and their sevenths below.
+    fn default() -> Self {
+        let roots = [60, 62, 64, 65, 67, 69, 71, 72];
+        let triads = ["major", "minor", "minor", "major", "major", "minor", "dim", "major"];
+        let sevenths = ["major7", "minor7", "minor7", "major7", "dominant7", "minor7", "half_dim7", "major7"];
+        let pads = triads
+            .iter()
+            .chain(sevenths.iter())
+            .zip(roots.iter().cycle())
+            .map(|(chord, root)| ChordPad { root: *root, chord: chord.to_string() })
+            .collect();
+        Self { enabled: false, channel: 1, channels: Vec::new(), allocation: VoiceAllocation::RoundRobin, pads, voicings: HashMap::new() }
+    }
+}
+
+impl ChordSettings {
+    /// Semitones above the root of a chord name; user voicings win over built-in names.
+    pub fn intervals(&self, name: &str) -> Option<Vec<u8>> {
+        self.voicings.get(name).cloned().or_else(|| chord::builtin(name).map(<[u8]>::to_vec))
+    }
+}
+
+/// Live-coding clients (SuperCollider and the like) over OSC: `/maschine/hello`,
+/// `/maschine/pattern` and tagged pad hits.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct LiveCodingSettings {
+    pub enabled: bool,
+    /// Group each pad's hits are tagged with, in pad order; pads past the end are in group 0
+    pub pad_groups: Vec<u8>,
+}
+
+/// Gate, trigger and CV messages for modular rigs, from Custom MIDI mode.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ModularSettings {
+    /// Send the pads and buttons as 0-1 floats under `prefix` too (see `modular.rs`).
+    pub enabled: bool,
+    pub prefix: String,
+    /// Length of a trigger pulse for OSC-to-CV bridges; 0 sends a bare 1 bang
+    pub trigger_ms: u64,
+}
+
+impl Default for ModularSettings {
+    fn default() -> Self {
+        Self { enabled: false, prefix: "/modular".to_string(), trigger_ms: 0 }
+    }
+}
+
+/// Keyboard page: the pads as a scale or a chromatic keyboard.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct KeyboardSettings {
+    pub enabled: bool,
+    /// MIDI channel the pads play on, 1-16
+    pub channel: u8,
+    /// Channels (1-16) to spread the notes over instead of `channel`
+    pub channels: Vec<u8>,
+    pub allocation: VoiceAllocation,
+    /// Note of the bottom-left pad to start with
+    pub root: u8,
+    pub scale: Scale,
+    pub out_of_scale: OutOfScale,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self { enabled: false, channel: 1, channels: Vec::new(), allocation: VoiceAllocation::RoundRobin, root: 48, scale: Scale::Major, out_of_scale: OutOfScale::Skip }
+    }
+}
+
+/// Priority queue in front of the MIDI output.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct MidiQueueSettings {
+    pub enabled: bool,
+    /// Messages each class may hold while the port can't keep up
+    pub capacity: usize,
+    /// Feedback messages sent per pass of the main loop
+    pub max_per_pass: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for MidiQueueSettings {
+    fn default() -> Self {
+        Self { enabled: true, capacity: 256, max_per_pass: 64, overflow: OverflowPolicy::Coalesce }
+    }
+}
+
+/// Ends notes whose release never came.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct NoteGuardSettings {
+    pub enabled: bool,
+    /// How long a note may stay on before it's ended for the driver
+    pub window_ms: u64,
+    /// Active Sensing on the MIDI output whenever it's quiet
+    pub active_sensing: bool,
+}
+
+impl Default for NoteGuardSettings {
+    fn default() -> Self {
+        Self { enabled: false, window_ms: 30_000, active_sensing: false }
+    }
+}
+
+/// The touch strip as a MIDI controller in Custom MIDI mode, besides `/maschine/slider`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct SliderSettings {
+    /// CC the strip's position goes out on, 0-127
+    pub cc: Option<u8>,
+    /// MIDI channel (1-16) of the CC; `midi_channel` without it
+    pub channel: Option<u8>,
+}
+
+/// Fixed velocity for pad hits, toggled from a button.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct FixedVelocitySettings {
+    pub enabled: bool,
+    pub button: String,
+    pub velocity: u8,
+    /// Held while it's on, hits play at `accent` instead
+    pub accent_button: String,
+    pub accent: u8,
+}
+
+impl Default for FixedVelocitySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            button: "FixedVol".to_string(),
+            velocity: 100,
+            accent_button: "Shift".to_string(),
+            accent: 127,
+        }
+    }
+}
+
+/// Attract loop for installations, after `idle_s` without a touch.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct AttractSettings {
+    pub enabled: bool,
+    pub idle_s: u64,
+    /// Bounces across the screen while the loop runs
+    pub text: String,
+    /// Notes played one per `step_ms` when the loop starts; none plays nothing
+    pub phrase: Vec<u8>,
+    pub step_ms: u64,
+    pub channel: u8,
+    pub velocity: u8,
+    /// The phrase comes back this often while the loop runs; 0 plays it once.
+    pub repeat_s: u64,
+    /// Send `/maschine/touch "<control>"` for every touch.
+    pub report_touches: bool,
+}
+
+impl Default for AttractSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_s: 120,
+            text: "TOUCH ME".to_string(),
+            phrase: Vec::new(),
+            step_ms: 250,
+            channel: 1,
+            velocity: 80,
+            repeat_s: 30,
+            report_touches: true,
+        }
+    }
+}
+
+/// Performance snapshots on Group+pad 1-4.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct SnapshotSettings {
+    pub enabled: bool,
+    /// Recalls wait for the next beat or bar while a clock runs.
+    pub quantize: Option<Quantize>,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self { enabled: true, quantize: None }
+    }
+}
+
+/// Following the host's transport from MIDI on the input port.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct TransportSettings {
+    /// Show the host's play state, position and tempo once it sends transport messages.
+    pub follow: bool,
+    /// The time signature: 7 and 8 for 7/8
+    pub beats_per_bar: u8,
+    pub beat_unit: u8,
+    /// Sweep a light across the pads, one per sixteenth, while a clock runs: the host's,
+    /// or the practice metronome.
+    pub pad_chase: bool,
+    pub chase_color: ColorName,
+    /// Light scene changes wait for the next beat or bar while a clock runs.
+    pub scene_quantize: Option<Quantize>,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        Self { follow: true, beats_per_bar: 4, beat_unit: 4, pad_chase: false, chase_color: ColorName::White, scene_quantize: None }
+    }
+}
+
+impl TransportSettings {
+    pub fn meter(&self) -> Meter {
+        Meter { beats_per_bar: self.beats_per_bar, beat_unit: self.beat_unit }
+    }
+}
+
+/// Show-control page for lighting and cue software.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ShowSettings {
+    pub enabled: bool,
+    /// Address prefix for everything the page sends and accepts
+    pub prefix: String,
+    /// Intensity master change per encoder detent, in percent; 1 while Shift is held
+    pub master_step: u8,
+}
+
+impl Default for ShowSettings {
+    fn default() -> Self {
+        Self { enabled: false, prefix: "/show".to_string(), master_step: 2 }
+    }
+}
+
+/// Page that turns the pads and slider into a display for `/maschine/lights/frame`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct VisualizerSettings {
+    pub enabled: bool,
+    /// Most LED updates per second, interpolation steps included
+    pub max_fps: u32,
+}
+
+impl Default for VisualizerSettings {
+    fn default() -> Self {
+        Self { enabled: false, max_fps: 30 }
+    }
+}
+
+/// Audio-reactive lights from a JACK input; needs a driver built with `--features audio`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct AudioSettings {
+    pub enabled: bool,
+    /// Output port to listen to, e.g. "system:capture_1"; unset leaves the connection to a patchbay
+    pub port: Option<String>,
+    /// Level meter on the pads, onsets flash them
+    pub pads: bool,
+    /// Level meter on the slider LEDs
+    pub slider: bool,
+    /// Level bar along the bottom of the screen
+    pub meter: bool,
+    /// Range the meter covers, in dB below full scale
+    pub range_db: f32,
+    /// How far a burst must rise over the running average to count as an onset
+    pub onset_threshold: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { enabled: false, port: None, pads: true, slider: true, meter: true, range_db: 48.0, onset_threshold: 1.8 }
+    }
+}
+
+/// Finger-drumming practice page: a metronome and per-pad hit statistics.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct PracticeSettings {
+    /// Adds the page to the Browse cycle.
+    pub enabled: bool,
+    pub bpm: f32,
+    /// Grid steps per beat that hits are measured against (4 = sixteenths).
+    pub subdivision: u8,
+    /// Note sent on every beat for an audible click; none just flashes Tap.
+    pub click_note: Option<u8>,
+    /// 1-16
+    pub click_channel: u8,
+    /// Where Duplicate writes the statistics as CSV.
+    pub export_path: String,
+    /// Taps averaged for the tapped tempo
+    pub tap_average: usize,
+    /// How far one encoder detent nudges the beat
+    pub nudge_ms: u64,
+}
+
+impl Default for PracticeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bpm: 120.0,
+            subdivision: 4,
+            click_note: None,
+            click_channel: 10,
+            export_path: "practice_stats.csv".to_string(),
+            tap_average: 4,
+            nudge_ms: 10,
+        }
+    }
+}
+
+/// What it takes to run a destructive action.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmGesture {
+    /// Runs on the first press
+    None,
+    /// A second press within `timeout_ms`
+    #[default]
+    Double,
+    /// Holding the button for `hold_ms`
+    Hold,
+}
+
+/// Confirmation for destructive actions; the pads blink red while one is waiting.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ConfirmSettings {
+    /// Erase in the looper, which clears the whole loop
+    pub clear_loop: ConfirmGesture,
+    /// Erase on the practice page, which clears the statistics
+    pub clear_stats: ConfirmGesture,
+    pub timeout_ms: u64,
+    pub hold_ms: u64,
+}
+
+impl Default for ConfirmSettings {
+    fn default() -> Self {
+        Self {
+            clear_loop: ConfirmGesture::Double,
+            clear_stats: ConfirmGesture::Double,
+            timeout_ms: 2000,
+            hold_ms: 1000,
+        }
+    }
+}
+
+/// What the encoder sends in Custom MIDI mode.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EncoderOutput {
+    #[default]
+    Osc,             // /maschine/encoder 1 / -1
+    Wheel,           // mouse wheel (uinput)
+    HorizontalWheel, // horizontal mouse wheel (uinput)
+    UpDown,          // Up/Down arrow keys (uinput)
+    LeftRight,       // Left/Right arrow keys (uinput)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct EncoderSettings {
+    pub output: EncoderOutput,
+    /// Wheel clicks or key taps per detent.
+    pub amount: u8,
+}
+
+impl Default for EncoderSettings {
+    fn default() -> Self {
+        Self {
+            output: EncoderOutput::Osc,
+            amount: 1,
+        }
+    }
+}
+
+/// Options for an exclusive group, keyed by its `group_id`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct GroupConfig {
+    /// Whether pressing the active member may switch the whole group off.
+    pub allow_none: bool,
+    /// Member selected at startup and on reset.
+    pub default: Option<String>,
+    /// Send every member's state and the group value when the mode is entered.
+    pub announce: bool,
+    /// CC carrying the group value (1-based member position, 0 when none is on).
+    pub cc: Option<u8>,
+    /// MIDI channel (1-16) of that CC; `midi_channel` without it
+    pub channel: Option<u8>,
+}
+
+impl Default for GroupConfig {
+    fn default() -> Self {
+        Self {
+            allow_none: true,
+            default: None,
+            announce: false,
+            cc: None,
+            channel: None,
+        }
+    }
+}
+
+/// A labeled macro on the deck page; the Nth entry is pad N.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct DeckPad {
+    /// Shown in the on-screen legend (first four characters).
+    pub label: String,
+    pub steps: Vec<MacroStep>,
+}
+
+/// One virtual parameter of the encoder knob bank; pad N selects the Nth knob.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct KnobConfig {
+    pub name: String,
+    pub cc: Option<u8>,
+    /// MIDI channel (1-16) of the CC; `midi_channel` without it
+    pub channel: Option<u8>,
+    /// OSC address that receives the value, as an int unless `osc_format` says otherwise.
+    pub osc: Option<String>,
+    pub osc_format: OscFormat,
+    pub initial: u8,
+    /// Value change per encoder detent; 1 while Shift is held.
+    pub step: u8,
+}
+
+impl Default for KnobConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            cc: None,
+            channel: None,
+            osc: None,
+            osc_format: OscFormat::Int,
+            initial: 0,
+            step: 1,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct WatchdogSettings {
+    /// Consecutive failures before the watchdog starts recovering a backend.
+    pub failure_threshold: u32,
+    /// Minimum time between two recovery attempts.
+    pub retry_interval_ms: u64,
+    /// Failed recoveries before the error is shown on the hardware.
+    pub max_recovery_attempts: u32,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 20,
+            retry_interval_ms: 2000,
+            max_recovery_attempts: 3,
+        }
+    }
+}
+
+/// Note a pad plays from a hit velocity of `velocity` upwards, e.g. sidestick, then
+/// rimshot, then the full snare.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct VelocityLayer {
+    pub velocity: u8,
+    pub note: u8,
+}
+
+/// Reassigns or disables physical controls for every mode, e.g. Follow acting as Shift
+/// or a broken pad switched off.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct RemapSettings {
+    /// Button name to the button it acts as, or "none" to disable it
+    pub buttons: HashMap<String, String>,
+    /// Pad number (1-16) to the pad it acts as, or 0 to disable it
+    pub pads: HashMap<String, u8>,
+    /// Button name to the pad (1-16) it plays instead, e.g. for a dead pad
+    pub button_pads: HashMap<String, u8>,
+    pub reverse_encoder: bool,
+}
+
+impl RemapSettings {
+    pub fn is_empty(&self) -> bool {
+        self.buttons.is_empty() && self.pads.is_empty() && self.button_pads.is_empty() && !self.reverse_encoder
+    }
+}
+
+/// How the unit is turned for the player. Pad numbers, notes and lights all follow the
+/// player's view, so pad 1 is always the top-left one as they see it.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PadGridSettings {
+    /// Clockwise turn of the unit as mounted: 0, 90, 180 or 270 degrees
+    pub rotation: u16,
+    /// Swap left and right, for left-handed playing
+    pub mirror: bool,
+}
+
+impl PadGridSettings {
+    pub fn is_identity(&self) -> bool {
+        self.rotation == 0 && !self.mirror
+    }
+
+    /// Where the player sees a physical pad.
+    pub fn to_logical(&self, physical: usize) -> usize {
+        let (row, col) = (physical / 4, physical % 4);
+        let (row, col) = match self.rotation {
+            90 => (col, 3 - row),
+            180 => (3 - row, 3 - col),
+            270 => (3 - col, row),
+            _ => (row, col),
+        };
+        row * 4 + if self.mirror { 3 - col } else { col }
+    }
+
+    /// Moves the pad lights from where the modes drew them onto the physical pads.
+    pub fn lights_to_physical(&self, lights: &Lights) -> Lights {
+        if self.is_identity() {
+            return lights.clone();
+        }
+        let mut out = lights.clone();
+        for physical in 0..16 {
+            let (color, brightness) = lights.get_pad(self.to_logical(physical));
+            out.set_pad(physical, color, brightness);
+        }
+        out
+    }
+}
+
+/// One half of a split pad grid: two columns of four pads.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct PadZone {
+    /// Notes for the zone's 8 pads, row by row
+    pub notes: Vec<u8>,
+    #[serde(default = "default_channel")]
+    pub channel: u8,
+    #[serde(default = "default_zone_color")]
+    pub color: ColorName,
+}
+
+fn default_channel() -> u8 {
+    1
+}
+
+fn default_zone_color() -> ColorName {
+    ColorName::Off
+}
+
+/// Splits the pads into a left and a right zone, each with its own notes, channel and
+/// color, e.g. drums on the left and bass notes on the right.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct SplitLayout {
+    pub left: PadZone,
+    pub right: PadZone,
+}
+
+/// Up to eight banks of 16 notes for Custom MIDI and Play modes, picked with `button`+pad.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct PadBankSettings {
+    pub enabled: bool,
+    /// Held with pad 1-8 to pick a bank
+    pub button: String,
+    /// Notes of each bank in pad order, like `notemaps`; without them there are eight
+    /// banks, each `notemaps` 16 notes higher than the one before (wrapping past 127).
+    pub notemaps: Vec<Vec<u8>>,
+    /// Idle pad color of each bank
+    pub colors: Vec<ColorName>,
+}
+
+impl Default for PadBankSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            button: "Pattern".to_string(),
+            notemaps: Vec::new(),
+            colors: vec![
+                ColorName::Red,
+                ColorName::Orange,
+                ColorName::Yellow,
+                ColorName::Green,
+                ColorName::Cyan,
+                ColorName::Blue,
+                ColorName::Violet,
+                ColorName::Magenta,
+            ],
+        }
+    }
+}
+
+/// Pad hits over OSC in Custom MIDI mode, next to their notes.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PadOscSettings {
+    /// Send `/maschine/pad <pad 1-16> <velocity>` on hits and velocity 0 on release.
+    pub enabled: bool,
+    /// Add the pad's name from `pad_names` as a third argument.
+    pub names: bool,
+}
+
+/// Pad legend shown while a button is held.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct OverlayConfig {
+    /// Labels in pad order, four characters each at most
+    pub pads: Vec<String>,
+}
+
+/// A full-surface light scene, recalled with `/maschine/scene "<name>"` or a program change.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct SceneConfig {
+    /// Pad colors in pad order; pads left out stay dark.
+    pub pads: Vec<ColorName>,
+    pub brightness: LightLevel,
+    /// Button lights by name, e.g. `play = "bright"`; the others stay dark.
+    pub buttons: HashMap<String, LightLevel>,
+    /// Program change number (any channel) that recalls the scene
+    pub program: Option<u8>,
+    /// Crossfade time into the scene and out of it
+    pub fade_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct LightSettings {
+    /// Time for a light to fade from off to full brightness; 0 switches at once.
+    pub fade_ms: u64,
+    /// Show the pressure on the last struck pad on the slider LEDs, over what the mode draws there.
+    pub pressure_meter: bool,
+    /// Briefly light every accepted input at this level, where the mode left the light off
+    pub input_ack: Option<LightLevel>,
+    /// Levels by button name in place of the usual off, dim and bright; `all` covers the
+    /// buttons without their own entry.
+    pub buttons: HashMap<String, ButtonLevels>,
+}
+
+/// What a button shows for each of its states; unset ones stay as they are.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub(crate) struct ButtonLevels {
+    /// Dark: nothing to show
+    pub off: Option<LightLevel>,
+    /// Dim: available, or one of several choices
+    pub indicated: Option<LightLevel>,
+    /// Bright (or normal): active
+    pub on: Option<LightLevel>,
+}
+
+impl LightSettings {
+    /// Swaps each button's off, dim and bright for the levels set under `[lights.buttons]`.
+    pub fn apply_button_levels(&self, lights: &mut Lights) {
+        if self.buttons.is_empty() {
+            return;
+        }
+        let all = self.buttons.get("all").copied().unwrap_or_default();
+        let mut levels = [all; 41];
+        for (name, own) in &self.buttons {
+            if let Some(button) = button_from_name(name) {
+                levels[button as usize] = *own;
+            }
+        }
+        for (i, levels) in levels.iter().enumerate() {
+            let Some(button) = num::FromPrimitive::from_usize(i) else {
+                continue;
+            };
+            if !lights.button_has_light(button) {
+                continue;
+            }
+            let level = match lights.get_button(button) {
+                Brightness::Off => levels.off,
+                Brightness::Dim => levels.indicated,
+                Brightness::Normal | Brightness::Bright => levels.on,
+            };
+            if let Some(level) = level {
+                lights.set_button(button, level.brightness());
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct PollingSettings {
+    /// Input wait while the controller is in use or animating.
+    pub active_interval_ms: u64,
+    /// Input wait once nothing has happened for `idle_after_ms`.
+    pub idle_interval_ms: u64,
+    pub idle_after_ms: u64,
+}
+
+impl Default for PollingSettings {
+    fn default() -> Self {
+        Self {
+            active_interval_ms: 1,
+            idle_interval_ms: 20,
+            idle_after_ms: 2000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct RealtimeSettings {
+    /// Request SCHED_FIFO for the MIDI loop thread.
+    pub enabled: bool,
+    pub priority: i32,
+    /// mlockall() so page faults can't stall note output.
+    pub lock_memory: bool,
+}
+
+impl Default for RealtimeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            priority: 70,
+            lock_memory: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupAnimation {
+    #[default]
+    Sweep,   // every light in turn, through all brightness levels
+    Rainbow, // color wave across the pads
+    None,    // fastest startup
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct StartupSettings {
+    pub text: String,
+    pub text_scale: usize,
+    /// 128x32 PBM image shown instead of `text`.
+    pub image: Option<String>,
+    /// How long the splash stays up before the light animation.
+    pub duration_ms: u64,
+    pub animation: StartupAnimation,
+}
+
+impl Default for StartupSettings {
+    fn default() -> Self {
+        Self {
+            text: "MASCHINE".to_string(),
+            text_scale: 2,
+            image: None,
+            duration_ms: 1000,
+            animation: StartupAnimation::Sweep,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct OscDestination {
+    /// `host:port` to send to.
+    pub address: String,
+    /// Address prefixes to send (e.g. "/maschine/pad/*"); empty sends everything.
+    pub include: Vec<String>,
+    /// Address prefixes never sent, checked after `include`.
+    pub exclude: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct OscSettings {
+    /// Named outgoing targets. When empty, everything goes to `osc_ip:osc_port`.
+    pub destinations: HashMap<String, OscDestination>,
+    /// Send to `osc_ip:osc_port` when there are no destinations; off sends only to subscribers.
+    pub default_peer: bool,
+    /// How long a `/maschine/subscribe` lasts without being renewed.
+    pub subscription_ttl_s: u64,
+    /// TCP port for the OSCQuery HTTP and WebSocket server; 0 leaves it off.
+    pub query_port: u16,
+    /// Send messages caused by an input in bundles timetagged with when the input was read.
+    pub timetags: bool,
+    pub failover: FailoverSettings,
+    /// Addresses to listen on, IPv4 or IPv6, each optionally with a port other than
+    /// `osc_listen_port`. Without any, the driver listens on `osc_ip` when that's this
+    /// machine (loopback) and on every IPv4 interface otherwise.
+    pub listen: Vec<String>,
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            destinations: HashMap::new(),
+            default_peer: true,
+            subscription_ttl_s: 60,
+            query_port: 0,
+            timetags: false,
+            failover: FailoverSettings::default(),
+            listen: Vec::new(),
+        }
+    }
+}
+
+/// Heartbeats to the primary OSC destination, and a backup to send to while it's silent.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct FailoverSettings {
+    pub enabled: bool,
+    /// Name in `[osc.destinations]`; empty for `osc_ip:osc_port`
+    pub primary: String,
+    /// `host:port` that gets the primary's messages while it doesn't answer
+    pub backup: String,
+    pub interval_ms: u64,
+    /// Silence after which the backup takes over
+    pub timeout_ms: u64,
+}
+
+impl Default for FailoverSettings {
+    fn default() -> Self {
+        Self { enabled: false, primary: String::new(), backup: String::new(), interval_ms: 1000, timeout_ms: 3000 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct LockSettings {
+    /// Long-pressing `button` toggles the performance lock.
+    pub enabled: bool,
+    pub button: String,
+    pub hold_ms: u64,
+}
+
+impl Default for LockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            button: "Lock".to_string(),
+            hold_ms: 1000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct FifoSettings {
+    /// Directory for the `events` and `commands` pipes (created as needed); unset leaves them off.
+    pub dir: Option<String>,
+}
+
+/// Periodic copies of the looper contents, reloadable from Play mode.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct AutosaveSettings {
+    /// Session directory for the saves (created as needed); unset leaves autosave off.
+    pub dir: Option<String>,
+    /// Seconds between saves; a loop that hasn't changed isn't saved again
+    pub interval_s: u64,
+    /// Saves kept; older ones are deleted
+    pub keep: usize,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self { dir: None, interval_s: 60, keep: 20 }
+    }
+}
+
+/// Shift+Rec in Play mode writes the loop as a Standard MIDI File.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct MidiExportSettings {
+    /// Where the files go (created as needed); unset leaves Shift+Rec to Rec.
+    pub dir: Option<String>,
+}
+
+/// Play mode's patterns and the scenes made of them.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PatternSettings {
+    /// Program changes 0-15 on the input launch scenes 1-16.
+    pub program_change: bool,
+    /// Follow actions Play mode starts with.
+    pub follow: Vec<FollowAction>,
+}
+
+/// After `passes` passes of `pattern`, Play mode goes on to one of `next`, at random.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct FollowAction {
+    /// 1-16, the pad of the pattern
+    pub pattern: u8,
+    pub passes: u32,
+    pub next: Vec<u8>,
+}
+
+/// Groove templates for Play mode's loop, picked with Swing held and the encoder.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct GrooveSettings {
+    /// Folder of short MIDI files to take grooves from; unset leaves Swing alone.
+    pub dir: Option<String>,
+    /// How much of the groove's timing and dynamics to apply, in percent
+    pub amount: u8,
+    /// Swing Play mode starts with, 0-75%; Swing and the encoder change it.
+    pub swing: u8,
+}
+
+impl Default for GrooveSettings {
+    fn default() -> Self {
+        Self { dir: None, amount: 100, swing: 0 }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub(crate) struct Settings {
+    /// Schema version the file was written for (see `migrate`); 0 when it has none.
+    #[serde(default)]
+    pub config_version: u32,
+    #[serde(default)]
+    pub notemaps: Vec<u8>,
+    /// MIDI channel (1-16) everything goes out on unless a control has its own
+    #[serde(default = "default_channel")]
+    pub midi_channel: u8,
+    /// Channel (1-16) of each pad, in `notemaps` order; pads past the end play on `midi_channel`
+    #[serde(default)]
+    pub pad_channels: Vec<u8>,
+    #[serde(default)]
+    pub slider: SliderSettings,
+    /// Pad names in pad order, e.g. "Kick": shown while Notes is held, and sent with
+    /// pad hits when `pad_osc.names` is on.
+    #[serde(default)]
+    pub pad_names: Vec<String>,
+    #[serde(default)]
+    pub pad_osc: PadOscSettings,
+    /// Replaces `notemaps` with two zones when set.
+    #[serde(default)]
+    pub split: Option<SplitLayout>,
+    #[serde(default)]
+    pub pad_banks: PadBankSettings,
+    /// Per pad (numbered 1-16), notes by hit strength. Softer hits than the lowest layer
+    /// play the pad's usual note.
+    #[serde(default)]
+    pub velocity_layers: HashMap<String, Vec<VelocityLayer>>,
+    /// Per pad (numbered 1-16), the `[softest, hardest]` raw reading, stretched onto the
+    /// full scale so every pad plays the same velocities. Measured by `--calibrate-pads`.
+    #[serde(default)]
+    pub pad_calibration: HashMap<String, [u16; 2]>,
+    #[serde(default)]
+    pub remap: RemapSettings,
     #[serde(default)]
-    pub notemaps: Vec<u8>,
+    pub pad_grid: PadGridSettings,
     #[serde(default)]
     pub client_name: String,
     #[serde(default)]
     pub port_name: String,
+    /// Virtual input port for feedback from the host; empty means "<client_name> MIDI In".
+    #[serde(default)]
+    pub input_port_name: String,
     #[serde(default)]
     pub osc_ip: String,
     #[serde(default)]
@@ -57,25 +1583,348 @@ pub(crate) struct Settings {
     
     #[serde(default)]
     pub button_configs: HashMap<String, ButtonConfig>,
+
+    #[serde(default)]
+    pub group_configs: HashMap<String, GroupConfig>,
+
+    #[serde(default)]
+    pub knobs: Vec<KnobConfig>,
+
+    #[serde(default)]
+    pub deck: Vec<DeckPad>,
+
+    /// Named macros, for `macro` steps and `/maschine/macro "<name>"`.
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<MacroStep>>,
+
+    /// Lets macros start external programs (`exec` steps). Off unless set explicitly.
+    #[serde(default)]
+    pub allow_exec: bool,
+
+    #[serde(default)]
+    pub encoder: EncoderSettings,
+
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+
+    #[serde(default)]
+    pub practice: PracticeSettings,
+
+    #[serde(default)]
+    pub show: ShowSettings,
+
+    #[serde(default)]
+    pub visualizer: VisualizerSettings,
+
+    #[serde(default)]
+    pub audio: AudioSettings,
+
+    #[serde(default)]
+    pub pad_feedback: PadFeedbackSettings,
+
+    #[serde(default)]
+    pub transport: TransportSettings,
+
+    #[serde(default)]
+    pub snapshots: SnapshotSettings,
+    #[serde(default)]
+    pub attract: AttractSettings,
+    #[serde(default)]
+    pub fixed_velocity: FixedVelocitySettings,
+    #[serde(default)]
+    pub midi_queue: MidiQueueSettings,
+    #[serde(default)]
+    pub note_guard: NoteGuardSettings,
+
+    #[serde(default)]
+    pub sysex: SysexSettings,
+
+    #[serde(default)]
+    pub live: LiveSettings,
+
+    #[serde(default)]
+    pub bitwig: BitwigSettings,
+
+    #[serde(default)]
+    pub reaper: ReaperSettings,
+
+    #[serde(default)]
+    pub mixxx: MixxxSettings,
+
+    #[serde(default)]
+    pub arp: ArpSettings,
+
+    #[serde(default)]
+    pub note_repeat: NoteRepeatSettings,
+
+    #[serde(default)]
+    pub clock_out: ClockOutSettings,
+
+    #[serde(default)]
+    pub clock_in: ClockInSettings,
+
+    #[serde(default)]
+    pub loop_wrap: LoopWrapSettings,
+
+    #[serde(default)]
+    pub link: LinkSettings,
+    #[serde(default)]
+    pub metronome: MetronomeSettings,
+
+    #[serde(default)]
+    pub polymeter: PolymeterSettings,
+
+    #[serde(default)]
+    pub keyboard: KeyboardSettings,
+
+    #[serde(default)]
+    pub modular: ModularSettings,
+
+    #[serde(default)]
+    pub chords: ChordSettings,
+
+    #[serde(default)]
+    pub livecoding: LiveCodingSettings,
+
+    #[serde(default)]
+    pub lights: LightSettings,
+
+    #[serde(default)]
+    pub scenes: HashMap<String, SceneConfig>,
+
+    /// Pad legends shown on the screen while a button is held, by button name.
+    #[serde(default)]
+    pub overlays: HashMap<String, OverlayConfig>,
+
+    #[serde(default)]
+    pub osc: OscSettings,
+
+    #[serde(default)]
+    pub fifo: FifoSettings,
+
+    #[serde(default)]
+    pub lock: LockSettings,
+
+    #[serde(default)]
+    pub confirm: ConfirmSettings,
+
+    #[serde(default)]
+    pub autosave: AutosaveSettings,
+    #[serde(default)]
+    pub midi_export: MidiExportSettings,
+    #[serde(default)]
+    pub groove: GrooveSettings,
+
+    #[serde(default)]
+    pub patterns: PatternSettings,
+
+    #[serde(default)]
+    pub watchdog: WatchdogSettings,
+
+    #[serde(default)]
+    pub polling: PollingSettings,
+
+    #[serde(default)]
+    pub realtime: RealtimeSettings,
+
+    #[serde(default)]
+    pub startup: StartupSettings,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
+            config_version: CURRENT_CONFIG_VERSION,
+            split: None,
+            pad_banks: PadBankSettings::default(),
+            velocity_layers: HashMap::new(),
+            pad_calibration: HashMap::new(),
+            remap: RemapSettings::default(),
+            pad_grid: PadGridSettings::default(),
             notemaps: vec![
                 49, 27, 31, 57, 48, 47, 43, 59, 36, 38, 46, 51, 36, 38, 42, 44,
             ],
             client_name: "Maschine Mikro MK3".to_string(),
             port_name: "Maschine Mikro MK3 MIDI Out".to_string(),
+            input_port_name: "Maschine Mikro MK3 MIDI In".to_string(),
             osc_ip: "127.0.0.1".to_string(),
             osc_port: 57120,
             osc_listen_port: 57121, // Default listener port
             button_configs: HashMap::new(),
+            group_configs: HashMap::new(),
+            knobs: Vec::new(),
+            deck: Vec::new(),
+            macros: HashMap::new(),
+            allow_exec: false,
+            encoder: EncoderSettings::default(),
+            accessibility: AccessibilitySettings::default(),
+            practice: PracticeSettings::default(),
+            show: ShowSettings::default(),
+            visualizer: VisualizerSettings::default(),
+            audio: AudioSettings::default(),
+            pad_feedback: PadFeedbackSettings::default(),
+            transport: TransportSettings::default(),
+            snapshots: SnapshotSettings::default(),
+            attract: AttractSettings::default(),
+            fixed_velocity: FixedVelocitySettings::default(),
+            midi_queue: MidiQueueSettings::default(),
+            note_guard: NoteGuardSettings::default(),
+            sysex: SysexSettings::default(),
+            live: LiveSettings::default(),
+            bitwig: BitwigSettings::default(),
+            reaper: ReaperSettings::default(),
+            mixxx: MixxxSettings::default(),
+            arp: ArpSettings::default(),
+            note_repeat: NoteRepeatSettings::default(),
+            clock_out: ClockOutSettings::default(),
+            clock_in: ClockInSettings::default(),
+            loop_wrap: LoopWrapSettings::default(),
+            link: LinkSettings::default(),
+            metronome: MetronomeSettings::default(),
+            polymeter: PolymeterSettings::default(),
+            keyboard: KeyboardSettings::default(),
+            modular: ModularSettings::default(),
+            chords: ChordSettings::default(),
+            livecoding: LiveCodingSettings::default(),
+            lights: LightSettings::default(),
+            scenes: HashMap::new(),
+            pad_names: Vec::new(),
+            midi_channel: 1,
+            pad_channels: Vec::new(),
+            slider: SliderSettings::default(),
+            pad_osc: PadOscSettings::default(),
+            overlays: HashMap::new(),
+            osc: OscSettings::default(),
+            fifo: FifoSettings::default(),
+            lock: LockSettings::default(),
+            confirm: ConfirmSettings::default(),
+            autosave: AutosaveSettings::default(),
+            midi_export: MidiExportSettings::default(),
+            groove: GrooveSettings::default(),
+            patterns: PatternSettings::default(),
+            watchdog: WatchdogSettings::default(),
+            polling: PollingSettings::default(),
+            realtime: RealtimeSettings::default(),
+            startup: StartupSettings::default(),
         }
     }
 }
 
 impl Settings {
+    /// OSC layout for a button's messages; buttons without a config send ints.
+    pub(crate) fn button_osc_format(&self, name: &str) -> OscFormat {
+        self.button_configs.get(name).map_or(OscFormat::Int, |c| c.osc_format)
+    }
+
+    pub(crate) fn pad_name(&self, index: usize) -> Option<&str> {
+        self.pad_names.get(index).map(String::as_str).filter(|n| !n.is_empty())
+    }
+
+    /// Number of pad banks; 1 without `[pad_banks]`.
+    pub(crate) fn bank_count(&self) -> usize {
+        match &self.pad_banks {
+            banks if !banks.enabled => 1,
+            banks if banks.notemaps.is_empty() => 8,
+            banks => banks.notemaps.len(),
+        }
+    }
+
+    /// 0-based channel of a control with `own` channel (1-16), or `midi_channel` without one
+    pub(crate) fn channel(&self, own: Option<u8>) -> u8 {
+        own.unwrap_or(self.midi_channel) - 1
+    }
+
+    /// 0-based channel a button's CC goes out on
+    pub(crate) fn button_channel(&self, name: &str) -> u8 {
+        self.channel(self.button_configs.get(name).and_then(|c| c.channel))
+    }
+
+    /// Channel (0-based) and note a pad plays in `bank`, following the split layout when
+    /// there is one.
+    pub(crate) fn pad_note(&self, index: usize, bank: usize) -> (u8, u8) {
+        let channel = self.channel(self.pad_channels.get(index).copied());
+        match &self.split {
+            Some(split) => {
+                let zone = if index % 4 < 2 { &split.left } else { &split.right };
+                (zone.channel - 1, zone.notes[index / 4 * 2 + index % 2])
+            }
+            None => match self.pad_banks.notemaps.get(bank) {
+                Some(notes) if self.pad_banks.enabled => (channel, notes[index]),
+                _ => (channel, ((self.notemaps[index] as usize + 16 * bank) % 128) as u8),
+            },
+        }
+    }
+
+    /// Channel and note for a hit of `velocity` in `bank`, after the pad's velocity layers
+    pub(crate) fn pad_note_at(&self, index: usize, bank: usize, velocity: u8) -> (u8, u8) {
+        let (channel, note) = self.pad_note(index, bank);
+        let layer = self
+            .velocity_layers
+            .get(&(index + 1).to_string())
+            .and_then(|layers| layers.iter().filter(|l| velocity >= l.velocity).max_by_key(|l| l.velocity));
+        (channel, layer.map_or(note, |l| l.note))
+    }
+
+    /// The pad that plays `note` on `channel` in `bank`, at any velocity
+    pub(crate) fn pad_for_note(&self, channel: u8, note: u8, bank: usize) -> Option<usize> {
+        (0..16).find(|i| {
+            let (c, n) = self.pad_note(*i, bank);
+            let layers = self.velocity_layers.get(&(i + 1).to_string());
+            c == channel && (n == note || layers.is_some_and(|l| l.iter().any(|l| l.note == note)))
+        })
+    }
+
+    /// Color of the split zone a pad belongs to, or of the pad bank in use, for pads with
+    /// nothing else to show
+    pub(crate) fn pad_zone_color(&self, index: usize, bank: usize) -> PadColors {
+        match &self.split {
+            Some(split) if index % 4 < 2 => split.left.color.pad_color(),
+            Some(split) => split.right.color.pad_color(),
+            None if self.pad_banks.enabled => self.bank_color(bank),
+            None => PadColors::Off,
+        }
+    }
+
+    /// Color of a pad bank; banks past the end of `[pad_banks] colors` are white.
+    pub(crate) fn bank_color(&self, bank: usize) -> PadColors {
+        self.pad_banks.colors.get(bank).map_or(PadColors::White, |c| c.pad_color())
+    }
+
+    /// Takes over from `old` what the driver only reads at startup, its ports, sockets,
+    /// threads and the helpers built from them, for a config reloaded while it runs.
+    /// Returns the sections that changed and only apply after a restart.
+    pub(crate) fn keep_startup(&mut self, old: &mut Settings) -> Vec<&'static str> {
+        let mut restart = Vec::new();
+        macro_rules! keep {
+            ($($field:ident),*) => {$(
+                if format!("{:?}", self.$field) != format!("{:?}", old.$field) {
+                    restart.push(stringify!($field));
+                }
+                self.$field = std::mem::take(&mut old.$field);
+            )*};
+        }
+        keep!(client_name, port_name, input_port_name, osc_ip, osc_port, osc_listen_port, osc, fifo, audio, midi_queue, note_guard);
+        keep!(realtime, startup, link, lock, arp, accessibility);
+        if self.needs_uinput() && !old.needs_uinput() {
+            restart.push("keyboard and mouse output");
+        }
+        restart
+    }
+
+    /// True when some mapping sends keyboard or mouse events.
+    pub(crate) fn needs_uinput(&self) -> bool {
+        self.encoder.output != EncoderOutput::Osc
+            || self.macro_steps().any(|s| s.needs_uinput())
+    }
+
+    pub(crate) fn macro_steps(&self) -> impl Iterator<Item = &MacroStep> {
+        self.deck
+            .iter()
+            .flat_map(|p| &p.steps)
+            .chain(self.button_configs.values().flat_map(|c| &c.on_press))
+            .chain(self.macros.values().flatten())
+    }
+
     pub(crate) fn validate(&self) -> Result<(), String> {
         // todo: is there a better way to do it that doesn't bring too many new useless dependencies?
 
@@ -84,10 +1933,150 @@ impl Settings {
             return Err(format!("The should be 16 pads exactly (found {padcnt})"));
         }
 
+        let channel_ok = |c: &Option<u8>| c.is_none_or(|c| (1..=16).contains(&c));
+        if !(1..=16).contains(&self.midi_channel) {
+            return Err("midi_channel must be between 1 and 16".to_string());
+        }
+        if self.pad_channels.len() > 16 {
+            return Err(format!("pad_channels: there are 16 pads (found {})", self.pad_channels.len()));
+        }
+        if self.pad_channels.iter().any(|c| !(1..=16).contains(c)) {
+            return Err("pad_channels must be between 1 and 16".to_string());
+        }
+        if let Some((name, _)) = self.button_configs.iter().find(|(_, c)| !channel_ok(&c.channel)) {
+            return Err(format!("button_configs.{name}.channel must be between 1 and 16"));
+        }
+        if let Some((id, _)) = self.group_configs.iter().find(|(_, c)| !channel_ok(&c.channel)) {
+            return Err(format!("group_configs.{id}.channel must be between 1 and 16"));
+        }
+        if let Some(knob) = self.knobs.iter().find(|k| !channel_ok(&k.channel)) {
+            return Err(format!("knobs: {}: channel must be between 1 and 16", knob.name));
+        }
+        if !channel_ok(&self.slider.channel) {
+            return Err("slider.channel must be between 1 and 16".to_string());
+        }
+        if self.slider.cc.is_some_and(|cc| cc >= 128) {
+            return Err("slider.cc must be 0 to 127".to_string());
+        }
+
         if self.notemaps.iter().any(|x| *x >= 128) {
             return Err("MIDI notes should be 0 to 127".to_string());
         }
 
+        if let Some(split) = &self.split {
+            for (side, zone) in [("left", &split.left), ("right", &split.right)] {
+                if zone.notes.len() != 8 {
+                    return Err(format!("split.{side}: a zone has 8 pads (found {} notes)", zone.notes.len()));
+                }
+                if zone.notes.iter().any(|n| *n >= 128) {
+                    return Err(format!("split.{side}: MIDI notes should be 0 to 127"));
+                }
+                if !(1..=16).contains(&zone.channel) {
+                    return Err(format!("split.{side}.channel must be between 1 and 16"));
+                }
+            }
+        }
+
+        if self.pad_banks.enabled {
+            if self.split.is_some() {
+                return Err("pad_banks: banks and [split] both replace notemaps; use one of them".to_string());
+            }
+            if !matches!(button_from_name(&self.pad_banks.button), Some(Buttons::Group | Buttons::Scene | Buttons::Pattern | Buttons::Shift)) {
+                return Err(format!("pad_banks.button should be Group, Scene, Pattern or Shift (found {})", self.pad_banks.button));
+            }
+            if button_from_name(&self.pad_banks.button) == Some(Buttons::Group) && self.snapshots.enabled {
+                return Err("pad_banks.button: Group+pad is taken by [snapshots]; pick another button or turn snapshots off".to_string());
+            }
+            if self.pad_banks.notemaps.len() > 8 {
+                return Err("pad_banks.notemaps: there are 8 banks at most".to_string());
+            }
+            for (bank, notes) in self.pad_banks.notemaps.iter().enumerate() {
+                if notes.len() != 16 {
+                    return Err(format!("pad_banks.notemaps: bank {} should have 16 notes (found {})", bank + 1, notes.len()));
+                }
+                if notes.iter().any(|n| *n >= 128) {
+                    return Err(format!("pad_banks.notemaps: bank {}: MIDI notes should be 0 to 127", bank + 1));
+                }
+            }
+        }
+
+        for (pad, layers) in &self.velocity_layers {
+            if !pad.parse::<usize>().is_ok_and(|p| (1..=16).contains(&p)) {
+                return Err(format!("velocity_layers.{pad}: pads are numbered 1 to 16"));
+            }
+            if layers.iter().any(|l| l.velocity >= 128 || l.note >= 128) {
+                return Err(format!("velocity_layers.{pad}: velocities and notes should be 0 to 127"));
+            }
+        }
+        for (pad, [min, max]) in &self.pad_calibration {
+            if !pad.parse::<usize>().is_ok_and(|p| (1..=16).contains(&p)) {
+                return Err(format!("pad_calibration.{pad}: pads are numbered 1 to 16"));
+            }
+            if min >= max || *max > PAD_FULL_SCALE {
+                return Err(format!("pad_calibration.{pad}: expected [min, max] with min < max <= {PAD_FULL_SCALE}"));
+            }
+        }
+
+        if self.lock.enabled && button_from_name(&self.lock.button).is_none() {
+            return Err(format!("lock.button: unknown button {}", self.lock.button));
+        }
+
+        if self.confirm.timeout_ms == 0 || self.confirm.hold_ms == 0 {
+            return Err("confirm.timeout_ms and confirm.hold_ms should be above 0".to_string());
+        }
+
+        if self.autosave.interval_s == 0 || self.autosave.keep == 0 {
+            return Err("autosave.interval_s and autosave.keep should be above 0".to_string());
+        }
+
+        for (i, follow) in self.patterns.follow.iter().enumerate() {
+            if !(1..=16).contains(&follow.pattern) || follow.next.iter().any(|p| !(1..=16).contains(p)) {
+                return Err(format!("patterns.follow: patterns are 1 to 16 (pattern {}, next {:?})", follow.pattern, follow.next));
+            }
+            if follow.passes == 0 || follow.passes > 64 || follow.next.is_empty() {
+                return Err(format!("patterns.follow: pattern {} needs 1 to 64 passes and a next pattern", follow.pattern));
+            }
+            if self.patterns.follow[..i].iter().any(|f| f.pattern == follow.pattern) {
+                return Err(format!("patterns.follow: pattern {} has two follow actions", follow.pattern));
+            }
+        }
+
+        if ![0, 90, 180, 270].contains(&self.pad_grid.rotation) {
+            return Err(format!("pad_grid.rotation should be 0, 90, 180 or 270 (found {})", self.pad_grid.rotation));
+        }
+
+        for (from, to) in &self.remap.buttons {
+            if button_from_name(from).is_none() {
+                return Err(format!("remap.buttons: unknown button {from}"));
+            }
+            if !to.eq_ignore_ascii_case("none") && button_from_name(to).is_none() {
+                return Err(format!("remap.buttons.{from}: unknown button {to}"));
+            }
+        }
+        for (from, to) in &self.remap.pads {
+            if !from.parse::<u8>().is_ok_and(|p| (1..=16).contains(&p)) {
+                return Err(format!("remap.pads.{from}: pads are numbered 1 to 16"));
+            }
+            if *to > 16 {
+                return Err(format!("remap.pads.{from}: pads are numbered 1 to 16, or 0 to disable"));
+            }
+        }
+        for (from, to) in &self.remap.button_pads {
+            if button_from_name(from).is_none() {
+                return Err(format!("remap.button_pads: unknown button {from}"));
+            }
+            if !(1..=16).contains(to) {
+                return Err(format!("remap.button_pads.{from}: pads are numbered 1 to 16"));
+            }
+        }
+
+        if self.config_version > CURRENT_CONFIG_VERSION {
+            return Err(format!(
+                "config_version {} is newer than this driver understands ({CURRENT_CONFIG_VERSION})",
+                self.config_version
+            ));
+        }
+
         if self.client_name.is_empty() {
             return Err("Client name must not be empty".to_string());
         }
@@ -96,6 +2085,368 @@ impl Settings {
             return Err("Port name must not be empty".to_string());
         }
 
+        for (id, group) in &self.group_configs {
+            let group_id: u8 = id.parse().map_err(|_| format!("group_configs.{id}: group ids are numbers 0-255"))?;
+            if let Some(default) = &group.default {
+                let member = self.button_configs.iter().find(|(name, _)| name.eq_ignore_ascii_case(default));
+                if !member.is_some_and(|(_, c)| c.mode == ButtonMode::Toggle && c.group_id == Some(group_id)) {
+                    return Err(format!("group_configs.{id}: default {default} is not a toggle in group {id}"));
+                }
+            }
+        }
+
+        if !(1..=16).contains(&self.live.channel) {
+            return Err("live.channel must be between 1 and 16".to_string());
+        }
+        if !(1..=16).contains(&self.bitwig.channel) {
+            return Err("bitwig.channel must be between 1 and 16".to_string());
+        }
+        if self.mixxx.pads.len() > 16 {
+            return Err(format!("mixxx.pads: at most 16 pads (found {})", self.mixxx.pads.len()));
+        }
+        if let Some(pad) = self.mixxx.pads.iter().find(|p| mixxx::split_control(p).is_none()) {
+            return Err(format!("mixxx.pads: expected \"[Group],key\" (found {pad})"));
+        }
+        for (button, control) in &self.mixxx.buttons {
+            if button_from_name(button).is_none() {
+                return Err(format!("mixxx.buttons: unknown button {button}"));
+            }
+            if mixxx::split_control(control).is_none() {
+                return Err(format!("mixxx.buttons.{button}: expected \"[Group],key\" (found {control})"));
+            }
+        }
+        if self.mixxx.pad_on == 0 || self.mixxx.pad_on >= 128 {
+            return Err("mixxx.pad_on must be between 1 and 127".to_string());
+        }
+        if self.arp.enabled && button_from_name(&self.arp.button).is_none() {
+            return Err(format!("arp.button: unknown button {}", self.arp.button));
+        }
+        if !RATES.contains(&self.arp.rate) {
+            return Err(format!("arp.rate should be 4, 8, 12, 16, 24 or 32 (found {})", self.arp.rate));
+        }
+        if !(1..=4).contains(&self.arp.octaves) {
+            return Err("arp.octaves must be between 1 and 4".to_string());
+        }
+        if !(1..=100).contains(&self.arp.gate) {
+            return Err("arp.gate must be between 1 and 100".to_string());
+        }
+        if !(20.0..=300.0).contains(&self.arp.bpm) {
+            return Err("arp.bpm must be between 20 and 300".to_string());
+        }
+        if !RATES.contains(&self.note_repeat.rate) {
+            return Err(format!("note_repeat.rate should be 4, 8, 12, 16, 24 or 32 (found {})", self.note_repeat.rate));
+        }
+        if !(20.0..=300.0).contains(&self.note_repeat.bpm) {
+            return Err("note_repeat.bpm must be between 20 and 300".to_string());
+        }
+        if self.note_repeat.enabled && self.arp.enabled && button_from_name(&self.arp.button) == Some(Buttons::NoteRepeat) {
+            return Err("arp.button: Note Repeat is taken by [note_repeat]; give the arpeggiator another button".to_string());
+        }
+        if !(1..=64).contains(&self.clock_out.beats_per_loop) {
+            return Err("clock_out.beats_per_loop must be between 1 and 64".to_string());
+        }
+        if let Some(track) = self.loop_wrap.tracks.iter().find(|t| !(1..=16).contains(&t.channel)) {
+            return Err(format!("loop_wrap.tracks: channel should be 1 to 16 (found {})", track.channel));
+        }
+        for (i, track) in self.polymeter.tracks.iter().enumerate() {
+            if !(1..=16).contains(&track.channel) {
+                return Err(format!("polymeter.tracks: channel should be 1 to 16 (found {})", track.channel));
+            }
+            if !(1..=64).contains(&track.beats) {
+                return Err(format!("polymeter.tracks: beats should be 1 to 64 (found {} on channel {})", track.beats, track.channel));
+            }
+            if self.polymeter.tracks[..i].iter().any(|t| t.channel == track.channel) {
+                return Err(format!("polymeter.tracks: channel {} has two tracks", track.channel));
+            }
+        }
+        if self.groove.amount > 100 {
+            return Err("groove.amount must be between 0 and 100".to_string());
+        }
+        if self.groove.swing > MAX_SWING {
+            return Err(format!("groove.swing must be between 0 and {MAX_SWING}"));
+        }
+        if self.metronome.enabled {
+            let metronome = &self.metronome;
+            if !(20.0..=300.0).contains(&metronome.bpm) {
+                return Err("metronome.bpm must be between 20 and 300".to_string());
+            }
+            if button_from_name(&metronome.button).is_none() {
+                return Err(format!("metronome.button: unknown button {}", metronome.button));
+            }
+            if metronome.pad.is_some_and(|pad| !(1..=16).contains(&pad)) {
+                return Err("metronome.pad must be between 1 and 16".to_string());
+            }
+            if metronome.click_note.is_some_and(|note| note > 127) || !(1..=16).contains(&metronome.click_channel) {
+                return Err("metronome: click_note should be 0-127 and click_channel 1-16".to_string());
+            }
+            if !(1..=127).contains(&metronome.velocity) || !(1..=127).contains(&metronome.accent_velocity) {
+                return Err("metronome: velocity and accent_velocity should be 1-127".to_string());
+            }
+        }
+        if !(1..=16).contains(&self.link.quantum) {
+            return Err("link.quantum must be between 1 and 16".to_string());
+        }
+        if self.link.enabled && self.clock_in.enabled {
+            return Err("link and clock_in can't both be on: the loop follows one clock".to_string());
+        }
+        if self.clock_in.enabled && self.clock_out.enabled {
+            return Err("clock_in and clock_out can't both be on: the loop either follows a clock or sends one".to_string());
+        }
+        if !(1..=16).contains(&self.keyboard.channel) {
+            return Err("keyboard.channel must be between 1 and 16".to_string());
+        }
+        if self.keyboard.channels.iter().any(|c| !(1..=16).contains(c)) {
+            return Err("keyboard.channels must be between 1 and 16".to_string());
+        }
+        if self.keyboard.root >= 128 {
+            return Err("keyboard.root should be a MIDI note, 0 to 127".to_string());
+        }
+        if !self.modular.prefix.starts_with('/') || self.modular.prefix.ends_with('/') {
+            return Err("modular.prefix must start with / and not end with one".to_string());
+        }
+        if self.modular.trigger_ms > 1000 {
+            return Err("modular.trigger_ms must be 1000 or less".to_string());
+        }
+        if !(1..=16).contains(&self.chords.channel) {
+            return Err("chords.channel must be between 1 and 16".to_string());
+        }
+        if self.chords.channels.iter().any(|c| !(1..=16).contains(c)) {
+            return Err("chords.channels must be between 1 and 16".to_string());
+        }
+        if self.chords.pads.len() > 16 {
+            return Err(format!("There are 16 pads to play chords on (found {} chords)", self.chords.pads.len()));
+        }
+        for (i, pad) in self.chords.pads.iter().enumerate() {
+            if pad.root >= 128 {
+                return Err(format!("chords.pads[{i}]: root should be a MIDI note, 0 to 127"));
+            }
+            if self.chords.intervals(&pad.chord).is_none() {
+                return Err(format!("chords.pads[{i}]: unknown chord {}", pad.chord));
+            }
+        }
+        if self.livecoding.pad_groups.len() > 16 {
+            return Err(format!("livecoding.pad_groups: there are 16 pads (found {} groups)", self.livecoding.pad_groups.len()));
+        }
+        for (name, voicing) in &self.chords.voicings {
+            if voicing.is_empty() || voicing.iter().any(|i| *i >= 48) {
+                return Err(format!("chords.voicings.{name}: give 1 or more intervals, each under 48 semitones"));
+            }
+        }
+        if !(1..=16).contains(&self.accessibility.cue_channel) {
+            return Err("accessibility.cue_channel must be between 1 and 16".to_string());
+        }
+
+        if self.accessibility.cue_velocity == 0 || self.accessibility.cue_velocity >= 128 {
+            return Err("accessibility.cue_velocity must be between 1 and 127".to_string());
+        }
+
+        if !(20.0..=300.0).contains(&self.practice.bpm) {
+            return Err("practice.bpm must be between 20 and 300".to_string());
+        }
+        if !(1..=16).contains(&self.practice.tap_average) {
+            return Err("practice.tap_average must be between 1 and 16".to_string());
+        }
+        if !(1..=8).contains(&self.practice.subdivision) {
+            return Err("practice.subdivision must be between 1 and 8".to_string());
+        }
+        if self.practice.click_note.is_some_and(|n| n >= 128) {
+            return Err("practice.click_note must be between 0 and 127".to_string());
+        }
+        if !(1..=16).contains(&self.practice.click_channel) {
+            return Err("practice.click_channel must be between 1 and 16".to_string());
+        }
+
+        if self.pad_feedback.channel.is_some_and(|c| !(1..=16).contains(&c)) {
+            return Err("pad_feedback.channel must be between 1 and 16".to_string());
+        }
+        if self.pad_feedback.colors.iter().any(|c| c.velocity >= 128) {
+            return Err("pad_feedback.colors: velocities should be 0 to 127".to_string());
+        }
+
+        if !self.show.prefix.starts_with('/') || self.show.prefix.ends_with('/') {
+            return Err("show.prefix must start with / and not end with one".to_string());
+        }
+
+        if !(1..=120).contains(&self.visualizer.max_fps) {
+            return Err("visualizer.max_fps should be 1 to 120".to_string());
+        }
+
+        if self.audio.range_db <= 0.0 || self.audio.onset_threshold <= 1.0 {
+            return Err("audio.range_db should be above 0 and audio.onset_threshold above 1".to_string());
+        }
+
+        if self.transport.beats_per_bar == 0 {
+            return Err("transport.beats_per_bar must be at least 1".to_string());
+        }
+        if ![2, 4, 8, 16].contains(&self.transport.beat_unit) {
+            return Err("transport.beat_unit must be 2, 4, 8 or 16".to_string());
+        }
+
+        if let Some(button) = self.lights.buttons.keys().find(|b| *b != "all" && button_from_name(b).is_none()) {
+            return Err(format!("lights.buttons: unknown button {button}"));
+        }
+
+        for (name, scene) in &self.scenes {
+            if name.eq_ignore_ascii_case("off") {
+                return Err("scenes: \"off\" is reserved for clearing the scene".to_string());
+            }
+            if scene.pads.len() > 16 {
+                return Err(format!("scenes.{name}: there are 16 pads (found {} colors)", scene.pads.len()));
+            }
+            if let Some(button) = scene.buttons.keys().find(|b| button_from_name(b).is_none()) {
+                return Err(format!("scenes.{name}: unknown button {button}"));
+            }
+            if scene.program.is_some_and(|p| p >= 128) {
+                return Err(format!("scenes.{name}: program should be 0 to 127"));
+            }
+        }
+
+        if self.pad_names.len() > 16 {
+            return Err(format!("There are 16 pads to name (found {} names)", self.pad_names.len()));
+        }
+
+        for (name, overlay) in &self.overlays {
+            if button_from_name(name).is_none() {
+                return Err(format!("overlays: unknown button {name}"));
+            }
+            if overlay.pads.len() > 16 {
+                return Err(format!("overlays.{name}: there are 16 pads (found {} labels)", overlay.pads.len()));
+            }
+        }
+
+        if self.encoder.amount == 0 {
+            return Err("encoder.amount must be at least 1".to_string());
+        }
+
+        if self.knobs.len() > 16 {
+            return Err(format!("There are 16 pads to select knobs with (found {} knobs)", self.knobs.len()));
+        }
+        for (i, knob) in self.knobs.iter().enumerate() {
+            if knob.initial >= 128 || knob.cc.is_some_and(|cc| cc >= 128) {
+                return Err(format!("knobs[{i}]: MIDI values should be 0 to 127"));
+            }
+            if knob.step == 0 {
+                return Err(format!("knobs[{i}]: step must be at least 1"));
+            }
+        }
+
+        if self.deck.len() > 16 {
+            return Err(format!("The deck has 16 pads (found {} entries)", self.deck.len()));
+        }
+        for (i, pad) in self.deck.iter().enumerate() {
+            for step in &pad.steps {
+                step.validate().map_err(|e| format!("deck[{i}]: {e}"))?;
+            }
+        }
+        for (name, config) in &self.button_configs {
+            for step in &config.on_press {
+                step.validate().map_err(|e| format!("button_configs.{name}.on_press: {e}"))?;
+            }
+        }
+        for (name, steps) in &self.macros {
+            for step in steps {
+                step.validate().map_err(|e| format!("macros.{name}: {e}"))?;
+                // Named macros don't call each other, so none can end up calling itself.
+                if let MacroStep::Macro { .. } = step {
+                    return Err(format!("macros.{name}: named macros can't run other macros"));
+                }
+            }
+        }
+        for step in self.macro_steps() {
+            if let MacroStep::Macro { name } = step
+                && !self.macros.contains_key(name)
+            {
+                return Err(format!("No macro named {name} in [macros]"));
+            }
+        }
+        if !self.allow_exec && self.macro_steps().any(|s| s.is_exec()) {
+            return Err("Macros with exec steps need allow_exec = true".to_string());
+        }
+
+        if self.osc.subscription_ttl_s == 0 {
+            return Err("osc.subscription_ttl_s must be at least 1".to_string());
+        }
+        for (name, dest) in &self.osc.destinations {
+            if dest.address.is_empty() {
+                return Err(format!("osc.destinations.{name} needs an address"));
+            }
+        }
+        for entry in &self.osc.listen {
+            crate::osc::listen_addr(entry, self.osc_listen_port).map_err(|e| format!("osc.listen: {e}"))?;
+        }
+        let failover = &self.osc.failover;
+        if failover.enabled {
+            if failover.backup.is_empty() {
+                return Err("osc.failover needs a backup address".to_string());
+            }
+            if failover.primary.is_empty() && (!self.osc.destinations.is_empty() || !self.osc.default_peer) {
+                return Err("osc.failover.primary: name the destination to watch".to_string());
+            }
+            if !failover.primary.is_empty() && !self.osc.destinations.contains_key(&failover.primary) {
+                return Err(format!("osc.failover.primary: no destination named {}", failover.primary));
+            }
+            if failover.interval_ms == 0 || failover.timeout_ms <= failover.interval_ms {
+                return Err("osc.failover.timeout_ms must be longer than interval_ms, which must be at least 1".to_string());
+            }
+        }
+
+        if self.note_guard.window_ms == 0 {
+            return Err("note_guard.window_ms must be at least 1".to_string());
+        }
+        if self.midi_queue.capacity == 0 || self.midi_queue.max_per_pass == 0 {
+            return Err("midi_queue.capacity and max_per_pass must be at least 1".to_string());
+        }
+
+        if self.fixed_velocity.enabled {
+            let fixed = &self.fixed_velocity;
+            if button_from_name(&fixed.button).is_none() {
+                return Err(format!("fixed_velocity.button: unknown button {}", fixed.button));
+            }
+            if button_from_name(&fixed.accent_button).is_none() {
+                return Err(format!("fixed_velocity.accent_button: unknown button {}", fixed.accent_button));
+            }
+            if fixed.button.eq_ignore_ascii_case(&fixed.accent_button) {
+                return Err("fixed_velocity.button and accent_button must differ".to_string());
+            }
+            if !(1..=127).contains(&fixed.velocity) || !(1..=127).contains(&fixed.accent) {
+                return Err("fixed_velocity: velocity and accent must be between 1 and 127".to_string());
+            }
+        }
+
+        if self.attract.enabled {
+            if self.attract.idle_s == 0 {
+                return Err("attract.idle_s must be at least 1".to_string());
+            }
+            if self.attract.phrase.iter().any(|n| *n >= 128) {
+                return Err("attract.phrase: MIDI notes should be 0 to 127".to_string());
+            }
+            if !(1..=16).contains(&self.attract.channel) {
+                return Err("attract.channel must be between 1 and 16".to_string());
+            }
+            if !(1..=127).contains(&self.attract.velocity) {
+                return Err("attract.velocity must be between 1 and 127".to_string());
+            }
+            if self.attract.step_ms == 0 {
+                return Err("attract.step_ms must be at least 1".to_string());
+            }
+        }
+
+        if self.polling.active_interval_ms > self.polling.idle_interval_ms {
+            return Err("polling.active_interval_ms must not exceed polling.idle_interval_ms".to_string());
+        }
+
+        if !(1..=99).contains(&self.realtime.priority) {
+            return Err("realtime.priority must be between 1 and 99".to_string());
+        }
+
+        if !(1..=4).contains(&self.startup.text_scale) {
+            return Err("startup.text_scale must be between 1 and 4".to_string());
+        }
+
+        if self.watchdog.failure_threshold == 0 {
+            return Err("watchdog.failure_threshold must be at least 1".to_string());
+        }
+
         Ok(())
     }
 }
\ No newline at end of file
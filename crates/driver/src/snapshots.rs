@@ -0,0 +1,97 @@
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::DriverContext;
+use crate::groups::GroupState;
+use crate::modes::custom_midi::button_from_name;
+use crate::modes::play_mode::Mix;
+use crate::settings::ButtonMode;
+
+pub const SLOTS: usize = 4;
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    /// Every toggle button, by its config name, and whether it was on
+    toggles: Vec<(String, bool)>,
+    /// Light scene that was up, if any
+    scene: Option<String>,
+    /// Patterns Play mode's tracks were playing
+    mix: Mix,
+}
+
+/// Four performance snapshots of the toggle states (mutes, exclusive groups), the light
+/// scene and Play mode's patterns. Shift+Group+pad 1-4 stores one, Group+pad 1-4 or
+/// `/maschine/snapshot 1-4` recalls it: the Mikro has one Group button, so pads 1-4 pick
+/// the slot.
+pub struct Snapshots {
+    slots: [Option<Snapshot>; SLOTS],
+}
+
+impl Snapshots {
+    pub fn new() -> Self {
+        Self { slots: Default::default() }
+    }
+
+    pub fn store(&mut self, slot: usize, scene: Option<&str>, mix: Mix, ctx: &DriverContext) {
+        self.slots[slot] = Some(Snapshot { toggles: toggle_states(ctx), scene: scene.map(str::to_string), mix });
+    }
+
+    pub fn is_stored(&self, slot: usize) -> bool {
+        self.slots.get(slot).is_some_and(Option::is_some)
+    }
+
+    /// Puts the toggles back and returns the scene to recall ("off" when none was up) and
+    /// Play mode's patterns, or None for an empty slot.
+    pub fn recall(&self, slot: usize, ctx: &mut DriverContext) -> Option<(String, Mix)> {
+        let snapshot = self.slots.get(slot)?.as_ref()?;
+        set_toggles(&snapshot.toggles, ctx);
+        Some((snapshot.scene.clone().unwrap_or_else(|| "off".to_string()), snapshot.mix))
+    }
+}
+
+/// Every toggle button by its config name, in button order, and whether it's on.
+pub fn toggle_states(ctx: &DriverContext) -> Vec<(String, bool)> {
+    let mut toggles: Vec<(String, bool)> = ctx
+        .settings
+        .button_configs
+        .iter()
+        .filter(|(_, config)| config.mode == ButtonMode::Toggle)
+        .filter_map(|(name, _)| button_from_name(name).map(|b| (name.clone(), ctx.groups.is_on(b))))
+        .collect();
+    toggles.sort_by_key(|(name, _)| button_from_name(name).map(|b| b as usize));
+    toggles
+}
+
+/// Sets toggles by config name, sending each one that changes as a press would so the
+/// host follows along. Names that aren't toggles in this config are skipped.
+pub fn set_toggles(toggles: &[(String, bool)], ctx: &mut DriverContext) {
+    let mut changed_groups = Vec::new();
+    for (name, on) in toggles {
+        let (Some(config), Some(button)) = (ctx.settings.button_configs.get(name), button_from_name(name)) else {
+            continue;
+        };
+        if config.mode != ButtonMode::Toggle || ctx.groups.is_on(button) == *on {
+            continue;
+        }
+        for other_name in ctx.groups.set(button, *on, ctx.settings) {
+            let format = ctx.settings.button_osc_format(&other_name);
+            ctx.osc.send_formatted(&format!("/maschine/{}", other_name.to_lowercase()), 0, format);
+        }
+        ctx.osc.send_formatted(&format!("/maschine/{}", name.to_lowercase()), *on as i32, config.osc_format);
+        if let Some(cc) = config.cc {
+            let message = MidiMessage::Controller { controller: cc.into(), value: (if *on { 127 } else { 0 }).into() };
+            let channel = ctx.settings.channel(config.channel);
+            let mut midibuf = Vec::new();
+            if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+                let _ = ctx.midi_port.send(&midibuf[..]);
+            }
+        }
+        if let Some(group_id) = config.group_id
+            && !changed_groups.contains(&group_id)
+        {
+            changed_groups.push(group_id);
+        }
+    }
+    changed_groups.sort();
+    for group_id in changed_groups {
+        GroupState::announce(ctx, group_id, false);
+    }
+}
@@ -0,0 +1,387 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use maschine_library::controls::Buttons;
+use maschine_library::font::Font;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use midly::{live::LiveEvent, MidiMessage};
+use crate::context::MidiSink;
+use crate::settings::AccessibilitySettings;
+
+const BLINK_PERIOD_MS: u128 = 500;
+const BANNER_ROW: usize = 24;
+const BAR_HEIGHT: usize = 8;
+
+/// First screen row modes may draw on; the rows above belong to the status bar.
+pub const CONTENT_ROW: usize = BAR_HEIGHT + 1;
+
+// Audio cue pitches: higher means better news.
+const CUE_MODE: u8 = 72;
+const CUE_INFO: u8 = 79;
+const CUE_WARNING: u8 = 60;
+const CUE_ERROR: u8 = 48;
+/// How long a cue note sounds
+const CUE_LENGTH: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn cue_note(&self) -> u8 {
+        match self {
+            Severity::Info => CUE_INFO,
+            Severity::Warning => CUE_WARNING,
+            Severity::Error => CUE_ERROR,
+        }
+    }
+
+    /// How long a banner of this severity stays up; errors stay until replaced or cleared.
+    fn lifetime(&self) -> Option<Duration> {
+        match self {
+            Severity::Info => Some(Duration::from_secs(2)),
+            Severity::Warning => Some(Duration::from_secs(5)),
+            Severity::Error => None,
+        }
+    }
+}
+
+/// Background conditions shown as a color-coded corner pad.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indicator {
+    /// Outgoing OSC packets can't be delivered (red blink, top-right pad).
+    OscUnreachable,
+}
+
+impl Indicator {
+    fn pad(&self) -> usize {
+        match self {
+            Indicator::OscUnreachable => 3,
+        }
+    }
+
+    fn color(&self) -> PadColors {
+        match self {
+            Indicator::OscUnreachable => PadColors::Red,
+        }
+    }
+
+    fn blinks(&self) -> bool {
+        match self {
+            Indicator::OscUnreachable => true,
+        }
+    }
+}
+
+/// What the always-visible top line of the screen shows. Fields that are unknown
+/// (no profile loaded, no tempo, no external clock) are simply left out.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatusBar {
+    pub mode: &'static str,
+    pub profile: Option<String>,
+    pub bpm: Option<f32>,
+    /// 0-based MIDI channel, shown 1-based.
+    pub channel: u8,
+    pub clock: Option<String>,
+    /// The performance lock is on.
+    pub locked: bool,
+    /// OSC goes to the failover backup (see `failover`).
+    pub failover: bool,
+}
+
+impl StatusBar {
+    fn text(&self) -> String {
+        let mut parts = vec![self.mode.to_string()];
+        if let Some(profile) = &self.profile {
+            parts.push(profile.clone());
+        }
+        parts.push(format!("CH{}", self.channel + 1));
+        if let Some(bpm) = self.bpm {
+            parts.push(format!("{:.0}", bpm));
+        }
+        if let Some(clock) = &self.clock {
+            parts.push(clock.clone());
+        }
+        if self.locked {
+            parts.push("LOCK".to_string());
+        }
+        if self.failover {
+            parts.push("BKUP".to_string());
+        }
+        parts.join(" ")
+    }
+}
+
+struct Banner {
+    text: String,
+    severity: Severity,
+    expires: Option<Instant>,
+}
+
+/// Surfaces driver status on the device itself. Everything here is composited over
+/// what the modes drew when the frame is written, so mode state is never overwritten.
+#[derive(Default)]
+pub struct StatusDisplay {
+    bar: StatusBar,
+    banner: Option<Banner>,
+    indicators: Vec<Indicator>,
+    fault: Option<String>,
+    blink_epoch: Option<Instant>,
+    blink_on: bool,
+    accessibility: AccessibilitySettings,
+    pending_cues: Vec<u8>,
+    /// Cue notes sounding, and when each ends
+    sounding_cues: Vec<(u8, Instant)>,
+    bar_hidden: bool,
+}
+
+impl StatusDisplay {
+    pub fn new(accessibility: &AccessibilitySettings) -> Self {
+        Self {
+            accessibility: accessibility.clone(),
+            ..Self::default()
+        }
+    }
+
+    fn cue(&mut self, note: u8) {
+        if self.accessibility.audio_cues {
+            self.pending_cues.push(note);
+        }
+    }
+
+    /// Starts the audio cues queued since the last call; `tick` ends each `CUE_LENGTH` later.
+    pub fn send_cues(&mut self, port: &mut dyn MidiSink, now: Instant) {
+        let vel = self.accessibility.cue_velocity;
+        for note in std::mem::take(&mut self.pending_cues) {
+            self.send_cue(port, MidiMessage::NoteOn { key: note.into(), vel: vel.into() });
+            // A cue played again while it sounds lasts from the new start.
+            self.sounding_cues.retain(|&(n, _)| n != note);
+            self.sounding_cues.push((note, now + CUE_LENGTH));
+        }
+    }
+
+    fn send_cue(&self, port: &mut dyn MidiSink, message: MidiMessage) {
+        let channel = self.accessibility.cue_channel.saturating_sub(1);
+        let mut midibuf = Vec::new();
+        if (LiveEvent::Midi { channel: channel.into(), message }).write(&mut midibuf).is_ok() {
+            let _ = port.send(&midibuf);
+        }
+    }
+
+    /// Replaces the status bar contents; returns true when that changes what is shown.
+    pub fn set_bar(&mut self, bar: StatusBar) -> bool {
+        if self.bar == bar {
+            return false;
+        }
+        if self.bar.mode != bar.mode {
+            self.cue(CUE_MODE);
+        }
+        self.bar = bar;
+        true
+    }
+
+    /// Leaves the top rows to a mode that needs the whole screen.
+    pub fn hide_bar(&mut self, hidden: bool) {
+        self.bar_hidden = hidden;
+    }
+
+    pub fn bar(&self) -> &StatusBar {
+        &self.bar
+    }
+
+    /// Shows a one-line banner at the bottom of the screen. A lower-severity message
+    /// does not replace a banner that is still showing a higher one.
+    pub fn post(&mut self, severity: Severity, text: &str, now: Instant) {
+        if let Some(current) = &self.banner
+            && current.severity > severity
+        {
+            return;
+        }
+        self.cue(severity.cue_note());
+        self.banner = Some(Banner {
+            text: text.to_string(),
+            severity,
+            expires: severity.lifetime().map(|d| now + d),
+        });
+    }
+
+    /// Turns an indicator on or off; returns true when that changes what is shown.
+    pub fn set_indicator(&mut self, indicator: Indicator, active: bool) -> bool {
+        let present = self.indicators.contains(&indicator);
+        if active && !present {
+            self.indicators.push(indicator);
+            true
+        } else if !active && present {
+            self.indicators.retain(|i| *i != indicator);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Puts the whole surface into an error state: every pad red and a sticky error banner.
+    pub fn set_fault(&mut self, text: &str, now: Instant) {
+        self.fault = Some(text.to_string());
+        self.post(Severity::Error, text, now);
+    }
+
+    pub fn clear_fault(&mut self) {
+        if self.fault.take().is_some() {
+            self.banner = None;
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.indicators.iter().any(|i| i.blinks())
+            || self.banner.as_ref().is_some_and(|b| b.expires.is_some())
+            || !self.sounding_cues.is_empty()
+    }
+
+    /// Advances blink phases and banner expiry, and ends the cue notes that have sounded
+    /// long enough. Returns true when the frame needs redrawing.
+    pub fn tick(&mut self, now: Instant, port: &mut dyn MidiSink) -> bool {
+        let mut changed = false;
+
+        let (ended, sounding): (Vec<_>, Vec<_>) = self.sounding_cues.iter().partition(|&&(_, end)| now >= end);
+        self.sounding_cues = sounding;
+        for (note, _) in ended {
+            self.send_cue(port, MidiMessage::NoteOff { key: note.into(), vel: 0.into() });
+        }
+
+        if self.banner.as_ref().and_then(|b| b.expires).is_some_and(|t| now >= t) {
+            self.banner = None;
+            changed = true;
+        }
+
+        let epoch = *self.blink_epoch.get_or_insert(now);
+        let blink_on = (now.duration_since(epoch).as_millis() / BLINK_PERIOD_MS).is_multiple_of(2);
+        if blink_on != self.blink_on {
+            self.blink_on = blink_on;
+            changed |= self.indicators.iter().any(|i| i.blinks());
+        }
+
+        changed
+    }
+
+    pub fn compose_lights(&self, lights: &Lights) -> Lights {
+        let mut frame = lights.clone();
+        if self.accessibility.enabled {
+            boost_lights(&mut frame);
+        }
+        if self.fault.is_some() {
+            for i in 0..16 {
+                frame.set_pad(i, PadColors::Red, Brightness::Bright);
+            }
+            return frame;
+        }
+        for indicator in &self.indicators {
+            if indicator.blinks() && !self.blink_on {
+                frame.set_pad(indicator.pad(), PadColors::Off, Brightness::Off);
+            } else {
+                frame.set_pad(indicator.pad(), indicator.color(), Brightness::Bright);
+            }
+        }
+        frame
+    }
+
+    pub fn compose_screen(&self, screen: &Screen) -> Screen {
+        if self.accessibility.enabled {
+            return self.compose_screen_large(screen);
+        }
+        let mut frame = screen.clone();
+
+        // Status bar: inverted top line so it reads apart from whatever the mode draws.
+        if !self.bar_hidden {
+            clear_rows(&mut frame, 0..BAR_HEIGHT);
+            Font::write_string(&mut frame, 0, 0, &self.bar.text(), 1);
+            invert_rows(&mut frame, 0..BAR_HEIGHT);
+        }
+
+        if let Some(banner) = &self.banner {
+            clear_rows(&mut frame, BANNER_ROW..BANNER_ROW + 8);
+            Font::write_string(&mut frame, BANNER_ROW, 0, &format!("{}{}", banner.prefix(), banner.text), 1);
+            if banner.severity == Severity::Error {
+                invert_rows(&mut frame, BANNER_ROW..BANNER_ROW + 8);
+            }
+        }
+        frame
+    }
+
+    /// Accessibility layout: two lines of double-size text. The top shows the mode, the
+    /// bottom a banner if there is one, else the first 8 characters of the mode's headline.
+    fn compose_screen_large(&self, screen: &Screen) -> Screen {
+        let mut frame = Screen::new();
+        Font::write_string(&mut frame, 0, 0, self.bar.mode, 2);
+        invert_rows(&mut frame, 0..16);
+
+        match &self.banner {
+            Some(banner) => {
+                Font::write_string(&mut frame, 16, 0, &format!("{}{}", banner.prefix(), banner.text), 2);
+                if banner.severity == Severity::Error {
+                    invert_rows(&mut frame, 16..32);
+                }
+            }
+            None => {
+                for i in 0..8 {
+                    for j in 0..64 {
+                        let on = screen.get(CONTENT_ROW + i, j);
+                        for (di, dj) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                            frame.set(16 + i * 2 + di, j * 2 + dj, on);
+                        }
+                    }
+                }
+            }
+        }
+        frame
+    }
+}
+
+impl Banner {
+    fn prefix(&self) -> &'static str {
+        match self.severity {
+            Severity::Info => "",
+            Severity::Warning => "! ",
+            Severity::Error => "!! ",
+        }
+    }
+}
+
+fn clear_rows(frame: &mut Screen, rows: Range<usize>) {
+    for i in rows {
+        for j in 0..128 {
+            frame.set(i, j, false);
+        }
+    }
+}
+
+fn invert_rows(frame: &mut Screen, rows: Range<usize>) {
+    for i in rows {
+        for j in 0..128 {
+            let on = frame.get(i, j);
+            frame.set(i, j, !on);
+        }
+    }
+}
+
+/// Lifts every lit LED one brightness step so dim states are still easy to see.
+fn boost_lights(frame: &mut Lights) {
+    let boost = |b: Brightness| match b {
+        Brightness::Dim => Brightness::Normal,
+        Brightness::Normal => Brightness::Bright,
+        other => other,
+    };
+    for i in 0..39 {
+        let button: Buttons = num::FromPrimitive::from_usize(i).unwrap();
+        frame.set_button(button, boost(frame.get_button(button)));
+    }
+    for i in 0..16 {
+        let (color, b) = frame.get_pad(i);
+        frame.set_pad(i, color, boost(b));
+    }
+    for i in 0..25 {
+        frame.set_slider(i, boost(frame.get_slider(i)));
+    }
+}
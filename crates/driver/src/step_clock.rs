@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+/// MIDI clock ticks in a bar of 4/4; every rate in `RATES` divides it.
+const CLOCKS_PER_BAR: u32 = 96;
+const CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// Step rates in steps per bar of 4/4: quarters to 32nds, with the triplets in between
+pub const RATES: [u32; 6] = [4, 8, 12, 16, 24, 32];
+
+/// Steps at `rate` per bar, for the arpeggiator and note repeat: on the host's MIDI clock
+/// while it runs, otherwise on an internal clock that starts with the first step.
+pub struct StepClock {
+    /// Start of the internal clock, while it runs
+    started: Option<Instant>,
+    /// Step last reported
+    last_step: Option<u32>,
+}
+
+impl StepClock {
+    pub fn new() -> Self {
+        Self { started: None, last_step: None }
+    }
+
+    /// Stops the internal clock; the next step starts it over.
+    pub fn reset(&mut self) {
+        self.started = None;
+        self.last_step = None;
+    }
+
+    /// Returns true when a new step has started. `clock` is the host's position in MIDI
+    /// clock ticks while it runs. The internal clock starts on a step; on the host's,
+    /// which is already running, the first one comes at the next boundary.
+    pub fn step(&mut self, clock: Option<u32>, bpm: f32, rate: u32, now: Instant) -> bool {
+        let ticks = match clock {
+            Some(ticks) => {
+                self.started = None;
+                ticks
+            }
+            None => {
+                let started = *self.started.get_or_insert(now);
+                (now.duration_since(started).as_secs_f64() * bpm as f64 / 60.0 * CLOCKS_PER_BEAT) as u32
+            }
+        };
+        let step = ticks / (CLOCKS_PER_BAR / rate);
+        let due = match self.last_step {
+            Some(last) => step != last,
+            None => clock.is_none(),
+        };
+        self.last_step = Some(step);
+        due
+    }
+
+    pub fn step_length(bpm: f32, rate: u32) -> Duration {
+        Duration::from_secs_f64(60.0 / bpm as f64 * (CLOCKS_PER_BAR / rate) as f64 / CLOCKS_PER_BEAT)
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use config::{Config, FileFormat};
+use serde::{Deserialize, Serialize};
+use crate::sequence::NoteSequence;
+use crate::settings::Settings;
+
+/// Non-commercial manufacturer ID, then 'M' so other tools' dumps are left alone.
+const HEADER: [u8; 3] = [0xF0, 0x7D, 0x4D];
+const DUMP_REQUEST: u8 = 0x01;
+const DUMP_PART: u8 = 0x02;
+/// Backup bytes per message; each goes out as two nibbles.
+const PART_BYTES: usize = 128;
+
+/// Everything a SysEx dump carries: the config file as written, the toggle states and
+/// the looper's loop.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Backup {
+    pub config: Option<String>,
+    pub toggles: BTreeMap<String, bool>,
+    #[serde(rename = "loop")]
+    pub sequence: Option<NoteSequence>,
+}
+
+/// `F0 7D 4D 01 F7` asks for a dump.
+pub fn is_dump_request(bytes: &[u8]) -> bool {
+    bytes == [HEADER[0], HEADER[1], HEADER[2], DUMP_REQUEST, 0xF7]
+}
+
+/// The backup as JSON, split into `F0 7D 4D 02 <part> <parts> <data> <checksum> F7`
+/// messages: part number and count are 14-bit (two 7-bit bytes, high first), each data
+/// byte is sent as its high then low nibble, and the checksum makes the data bytes plus
+/// itself add up to a multiple of 128. Sending the messages back restores the backup.
+pub fn encode(backup: &Backup) -> Vec<Vec<u8>> {
+    let json = serde_json::to_vec(backup).expect("a backup always serializes");
+    let parts = json.len().div_ceil(PART_BYTES);
+    json.chunks(PART_BYTES)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut msg = HEADER.to_vec();
+            msg.push(DUMP_PART);
+            msg.extend_from_slice(&to_14bit(i));
+            msg.extend_from_slice(&to_14bit(parts));
+            let data: Vec<u8> = chunk.iter().flat_map(|b| [b >> 4, b & 0x0F]).collect();
+            let sum = checksum(&data);
+            msg.extend(data);
+            msg.push(sum);
+            msg.push(0xF7);
+            msg
+        })
+        .collect()
+}
+
+fn to_14bit(n: usize) -> [u8; 2] {
+    [((n >> 7) & 0x7F) as u8, (n & 0x7F) as u8]
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    let sum: u32 = data.iter().map(|b| *b as u32).sum();
+    ((128 - sum % 128) % 128) as u8
+}
+
+/// Replaces the config file with one from a backup once it loads and validates, keeping
+/// the old one as `<path>.bak`. It takes effect on the next start.
+pub fn write_config(path: &str, text: &str) -> Result<(), String> {
+    let settings: Settings = Config::builder()
+        .add_source(config::File::from_str(text, FileFormat::Toml))
+        .build()
+        .and_then(|c| c.try_deserialize())
+        .map_err(|e| format!("the restored config doesn't load: {e}"))?;
+    settings.validate().map_err(|e| format!("the restored config doesn't validate: {e}"))?;
+    let backup = format!("{path}.bak");
+    fs::copy(path, &backup).map_err(|e| format!("{backup}: {e}"))?;
+    fs::write(path, text).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Collects dump parts sent back to the driver until the backup is complete.
+#[derive(Default)]
+pub struct Restore {
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl Restore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes one incoming message; returns the backup once its last part is in, or why
+    /// it can't be used. Messages that aren't dump parts are ignored.
+    pub fn feed(&mut self, bytes: &[u8]) -> Option<Result<Backup, String>> {
+        let body = bytes.strip_prefix(&HEADER[..])?.strip_suffix(&[0xF7])?;
+        let (&[DUMP_PART, part_hi, part_lo, count_hi, count_lo], rest) = body.split_first_chunk::<5>()? else {
+            return None;
+        };
+        let (part, count) = (((part_hi as usize) << 7) | part_lo as usize, ((count_hi as usize) << 7) | count_lo as usize);
+        let Some((&sum, data)) = rest.split_last() else {
+            return Some(Err("SysEx dump part without data".to_string()));
+        };
+        if part >= count || !data.len().is_multiple_of(2) || checksum(data) != sum {
+            self.parts.clear();
+            return Some(Err(format!("SysEx dump part {} is damaged", part + 1)));
+        }
+        if part == 0 || self.parts.len() != count {
+            self.parts = vec![None; count];
+        }
+        self.parts[part] = Some(data.chunks_exact(2).map(|n| (n[0] << 4) | (n[1] & 0x0F)).collect());
+        if self.parts.iter().any(Option::is_none) {
+            return None;
+        }
+        let json: Vec<u8> = self.parts.drain(..).flatten().flatten().collect();
+        Some(serde_json::from_slice(&json).map_err(|e| format!("SysEx dump doesn't read back: {e}")))
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A pause this long starts a new run of taps.
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+const MIN_BPM: f32 = 20.0;
+const MAX_BPM: f32 = 300.0;
+
+/// Tempo from taps on a button, averaged over the last few so one early or late tap
+/// doesn't throw it off.
+pub struct TapTempo {
+    taps: VecDeque<Instant>,
+}
+
+impl TapTempo {
+    pub fn new() -> Self {
+        Self { taps: VecDeque::new() }
+    }
+
+    /// Records a tap; returns the tempo over the last `average` intervals once there are
+    /// two taps in a row.
+    pub fn tap(&mut self, now: Instant, average: usize) -> Option<f32> {
+        if self.taps.back().is_some_and(|last| now.duration_since(*last) > TAP_TIMEOUT) {
+            self.taps.clear();
+        }
+        self.taps.push_back(now);
+        while self.taps.len() > average + 1 {
+            self.taps.pop_front();
+        }
+        let (first, last) = (self.taps.front()?, self.taps.back()?);
+        let intervals = self.taps.len() as u32 - 1;
+        if intervals == 0 {
+            return None;
+        }
+        let interval = last.duration_since(*first) / intervals;
+        Some((60.0 / interval.as_secs_f32()).clamp(MIN_BPM, MAX_BPM))
+    }
+}
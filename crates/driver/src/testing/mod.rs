@@ -0,0 +1,515 @@
+//! Golden-file harness: drives a mode with a scripted sequence of hardware events
+//! against fake MIDI/OSC sinks and compares the resulting transcript with a checked-in file.
+//!
+//! Time only moves on `wait <ms>` steps, which advance a `MockClock` one millisecond at a
+//! time and tick the mode and flush the MIDI output in between, like the main loop does;
+//! the output goes through the note guard, as there. `osc`, `cc` and `note` steps feed
+//! host feedback to the mode, `loop <json>` loads a note sequence as if sent over OSC,
+//! `pattern <key> <values...>` loads one from a `/maschine/pattern` message,
+//! `dump json|blob` asks for the current loop back, `frame <hex>` sends a light frame blob
+//! and `midi <hex>` feeds raw bytes through the driver's MIDI input parser. `clock <ticks>
+//! <ms>` plays the host's MIDI clock: that many ticks, `ms` apart, and `link <beat> <bpm>`
+//! tells the mode where a Link session is.
+//!
+//! `button`, `pad`, `encoder` and `slider` steps send one hardware event each. `report
+//! [<button>...] [encoder <n>] [slider <n>]` sends a whole button report through the
+//! driver's parser, like the hardware does: every button's state, with the ones named held,
+//! then the encoder, where the last report left it unless given, and the slider.
+//! Hardware events go through fixed velocity first, as in the main loop. `mode modes`
+//! puts every mode under test, with Maschine, Star and Browse picking between them.
+//!
+//! A script can override settings with a `<name>.settings.toml` next to it.
+//!
+//! Run with `UPDATE_GOLDEN=1 cargo test` to (re)write the golden files after an intended change.
+
+mod scenarios;
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use config::{Config, FileFormat};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::Lights;
+use maschine_library::screen::Screen;
+use midir::SendError;
+use midly::live::LiveEvent;
+use midly::MidiMessage;
+use rosc::{OscMessage, OscType};
+
+use crate::clock::MockClock;
+use crate::context::{DriverContext, MidiSink, OscSink, UinputSink};
+use crate::groups::GroupState;
+use crate::input::{parse_hid_report, parse_midi_input, parse_osc_feedback, HardwareEvent, HostEvent};
+use crate::fixed_velocity::FixedVelocity;
+use crate::livecoding;
+use crate::modes::clock_in::ClockMessage;
+use crate::modes::custom_midi::button_from_name;
+use crate::light_frame::LightFrame;
+use crate::note_guard::NoteGuard;
+use crate::modes::{BitwigMode, ChordMode, CustomMidiMode, DeckMode, GameMode, KeyboardMode, KnobBankMode, LiveMode, MachineMode, ModeManager, PlayMode, PracticeMode, ReaperMode, ShowMode, VisualizerMode};
+use crate::osc::decode_hex;
+use crate::sequence::{LoopFormat, NoteSequence};
+use crate::settings::Settings;
+
+#[derive(Default)]
+pub struct FakeMidi {
+    pub sent: Vec<Vec<u8>>,
+}
+
+impl MidiSink for FakeMidi {
+    fn send(&mut self, message: &[u8]) -> Result<(), SendError> {
+        self.sent.push(message.to_vec());
+        Ok(())
+    }
+
+    fn take_sent(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.sent)
+    }
+}
+
+#[derive(Default)]
+pub struct FakeOsc {
+    pub sent: RefCell<Vec<OscMessage>>,
+}
+
+impl OscSink for FakeOsc {
+    fn send(&self, msg: OscMessage) {
+        self.sent.borrow_mut().push(msg);
+    }
+}
+
+#[derive(Default)]
+pub struct FakeUinput {
+    pub sent: Vec<String>,
+}
+
+impl UinputSink for FakeUinput {
+    fn scroll(&mut self, vertical: i32, horizontal: i32) {
+        self.sent.push(format!("scroll {} {}", vertical, horizontal));
+    }
+
+    fn key(&mut self, code: u16, pressed: bool) {
+        let state = if pressed { "down" } else { "up" };
+        self.sent.push(format!("key {:?} {}", evdev::KeyCode::new(code), state));
+    }
+}
+
+enum ModeUnderTest {
+    CustomMidi(CustomMidiMode),
+    Play(Box<PlayMode>),
+    Knobs(KnobBankMode),
+    Deck(DeckMode),
+    Practice(PracticeMode),
+    Show(ShowMode),
+    Game(GameMode),
+    Visualizer(VisualizerMode),
+    Live(LiveMode),
+    Bitwig(BitwigMode),
+    Reaper(ReaperMode),
+    Keyboard(KeyboardMode),
+    Chord(ChordMode),
+    Modes(Box<ModeManager>),
+}
+
+impl ModeUnderTest {
+    fn mode(&mut self) -> &mut dyn MachineMode {
+        match self {
+            ModeUnderTest::CustomMidi(m) => m,
+            ModeUnderTest::Play(m) => m.as_mut(),
+            ModeUnderTest::Knobs(m) => m,
+            ModeUnderTest::Deck(m) => m,
+            ModeUnderTest::Practice(m) => m,
+            ModeUnderTest::Show(m) => m,
+            ModeUnderTest::Game(m) => m,
+            ModeUnderTest::Visualizer(m) => m,
+            ModeUnderTest::Live(m) => m,
+            ModeUnderTest::Bitwig(m) => m,
+            ModeUnderTest::Reaper(m) => m,
+            ModeUnderTest::Keyboard(m) => m,
+            ModeUnderTest::Chord(m) => m,
+            ModeUnderTest::Modes(m) => m.current(),
+        }
+    }
+
+    /// Hands a hardware event to the mode. With every mode under test, Maschine, Star and
+    /// Browse pick the mode as in the main loop and the rest goes to the one that's up.
+    fn handle_event(&mut self, event: &HardwareEvent, shift_held: bool, ctx: &mut DriverContext) {
+        let ModeUnderTest::Modes(modes) = self else {
+            self.mode().handle_event(event, ctx);
+            return;
+        };
+        let selection = modes.selection(event, shift_held, ctx.settings);
+        match event {
+            HardwareEvent::Button { index: Buttons::Maschine | Buttons::Star | Buttons::Browse, pressed: true } => {
+                if let Some(id) = selection {
+                    modes.switch(id, ctx);
+                }
+            }
+            _ => modes.current().handle_event(event, ctx),
+        }
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+/// Shared `settings.toml`, with `<name>.settings.toml` layered on top when a script has one.
+fn load_settings(name: &str) -> Settings {
+    let text = fs::read_to_string(golden_dir().join("settings.toml")).expect("Can't read golden settings");
+    let mut builder = Config::builder().add_source(config::File::from_str(&text, FileFormat::Toml));
+    if let Ok(overlay) = fs::read_to_string(golden_dir().join(format!("{name}.settings.toml"))) {
+        builder = builder.add_source(config::File::from_str(&overlay, FileFormat::Toml));
+    }
+    builder
+        .build()
+        .and_then(|c| c.try_deserialize())
+        .expect("Can't parse golden settings")
+}
+
+fn parse_event(words: &[&str]) -> Result<HardwareEvent, String> {
+    let num = |i: usize| -> Result<u16, String> {
+        words
+            .get(i)
+            .and_then(|w| w.parse().ok())
+            .ok_or_else(|| format!("expected a number at position {}", i + 1))
+    };
+
+    match words.first().copied() {
+        Some("button") => {
+            let name = words.get(1).ok_or("missing button name")?;
+            let index = button_from_name(name).ok_or_else(|| format!("unknown button {name}"))?;
+            let pressed = match words.get(2).copied() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return Err("button state must be on/off".to_string()),
+            };
+            Ok(HardwareEvent::Button { index, pressed })
+        }
+        Some("pad") => {
+            let event_type = match words.get(2).copied() {
+                Some("NoteOn") => PadEventType::NoteOn,
+                Some("NoteOff") => PadEventType::NoteOff,
+                Some("PressOn") => PadEventType::PressOn,
+                Some("PressOff") => PadEventType::PressOff,
+                Some("Aftertouch") => PadEventType::Aftertouch,
+                other => return Err(format!("unknown pad event {other:?}")),
+            };
+            Ok(HardwareEvent::Pad { index: num(1)? as usize, event_type, value: num(3)? })
+        }
+        Some("encoder") => Ok(HardwareEvent::Encoder { value: num(1)? as u8 }),
+        Some("slider") => Ok(HardwareEvent::Slider { value: num(1)? as u8 }),
+        other => Err(format!("unknown step {other:?}")),
+    }
+}
+
+/// Events of a `report` step, read back from the bytes the hardware would send.
+fn parse_report(words: &[&str], encoder: &mut u8) -> Result<Vec<HardwareEvent>, String> {
+    let mut buf = [0u8; 11];
+    buf[0] = 0x01;
+    let mut slider = 0;
+    let mut words = words[1..].iter();
+    while let Some(&word) = words.next() {
+        match word {
+            "encoder" | "slider" => {
+                let value = words.next().and_then(|w| w.parse().ok()).ok_or_else(|| format!("{word} needs a value"))?;
+                if word == "encoder" {
+                    *encoder = value;
+                } else {
+                    slider = value;
+                }
+            }
+            name => {
+                let index = button_from_name(name).ok_or_else(|| format!("unknown button {name}"))? as usize;
+                buf[1 + index / 8] |= 1 << (index % 8);
+            }
+        }
+    }
+    buf[7] = *encoder;
+    buf[10] = slider;
+    Ok(parse_hid_report(&buf))
+}
+
+/// `osc <address> <args...>` (ints, floats or "strings"), or `cc <controller> <value>`
+/// (channel 1), as sent by the host.
+/// Ints, floats or "strings"
+fn osc_args(words: &[&str]) -> Vec<OscType> {
+    words
+        .iter()
+        .map(|w| match (w.parse::<i32>(), w.parse::<f32>()) {
+            (Ok(i), _) => OscType::Int(i),
+            (_, Ok(f)) => OscType::Float(f),
+            _ => OscType::String(w.trim_matches('"').to_string()),
+        })
+        .collect()
+}
+
+fn parse_host_event(words: &[&str]) -> Result<HostEvent, String> {
+    if words[0] == "osc" {
+        let addr = words.get(1).ok_or("missing address")?.to_string();
+        let args = osc_args(&words[2..]);
+        return parse_osc_feedback(&OscMessage { addr, args }).ok_or_else(|| "expected a value at position 3".to_string());
+    }
+    if words[0] == "note" {
+        // note <channel 1-16> <note> <velocity>
+        let numbers: Vec<u8> = words[1..].iter().filter_map(|w| w.parse().ok()).collect();
+        let [channel, note, velocity] = numbers[..] else {
+            return Err("expected channel, note and velocity".to_string());
+        };
+        return Ok(HostEvent::Note { channel: channel - 1, note, velocity });
+    }
+    // cc <controller> <value> [channel 1-16]
+    let value = words
+        .get(2)
+        .and_then(|w| w.parse::<i32>().ok())
+        .ok_or("expected a value at position 3")?;
+    let controller = words
+        .get(1)
+        .and_then(|w| w.parse().ok())
+        .ok_or("expected a controller at position 2")?;
+    let channel = match words.get(3) {
+        Some(w) => w.parse::<u8>().ok().filter(|c| (1..=16).contains(c)).ok_or("expected a channel 1-16 at position 4")? - 1,
+        None => 0,
+    };
+    Ok(HostEvent::ControlChange { channel, controller, value: value as u8 })
+}
+
+fn describe_midi(bytes: &[u8]) -> String {
+    match LiveEvent::parse(bytes) {
+        Ok(LiveEvent::Midi { channel, message }) => match message {
+            MidiMessage::NoteOn { key, vel } => format!("NoteOn ch{} key {} vel {}", channel, key, vel),
+            MidiMessage::NoteOff { key, vel } => format!("NoteOff ch{} key {} vel {}", channel, key, vel),
+            MidiMessage::Controller { controller, value } => format!("CC ch{} {}={}", channel, controller, value),
+            other => format!("{:?} ch{}", other, channel),
+        },
+        _ => bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn describe_osc(msg: &OscMessage) -> String {
+    let mut out = msg.addr.clone();
+    for arg in &msg.args {
+        match arg {
+            OscType::Int(i) => write!(out, " {}", i).unwrap(),
+            OscType::Float(f) => write!(out, " {}", f).unwrap(),
+            OscType::String(s) => write!(out, " {:?}", s).unwrap(),
+            other => write!(out, " {:?}", other).unwrap(),
+        }
+    }
+    out
+}
+
+fn diff_lights(before: &Lights, after: &Lights, out: &mut String) {
+    for i in 0..39 {
+        let button: Buttons = num::FromPrimitive::from_usize(i).unwrap();
+        let (a, b) = (before.get_button(button), after.get_button(button));
+        if a != b {
+            writeln!(out, "  light {:?} {:?} -> {:?}", button, a, b).unwrap();
+        }
+    }
+    for i in 0..16 {
+        let (a, b) = (before.get_pad(i), after.get_pad(i));
+        if a != b {
+            writeln!(out, "  light pad {} {:?}/{:?} -> {:?}/{:?}", i, a.0, a.1, b.0, b.1).unwrap();
+        }
+    }
+    for i in 0..25 {
+        let (a, b) = (before.get_slider(i), after.get_slider(i));
+        if a != b {
+            writeln!(out, "  light slider {} {:?} -> {:?}", i, a, b).unwrap();
+        }
+    }
+}
+
+/// Runs `<name>.script` and returns the transcript of everything the mode emitted.
+pub fn run_script(name: &str) -> String {
+    let script = fs::read_to_string(golden_dir().join(format!("{name}.script")))
+        .unwrap_or_else(|e| panic!("Can't read script {name}: {e}"));
+
+    let settings = load_settings(name);
+    let mut lights = Lights::new();
+    let mut screen = Screen::new();
+    let clock = MockClock::new();
+    let mut midi = NoteGuard::new(FakeMidi::default(), &settings.note_guard, &clock);
+    let osc = FakeOsc::default();
+    let mut uinput = FakeUinput::default();
+    let mut groups = GroupState::new(&settings);
+    let mut mode: Option<ModeUnderTest> = None;
+    let mut fixed_velocity = FixedVelocity::new();
+    let mut shift_held = false;
+    let mut encoder = 0;
+    let mut transcript = String::new();
+
+    for (line_no, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let before = lights.clone();
+        let screen_before = screen.clone();
+        writeln!(transcript, "> {}", line).unwrap();
+
+        let mut ctx = DriverContext {
+            lights: &mut lights,
+            screen: &mut screen,
+            midi_port: &mut midi,
+            osc: &osc,
+            uinput: &mut uinput,
+            settings: &settings,
+            clock: &clock,
+            groups: &mut groups,
+        };
+
+        match words[0] {
+            "mode" => {
+                mode = Some(match words.get(1).copied() {
+                    Some("custom_midi") => ModeUnderTest::CustomMidi(CustomMidiMode::new()),
+                    Some("play") => ModeUnderTest::Play(Box::new(PlayMode::new(&settings, 1))),
+                    Some("knobs") => ModeUnderTest::Knobs(KnobBankMode::new(&settings)),
+                    Some("deck") => ModeUnderTest::Deck(DeckMode::new()),
+                    Some("practice") => ModeUnderTest::Practice(PracticeMode::new()),
+                    Some("show") => ModeUnderTest::Show(ShowMode::new()),
+                    Some("game") => ModeUnderTest::Game(GameMode::new(1)),
+                    Some("visualizer") => ModeUnderTest::Visualizer(VisualizerMode::new()),
+                    Some("live") => ModeUnderTest::Live(LiveMode::new()),
+                    Some("bitwig") => ModeUnderTest::Bitwig(BitwigMode::new()),
+                    Some("reaper") => ModeUnderTest::Reaper(ReaperMode::new()),
+                    Some("keyboard") => ModeUnderTest::Keyboard(KeyboardMode::new(&settings)),
+                    Some("chord") => ModeUnderTest::Chord(ChordMode::new()),
+                    Some("modes") => ModeUnderTest::Modes(Box::new(ModeManager::new(&settings, 1))),
+                    other => panic!("{name}:{}: unknown mode {other:?}", line_no + 1),
+                });
+            }
+            "enter" => mode.as_mut().expect("no mode selected").mode().on_enter(&mut ctx),
+            "exit" => mode.as_mut().expect("no mode selected").mode().on_exit(&mut ctx),
+            "tick" => {
+                mode.as_mut().expect("no mode selected").mode().tick(&mut ctx);
+            }
+            "wait" => {
+                let millis: u64 = words
+                    .get(1)
+                    .and_then(|w| w.parse().ok())
+                    .unwrap_or_else(|| panic!("{name}:{}: wait needs milliseconds", line_no + 1));
+                for _ in 0..millis {
+                    clock.advance(Duration::from_millis(1));
+                    if let Some(mode) = mode.as_mut() {
+                        mode.mode().tick(&mut ctx);
+                    }
+                    ctx.midi_port.flush();
+                }
+            }
+            "loop" => {
+                let sequence = NoteSequence::from_json(line["loop".len()..].trim())
+                    .unwrap_or_else(|e| panic!("{name}:{}: {e}", line_no + 1));
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&HostEvent::LoadLoop(sequence), &mut ctx);
+            }
+            "pattern" => {
+                let msg = OscMessage { addr: "/maschine/pattern".to_string(), args: osc_args(&words[1..]) };
+                let sequence = livecoding::parse_pattern(&msg).unwrap_or_else(|e| panic!("{name}:{}: {e}", line_no + 1));
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&HostEvent::LoadLoop(sequence), &mut ctx);
+            }
+            "dump" => {
+                let format = match words.get(1).copied() {
+                    Some("blob") => LoopFormat::Blob,
+                    _ => LoopFormat::Json,
+                };
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&HostEvent::DumpLoop(format), &mut ctx);
+            }
+            "frame" => {
+                let frame = words
+                    .get(1)
+                    .and_then(|hex| decode_hex(hex))
+                    .ok_or("expected the blob in hex".to_string())
+                    .and_then(|blob| LightFrame::from_blob(&blob))
+                    .unwrap_or_else(|e| panic!("{name}:{}: {e}", line_no + 1));
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&HostEvent::LightFrame(frame), &mut ctx);
+            }
+            "midi" => {
+                let event = words
+                    .get(1)
+                    .and_then(|hex| decode_hex(hex))
+                    .and_then(|bytes| parse_midi_input(&bytes))
+                    .unwrap_or_else(|| panic!("{name}:{}: expected MIDI the driver reads, in hex", line_no + 1));
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&event, &mut ctx);
+            }
+            "clock" => {
+                let numbers: Vec<u64> = words[1..].iter().filter_map(|w| w.parse().ok()).collect();
+                let [ticks, millis] = numbers[..] else {
+                    panic!("{name}:{}: clock needs ticks and milliseconds between them", line_no + 1);
+                };
+                let mode = mode.as_mut().expect("no mode selected").mode();
+                for _ in 0..ticks {
+                    mode.handle_host_event(&HostEvent::Clock(ClockMessage::Tick), &mut ctx);
+                    for _ in 0..millis {
+                        clock.advance(Duration::from_millis(1));
+                        mode.tick(&mut ctx);
+                    }
+                }
+            }
+            "link" => {
+                let numbers: Vec<f64> = words[1..].iter().filter_map(|w| w.parse().ok()).collect();
+                let [beat, bpm] = numbers[..] else {
+                    panic!("{name}:{}: link needs a beat and a tempo", line_no + 1);
+                };
+                let event = HostEvent::Clock(ClockMessage::Beat { beat, bpm });
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&event, &mut ctx);
+            }
+            "osc" | "cc" | "note" => {
+                let event = parse_host_event(&words).unwrap_or_else(|e| panic!("{name}:{}: {e}", line_no + 1));
+                mode.as_mut().expect("no mode selected").mode().handle_host_event(&event, &mut ctx);
+            }
+            _ => {
+                let mut events = if words[0] == "report" {
+                    parse_report(&words, &mut encoder)
+                } else {
+                    parse_event(&words).map(|event| vec![event])
+                }
+                .unwrap_or_else(|e| panic!("{name}:{}: {e}", line_no + 1));
+                if let Some(on) = fixed_velocity.apply(&mut events, &settings.fixed_velocity) {
+                    writeln!(transcript, "  fixed velocity {}", if on { "on" } else { "off" }).unwrap();
+                }
+                for event in events {
+                    mode.as_mut().expect("no mode selected").handle_event(&event, shift_held, &mut ctx);
+                    if let HardwareEvent::Button { index: Buttons::Shift, pressed } = event {
+                        shift_held = pressed;
+                    }
+                }
+            }
+        }
+
+        for bytes in midi.take_sent() {
+            writeln!(transcript, "  midi {}", describe_midi(&bytes)).unwrap();
+        }
+        for msg in osc.sent.borrow_mut().drain(..) {
+            writeln!(transcript, "  osc {}", describe_osc(&msg)).unwrap();
+        }
+        for event in uinput.sent.drain(..) {
+            writeln!(transcript, "  uinput {}", event).unwrap();
+        }
+        diff_lights(&before, &lights, &mut transcript);
+        if screen != screen_before {
+            writeln!(transcript, "  screen updated").unwrap();
+        }
+    }
+
+    transcript
+}
+
+/// Compares the transcript of `<name>.script` with `<name>.golden`.
+pub fn assert_golden(name: &str) {
+    let actual = run_script(name);
+    let golden_path = golden_dir().join(format!("{name}.golden"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, &actual).expect("Can't write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden_path)
+        .unwrap_or_else(|_| panic!("Missing {}; run with UPDATE_GOLDEN=1 to create it", golden_path.display()));
+    assert_eq!(expected, actual, "transcript of {name} differs from its golden file");
+}
@@ -0,0 +1,266 @@
+use super::assert_golden;
+
+#[test]
+fn custom_midi_trigger_and_toggle() {
+    assert_golden("custom_midi_trigger_and_toggle");
+}
+
+#[test]
+fn custom_midi_exclusive_group() {
+    assert_golden("custom_midi_exclusive_group");
+}
+
+#[test]
+fn bitwig_remote_controls() {
+    assert_golden("bitwig_remote_controls");
+}
+
+#[test]
+fn custom_midi_mixxx_feedback() {
+    assert_golden("custom_midi_mixxx_feedback");
+}
+
+#[test]
+fn reaper_tracks() {
+    assert_golden("reaper_tracks");
+}
+
+#[test]
+fn custom_midi_encoder_keys() {
+    assert_golden("custom_midi_encoder_keys");
+}
+
+#[test]
+fn custom_midi_momentary_hold() {
+    assert_golden("custom_midi_momentary_hold");
+}
+
+#[test]
+fn custom_midi_radio_group() {
+    assert_golden("custom_midi_radio_group");
+}
+
+#[test]
+fn custom_midi_pads_encoder_slider() {
+    assert_golden("custom_midi_pads_encoder_slider");
+}
+
+#[test]
+fn custom_midi_exit_releases_notes() {
+    assert_golden("custom_midi_exit_releases_notes");
+}
+
+#[test]
+fn custom_midi_pad_names() {
+    assert_golden("custom_midi_pad_names");
+}
+
+#[test]
+fn custom_midi_host_sync() {
+    assert_golden("custom_midi_host_sync");
+}
+
+#[test]
+fn deck_macros() {
+    assert_golden("deck_macros");
+}
+
+#[test]
+fn knob_bank_select_and_edit() {
+    assert_golden("knob_bank_select_and_edit");
+}
+
+#[test]
+fn play_mode_arm_record_pause() {
+    assert_golden("play_mode_arm_record_pause");
+}
+
+#[test]
+fn play_mode_erase() {
+    assert_golden("play_mode_erase");
+}
+
+#[test]
+fn play_mode_loop_playback() {
+    assert_golden("play_mode_loop_playback");
+}
+
+#[test]
+fn game_simon() {
+    assert_golden("game_simon");
+}
+
+#[test]
+fn practice_timing() {
+    assert_golden("practice_timing");
+}
+
+#[test]
+fn practice_meter() {
+    assert_golden("practice_meter");
+}
+
+#[test]
+fn play_mode_load_loop() {
+    assert_golden("play_mode_load_loop");
+}
+
+#[test]
+fn play_mode_dump_loop() {
+    assert_golden("play_mode_dump_loop");
+}
+
+#[test]
+fn play_mode_follow_actions() {
+    assert_golden("play_mode_follow_actions");
+}
+
+#[test]
+fn custom_midi_pad_feedback() {
+    assert_golden("custom_midi_pad_feedback");
+}
+
+#[test]
+fn show_cues() {
+    assert_golden("show_cues");
+}
+
+#[test]
+fn visualizer_frames() {
+    assert_golden("visualizer_frames");
+}
+
+#[test]
+fn live_session() {
+    assert_golden("live_session");
+}
+
+#[test]
+fn custom_midi_note_repeat() {
+    assert_golden("custom_midi_note_repeat");
+}
+
+#[test]
+fn keyboard_scale() {
+    assert_golden("keyboard_scale");
+}
+
+#[test]
+fn custom_midi_modular() {
+    assert_golden("custom_midi_modular");
+}
+
+#[test]
+fn chord_pads() {
+    assert_golden("chord_pads");
+}
+
+#[test]
+fn chord_voice_allocation() {
+    assert_golden("chord_voice_allocation");
+}
+
+#[test]
+fn play_mode_pattern() {
+    assert_golden("play_mode_pattern");
+}
+
+#[test]
+fn custom_midi_pad_banks() {
+    assert_golden("custom_midi_pad_banks");
+}
+
+#[test]
+fn play_mode_fixed_velocity() {
+    assert_golden("play_mode_fixed_velocity");
+}
+
+#[test]
+fn custom_midi_channels() {
+    assert_golden("custom_midi_channels");
+}
+
+#[test]
+fn play_mode_clock_out() {
+    assert_golden("play_mode_clock_out");
+}
+
+#[test]
+fn play_mode_clock_in() {
+    assert_golden("play_mode_clock_in");
+}
+
+#[test]
+fn play_mode_link() {
+    assert_golden("play_mode_link");
+}
+
+#[test]
+fn play_mode_loop_wrap() {
+    assert_golden("play_mode_loop_wrap");
+}
+
+#[test]
+fn play_mode_metronome() {
+    assert_golden("play_mode_metronome");
+}
+
+#[test]
+fn play_mode_scenes() {
+    assert_golden("play_mode_scenes");
+}
+
+#[test]
+fn play_mode_swing() {
+    assert_golden("play_mode_swing");
+}
+
+#[test]
+fn play_mode_patterns() {
+    assert_golden("play_mode_patterns");
+}
+
+#[test]
+fn play_mode_undo() {
+    assert_golden("play_mode_undo");
+}
+
+#[test]
+fn play_mode_undo_reports() {
+    assert_golden("play_mode_undo_reports");
+}
+
+#[test]
+fn play_mode_polymeter() {
+    assert_golden("play_mode_polymeter");
+}
+
+#[test]
+fn play_mode_param_locks() {
+    assert_golden("play_mode_param_locks");
+}
+
+#[test]
+fn play_mode_step_reports() {
+    assert_golden("play_mode_step_reports");
+}
+
+#[test]
+fn play_mode_groove_reports() {
+    assert_golden("play_mode_groove_reports");
+}
+
+#[test]
+fn play_mode_reports() {
+    assert_golden("play_mode_reports");
+}
+
+#[test]
+fn modes_selectors() {
+    assert_golden("modes_selectors");
+}
+
+#[test]
+fn note_guard_window() {
+    assert_golden("note_guard_window");
+}
@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use midly::live::{LiveEvent, SystemCommon, SystemRealtime};
+
+/// MIDI clock runs at 24 ticks per quarter note; song position counts sixteenths.
+const CLOCKS_PER_BEAT: u32 = 24;
+const CLOCKS_PER_SIXTEENTH: u32 = 6;
+const BEAT_FLASH: Duration = Duration::from_millis(100);
+/// Weight of the newest tick in the smoothed tick interval.
+const TEMPO_SMOOTHING: f64 = 0.1;
+/// Ticks further apart than this mean the clock stopped; don't average across the gap.
+const CLOCK_GAP: Duration = Duration::from_millis(250);
+
+// MIDI Machine Control commands, in F0 7F <device> 06 <command> F7.
+const MMC_STOP: u8 = 0x01;
+const MMC_PLAY: u8 = 0x02;
+const MMC_DEFERRED_PLAY: u8 = 0x03;
+const MMC_PAUSE: u8 = 0x09;
+
+/// The time signature, from `[transport] beats_per_bar` over `beat_unit`. Tempo stays in
+/// quarter notes, as MIDI clock counts it; the meter only says where beats and bars fall.
+/// 6/8, 9/8 and 12/8 are felt in dotted quarters, so their beats are three eighths long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meter {
+    pub beats_per_bar: u8,
+    pub beat_unit: u8,
+}
+
+impl Meter {
+    fn is_compound(&self) -> bool {
+        self.beat_unit >= 8 && self.beats_per_bar > 3 && self.beats_per_bar.is_multiple_of(3)
+    }
+
+    /// Length of a felt beat, in sixteenths
+    pub fn beat_sixteenths(&self) -> u32 {
+        let unit = 16 / self.beat_unit as u32;
+        if self.is_compound() { unit * 3 } else { unit }
+    }
+
+    pub fn bar_sixteenths(&self) -> u32 {
+        self.beats_per_bar as u32 * 16 / self.beat_unit as u32
+    }
+
+    /// Felt beats in a bar: two in 6/8, seven in 7/8
+    pub fn beats(&self) -> u32 {
+        self.bar_sixteenths() / self.beat_sixteenths()
+    }
+}
+
+/// Follows the host's transport from what arrives on the MIDI input: Start/Stop/Continue
+/// or MMC for the play state, song position pointer and clock for the position. The result
+/// is drawn over whatever the modes show: Play/Stop lights, a beat flash on Tap, and
+/// bar:beat plus tempo in the status bar.
+#[derive(Default)]
+pub struct HostTransport {
+    /// Set by the first transport message; until then nothing is shown.
+    active: bool,
+    playing: bool,
+    /// Clock ticks since the start of the song
+    position: u32,
+    last_tick: Option<Instant>,
+    tick_ms: Option<f64>,
+    flash_until: Option<Instant>,
+}
+
+impl HostTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one incoming MIDI message; returns true when the display should change.
+    pub fn handle(&mut self, bytes: &[u8], now: Instant) -> bool {
+        if let [0xF0, 0x7F, _, 0x06, command, ..] = bytes {
+            return match *command {
+                MMC_PLAY | MMC_DEFERRED_PLAY => self.set_playing(true),
+                MMC_STOP | MMC_PAUSE => self.set_playing(false),
+                _ => false,
+            };
+        }
+        match LiveEvent::parse(bytes) {
+            Ok(LiveEvent::Realtime(SystemRealtime::Start)) => {
+                self.position = 0;
+                self.set_playing(true);
+                true
+            }
+            Ok(LiveEvent::Realtime(SystemRealtime::Continue)) => self.set_playing(true),
+            Ok(LiveEvent::Realtime(SystemRealtime::Stop)) => self.set_playing(false),
+            Ok(LiveEvent::Common(SystemCommon::SongPosition(sixteenths))) => {
+                self.active = true;
+                self.position = sixteenths.as_int() as u32 * CLOCKS_PER_SIXTEENTH;
+                true
+            }
+            Ok(LiveEvent::Realtime(SystemRealtime::TimingClock)) => self.clock_tick(now),
+            _ => false,
+        }
+    }
+
+    fn set_playing(&mut self, playing: bool) -> bool {
+        let changed = !self.active || self.playing != playing;
+        self.active = true;
+        self.playing = playing;
+        changed
+    }
+
+    fn clock_tick(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last_tick
+            && now.duration_since(last) < CLOCK_GAP
+        {
+            let ms = now.duration_since(last).as_secs_f64() * 1000.0;
+            self.tick_ms = Some(match self.tick_ms {
+                Some(avg) => avg + (ms - avg) * TEMPO_SMOOTHING,
+                None => ms,
+            });
+        }
+        self.last_tick = Some(now);
+
+        if !self.playing {
+            return false;
+        }
+        // After Start or a song position, the next tick falls on `position`. The Tap flash
+        // keeps to quarters; the meter only changes how they're counted.
+        let on_beat = self.position.is_multiple_of(CLOCKS_PER_BEAT);
+        self.position += 1;
+        if on_beat {
+            self.flash_until = Some(now + BEAT_FLASH);
+        }
+        on_beat
+    }
+
+    /// "bar:beat", counted from 1
+    pub fn bar_beat(&self, meter: Meter) -> Option<String> {
+        if !self.active {
+            return None;
+        }
+        let sixteenths = self.position / CLOCKS_PER_SIXTEENTH;
+        let bar = sixteenths / meter.bar_sixteenths();
+        let beat = sixteenths % meter.bar_sixteenths() / meter.beat_sixteenths();
+        Some(format!("{}:{}", bar + 1, beat + 1))
+    }
+
+    /// Tempo measured from the clock, while it's coming in
+    pub fn bpm(&self) -> Option<f32> {
+        self.tick_ms.filter(|_| self.playing).map(|ms| (60_000.0 / (ms * CLOCKS_PER_BEAT as f64)) as f32)
+    }
+
+    /// Clock ticks played since the start of the song, while the host's clock runs
+    pub fn clock_ticks(&self) -> Option<u32> {
+        (self.playing && self.last_tick.is_some()).then(|| self.position.saturating_sub(1))
+    }
+
+    /// Sixteenths played since the start of the song, while the host's clock runs
+    pub fn clock_position(&self) -> Option<u32> {
+        self.clock_ticks().map(|ticks| ticks / CLOCKS_PER_SIXTEENTH)
+    }
+
+    /// Ends the beat flash; returns true when the lights changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        if self.flash_until.is_some_and(|until| now >= until) {
+            self.flash_until = None;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.flash_until.is_some()
+    }
+
+    /// Transport lights go on buttons the mode left dark, so a running internal
+    /// looper keeps showing its own state.
+    pub fn compose_lights(&self, lights: &mut Lights) {
+        if !self.active {
+            return;
+        }
+        let (play, stop) = if self.playing {
+            (Brightness::Bright, Brightness::Dim)
+        } else {
+            (Brightness::Dim, Brightness::Bright)
+        };
+        for (button, b) in [(Buttons::Play, play), (Buttons::Stop, stop)] {
+            if lights.get_button(button) == Brightness::Off {
+                lights.set_button(button, b);
+            }
+        }
+        if self.flash_until.is_some() && lights.get_button(Buttons::Tap) != Brightness::Bright {
+            lights.set_button(Buttons::Tap, Brightness::Bright);
+        }
+    }
+}
+
+/// Lights the pad for the current sixteenth, going through the pads in order from the top
+/// of each bar: dim on off-beats, brighter on each beat and brightest on the first beat
+/// of the bar.
+pub fn draw_pad_chase(lights: &mut Lights, sixteenth: u32, meter: Meter, color: PadColors) {
+    let in_bar = sixteenth % meter.bar_sixteenths();
+    let b = if in_bar == 0 {
+        Brightness::Bright
+    } else if in_bar.is_multiple_of(meter.beat_sixteenths()) {
+        Brightness::Normal
+    } else {
+        Brightness::Dim
+    };
+    lights.set_pad(in_bar as usize % 16, color, b);
+}
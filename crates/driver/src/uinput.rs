@@ -0,0 +1,75 @@
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, EventType, InputEvent, KeyCode, RelativeAxisCode};
+use crate::context::UinputSink;
+
+/// Highest keyboard key the virtual device advertises (KEY_MICMUTE).
+const LAST_KEY: u16 = 248;
+
+/// Emulated keyboard and mouse wheel on /dev/uinput. Only created when a mapping needs
+/// it; when that fails (usually permissions) everything sent here is dropped.
+pub struct UinputOut {
+    device: Option<VirtualDevice>,
+}
+
+impl UinputOut {
+    pub fn disabled() -> Self {
+        Self { device: None }
+    }
+
+    pub fn open(name: &str) -> Self {
+        match Self::build(name) {
+            Ok(device) => Self { device: Some(device) },
+            Err(e) => {
+                eprintln!("Couldn't create uinput device ({}); is /dev/uinput writable?", e);
+                Self::disabled()
+            }
+        }
+    }
+
+    fn build(name: &str) -> std::io::Result<VirtualDevice> {
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for code in 1..=LAST_KEY {
+            keys.insert(KeyCode::new(code));
+        }
+        // Without buttons and X/Y, desktops don't treat the device as a pointer and ignore its wheel.
+        for button in [KeyCode::BTN_LEFT, KeyCode::BTN_RIGHT, KeyCode::BTN_MIDDLE] {
+            keys.insert(button);
+        }
+        let mut axes = AttributeSet::<RelativeAxisCode>::new();
+        for axis in [RelativeAxisCode::REL_X, RelativeAxisCode::REL_Y, RelativeAxisCode::REL_WHEEL, RelativeAxisCode::REL_HWHEEL] {
+            axes.insert(axis);
+        }
+        VirtualDevice::builder()?
+            .name(name)
+            .with_keys(&keys)?
+            .with_relative_axes(&axes)?
+            .build()
+    }
+
+    fn emit(&mut self, events: &[InputEvent]) {
+        if let Some(device) = &mut self.device
+            && let Err(e) = device.emit(events)
+        {
+            eprintln!("uinput error: {}", e);
+        }
+    }
+}
+
+impl UinputSink for UinputOut {
+    fn scroll(&mut self, vertical: i32, horizontal: i32) {
+        let mut events = Vec::new();
+        if vertical != 0 {
+            events.push(InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_WHEEL.0, vertical));
+        }
+        if horizontal != 0 {
+            events.push(InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_HWHEEL.0, horizontal));
+        }
+        if !events.is_empty() {
+            self.emit(&events);
+        }
+    }
+
+    fn key(&mut self, code: u16, pressed: bool) {
+        self.emit(&[InputEvent::new(EventType::KEY.0, code, pressed as i32)]);
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+use crate::settings::WatchdogSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchdogAction {
+    /// Try to reopen the backend.
+    Recover,
+    /// Recovery keeps failing: surface the problem to the performer.
+    Escalate,
+    /// A previously escalated backend works again.
+    Recovered,
+}
+
+/// Failure bookkeeping for a single backend (HID device or MIDI port).
+#[derive(Default)]
+pub struct BackendWatch {
+    recovery_attempts: u32,
+    next_attempt: Option<Instant>,
+    escalated: bool,
+}
+
+impl BackendWatch {
+    /// Feeds the backend's current count of consecutive failures and returns what to do about it.
+    pub fn observe(&mut self, consecutive_failures: u32, now: Instant, settings: &WatchdogSettings) -> Option<WatchdogAction> {
+        if consecutive_failures == 0 {
+            self.recovery_attempts = 0;
+            self.next_attempt = None;
+            if self.escalated {
+                self.escalated = false;
+                return Some(WatchdogAction::Recovered);
+            }
+            return None;
+        }
+
+        if consecutive_failures < settings.failure_threshold {
+            return None;
+        }
+        if self.next_attempt.is_some_and(|t| now < t) {
+            return None;
+        }
+        self.next_attempt = Some(now + Duration::from_millis(settings.retry_interval_ms));
+
+        if self.recovery_attempts >= settings.max_recovery_attempts && !self.escalated {
+            self.escalated = true;
+            return Some(WatchdogAction::Escalate);
+        }
+        self.recovery_attempts += 1;
+        Some(WatchdogAction::Recover)
+    }
+}
+
+/// Watches the HID and MIDI backends so a stuck device doesn't leave the driver silently spinning.
+#[derive(Default)]
+pub struct Watchdog {
+    pub hid: BackendWatch,
+    pub midi: BackendWatch,
+}
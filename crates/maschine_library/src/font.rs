@@ -139,9 +139,9 @@ impl Font {
             let char_code = c as usize;
             if char_code < FONT.len() {
                 let sym = FONT[char_code];
-                for i in 0..8 {
+                for (i, row) in sym.iter().enumerate() {
                     for j in 0..8 {
-                        let bit = (sym[i] >> j) & 1 == 1;
+                        let bit = (row >> j) & 1 == 1;
                         for y_offset in 0..scale {
                             for x_offset in 0..scale {
                                 s.set(
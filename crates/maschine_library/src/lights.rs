@@ -33,6 +33,11 @@ pub enum PadColors {
     White = 17,
 }
 
+/// Brightness steps in order, for fading between them.
+const LEVELS: [Brightness; 4] = [Brightness::Off, Brightness::Dim, Brightness::Normal, Brightness::Bright];
+const PADS: std::ops::Range<usize> = 39..55;
+
+#[derive(Clone, PartialEq)]
 pub struct Lights {
     status: [u8; 80],
 }
@@ -63,6 +68,10 @@ impl Lights {
         self.status[55 + id] = b as u8;
     }
 
+    pub fn get_slider(&self, id: usize) -> Brightness {
+        num::FromPrimitive::from_u8(self.status[55 + id]).unwrap_or(Brightness::Off)
+    }
+
     pub fn set_pad(&mut self, id: usize, c: PadColors, b: Brightness) {
         let val = match b {
             Brightness::Off => 0,
@@ -91,6 +100,41 @@ impl Lights {
         (color, b)
     }
 
+    /// Moves every light one brightness step towards `target`. A pad changing color fades
+    /// out in the old one before fading in. Returns false once everything matches.
+    pub fn step_towards(&mut self, target: &Lights) -> bool {
+        let mut changed = false;
+        for i in 0..self.status.len() {
+            let (now, goal) = (self.status[i], target.status[i]);
+            if now == goal {
+                continue;
+            }
+            changed = true;
+            self.status[i] = if PADS.contains(&i) {
+                let (color, level) = (now >> 2, level_of(self.get_pad(i - PADS.start).1));
+                let (goal_color, goal_level) = (goal >> 2, level_of(target.get_pad(i - PADS.start).1));
+                if level == 0 {
+                    (goal_color << 2) + (LEVELS[1] as u8 & 0b11)
+                } else if color != goal_color || level > goal_level {
+                    if level == 1 { 0 } else { (color << 2) + (LEVELS[level - 1] as u8 & 0b11) }
+                } else if level < goal_level {
+                    (color << 2) + (LEVELS[level + 1] as u8 & 0b11)
+                } else {
+                    goal
+                }
+            } else {
+                let level = LEVELS.iter().position(|b| *b as u8 == now).unwrap_or(0);
+                let goal_level = LEVELS.iter().position(|b| *b as u8 == goal).unwrap_or(0);
+                match level.cmp(&goal_level) {
+                    std::cmp::Ordering::Less => LEVELS[level + 1] as u8,
+                    std::cmp::Ordering::Greater => LEVELS[level - 1] as u8,
+                    std::cmp::Ordering::Equal => goal,
+                }
+            };
+        }
+        changed
+    }
+
     pub fn write(&self, h: &HidDevice) -> HidResult<()> {
         // OPTIMIZATION: Use a fixed buffer on the stack to avoid heap allocation (Vec)
         let mut report = [0u8; 81];
@@ -100,4 +144,8 @@ impl Lights {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn level_of(b: Brightness) -> usize {
+    LEVELS.iter().position(|l| *l == b).unwrap_or(0)
+}
@@ -3,6 +3,7 @@ use hidapi::{HidDevice, HidResult};
 const HEADER_HI: [u8; 9] = [0xe0, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x02, 0x00];
 const HEADER_LO: [u8; 9] = [0xe0, 0x00, 0x00, 0x02, 0x00, 0x80, 0x00, 0x02, 0x00];
 
+#[derive(Clone, PartialEq)]
 pub struct Screen {
     buffer: [u8; 512],
 }
@@ -29,6 +30,10 @@ impl Screen {
     }
 
     pub fn set(&mut self, i: usize, j: usize, val: bool) {
+        // Anything drawn past the 32x128 panel is clipped.
+        if i >= 32 || j >= 128 {
+            return;
+        }
         let chunk = i / 8;
         let imod: u8 = (i % 8) as u8;
         let idx = chunk * 128 + j;